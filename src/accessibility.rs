@@ -0,0 +1,184 @@
+// accessibility.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::settings::GameConfig;
+use crate::spell_fire::SpellKind;
+
+/// AccessibilityPlugin keeps the player's accessibility settings with the rest of
+/// the `GameConfig`.
+///
+/// `AccessibilitySettings` is loaded with the `GameConfig` in `main` and copied
+/// back into it whenever it changes, so it's saved with the other settings. The
+/// colors of spells, impacts and markers all come from its `palette`, so turning
+/// on `colorblind_mode` swaps the reds and greens for colors that stay apart with
+/// any kind of color blindness. Effects are rebuilt by their own plugins when it
+/// changes.
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AccessibilitySettings>()
+            .add_systems(Update, store_accessibility_settings);
+    }
+}
+
+/// Accessibility options chosen by the player.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Draws spells and markers in `COLORBLIND_PALETTE` instead of `STANDARD_PALETTE`.
+    pub colorblind_mode: bool,
+}
+
+impl AccessibilitySettings {
+    /// The palette to draw with.
+    pub fn palette(&self) -> &'static Palette {
+        if self.colorblind_mode {
+            &COLORBLIND_PALETTE
+        } else {
+            &STANDARD_PALETTE
+        }
+    }
+}
+
+/// Colors of everything that tells the player something by its color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// Colors a spell_fire projectile's particles pass through as they age, after
+    /// starting out white: its hot core, then its edge.
+    pub spell_fire: [Vec4; 2],
+    /// Solid core at the heart of a spell_fire projectile.
+    pub spell_core: Color,
+    /// Colors the trail behind a projectile fades from and to.
+    pub spell_trail: [Vec4; 2],
+    /// Impact burst of a fire spell.
+    pub impact_fire: Vec4,
+    /// Impact burst of a homing spell.
+    pub impact_homing: Vec4,
+    /// Impact burst of an ice spell.
+    pub impact_ice: Vec4,
+    /// Blast of an explosive spell.
+    pub impact_explosion: Vec4,
+    /// Burst shown where an enemy dies.
+    pub enemy_death: Vec4,
+    /// The player's dot on the minimap.
+    pub minimap_player: Color,
+}
+
+impl Palette {
+    /// Color of a kind of spell's impact burst.
+    pub fn impact(&self, kind: SpellKind) -> Vec4 {
+        match kind {
+            SpellKind::Fire | SpellKind::Firewall => self.impact_fire,
+            SpellKind::Homing => self.impact_homing,
+            SpellKind::Ice => self.impact_ice,
+            SpellKind::Explosion => self.impact_explosion,
+        }
+    }
+}
+
+/// The game's usual colors: red fire against green enemy deaths.
+pub const STANDARD_PALETTE: Palette = Palette {
+    spell_fire: [Vec4::new(1.0, 1.0, 0.0, 1.0), Vec4::new(1.0, 0.0, 0.0, 1.0)],
+    spell_core: Color::RED,
+    spell_trail: [Vec4::new(1.0, 0.6, 0.0, 0.8), Vec4::new(1.0, 0.0, 0.0, 0.0)],
+    impact_fire: Vec4::new(1.0, 0.5, 0.0, 1.0),
+    impact_homing: Vec4::new(0.6, 0.3, 1.0, 1.0),
+    impact_ice: Vec4::new(0.5, 0.9, 1.0, 1.0),
+    impact_explosion: Vec4::new(1.0, 0.8, 0.2, 1.0),
+    enemy_death: Vec4::new(0.4, 0.8, 0.2, 1.0),
+    minimap_player: Color::RED,
+};
+
+/// Colors from the Okabe-Ito set, which stay distinct with red-green and
+/// blue-yellow color blindness: orange fire against blue enemy deaths, with no
+/// spell relying on red or green.
+pub const COLORBLIND_PALETTE: Palette = Palette {
+    spell_fire: [
+        Vec4::new(0.94, 0.89, 0.26, 1.0),
+        Vec4::new(0.9, 0.62, 0.0, 1.0),
+    ],
+    spell_core: Color::rgb(0.9, 0.62, 0.0),
+    spell_trail: [
+        Vec4::new(0.9, 0.62, 0.0, 0.8),
+        Vec4::new(0.84, 0.37, 0.0, 0.0),
+    ],
+    impact_fire: Vec4::new(0.9, 0.62, 0.0, 1.0),
+    impact_homing: Vec4::new(0.8, 0.47, 0.65, 1.0),
+    impact_ice: Vec4::new(0.34, 0.71, 0.91, 1.0),
+    impact_explosion: Vec4::new(0.94, 0.89, 0.26, 1.0),
+    enemy_death: Vec4::new(0.0, 0.45, 0.7, 1.0),
+    minimap_player: Color::WHITE,
+};
+
+/// Run condition for rebuilding colored effects: true when the settings change
+/// after startup, when the effects were first built from them.
+pub fn palette_changed(settings: Res<AccessibilitySettings>) -> bool {
+    settings.is_changed() && !settings.is_added()
+}
+
+/// Copies changed accessibility settings into the `GameConfig`, which saves them.
+fn store_accessibility_settings(
+    settings: Res<AccessibilitySettings>,
+    mut config: ResMut<GameConfig>,
+) {
+    if !settings.is_changed() || settings.is_added() || config.accessibility == *settings {
+        return;
+    }
+    config.accessibility = settings.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colorblind_mode_swaps_palette() {
+        let standard = AccessibilitySettings::default().palette();
+        let colorblind = AccessibilitySettings {
+            colorblind_mode: true,
+        }
+        .palette();
+        assert_eq!(standard, &STANDARD_PALETTE);
+        assert_eq!(colorblind, &COLORBLIND_PALETTE);
+
+        // Every spell looks different in colorblind mode, and so does an enemy dying
+        for kind in [
+            SpellKind::Fire,
+            SpellKind::Homing,
+            SpellKind::Ice,
+            SpellKind::Explosion,
+        ] {
+            assert_ne!(standard.impact(kind), colorblind.impact(kind), "{:?}", kind);
+        }
+        assert_ne!(standard.spell_fire, colorblind.spell_fire);
+        assert_ne!(standard.spell_core, colorblind.spell_core);
+        assert_ne!(standard.enemy_death, colorblind.enemy_death);
+
+        // Fire and enemy deaths no longer split by red against green: the fire has
+        // more green than pure red, and the death burst is mostly blue
+        assert!(colorblind.spell_fire[1].y > 0.5);
+        assert!(colorblind.spell_core.g() > 0.5);
+        assert!(colorblind.enemy_death.z > colorblind.enemy_death.y);
+    }
+
+    #[test]
+    fn test_settings_stored_in_config() {
+        let mut app = App::new();
+        app.init_resource::<AccessibilitySettings>()
+            .init_resource::<GameConfig>()
+            .add_systems(Update, store_accessibility_settings);
+        app.update();
+        app.world
+            .resource_mut::<AccessibilitySettings>()
+            .colorblind_mode = true;
+        app.update();
+        assert!(
+            app.world
+                .resource::<GameConfig>()
+                .accessibility
+                .colorblind_mode
+        );
+    }
+}
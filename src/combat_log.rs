@@ -0,0 +1,263 @@
+// combat_log.rs
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::enemy::{Damaged, EnemyKilled};
+use crate::score::CollectiblePicked;
+
+/// CombatLogPlugin keeps a feed of recent gameplay events in the bottom-left corner.
+///
+/// Damage dealt and taken, enemies killed and items picked up are read from their
+/// events and written to the `CombatLog` as one line each. Only the last
+/// `COMBAT_LOG_LENGTH` lines are kept, the oldest dropped first. Each line stays up
+/// for `COMBAT_LOG_SECONDS`, fading out over the last `COMBAT_LOG_FADE_SECONDS`,
+/// so a quiet moment clears the feed.
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLog>()
+            .add_systems(Startup, setup_combat_log)
+            .add_systems(
+                Update,
+                (
+                    record_combat_events,
+                    age_combat_log.after(record_combat_events),
+                    update_combat_log_text.after(age_combat_log),
+                ),
+            );
+    }
+}
+
+/// Marker for the text showing the combat log.
+#[derive(Component)]
+struct CombatLogText;
+
+/// A line in the combat log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombatLogEntry {
+    pub text: String,
+    /// Seconds since the line was written.
+    pub age: f32,
+}
+
+impl CombatLogEntry {
+    /// Opacity of the line at its age.
+    ///
+    /// # Returns
+    /// 1.0 until the last `COMBAT_LOG_FADE_SECONDS`, then falling to 0.0 as the line
+    /// reaches `COMBAT_LOG_SECONDS`.
+    pub fn alpha(&self) -> f32 {
+        ((COMBAT_LOG_SECONDS - self.age) / COMBAT_LOG_FADE_SECONDS).clamp(0.0, 1.0)
+    }
+}
+
+/// The most recent lines of the combat log, oldest first, holding at most
+/// `capacity` of them.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct CombatLog {
+    entries: VecDeque<CombatLogEntry>,
+    capacity: usize,
+}
+
+impl Default for CombatLog {
+    fn default() -> Self {
+        CombatLog::new(COMBAT_LOG_LENGTH)
+    }
+}
+
+impl CombatLog {
+    /// Creates an empty log keeping up to `capacity` lines.
+    pub fn new(capacity: usize) -> Self {
+        CombatLog {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Writes a line, dropping the oldest lines past the capacity.
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.entries.push_back(CombatLogEntry {
+            text: text.into(),
+            age: 0.0,
+        });
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Ages every line by `seconds`, dropping the lines that have faded out.
+    pub fn age(&mut self, seconds: f32) {
+        for entry in self.entries.iter_mut() {
+            entry.age += seconds;
+        }
+        self.entries.retain(|entry| entry.age < COMBAT_LOG_SECONDS);
+    }
+
+    /// The lines kept, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &CombatLogEntry> {
+        self.entries.iter()
+    }
+
+    /// Checks if there are no lines to show.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Spawns the combat log text in the bottom-left corner.
+fn setup_combat_log(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::default().with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(HUD_MARGIN),
+            bottom: Val::Px(HUD_MARGIN),
+            ..default()
+        }),
+        CombatLogText,
+        Name::new("Combat log"),
+    ));
+}
+
+/// Writes a line to the combat log for each hit, kill and pickup.
+///
+/// # Arguments
+/// * `log` - Resource receiving the lines.
+/// * `damaged_events` - Event reader for enemies and players being hurt.
+/// * `killed_events` - Event reader for enemies being killed.
+/// * `picked_events` - Event reader for collectibles being picked up.
+/// * `player_query` - Query used to tell damage taken from damage dealt.
+///
+fn record_combat_events(
+    mut log: ResMut<CombatLog>,
+    mut damaged_events: EventReader<Damaged>,
+    mut killed_events: EventReader<EnemyKilled>,
+    mut picked_events: EventReader<CollectiblePicked>,
+    player_query: Query<(), With<Player>>,
+) {
+    for damaged in damaged_events.iter() {
+        if player_query.contains(damaged.target) {
+            log.push(format!("Took {:.1} damage", damaged.amount));
+        } else {
+            log.push(format!("Hit enemy for {:.1}", damaged.amount));
+        }
+    }
+    for _ in killed_events.iter() {
+        log.push("Enemy killed");
+    }
+    for picked in picked_events.iter() {
+        log.push(format!("Picked up {} points", picked.collectible.value));
+    }
+}
+
+/// Ages the combat log's lines, fading them out.
+fn age_combat_log(time: Res<Time>, mut log: ResMut<CombatLog>) {
+    // Nothing to fade, so leave the log unchanged and the text alone
+    if log.is_empty() {
+        return;
+    }
+    log.age(time.delta_seconds());
+}
+
+/// Redraws the combat log text whenever the log changes, one line per entry.
+fn update_combat_log_text(log: Res<CombatLog>, mut query: Query<&mut Text, With<CombatLogText>>) {
+    if !log.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections = log
+            .entries()
+            .map(|entry| {
+                TextSection::new(
+                    format!("{}\n", entry.text),
+                    TextStyle {
+                        font_size: COMBAT_LOG_FONT_SIZE,
+                        color: Color::rgba(1.0, 1.0, 1.0, entry.alpha()),
+                        ..default()
+                    },
+                )
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of every line in `log`, oldest first.
+    fn lines(log: &CombatLog) -> Vec<String> {
+        log.entries().map(|entry| entry.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_log_caps_length_dropping_oldest() {
+        let mut log = CombatLog::new(3);
+        for line in ["one", "two", "three"] {
+            log.push(line);
+        }
+        assert_eq!(lines(&log), vec!["one", "two", "three"]);
+
+        log.push("four");
+        log.push("five");
+        assert_eq!(lines(&log), vec!["three", "four", "five"]);
+
+        // The default log holds the configured number of lines
+        let mut log = CombatLog::default();
+        for index in 0..COMBAT_LOG_LENGTH + 2 {
+            log.push(index.to_string());
+        }
+        assert_eq!(log.entries().count(), COMBAT_LOG_LENGTH);
+        assert_eq!(log.entries().next().unwrap().text, "2");
+    }
+
+    #[test]
+    fn test_old_lines_fade_then_drop() {
+        let mut log = CombatLog::default();
+        log.push("old");
+        log.age(COMBAT_LOG_SECONDS - COMBAT_LOG_FADE_SECONDS / 2.0);
+        log.push("new");
+        let alphas: Vec<f32> = log.entries().map(CombatLogEntry::alpha).collect();
+        assert!((alphas[0] - 0.5).abs() < 1e-4);
+        assert_eq!(alphas[1], 1.0);
+
+        log.age(COMBAT_LOG_FADE_SECONDS);
+        assert_eq!(lines(&log), vec!["new"]);
+    }
+
+    #[test]
+    fn test_records_combat_events() {
+        let mut app = App::new();
+        app.init_resource::<CombatLog>()
+            .add_event::<Damaged>()
+            .add_event::<EnemyKilled>()
+            .add_event::<CollectiblePicked>()
+            .add_systems(Update, record_combat_events);
+        let player = app.world.spawn(Player).id();
+        let enemy = app.world.spawn(Enemy).id();
+        app.world.send_event(Damaged {
+            target: enemy,
+            amount: 2.0,
+        });
+        app.world.send_event(Damaged {
+            target: player,
+            amount: 1.0,
+        });
+        app.world.send_event(EnemyKilled { enemy });
+        app.world.send_event(CollectiblePicked {
+            collectible: Collectible { value: 50 },
+        });
+        app.update();
+        assert_eq!(
+            lines(app.world.resource::<CombatLog>()),
+            vec![
+                "Hit enemy for 2.0",
+                "Took 1.0 damage",
+                "Enemy killed",
+                "Picked up 50 points"
+            ]
+        );
+    }
+}
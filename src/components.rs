@@ -1,28 +1,64 @@
 // components.rs
 
-use bevy::prelude::{Bundle, Component, SpriteSheetBundle, Timer, TimerMode};
+use bevy::prelude::{
+    Bundle, Component, Entity, IVec2, KeyCode, Reflect, ReflectComponent, SpriteSheetBundle, Timer,
+    TimerMode, Vec2, Vec3,
+};
+use bevy_ecs_ldtk::ldtk::{EntityInstance, FieldValue};
 use bevy_ecs_ldtk::{GridCoords, LdtkEntity, LdtkIntCell};
+use bevy_rapier2d::prelude::{CollisionGroups, Group, SolverGroups};
 
 use crate::constants::*;
+use crate::util::ldtk_field;
 
 /// Plugin responsible for adding player-related systems to the game.
 pub struct PlayerPlugin;
 
 /// Component representing the player entity.
 /// This component is used to identify and interact with the player in the game world.
-#[derive(Default, Component, Debug)]
+#[derive(Default, Component, Debug, Reflect)]
+#[reflect(Component)]
 pub struct Player;
 
+/// Component telling local co-op players apart. Player one, `PlayerId::ONE`, is
+/// the one spawned from LDtk; a player without a `PlayerId` counts as player one.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub struct PlayerId(pub usize);
+
+impl PlayerId {
+    /// The player spawned with the level, using `KeyBindings`.
+    pub const ONE: PlayerId = PlayerId(0);
+    /// The player who joins with F5, using `CoopBindings`.
+    pub const TWO: PlayerId = PlayerId(1);
+
+    /// The id of a player that may not have one: player one if it doesn't.
+    pub fn of(id: Option<&PlayerId>) -> PlayerId {
+        id.copied().unwrap_or(PlayerId::ONE)
+    }
+}
+
 /// Component for handling sprite animation.
 ///
 /// Contains a list of frame indices for the animation and a timer to control the
-/// frame rate of the animation.
-#[derive(Component)]
+/// frame rate of the animation. One-shot (non-looping) animations stop on their
+/// last frame and set `finished`.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Animation {
     /// Indices of the frames in the sprite sheet used for animation.
     pub frames: Vec<usize>,
     /// Timer to control when the frame should be updated.
     pub timer: Timer,
+    /// Whether the animation wraps around to the first frame after the last.
+    pub looping: bool,
+    /// Set once a one-shot animation has shown its last frame for a full tick.
+    pub finished: bool,
+    /// Position in `frames` of the frame being shown.
+    pub current: usize,
+    /// Positions in `frames` that send an `AnimationFrameReached` when shown, for
+    /// footsteps, hitboxes or sound effects.
+    pub event_frames: Vec<usize>,
 }
 
 /// Bundle for creating an animation component.
@@ -32,26 +68,256 @@ impl Default for Animation {
         Animation {
             frames: Default::default(),
             timer: Timer::from_seconds(SPRITE_ANIMATION_SPEED, TimerMode::Repeating),
+            looping: true,
+            finished: false,
+            current: 0,
+            event_frames: Vec::new(),
+        }
+    }
+}
+
+impl Animation {
+    /// Creates a one-shot animation that stops on its last frame.
+    pub fn once(frames: Vec<usize>) -> Self {
+        Animation {
+            frames,
+            looping: false,
+            ..Default::default()
+        }
+    }
+
+    /// Steps to the next frame and returns its sprite index.
+    ///
+    /// Looping animations wrap around to the first frame. One-shot animations stay
+    /// on their last frame and mark themselves `finished`, straight away if they
+    /// have no frames at all.
+    pub fn advance(&mut self) -> Option<usize> {
+        let Some(last) = self.frames.len().checked_sub(1) else {
+            self.finished = !self.looping;
+            return None;
+        };
+        if self.current < last {
+            self.current += 1;
+        } else if self.looping {
+            self.current = 0;
+        } else {
+            self.finished = true;
+        }
+        Some(self.frames[self.current])
+    }
+
+    /// Goes back to the first frame with a fresh timer, as if the animation had
+    /// just started.
+    ///
+    /// # Returns
+    /// The sprite index of the first frame, or `None` if there are no frames.
+    pub fn restart(&mut self) -> Option<usize> {
+        self.timer.reset();
+        self.current = 0;
+        self.finished = false;
+        self.frames.first().copied()
+    }
+
+    /// Checks if the frame being shown is one of the `event_frames`.
+    pub fn on_event_frame(&self) -> bool {
+        self.event_frames.contains(&self.current)
+    }
+}
+
+/// Component holding which way the player last moved.
+///
+/// Moving with any sideways part faces left or right, which flips the sprite;
+/// moving straight up or down faces up or down and leaves the flip alone.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+    Up,
+    Down,
+}
+
+impl Facing {
+    /// The facing for a move along `direction`, or `None` when not moving.
+    pub fn from_direction(direction: Vec2) -> Option<Facing> {
+        if direction.x < 0.0 {
+            Some(Facing::Left)
+        } else if direction.x > 0.0 {
+            Some(Facing::Right)
+        } else if direction.y > 0.0 {
+            Some(Facing::Up)
+        } else if direction.y < 0.0 {
+            Some(Facing::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// Component holding the player's current animation state.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationState {
+    /// The regular animation cycle.
+    #[default]
+    Idle,
+    /// Playing the one-shot cast animation; movement is unaffected.
+    Casting,
+    /// Playing the one-shot death animation; input is disabled.
+    Dying,
+}
+
+/// Component holding an entity's hit points.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            current: PLAYER_HEALTH_MAX,
+            max: PLAYER_HEALTH_MAX,
+        }
+    }
+}
+
+impl Health {
+    /// Checks if the hit points have run out.
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Component holding the stats a player starts a level with.
+///
+/// Built from the LDtk "Player" entity's optional `speed` and `health` (float)
+/// custom fields, so designers can set them per level, falling back to
+/// `PLAYER_SPRITE_SPEED` and `PLAYER_HEALTH_MAX`. Values that aren't positive are
+/// ignored.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerStats {
+    /// Top walking speed, in pixels per second, before difficulty scaling.
+    pub speed: f32,
+    /// Hit points the player starts with.
+    pub health: f32,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats {
+            speed: PLAYER_SPRITE_SPEED,
+            health: PLAYER_HEALTH_MAX,
         }
     }
 }
 
+impl PlayerStats {
+    /// Full health at the starting hit points.
+    pub fn starting_health(&self) -> Health {
+        Health {
+            current: self.health,
+            max: self.health,
+        }
+    }
+}
+
+impl From<&EntityInstance> for PlayerStats {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let fields = &entity_instance.field_instances;
+        let mut stats = PlayerStats::default();
+        if let Some(FieldValue::Float(Some(speed))) = ldtk_field(fields, PLAYER_SPEED_FIELD) {
+            if *speed > 0.0 {
+                stats.speed = *speed;
+            }
+        }
+        if let Some(FieldValue::Float(Some(health))) = ldtk_field(fields, PLAYER_HEALTH_FIELD) {
+            if *health > 0.0 {
+                stats.health = *health;
+            }
+        }
+        stats
+    }
+}
+
+/// The player starts each level with the health its LDtk entity sets.
+fn player_health(entity_instance: &EntityInstance) -> Health {
+    PlayerStats::from(entity_instance).starting_health()
+}
+
 /// Bundle for creating a player entity.
 /// Groups all necessary components for a player entity, including sprite, grid position, and animation.
 #[derive(Default, Bundle, LdtkEntity)]
 pub struct PlayerBundle {
     pub player: Player,
+    pub player_id: PlayerId,
+    pub facing: Facing,
+    #[from_entity_instance]
+    pub stats: PlayerStats,
+    #[with(player_health)]
+    pub health: Health,
+    pub animation_state: AnimationState,
+    pub velocity: Velocity2d,
+    pub interpolated: Interpolated,
     #[sprite_sheet_bundle]
     pub sprite_bundle: SpriteSheetBundle,
     #[grid_coords]
     pub grid_coords: GridCoords,
 }
 
+/// Component holding how fast the player is walking, in pixels per second.
+/// Input accelerates it toward full speed and friction slows it to a stop.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq)]
+pub struct Velocity2d(pub Vec2);
+
+/// Component smoothing an entity's movement between fixed gameplay steps.
+///
+/// Gameplay moves the entity's `Transform` in `FixedUpdate`. The translations left
+/// by the last two steps are kept here so each frame can be drawn partway between them.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq)]
+pub struct Interpolated {
+    /// Translation after the step before last.
+    pub previous: Vec3,
+    /// Translation after the last step, where gameplay sees the entity.
+    pub current: Vec3,
+    /// Translation last written to the `Transform`, used to spot moves made
+    /// outside the fixed steps.
+    pub rendered: Vec3,
+}
+
+impl Interpolated {
+    /// Jumps straight to `translation` without sliding there.
+    pub fn snap_to(&mut self, translation: Vec3) {
+        self.previous = translation;
+        self.current = translation;
+        self.rendered = translation;
+    }
+
+    /// The translation `fraction` of the way from the step before last to the last step.
+    pub fn translation_at(&self, fraction: f32) -> Vec3 {
+        self.previous.lerp(self.current, fraction)
+    }
+}
+
+/// Component marking where the player appears, placed in LDtk separately from the player.
+#[derive(Default, Component, Debug)]
+pub struct PlayerSpawn;
+
+/// Bundle for creating a player spawn marker.
+/// Only the marker's grid position matters; it isn't drawn.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct PlayerSpawnBundle {
+    pub player_spawn: PlayerSpawn,
+    #[grid_coords]
+    pub grid_coords: GridCoords,
+}
+
 /// Plugin responsible for adding map-related systems to the game.
 pub struct MapPlugin;
 
 /// Component representing a wall in the game world.
-#[derive(Default, Component)]
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
 pub struct Wall;
 
 /// Bundle for creating a wall entity.
@@ -61,10 +327,728 @@ pub struct WallBundle {
     pub wall: Wall,
 }
 
+/// Marker for a wall's collider: a merged rectangle of wall cells, or the one-cell
+/// collider of a breakable or moving wall.
+#[derive(Default, Component, Debug)]
+pub struct WallCollider;
+
+/// Component for a wall that spells can break down, opening a passage.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Breakable {
+    /// Damage the wall can take before it breaks.
+    pub health: f32,
+}
+
+impl Default for Breakable {
+    fn default() -> Self {
+        Breakable {
+            health: WALL_BREAKABLE_HEALTH,
+        }
+    }
+}
+
+/// Bundle for creating a breakable wall entity.
+/// A wall like any other until spells have broken it.
+#[derive(Default, Bundle, LdtkIntCell)]
+pub struct BreakableWallBundle {
+    pub wall: Wall,
+    pub breakable: Breakable,
+}
+
+/// Which side of a fight a collider is on, deciding what it collides with.
+///
+/// Spells pass through whoever cast them and their allies, but hit walls and the
+/// other side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionLayer {
+    Wall,
+    Player,
+    Enemy,
+    PlayerSpell,
+    EnemySpell,
+}
+
+impl CollisionLayer {
+    /// The collision group colliders on this layer belong to.
+    pub fn membership(self) -> Group {
+        match self {
+            CollisionLayer::Wall => COLLISION_GROUP_WALL,
+            CollisionLayer::Player => COLLISION_GROUP_PLAYER,
+            CollisionLayer::Enemy => COLLISION_GROUP_ENEMY,
+            CollisionLayer::PlayerSpell => COLLISION_GROUP_PLAYER_SPELL,
+            CollisionLayer::EnemySpell => COLLISION_GROUP_ENEMY_SPELL,
+        }
+    }
+
+    /// The collision groups colliders on this layer collide with.
+    pub fn filter(self) -> Group {
+        match self {
+            CollisionLayer::Wall => {
+                COLLISION_GROUP_PLAYER
+                    | COLLISION_GROUP_ENEMY
+                    | COLLISION_GROUP_PLAYER_SPELL
+                    | COLLISION_GROUP_ENEMY_SPELL
+            }
+            CollisionLayer::Player => {
+                COLLISION_GROUP_WALL | COLLISION_GROUP_ENEMY | COLLISION_GROUP_ENEMY_SPELL
+            }
+            CollisionLayer::Enemy => {
+                COLLISION_GROUP_WALL | COLLISION_GROUP_PLAYER | COLLISION_GROUP_PLAYER_SPELL
+            }
+            CollisionLayer::PlayerSpell => COLLISION_GROUP_WALL | COLLISION_GROUP_ENEMY,
+            CollisionLayer::EnemySpell => COLLISION_GROUP_WALL | COLLISION_GROUP_PLAYER,
+        }
+    }
+
+    /// Rapier components putting a collider on this layer, for both contact
+    /// events and the contact forces between bodies.
+    pub fn groups(self) -> (CollisionGroups, SolverGroups) {
+        (
+            CollisionGroups::new(self.membership(), self.filter()),
+            SolverGroups::new(self.membership(), self.filter()),
+        )
+    }
+}
+
+/// Plugin responsible for walls that move, like elevators and sliding blocks.
+pub struct MovingWallPlugin;
+
+/// Component for a wall that moves through its waypoints on a cycle.
+///
+/// Built from the LDtk "MovingWall" entity's `waypoints` (points) and `speed`
+/// (cells per second) custom fields. Unlike `Wall`, it isn't part of `LevelWalls`.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct MovingWall {
+    /// Cells the wall moves through in order, looping back to the first, as offsets
+    /// in cells from where it starts, with y pointing up. The first is always its start.
+    pub waypoints: Vec<IVec2>,
+    /// How fast the wall moves, in cells per second.
+    pub speed: f32,
+    /// Index of the waypoint the wall is heading for.
+    pub next: usize,
+    /// Translation the wall started at, recorded the first time it moves.
+    pub start: Option<Vec2>,
+}
+
+impl Default for MovingWall {
+    fn default() -> Self {
+        MovingWall {
+            waypoints: vec![IVec2::ZERO],
+            speed: MOVING_WALL_SPEED,
+            next: 0,
+            start: None,
+        }
+    }
+}
+
+impl MovingWall {
+    /// Moves a wall at `position` `distance` pixels along its waypoints, turning
+    /// the corner at each one it reaches.
+    ///
+    /// # Arguments
+    /// * `start` - Translation the wall started at, which the waypoints are offsets from.
+    /// * `position` - Translation the wall is at now.
+    /// * `distance` - How far to move, in pixels.
+    /// * `cell_size` - Size of a cell, in pixels.
+    ///
+    /// # Returns
+    /// The wall's new translation.
+    pub fn advance(&mut self, start: Vec2, position: Vec2, distance: f32, cell_size: f32) -> Vec2 {
+        let mut position = position;
+        let mut distance = distance;
+        // Each waypoint is reached at most once per step, so a path of zero length can't spin
+        for _ in 0..self.waypoints.len() {
+            let target = start + self.waypoints[self.next].as_vec2() * cell_size;
+            let gap = position.distance(target);
+            if gap > distance {
+                return position + (target - position) / gap * distance;
+            }
+            position = target;
+            distance -= gap;
+            self.next = (self.next + 1) % self.waypoints.len();
+        }
+        position
+    }
+}
+
+impl From<&EntityInstance> for MovingWall {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let fields = &entity_instance.field_instances;
+        let mut waypoints = vec![IVec2::ZERO];
+        if let Some(FieldValue::Points(points)) = ldtk_field(fields, MOVING_WALL_WAYPOINTS_FIELD) {
+            // LDtk counts cells from the top, so flip the offsets to point y up
+            waypoints.extend(points.iter().flatten().map(|point| {
+                let offset = *point - entity_instance.grid;
+                IVec2::new(offset.x, -offset.y)
+            }));
+        }
+        let speed = match ldtk_field(fields, MOVING_WALL_SPEED_FIELD) {
+            Some(FieldValue::Float(Some(speed))) => *speed,
+            _ => MOVING_WALL_SPEED,
+        };
+        MovingWall {
+            next: 1 % waypoints.len(),
+            waypoints,
+            speed,
+            start: None,
+        }
+    }
+}
+
+/// Bundle for creating a moving wall entity.
+/// Groups the wall's path with its sprite, and smooths its movement between steps.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct MovingWallBundle {
+    #[from_entity_instance]
+    pub moving_wall: MovingWall,
+    #[sprite_sheet_bundle]
+    pub sprite_bundle: SpriteSheetBundle,
+    pub interpolated: Interpolated,
+}
+
 /// Plugin responsible for adding spell_fire-related systems to the game.
 pub struct SpellFirePlugin;
 
 /// Component representing a Spell Fire entity.
 /// This component is used to identify and interact with spell_fire entities in the game world.
-#[derive(Default, Component, Debug)]
+#[derive(Default, Component, Debug, Reflect)]
+#[reflect(Component)]
 pub struct SpellFire;
+
+/// Component for a spell projectile in flight.
+/// Moves the projectile each frame and despawns it when it has flown further than
+/// its range. Its lifetime is kept by a `DespawnTimer`.
+#[derive(Component, Debug)]
+pub struct SpellProjectile {
+    /// Velocity of the projectile, in pixels per second.
+    pub velocity: Vec2,
+    /// Damage dealt on hit.
+    pub damage: f32,
+    /// Distance flown so far along the projectile's path, in pixels, including
+    /// every turn a homing spell makes.
+    pub traveled: f32,
+    /// How far the projectile flies before it fizzles out, in pixels.
+    pub range: f32,
+}
+
+impl SpellProjectile {
+    /// Checks if the projectile has flown further than its range.
+    pub fn out_of_range(&self) -> bool {
+        self.traveled > self.range
+    }
+}
+
+/// Component for a spell projectile that curves toward the nearest enemy.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Homing {
+    /// Enemies further away than this (in pixels) are ignored.
+    pub radius: f32,
+    /// Maximum rate the projectile can turn, in radians per second.
+    pub turn_rate: f32,
+}
+
+impl Default for Homing {
+    fn default() -> Self {
+        Homing {
+            radius: SPELL_HOMING_RADIUS,
+            turn_rate: SPELL_HOMING_TURN_RATE,
+        }
+    }
+}
+
+/// Component letting a projectile pass through enemies.
+/// Projectiles without it are used up by the first enemy they hit.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Piercing {
+    /// How many more enemies the projectile can damage before it's used up.
+    pub hits_remaining: usize,
+    /// Enemies already damaged, which the projectile passes through.
+    pub already_hit: Vec<Entity>,
+}
+
+impl Piercing {
+    pub fn new(hits: usize) -> Self {
+        Piercing {
+            hits_remaining: hits,
+            already_hit: Vec::new(),
+        }
+    }
+}
+
+/// Component for a spell projectile that slows the enemies it hits.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Freezing {
+    /// Multiplier applied to a hit enemy's speed, from 0.0 (frozen) to 1.0.
+    pub factor: f32,
+    /// How long the slow lasts, in seconds.
+    pub seconds: f32,
+}
+
+impl Default for Freezing {
+    fn default() -> Self {
+        Freezing {
+            factor: SPELL_ICE_SLOW_FACTOR,
+            seconds: SPELL_ICE_SLOW_SECONDS,
+        }
+    }
+}
+
+/// Component for a spell projectile that explodes where it lands, damaging every
+/// enemy within its radius instead of the one it hit.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Explosive {
+    /// Enemies further away than this (in cells) are unharmed.
+    pub radius: f32,
+}
+
+impl Default for Explosive {
+    fn default() -> Self {
+        Explosive {
+            radius: SPELL_EXPLOSION_RADIUS,
+        }
+    }
+}
+
+/// Component for a patch of burning ground, like a firewall, hurting whatever
+/// stands in its cells each time its `tick` comes round. It lasts as long as its
+/// `DespawnTimer`.
+#[derive(Component, Debug, Clone)]
+pub struct DamageField {
+    /// The cells that burn, in world grid coordinates.
+    pub cells: Vec<GridCoords>,
+    /// Damage dealt to each thing in the cells every tick.
+    pub damage: f32,
+    pub tick: Timer,
+    /// Whether the player is hurt too, not only enemies.
+    pub hurts_player: bool,
+}
+
+impl DamageField {
+    /// Creates a field over `cells`, dealing `damage` every `interval` seconds.
+    pub fn new(cells: Vec<GridCoords>, damage: f32, interval: f32, hurts_player: bool) -> Self {
+        DamageField {
+            cells,
+            damage,
+            tick: Timer::from_seconds(interval, TimerMode::Repeating),
+            hurts_player,
+        }
+    }
+
+    /// Checks if `cell` is one of the burning cells.
+    pub fn covers(&self, cell: GridCoords) -> bool {
+        self.cells.contains(&cell)
+    }
+}
+
+/// Component pushing the player back after casting a spell.
+/// Decays quickly and is removed once it has died down.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Recoil {
+    /// Current recoil velocity, in pixels per second.
+    pub velocity: Vec2,
+}
+
+/// Component tracking a spell being charged while its cast key is held.
+/// Added to the player on key press and removed when the spell is released.
+#[derive(Component, Debug)]
+pub struct SpellCharge {
+    /// The cast key being held.
+    pub key: KeyCode,
+    /// Direction the spell will be fired in.
+    pub direction: Vec2,
+    /// Time the key has been held, capped at full charge.
+    pub timer: Timer,
+}
+
+impl SpellCharge {
+    pub fn new(key: KeyCode, direction: Vec2) -> Self {
+        SpellCharge {
+            key,
+            direction,
+            timer: Timer::from_seconds(SPELL_FIRE_MAX_CHARGE_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Plugin responsible for adding door-related systems to the game.
+pub struct DoorPlugin;
+
+/// Component representing a door that teleports the player to another level.
+///
+/// Built from the LDtk "Door" entity's `target_level` (level IID) and
+/// `target_cell` (point) custom fields.
+#[derive(Default, Component, Debug, Clone, PartialEq)]
+pub struct Door {
+    /// IID of the level the door leads to.
+    pub target_level: String,
+    /// Cell in the target level, in LDtk coordinates (y pointing down).
+    pub target_cell: IVec2,
+}
+
+impl From<&EntityInstance> for Door {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let fields = &entity_instance.field_instances;
+        let target_level = match ldtk_field(fields, DOOR_TARGET_LEVEL_FIELD) {
+            Some(FieldValue::String(Some(iid))) => iid.clone(),
+            _ => String::new(),
+        };
+        let target_cell = match ldtk_field(fields, DOOR_TARGET_CELL_FIELD) {
+            Some(FieldValue::Point(Some(cell))) => *cell,
+            _ => IVec2::ZERO,
+        };
+        Door {
+            target_level,
+            target_cell,
+        }
+    }
+}
+
+/// Bundle for creating a door entity.
+/// Groups the door's target with its sprite and grid position.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[from_entity_instance]
+    pub door: Door,
+    #[with(door_interactable)]
+    pub interactable: Interactable,
+    #[sprite_sheet_bundle]
+    pub sprite_bundle: SpriteSheetBundle,
+    #[grid_coords]
+    pub grid_coords: GridCoords,
+}
+
+/// Plugin responsible for adding minimap-related systems to the game.
+pub struct MinimapPlugin;
+
+/// Plugin responsible for adding enemy-related systems to the game.
+pub struct EnemyPlugin;
+
+/// Plugin responsible for capturing screenshots.
+pub struct ScreenshotPlugin;
+
+/// Plugin responsible for choosing the game's difficulty.
+pub struct DifficultyPlugin;
+
+/// Plugin responsible for the score and the persisted high score.
+pub struct ScorePlugin;
+
+/// Plugin responsible for saving the session to a save slot and restoring it.
+pub struct SavePlugin;
+
+/// Plugin responsible for the heads-up display.
+pub struct HudPlugin;
+
+/// Plugin responsible for switching between playing, game over and the main menu.
+pub struct GameStatePlugin;
+
+/// Plugin responsible for loading, applying and saving the player's settings.
+pub struct SettingsPlugin;
+
+/// Plugin responsible for the settings menu.
+pub struct SettingsMenuPlugin;
+
+/// Plugin responsible for music and sound effect volumes.
+pub struct SoundPlugin;
+
+/// Plugin responsible for the fixed gameplay step and smoothing movement between steps.
+pub struct InterpolationPlugin;
+
+/// Plugin responsible for the seeded random number generator used by gameplay.
+pub struct RngPlugin;
+
+/// Plugin responsible for recording runs and replaying them.
+pub struct ReplayPlugin;
+
+/// Plugin responsible for bloom and tonemapping settings.
+pub struct VisualsPlugin;
+
+/// Plugin responsible for registering components and resources for reflection, so
+/// the world inspector shows their fields.
+pub struct InspectablePlugin;
+
+/// Plugin responsible for despawning short-lived entities once their time is up.
+pub struct DespawnPlugin;
+
+/// Plugin responsible for cycling animated background tiles, like torches and water.
+pub struct TileAnimationPlugin;
+
+/// Plugin responsible for darkening the edges of the screen as the player's health drops.
+pub struct VignettePlugin;
+
+/// Plugin responsible for accessibility settings, like the colorblind palette.
+pub struct AccessibilityPlugin;
+
+/// Plugin responsible for diagnostics counting live spells, wall colliders and enemies.
+pub struct EntityDiagnosticsPlugin;
+
+/// Plugin responsible for briefly freezing the game when a hit lands hard.
+pub struct HitStopPlugin;
+
+/// Plugin responsible for the feed of recent gameplay events.
+pub struct CombatLogPlugin;
+
+/// Plugin responsible for moving through the menus with the keyboard or a gamepad.
+pub struct MenuNavigationPlugin;
+
+/// Plugin responsible for applying the physics settings to Rapier.
+pub struct PhysicsPlugin;
+
+/// Plugin responsible for the practice range's target dummies.
+pub struct TargetDummyPlugin;
+
+/// Component that despawns its entity, along with its children, once the timer
+/// finishes.
+#[derive(Component, Debug)]
+pub struct DespawnTimer {
+    pub timer: Timer,
+}
+
+impl DespawnTimer {
+    /// Despawns the entity after `seconds`.
+    pub fn new(seconds: f32) -> Self {
+        DespawnTimer {
+            timer: Timer::from_seconds(seconds, TimerMode::Once),
+        }
+    }
+}
+
+/// Plugin responsible for the player interacting with things next to it.
+pub struct InteractionPlugin;
+
+/// Component for something the player can use with the interact key while standing
+/// on or next to it, like a door.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Interactable {
+    /// What interacting does, shown in the prompt.
+    pub prompt: String,
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Interactable {
+            prompt: INTERACT_DEFAULT_PROMPT.to_string(),
+        }
+    }
+}
+
+/// Doors are entered by interacting with them, as well as by stepping on them.
+fn door_interactable(_: &EntityInstance) -> Interactable {
+    Interactable {
+        prompt: DOOR_INTERACT_PROMPT.to_string(),
+    }
+}
+
+/// Component holding the damage an entity does to the player on contact.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ContactDamage(pub f32);
+
+/// Component representing an enemy entity.
+#[derive(Default, Component, Debug)]
+pub struct Enemy;
+
+/// Component holding what an enemy is doing.
+#[derive(Default, Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnemyState {
+    /// Standing still, waiting for the player or the urge to patrol.
+    #[default]
+    Idle,
+    /// Wandering to `EnemyAi::patrol_target` near its spawn.
+    Patrol,
+    /// Closing in on the player.
+    Chase,
+    /// Heading back to its spawn after the player got away.
+    Return,
+}
+
+/// Component driving how an enemy notices, follows and gives up on the player.
+#[derive(Component, Debug, Clone)]
+pub struct EnemyAi {
+    /// Cell the enemy spawned in, which it patrols around and returns to.
+    pub origin: GridCoords,
+    /// Distance within which the enemy starts chasing the player, in cells.
+    pub detection_radius: f32,
+    /// Distance beyond which the enemy stops chasing and returns, in cells.
+    pub leash_radius: f32,
+    /// Cell the enemy is patrolling to, if it's patrolling.
+    pub patrol_target: Option<GridCoords>,
+    /// Time between steps from one cell to the next.
+    pub step_timer: Timer,
+}
+
+impl EnemyAi {
+    pub fn new(origin: GridCoords) -> Self {
+        EnemyAi {
+            origin,
+            detection_radius: ENEMY_DETECTION_RADIUS,
+            leash_radius: ENEMY_LEASH_RADIUS,
+            patrol_target: None,
+            step_timer: Timer::from_seconds(ENEMY_STEP_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Component slowing an enemy hit by a `Freezing` spell, removed when it wears off.
+/// Further hits stack the slow, down to `ENEMY_SLOW_MIN_FACTOR`, and restart the timer.
+#[derive(Component, Debug, Clone)]
+pub struct Slowed {
+    /// Time left before the slow wears off.
+    pub timer: Timer,
+    /// Multiplier applied to the enemy's speed.
+    pub factor: f32,
+}
+
+impl Slowed {
+    pub fn new(freezing: &Freezing) -> Self {
+        Slowed {
+            timer: Timer::from_seconds(freezing.seconds, TimerMode::Once),
+            factor: freezing.factor.clamp(ENEMY_SLOW_MIN_FACTOR, 1.0),
+        }
+    }
+
+    /// Slows the enemy further for another hit, and restarts the timer.
+    pub fn stack(&mut self, freezing: &Freezing) {
+        self.factor = (self.factor * freezing.factor).clamp(ENEMY_SLOW_MIN_FACTOR, 1.0);
+        self.timer = Timer::from_seconds(
+            freezing.seconds.max(self.timer.remaining_secs()),
+            TimerMode::Once,
+        );
+    }
+}
+
+/// Component for an enemy that shoots at the player instead of closing to melee.
+#[derive(Component, Debug, Clone)]
+pub struct RangedAttack {
+    /// Furthest the enemy shoots from, in cells.
+    pub range: f32,
+    /// Damage dealt by each projectile.
+    pub damage: f32,
+    /// Time until the enemy can shoot again.
+    pub cooldown: Timer,
+}
+
+impl Default for RangedAttack {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(ENEMY_RANGED_COOLDOWN, TimerMode::Once);
+        // Ready to shoot as soon as the player shows up
+        cooldown.tick(cooldown.duration());
+        RangedAttack {
+            range: ENEMY_RANGED_RANGE,
+            damage: ENEMY_PROJECTILE_DAMAGE,
+            cooldown,
+        }
+    }
+}
+
+/// Marker for a projectile shot by an enemy, which only damages the player.
+#[derive(Default, Component, Debug)]
+pub struct EnemyProjectile;
+
+/// Component holding what an enemy may drop when it dies.
+///
+/// Built from the LDtk "Enemy" entity's optional `loot_chance` (float) and
+/// `loot_value` (int) custom fields, falling back to `ENEMY_LOOT_CHANCE` and
+/// `ENEMY_LOOT_VALUE`.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct LootDrop {
+    /// Probability of dropping a `Collectible`, from 0.0 (never) to 1.0 (always).
+    pub chance: f32,
+    /// Points the dropped `Collectible` is worth.
+    pub value: u32,
+}
+
+impl Default for LootDrop {
+    fn default() -> Self {
+        LootDrop {
+            chance: ENEMY_LOOT_CHANCE,
+            value: ENEMY_LOOT_VALUE,
+        }
+    }
+}
+
+impl From<&EntityInstance> for LootDrop {
+    fn from(entity_instance: &EntityInstance) -> Self {
+        let fields = &entity_instance.field_instances;
+        let mut loot = LootDrop::default();
+        if let Some(FieldValue::Float(Some(chance))) = ldtk_field(fields, ENEMY_LOOT_CHANCE_FIELD) {
+            loot.chance = *chance;
+        }
+        if let Some(FieldValue::Int(Some(value))) = ldtk_field(fields, ENEMY_LOOT_VALUE_FIELD) {
+            loot.value = (*value).max(0) as u32;
+        }
+        loot
+    }
+}
+
+/// Component for an item lying in a cell, picked up when the player steps on it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collectible {
+    /// Points scored for picking it up.
+    pub value: u32,
+}
+
+/// Bundle for creating an enemy entity.
+/// Groups all necessary components for an enemy entity, including sprite and grid position.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct EnemyBundle {
+    pub enemy: Enemy,
+    #[from_entity_instance]
+    pub loot: LootDrop,
+    #[sprite_sheet_bundle]
+    pub sprite_bundle: SpriteSheetBundle,
+    #[grid_coords]
+    pub grid_coords: GridCoords,
+}
+
+/// Bundle for creating an enemy that shoots at the player.
+/// A regular enemy with a `RangedAttack`, placed in LDtk as a "RangedEnemy".
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct RangedEnemyBundle {
+    #[ldtk_entity]
+    pub enemy_bundle: EnemyBundle,
+    pub ranged_attack: RangedAttack,
+}
+
+/// Component for a practice target that spells hit like an enemy, but that never
+/// moves or fights back and is never killed.
+///
+/// It tallies the damage it has taken, and is put back to full health once `reset`
+/// runs out without another hit.
+#[derive(Component, Debug, Clone)]
+pub struct TargetDummy {
+    /// Damage taken since the dummy was last reset.
+    pub damage_taken: f32,
+    /// Time since the last hit, running out when the dummy resets.
+    pub reset: Timer,
+}
+
+impl Default for TargetDummy {
+    fn default() -> Self {
+        TargetDummy {
+            damage_taken: 0.0,
+            reset: Timer::from_seconds(TARGET_DUMMY_RESET_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Target dummies start with `TARGET_DUMMY_HEALTH`. Having health already, they're
+/// left alone by the enemy setup, so they get no AI or contact damage.
+fn target_dummy_health(_: &EntityInstance) -> Health {
+    Health {
+        current: TARGET_DUMMY_HEALTH,
+        max: TARGET_DUMMY_HEALTH,
+    }
+}
+
+/// Bundle for creating a target dummy, placed in LDtk as a "TargetDummy".
+/// Dummies are `Enemy`s, so spells find and hurt them the same way.
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct TargetDummyBundle {
+    pub target_dummy: TargetDummy,
+    pub enemy: Enemy,
+    #[with(target_dummy_health)]
+    pub health: Health,
+    #[sprite_sheet_bundle]
+    pub sprite_bundle: SpriteSheetBundle,
+    #[grid_coords]
+    pub grid_coords: GridCoords,
+}
@@ -1,15 +1,34 @@
 // constants.rs
 
+use bevy_rapier2d::prelude::Group;
+
 /// Filename of the LDtk map used in the game.
 pub const MAP_FILENAME: &str = "map.ldtk";
 
-/// Size of each grid cell in the map, in pixels.
+/// Size of each grid cell in the map, in pixels, until the LDtk project's own grid
+/// size is read into `GridSize`. Also the Rapier pixels-per-meter, which is fixed
+/// when the physics plugin is built.
 pub const GRID_SIZE: i32 = 16;
 
-/// Width of the game window, in pixels.
+/// Rapier collision group of walls.
+pub const COLLISION_GROUP_WALL: Group = Group::GROUP_1;
+
+/// Rapier collision group of the player.
+pub const COLLISION_GROUP_PLAYER: Group = Group::GROUP_2;
+
+/// Rapier collision group of enemies.
+pub const COLLISION_GROUP_ENEMY: Group = Group::GROUP_3;
+
+/// Rapier collision group of the player's spells.
+pub const COLLISION_GROUP_PLAYER_SPELL: Group = Group::GROUP_4;
+
+/// Rapier collision group of enemies' projectiles.
+pub const COLLISION_GROUP_ENEMY_SPELL: Group = Group::GROUP_5;
+
+/// Default width of the game window, in pixels.
 pub const WINDOW_WIDTH: f32 = 1280.0;
 
-/// Height of the game window, in pixels.
+/// Default height of the game window, in pixels.
 pub const WINDOW_HEIGHT: f32 = 720.0;
 
 /// Scale factor for the camera.
@@ -20,13 +39,26 @@ pub const CAMERA_SCALE: f32 = 0.5;
 /// Adjusts the camera's height position when following the player.
 pub const CAMERA_HEIGHT_OFFSET: f32 = 1.5; // TODO: This is bogus. How does camera x,y work?
 
-/// Dimensions for the wall sprites (16, 16)
-pub const WALL_SPRITE_WIDTH: f32 = GRID_SIZE as f32;
-pub const WALL_SPRITE_HEIGHT: f32 = GRID_SIZE as f32;
+/// Room kept between the players and the edge of the view when the camera zooms
+/// out to fit them both, in pixels.
+pub const CAMERA_FIT_MARGIN: f32 = 48.0;
+
+/// The furthest the camera zooms out to fit the players, as a multiple of the
+/// normal view.
+pub const CAMERA_MAX_ZOOM_OUT: f32 = 3.0;
+
+/// Length of one fixed gameplay step, in seconds.
+/// Movement and enemy AI advance in steps of this length however fast frames are drawn.
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
 
-/// Dimensions for the player sprite (16, 32)
-pub const PLAYER_SPRITE_WIDTH: f32 = GRID_SIZE as f32;
-pub const PLAYER_SPRITE_HEIGHT: f32 = 2.0 * GRID_SIZE as f32;
+/// Fastest `TimeScale` allowed, as a multiple of real time.
+pub const TIME_SCALE_MAX: f32 = 8.0;
+
+/// How long a big hit freezes the game, in seconds of real time.
+pub const HIT_STOP_SECONDS: f32 = 0.06;
+
+/// `TimeScale` the game runs at while frozen by a big hit: all but stopped.
+pub const HIT_STOP_TIME_SCALE: f32 = 0.02;
 
 /// Speed of the player sprite.
 /// This value determines how fast the player moves in the game world.
@@ -37,6 +69,114 @@ pub const PLAYER_SPRITE_SPEED: f32 = 100.0;
 /// TODO: PLAYER_SPRITE_FRAMES needs to be loaded from the LDTK player entity metadata.
 pub const PLAYER_SPRITE_FRAMES: [usize; 9] = [136, 137, 138, 139, 140, 141, 142, 143, 144];
 
+/// List of player death animation frame indexes, played once before respawning.
+/// TODO: The 0x72 tileset has no death sequence for the wizard, so this only holds
+/// its "hit" frame (144) for eight frames to pause before the respawn. Replace it
+/// with real death frames once the sprite sheet has them.
+pub const PLAYER_DEATH_FRAMES: [usize; 8] = [144; 8];
+
+/// List of player cast animation frame indexes, played once when a spell is cast.
+/// The tileset has no cast sequence, so the wizard's last run frame leads into its "hit" frame.
+pub const PLAYER_CAST_FRAMES: [usize; 3] = [143, 144, 144];
+
+/// How quickly the player speeds up toward `PLAYER_SPRITE_SPEED`, in pixels per second squared.
+pub const PLAYER_ACCELERATION: f32 = 800.0;
+
+/// How quickly the player coasts to a stop with no key held, in pixels per second squared.
+pub const PLAYER_FRICTION: f32 = 1000.0;
+
+/// Initial speed of the push back from casting an uncharged spell, in pixels per second.
+/// Scaled up with the spell's charge; set to zero to disable recoil.
+pub const PLAYER_RECOIL_SPEED: f32 = 60.0;
+
+/// Fraction of the recoil speed lost per second.
+pub const PLAYER_RECOIL_DECAY: f32 = 10.0;
+
+/// Recoil slower than this is dropped, in pixels per second.
+pub const PLAYER_RECOIL_MIN_SPEED: f32 = 1.0;
+
+/// Hit points the player starts with.
+pub const PLAYER_HEALTH_MAX: f32 = 3.0;
+
+/// LDtk field on a Player entity overriding its top speed, in pixels per second.
+pub const PLAYER_SPEED_FIELD: &str = "speed";
+
+/// LDtk field on a Player entity overriding the hit points it starts with.
+pub const PLAYER_HEALTH_FIELD: &str = "health";
+
+/// Tint of player two's sprite, so the two wizards can be told apart.
+pub const PLAYER_TWO_TINT: [f32; 3] = [0.6, 0.8, 1.0];
+
+/// Hit points an enemy starts with, before difficulty scaling.
+pub const ENEMY_HEALTH_MAX: f32 = 2.0;
+
+/// Damage an enemy does to the player on contact, before difficulty scaling.
+pub const ENEMY_CONTACT_DAMAGE: f32 = 1.0;
+
+/// Distance between a spell and an enemy's center at which the spell hits, in cells.
+pub const ENEMY_HIT_RADIUS: f32 = 0.5;
+
+/// Points scored for killing an enemy.
+pub const ENEMY_SCORE: u32 = 100;
+
+/// Distance within which an enemy notices the player and gives chase, in cells.
+pub const ENEMY_DETECTION_RADIUS: f32 = 5.0;
+
+/// Distance beyond which a chasing enemy gives up and returns to its spawn, in cells.
+pub const ENEMY_LEASH_RADIUS: f32 = 8.0;
+
+/// Time an enemy takes to step from one cell to the next, in seconds.
+pub const ENEMY_STEP_SECONDS: f32 = 0.4;
+
+/// Probability an idle enemy sets off on a patrol at each step.
+pub const ENEMY_PATROL_CHANCE: f32 = 0.2;
+
+/// Furthest an enemy patrols from its spawn, in cells along each axis.
+pub const ENEMY_PATROL_RADIUS: i32 = 3;
+
+/// Slowest stacked slows can make an enemy, as a multiplier of its speed.
+pub const ENEMY_SLOW_MIN_FACTOR: f32 = 0.25;
+
+/// Furthest a ranged enemy shoots from, in cells.
+pub const ENEMY_RANGED_RANGE: f32 = 6.0;
+
+/// Time between a ranged enemy's shots, in seconds.
+pub const ENEMY_RANGED_COOLDOWN: f32 = 1.5;
+
+/// Speed of an enemy's projectile, in pixels per second.
+pub const ENEMY_PROJECTILE_SPEED: f32 = 100.0;
+
+/// Damage dealt by an enemy's projectile.
+pub const ENEMY_PROJECTILE_DAMAGE: f32 = 1.0;
+
+/// How far an enemy's projectile flies before it fizzles out, in cells.
+pub const ENEMY_PROJECTILE_RANGE: f32 = 12.0;
+
+/// Number of enemies from which `EnemyPositions` buckets them into cells rather than
+/// checking each one.
+pub const ENEMY_INDEX_MIN_COUNT: usize = 64;
+
+/// Width of an `EnemyPositions` cell, in grid cells.
+pub const ENEMY_INDEX_CELL_SIZE: f32 = 4.0;
+
+/// Distance between an enemy's projectile and the player's center at which it hits, in cells.
+pub const PLAYER_HIT_RADIUS: f32 = 1.0;
+
+/// Probability of an enemy dropping loot when it dies, unless set in LDtk.
+pub const ENEMY_LOOT_CHANCE: f32 = 0.25;
+
+/// Points a dropped collectible is worth, unless set in LDtk.
+pub const ENEMY_LOOT_VALUE: u32 = 50;
+
+/// LDtk field on an Enemy entity overriding its loot drop probability.
+pub const ENEMY_LOOT_CHANCE_FIELD: &str = "loot_chance";
+
+/// LDtk field on an Enemy entity overriding the value of its loot.
+pub const ENEMY_LOOT_VALUE_FIELD: &str = "loot_value";
+
+/// Sprite index of a dropped collectible (a coin) in the 16x16 tileset grid.
+pub const COLLECTIBLE_SPRITE_INDEX: usize = 562;
+
 pub const _SPELL_FIRE_SPRITE_WIDTH: f32 = GRID_SIZE as f32;
 pub const _SPELL_FIRE_SPRITE_HEIGHT: f32 = GRID_SIZE as f32;
 
@@ -44,5 +184,250 @@ pub const _SPELL_FIRE_SPRITE_HEIGHT: f32 = GRID_SIZE as f32;
 /// This value determines the delay between player sprite animation frames.
 pub const SPRITE_ANIMATION_SPEED: f32 = 0.1;
 
-/// Speed of the spell_fire sprite.
-pub const SPELL_FIRE_SPEED: f32 = 2.0;
+/// Seconds each frame of an animated LDtk tile is shown, unless its tileset custom
+/// data gives its own `frame_seconds`.
+pub const TILE_ANIMATION_SPEED: f32 = 0.15;
+
+/// Speed of an uncharged spell_fire projectile, in pixels per second.
+pub const SPELL_FIRE_SPEED: f32 = 150.0;
+
+/// Damage dealt by an uncharged spell_fire projectile.
+pub const SPELL_FIRE_DAMAGE: f32 = 1.0;
+
+/// How long a spell_fire projectile flies before it fizzles out, in seconds.
+pub const SPELL_FIRE_LIFETIME: f32 = 2.0;
+
+/// How far a spell_fire projectile flies from where it was cast before it fizzles
+/// out, in cells, whatever its speed.
+pub const SPELL_FIRE_RANGE: f32 = 18.0;
+
+/// How long a cast key must be held to reach full charge, in seconds.
+pub const SPELL_FIRE_MAX_CHARGE_SECONDS: f32 = 1.0;
+
+/// Multiplier applied to spell_fire speed, damage and particle size at full charge.
+pub const SPELL_FIRE_MAX_CHARGE_MULTIPLIER: f32 = 3.0;
+
+/// Number of steps the charge is rounded to, so each step can share one effect asset.
+pub const SPELL_FIRE_CHARGE_LEVELS: usize = 4;
+
+/// How far in front of the player's center, in the cast direction, the wizard's
+/// hands hold a spell, in pixels.
+pub const SPELL_FIRE_HAND_REACH: f32 = 8.0;
+
+/// How far above the player's center the wizard's hands are, in pixels. The sprite
+/// is two cells tall, so its hands sit below its center.
+pub const SPELL_FIRE_HAND_HEIGHT: f32 = -4.0;
+
+/// Maximum number of spell_fire projectiles alive at once. Casts beyond this are refused.
+pub const SPELL_FIRE_MAX_LIVE: usize = 8;
+
+/// Seconds after a cast before the player can cast again.
+pub const SPELL_FIRE_COOLDOWN: f32 = 0.4;
+
+/// How long a spell released during the cooldown is kept, to be cast the moment the
+/// cooldown is over, in seconds.
+pub const SPELL_CAST_BUFFER_SECONDS: f32 = 0.15;
+
+/// LDtk field on a Door entity holding the IID of the level it leads to.
+pub const DOOR_TARGET_LEVEL_FIELD: &str = "target_level";
+
+/// LDtk field on a Door entity holding the destination cell in the target level.
+pub const DOOR_TARGET_CELL_FIELD: &str = "target_cell";
+
+/// Duration of the fade out and back in when the player goes through a door, in seconds.
+pub const DOOR_FADE_SECONDS: f32 = 0.6;
+
+/// Damage a breakable wall takes before it breaks.
+pub const WALL_BREAKABLE_HEALTH: f32 = 3.0;
+
+/// LDtk field on a MovingWall entity holding the cells it moves through.
+pub const MOVING_WALL_WAYPOINTS_FIELD: &str = "waypoints";
+
+/// LDtk field on a MovingWall entity holding its speed, in cells per second.
+pub const MOVING_WALL_SPEED_FIELD: &str = "speed";
+
+/// Speed of a moving wall that doesn't set its own, in cells per second.
+pub const MOVING_WALL_SPEED: f32 = 2.0;
+
+/// Prompt shown next to a door.
+pub const DOOR_INTERACT_PROMPT: &str = "Enter";
+
+/// Prompt shown next to an `Interactable` that doesn't set its own.
+pub const INTERACT_DEFAULT_PROMPT: &str = "Interact";
+
+/// Size of the minimap in the corner of the screen, in pixels.
+pub const MINIMAP_SIZE: u32 = 128;
+
+/// Margin between the minimap and the edge of the screen, in pixels.
+pub const MINIMAP_MARGIN: f32 = 8.0;
+
+/// Number of enemies a fully charged spell_fire projectile damages before it's used up.
+pub const SPELL_FIRE_PIERCE_HITS: usize = 2;
+
+/// Particles per second emitted by a projectile's trail.
+pub const SPELL_TRAIL_RATE: f32 = 60.0;
+
+/// How long each trail particle lasts, in seconds.
+pub const SPELL_TRAIL_LIFETIME: f32 = 0.4;
+
+/// How long a spell's impact burst lasts, in seconds.
+pub const SPELL_IMPACT_SECONDS: f32 = 0.5;
+
+/// Number of particles in a spell's impact burst.
+pub const SPELL_IMPACT_PARTICLES: f32 = 48.0;
+
+/// Speed the impact burst's particles fly out at.
+pub const SPELL_IMPACT_SPEED: f32 = 30.0;
+
+/// Distance within which homing spells look for an enemy, in pixels.
+pub const SPELL_HOMING_RADIUS: f32 = 120.0;
+
+/// Maximum rate a homing spell turns toward its target, in radians per second.
+pub const SPELL_HOMING_TURN_RATE: f32 = 3.0;
+
+/// Maximum mana the player can hold.
+pub const PLAYER_MANA_MAX: f32 = 100.0;
+
+/// Mana the player regenerates per second.
+pub const PLAYER_MANA_REGEN: f32 = 10.0;
+
+/// Mana cost of casting a fire spell.
+pub const SPELL_FIRE_MANA_COST: f32 = 10.0;
+
+/// Mana cost of casting a homing spell.
+pub const SPELL_HOMING_MANA_COST: f32 = 20.0;
+
+/// Mana cost of casting an ice spell.
+pub const SPELL_ICE_MANA_COST: f32 = 15.0;
+
+/// Multiplier an ice spell applies to the speed of the enemies it hits.
+pub const SPELL_ICE_SLOW_FACTOR: f32 = 0.5;
+
+/// How long an ice spell's slow lasts, in seconds.
+pub const SPELL_ICE_SLOW_SECONDS: f32 = 3.0;
+
+/// Mana cost of casting an explosion spell.
+pub const SPELL_EXPLOSION_MANA_COST: f32 = 30.0;
+
+/// Distance an explosion spell damages enemies within, in cells.
+pub const SPELL_EXPLOSION_RADIUS: f32 = 3.0;
+
+/// Number of particles in an explosion spell's blast.
+pub const SPELL_EXPLOSION_PARTICLES: f32 = 256.0;
+
+/// Mana cost of casting a firewall spell.
+pub const SPELL_FIREWALL_MANA_COST: f32 = 25.0;
+
+/// Number of cells a firewall burns, in a line across the direction it's cast.
+pub const SPELL_FIREWALL_WIDTH: i32 = 3;
+
+/// How long a firewall burns before it goes out, in seconds.
+pub const SPELL_FIREWALL_SECONDS: f32 = 4.0;
+
+/// Time between a firewall's burns, in seconds.
+pub const SPELL_FIREWALL_TICK_SECONDS: f32 = 0.5;
+
+/// Damage a firewall does to everything standing in it each burn.
+pub const SPELL_FIREWALL_DAMAGE: f32 = 0.5;
+
+/// Whether a firewall burns the player as well as enemies.
+pub const SPELL_FIREWALL_HURTS_PLAYER: bool = false;
+
+/// Flame particles each burning cell of a firewall emits per second.
+pub const SPELL_FIREWALL_PARTICLE_RATE: f32 = 60.0;
+
+/// Directory screenshots are saved to, relative to the working directory.
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// File the best score is kept in between runs, relative to the working directory.
+pub const HIGH_SCORE_FILENAME: &str = "highscore.json";
+
+/// File the session is saved to with F6 and loaded from with F8, relative to the
+/// working directory.
+pub const SAVE_FILENAME: &str = "save.json";
+
+/// Version written into each save. Saves from any other version are rejected, so
+/// bump it whenever `SaveGame` changes shape.
+pub const SAVE_VERSION: u32 = 1;
+
+/// File the player's settings are kept in between runs, relative to the working directory.
+pub const SETTINGS_FILENAME: &str = "settings.json";
+
+/// Font size of the HUD text, in pixels.
+pub const HUD_FONT_SIZE: f32 = 16.0;
+
+/// Margin between the HUD text and the edge of the screen, in pixels.
+pub const HUD_MARGIN: f32 = 8.0;
+
+/// Width of the HUD's spell cooldown bar when full, in pixels.
+pub const HUD_COOLDOWN_BAR_WIDTH: f32 = 64.0;
+
+/// Height of the HUD's spell cooldown bar, in pixels.
+pub const HUD_COOLDOWN_BAR_HEIGHT: f32 = 6.0;
+
+/// How long the HUD's mana text stays red after a cast fails for lack of mana, in seconds.
+pub const HUD_NO_MANA_SECONDS: f32 = 1.0;
+
+/// LDtk field on a level holding the name shown when the player enters it.
+pub const LEVEL_DISPLAY_NAME_FIELD: &str = "display_name";
+
+/// How long a level's title card stays up, in seconds, including its fade.
+pub const LEVEL_TITLE_SECONDS: f32 = 2.5;
+
+/// How long a level's title card takes to fade out at the end, in seconds.
+pub const LEVEL_TITLE_FADE_SECONDS: f32 = 1.0;
+
+/// Font size of a level's title card.
+pub const LEVEL_TITLE_FONT_SIZE: f32 = 32.0;
+
+/// Distance of a level's title card from the top of the screen, in percent.
+pub const LEVEL_TITLE_TOP_PERCENT: f32 = 30.0;
+
+/// Number of lines the combat log keeps; older lines are dropped.
+pub const COMBAT_LOG_LENGTH: usize = 6;
+
+/// How long a line stays in the combat log, in seconds, including its fade.
+pub const COMBAT_LOG_SECONDS: f32 = 5.0;
+
+/// How long a combat log line takes to fade out at the end, in seconds.
+pub const COMBAT_LOG_FADE_SECONDS: f32 = 1.5;
+
+/// Font size of the combat log text.
+pub const COMBAT_LOG_FONT_SIZE: f32 = 12.0;
+
+/// Hit points of a target dummy on the practice range.
+pub const TARGET_DUMMY_HEALTH: f32 = 100.0;
+
+/// How long a target dummy goes without a hit before it's back at full health, in seconds.
+pub const TARGET_DUMMY_RESET_SECONDS: f32 = 3.0;
+
+/// Font size of the damage shown above a target dummy.
+pub const TARGET_DUMMY_FONT_SIZE: f32 = 8.0;
+
+/// Number of steps the settings menu's volume sliders move through, from silent to full.
+pub const SETTINGS_VOLUME_STEPS: i32 = 10;
+
+/// Strongest bloom the settings allow.
+pub const VISUAL_BLOOM_MAX: f32 = 1.0;
+
+/// How far each press of a bloom button moves the intensity.
+pub const VISUAL_BLOOM_STEP: f32 = 0.05;
+
+/// Opacity of the low health vignette's edges when the player is out of health.
+pub const VIGNETTE_MAX_ALPHA: f32 = 0.75;
+
+/// Share of the player's health at or below which the low health vignette pulses.
+pub const VIGNETTE_CRITICAL_FRACTION: f32 = 0.34;
+
+/// Pulses per second of the low health vignette at critical health.
+pub const VIGNETTE_PULSE_RATE: f32 = 1.5;
+
+/// How much of the low health vignette's opacity fades away at the bottom of a pulse.
+pub const VIGNETTE_PULSE_DEPTH: f32 = 0.4;
+
+/// Width and height of the generated vignette texture, in pixels. It's stretched
+/// over the screen, so it only needs enough pixels for a smooth fade.
+pub const VIGNETTE_TEXTURE_SIZE: u32 = 64;
+
+/// Number of frames of each entity count diagnostic kept for its average.
+pub const ENTITY_DIAGNOSTIC_HISTORY: usize = 20;
@@ -0,0 +1,63 @@
+// despawn.rs
+
+use bevy::prelude::*;
+
+use crate::components::*;
+
+/// DespawnPlugin despawns entities whose `DespawnTimer` has run out.
+///
+/// Anything short-lived, like projectiles and impact bursts, gets a `DespawnTimer`
+/// instead of a despawn system of its own. The entity goes along with its
+/// children, so trails and other attached effects don't linger.
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tick_despawn_timers);
+    }
+}
+
+/// Ticks every `DespawnTimer`, despawning entities whose timer has finished.
+///
+/// # Arguments
+/// * `commands` - Used to despawn entities and their children.
+/// * `time` - Resource giving the time since the last frame.
+/// * `query` - Query to access entities' despawn timers.
+///
+pub fn tick_despawn_timers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut DespawnTimer)>,
+) {
+    for (entity, mut despawn_timer) in query.iter_mut() {
+        if despawn_timer.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_elapsed_timer_despawns_with_children() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_systems(Update, tick_despawn_timers);
+        let parent = app.world.spawn(DespawnTimer::new(1.0)).id();
+        let child = app.world.spawn_empty().set_parent(parent).id();
+        let lasting = app.world.spawn(DespawnTimer::new(5.0)).id();
+
+        app.update();
+        assert!(app.world.get_entity(parent).is_some());
+
+        let elapsed = app.world.resource::<Time>().startup() + Duration::from_secs_f32(1.5);
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(elapsed);
+        app.update();
+        assert!(app.world.get_entity(parent).is_none());
+        assert!(app.world.get_entity(child).is_none());
+        assert!(app.world.get_entity(lasting).is_some());
+    }
+}
@@ -0,0 +1,153 @@
+// difficulty.rs
+
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::settings::GameConfig;
+
+/// DifficultyPlugin picks the difficulty for the run.
+///
+/// It's read from a `--difficulty=<easy|normal|hard>` command-line argument,
+/// falling back to the one saved in the `GameConfig`, then to `Normal`.
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        let saved = app
+            .world
+            .get_resource::<GameConfig>()
+            .map(|config| config.difficulty);
+        let difficulty = Difficulty::from_args(std::env::args())
+            .or(saved)
+            .unwrap_or_default();
+        info!("difficulty: {:?}", difficulty);
+        app.insert_resource(difficulty);
+    }
+}
+
+/// How hard the game is. Systems scale their values by `Difficulty::multipliers`
+/// rather than reading the raw constants.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+/// Factors applied to base values for a difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyMultipliers {
+    /// Scales `PLAYER_SPRITE_SPEED`.
+    pub player_speed: f32,
+    /// Scales `ENEMY_HEALTH_MAX`.
+    pub enemy_health: f32,
+    /// Scales `ENEMY_CONTACT_DAMAGE`.
+    pub enemy_damage: f32,
+    /// Scales how many of the enemies placed in a level appear.
+    pub enemy_count: f32,
+}
+
+impl Difficulty {
+    /// The multipliers for this difficulty. Every difficulty-dependent value is tuned here.
+    pub fn multipliers(self) -> DifficultyMultipliers {
+        match self {
+            Difficulty::Easy => DifficultyMultipliers {
+                player_speed: 1.2,
+                enemy_health: 0.5,
+                enemy_damage: 0.5,
+                enemy_count: 0.5,
+            },
+            Difficulty::Normal => DifficultyMultipliers {
+                player_speed: 1.0,
+                enemy_health: 1.0,
+                enemy_damage: 1.0,
+                enemy_count: 1.0,
+            },
+            Difficulty::Hard => DifficultyMultipliers {
+                player_speed: 0.9,
+                enemy_health: 2.0,
+                enemy_damage: 2.0,
+                enemy_count: 1.5,
+            },
+        }
+    }
+
+    /// The next difficulty up, wrapping from `Hard` back to `Easy`.
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// How many lives the player starts a run with.
+    pub fn starting_lives(self) -> u32 {
+        match self {
+            Difficulty::Easy => 5,
+            Difficulty::Normal => 3,
+            Difficulty::Hard => 1,
+        }
+    }
+
+    /// Finds a `--difficulty=<name>` argument.
+    ///
+    /// # Returns
+    /// The difficulty named by the last such argument, or `None` if there isn't a
+    /// valid one.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        args.into_iter()
+            .filter_map(|arg| arg.strip_prefix("--difficulty=")?.parse().ok())
+            .last()
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty {:?}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difficulty_from_str() {
+        assert_eq!("easy".parse(), Ok(Difficulty::Easy));
+        assert_eq!("Hard".parse(), Ok(Difficulty::Hard));
+        assert!("nightmare".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn test_difficulty_from_args() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(Difficulty::from_args(args(&["game"])), None);
+        assert_eq!(
+            Difficulty::from_args(args(&["game", "--difficulty=hard"])),
+            Some(Difficulty::Hard)
+        );
+        assert_eq!(
+            Difficulty::from_args(args(&["game", "--difficulty=easy", "--difficulty=bogus"])),
+            Some(Difficulty::Easy)
+        );
+    }
+
+    #[test]
+    fn test_normal_is_unscaled() {
+        let multipliers = Difficulty::Normal.multipliers();
+        assert_eq!(multipliers.player_speed, 1.0);
+        assert_eq!(multipliers.enemy_health, 1.0);
+        assert_eq!(multipliers.enemy_damage, 1.0);
+        assert_eq!(multipliers.enemy_count, 1.0);
+    }
+}
@@ -0,0 +1,385 @@
+// door.rs
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::interaction::InteractEvent;
+use crate::map::{GridSize, LevelWalls};
+use crate::player::{player_translation, CameraSnap};
+
+/// DoorPlugin is responsible for door-related functionalities in the game.
+/// This includes teleporting the player when it steps on or interacts with a
+/// door, placing it in the target level once that level's walls are cached, and
+/// fading the screen to hide the level load.
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingTeleport>()
+            .init_resource::<ScreenFade>()
+            .add_systems(Startup, setup_screen_fade)
+            .add_systems(Update, (enter_doors, finish_teleport, update_screen_fade))
+            .register_ldtk_entity::<DoorBundle>("Door");
+    }
+}
+
+/// A teleport that has been started by a door but not finished yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeleportTarget {
+    /// The player who set the teleport off. Every other player goes along with it.
+    pub player: Entity,
+    /// IID of the level the player is going to.
+    pub level_iid: String,
+    /// Destination cell in the target level.
+    pub cell: GridCoords,
+}
+
+/// Holds the teleport in progress while the target level loads.
+#[derive(Default, Resource)]
+pub struct PendingTeleport(pub Option<TeleportTarget>);
+
+/// Drives the full-screen fade used to hide level loads.
+#[derive(Default, Resource)]
+pub struct ScreenFade {
+    timer: Option<Timer>,
+}
+
+impl ScreenFade {
+    /// Starts (or restarts) a fade out and back in.
+    pub fn start(&mut self) {
+        self.timer = Some(Timer::from_seconds(DOOR_FADE_SECONDS, TimerMode::Once));
+    }
+}
+
+/// Marker for the UI node that covers the screen during a fade.
+#[derive(Component)]
+struct ScreenFadeOverlay;
+
+/// Resolves where a door leads.
+///
+/// # Arguments
+/// * `door` - The door the player stepped on.
+/// * `levels` - IID and height (in cells) of every level in the project.
+///
+/// # Returns
+/// The target level IID and destination cell converted from LDtk's y-down cell
+/// coordinates to `GridCoords`, or `None` if the door points at a level that
+/// doesn't exist.
+pub fn resolve_door_target(door: &Door, levels: &[(String, i32)]) -> Option<(String, GridCoords)> {
+    levels
+        .iter()
+        .find(|(iid, _)| *iid == door.target_level)
+        .map(|(iid, level_height)| {
+            (
+                iid.clone(),
+                GridCoords::new(door.target_cell.x, level_height - 1 - door.target_cell.y),
+            )
+        })
+}
+
+/// Opacity of the fade overlay given how far through the fade we are.
+///
+/// Ramps from clear to black over the first half and back to clear over the second.
+pub fn fade_alpha(fraction: f32) -> f32 {
+    1.0 - (2.0 * fraction.clamp(0.0, 1.0) - 1.0).abs()
+}
+
+/// Starts moving the players to a cell in another (or the same) level.
+///
+/// Every player is detached from its level so it survives the old level being
+/// despawned, `LevelSelection` is switched to the target and the screen fade is
+/// started. `finish_teleport` places the players once the target level's walls
+/// are cached. In local co-op the players travel together, since only the selected
+/// level is kept loaded.
+///
+/// # Arguments
+/// * `commands` - Used to detach the players from their levels.
+/// * `target` - The player setting the teleport off and where it's going.
+/// * `players` - Every player, all of whom go along.
+/// * `level_selection` - Resource switched to the target level.
+/// * `pending_teleport` - Resource recording the teleport until the target level is ready.
+/// * `screen_fade` - Resource driving the fade overlay.
+///
+pub fn start_teleport(
+    commands: &mut Commands,
+    target: TeleportTarget,
+    players: impl IntoIterator<Item = Entity>,
+    level_selection: &mut LevelSelection,
+    pending_teleport: &mut PendingTeleport,
+    screen_fade: &mut ScreenFade,
+) {
+    commands.entity(target.player).remove_parent();
+    for player in players {
+        commands.entity(player).remove_parent();
+    }
+    *level_selection = LevelSelection::Iid(target.level_iid.clone());
+    pending_teleport.0 = Some(target);
+    screen_fade.start();
+}
+
+/// Spawns the (initially transparent) full-screen fade overlay.
+fn setup_screen_fade(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.0).into(),
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        ScreenFadeOverlay,
+        Name::new("Screen fade"),
+    ));
+}
+
+/// Starts a teleport when the player steps onto a door in the current level, or
+/// interacts with one.
+///
+/// See `start_teleport`. Doors leading to unknown levels are logged and ignored.
+///
+/// # Arguments
+/// * `commands` - Used to detach the players from their levels.
+/// * `player_query` - Query selecting players whose grid position just changed.
+/// * `players` - Query selecting every player, to take them all along.
+/// * `interact_events` - Event reader for players interacting with doors.
+/// * `door_query` - Query to access doors, their grid positions and parent layers.
+/// * `parent_query` - Query used to walk from a door's layer up to its level.
+/// * `level_entities` - Query used to find the current level's entity.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+/// * `level_walls` - Resource identifying the current level.
+/// * `level_selection` - Resource switched to the door's target level.
+/// * `pending_teleport` - Resource recording the teleport until the target level is ready.
+/// * `screen_fade` - Resource driving the fade overlay.
+/// * `ldtk_project_entities` - Query to access the LDtk project handle.
+/// * `ldtk_project_assets` - Loaded LDtk projects, used to look up the target level.
+/// * `grid_size` - Resource giving the size of a cell, to measure the target level.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn enter_doors(
+    mut commands: Commands,
+    player_query: Query<(Entity, &GridCoords), (With<Player>, Changed<GridCoords>)>,
+    players: Query<Entity, With<Player>>,
+    mut interact_events: EventReader<InteractEvent>,
+    door_query: Query<(&Door, &GridCoords, &Parent), Without<Player>>,
+    parent_query: Query<&Parent, Without<Door>>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    level_walls: Res<LevelWalls>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut screen_fade: ResMut<ScreenFade>,
+    ldtk_project_entities: Query<&Handle<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
+    grid_size: Res<GridSize>,
+) {
+    let used: Vec<(Entity, &Door, &GridCoords)> = interact_events
+        .iter()
+        .filter_map(|event| {
+            door_query
+                .get(event.target)
+                .ok()
+                .map(|(door, door_coords, _)| (event.player, door, door_coords))
+        })
+        .collect();
+    if pending_teleport.0.is_some() {
+        return;
+    }
+    let Some(current_level) = level_entities.iter().find_map(|(entity, handle)| {
+        level_assets
+            .get(handle)
+            .filter(|ldtk_level| ldtk_level.level.iid == level_walls.level_iid())
+            .map(|_| entity)
+    }) else {
+        return;
+    };
+
+    // Doors are children of the Entities layer, which is a child of the level
+    let stepped_on = player_query.iter().filter_map(|(player, player_coords)| {
+        door_query
+            .iter()
+            .find(|(_, door_coords, layer)| {
+                *door_coords == player_coords
+                    && parent_query
+                        .get(layer.get())
+                        .is_ok_and(|level| level.get() == current_level)
+            })
+            .map(|(door, door_coords, _)| (player, door, door_coords))
+    });
+    for (player, door, door_coords) in stepped_on.chain(used) {
+        let Some(ldtk_project) = ldtk_project_entities
+            .get_single()
+            .ok()
+            .and_then(|handle| ldtk_project_assets.get(handle))
+        else {
+            return;
+        };
+        let levels: Vec<(String, i32)> = ldtk_project
+            .iter_levels()
+            .map(|level| (level.iid.clone(), level.px_hei / grid_size.0))
+            .collect();
+
+        match resolve_door_target(door, &levels) {
+            Some((level_iid, cell)) => {
+                info!(
+                    "🚪door at {:?} leads to {} {:?}",
+                    door_coords, level_iid, cell
+                );
+                start_teleport(
+                    &mut commands,
+                    TeleportTarget {
+                        player,
+                        level_iid,
+                        cell,
+                    },
+                    players.iter(),
+                    &mut level_selection,
+                    &mut pending_teleport,
+                    &mut screen_fade,
+                );
+            }
+            None => warn!(
+                "🚪door at {:?} leads to unknown level {:?}, ignoring",
+                door_coords, door.target_level
+            ),
+        }
+        return;
+    }
+}
+
+/// Places the teleporting players in their target level once that level's walls
+/// are cached.
+///
+/// The destination is snapped to the nearest walkable cell so a door pointing into
+/// geometry doesn't trap the player, and the camera snaps there with it. Every
+/// player lands on that cell, so a co-op partner isn't left in a level that's no
+/// longer loaded.
+pub(crate) fn finish_teleport(
+    mut commands: Commands,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    level_walls: Res<LevelWalls>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    mut player_query: Query<(Entity, &mut Transform, &mut GridCoords), With<Player>>,
+    grid_size: Res<GridSize>,
+    mut camera_snap: ResMut<CameraSnap>,
+) {
+    let Some(target) = &pending_teleport.0 else {
+        return;
+    };
+    if level_walls.level_iid() != target.level_iid || !level_walls.has_level(&target.level_iid) {
+        return; // Target level isn't loaded (or its walls aren't cached) yet
+    }
+    let Some(level_entity) = level_entities.iter().find_map(|(entity, handle)| {
+        level_assets
+            .get(handle)
+            .filter(|ldtk_level| ldtk_level.level.iid == target.level_iid)
+            .map(|_| entity)
+    }) else {
+        return;
+    };
+
+    let cell = level_walls
+        .nearest_walkable(target.cell)
+        .unwrap_or(target.cell);
+    let translation = player_translation(cell, *grid_size);
+    for (player, mut player_transform, mut player_grid_coords) in player_query.iter_mut() {
+        player_transform.translation.x = translation.x;
+        player_transform.translation.y = translation.y;
+        *player_grid_coords = cell;
+        commands.entity(player).set_parent(level_entity);
+        camera_snap.0 = true;
+        info!(
+            "🚪teleported player {:?} to {} {:?}",
+            player, target.level_iid, cell
+        );
+    }
+    pending_teleport.0 = None;
+}
+
+/// Advances the screen fade and applies its opacity to the overlay.
+fn update_screen_fade(
+    time: Res<Time>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut overlay_query: Query<&mut BackgroundColor, With<ScreenFadeOverlay>>,
+) {
+    let Some(timer) = screen_fade.timer.as_mut() else {
+        return;
+    };
+    timer.tick(time.delta());
+    let alpha = if timer.finished() {
+        screen_fade.timer = None;
+        0.0
+    } else {
+        fade_alpha(timer.percent())
+    };
+    for mut background_color in overlay_query.iter_mut() {
+        background_color.0.set_a(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs_ldtk::ldtk::{EntityInstance, FieldInstance};
+
+    use super::*;
+
+    fn door_entity_instance() -> EntityInstance {
+        let fields: Vec<FieldInstance> = serde_json::from_str(
+            r#"[
+                { "__identifier": "target_level", "__type": "String", "__value": "level-b",
+                  "__tile": null, "defUid": 1, "realEditorValues": [] },
+                { "__identifier": "target_cell", "__type": "Point", "__value": { "cx": 3, "cy": 1 },
+                  "__tile": null, "defUid": 2, "realEditorValues": [] }
+            ]"#,
+        )
+        .unwrap();
+        EntityInstance {
+            identifier: "Door".to_string(),
+            field_instances: fields,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_door_from_fields() {
+        let door = Door::from(&door_entity_instance());
+        assert_eq!(door.target_level, "level-b");
+        assert_eq!(door.target_cell, IVec2::new(3, 1));
+    }
+
+    #[test]
+    fn test_door_missing_fields() {
+        let door = Door::from(&EntityInstance::default());
+        assert_eq!(door, Door::default());
+    }
+
+    #[test]
+    fn test_resolve_door_target() {
+        let levels = vec![("level-a".to_string(), 10), ("level-b".to_string(), 5)];
+        let door = Door::from(&door_entity_instance());
+
+        // LDtk counts cells from the top, GridCoords from the bottom
+        assert_eq!(
+            resolve_door_target(&door, &levels),
+            Some(("level-b".to_string(), GridCoords::new(3, 3)))
+        );
+
+        let broken = Door {
+            target_level: "no-such-level".to_string(),
+            ..door
+        };
+        assert_eq!(resolve_door_target(&broken, &levels), None);
+    }
+
+    #[test]
+    fn test_fade_alpha() {
+        assert_eq!(fade_alpha(0.0), 0.0);
+        assert_eq!(fade_alpha(0.5), 1.0);
+        assert_eq!(fade_alpha(1.0), 0.0);
+        assert_eq!(fade_alpha(0.25), 0.5);
+    }
+}
@@ -0,0 +1,1946 @@
+// enemy.rs
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_hanabi::prelude::*;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::accessibility::{palette_changed, AccessibilitySettings};
+use crate::components::*;
+use crate::constants::*;
+use crate::despawn::tick_despawn_timers;
+use crate::difficulty::Difficulty;
+use crate::interpolation::GameplayStep;
+use crate::map::{GridSize, LevelWalls};
+use crate::player::{move_player_from_input, MovementMode, PendingTurns};
+use crate::rng::GameRng;
+use crate::spell_fire::{
+    spell_impact_effect, ImpactBurst, SpellExplosion, SpellFireAssets, SpellImpact, SpellKind,
+};
+use crate::util::grid_manhattan;
+
+/// EnemyPlugin is responsible for enemy-related functionalities in the game.
+/// This registers the LDtk "Enemy" entity so levels can place enemies, gives
+/// enemies their difficulty-scaled stats, hurts the player on contact and lets
+/// spells kill enemies, leaving a burst and sometimes loot behind. Enemies idle
+/// and patrol around their spawn until the player comes close, chase it, and
+/// return home once it gets away. Ranged enemies shoot from a distance instead.
+/// Explosive spells damage every enemy around where they go off, less further out.
+/// Burning `DamageField`s, like a firewall, hurt the enemies standing in them on
+/// every tick until they go out. A `TargetDummy` is hurt like any enemy but never
+/// killed.
+/// Enemies move in the fixed gameplay step, more slowly while `Slowed` by an ice
+/// spell. Each frame their positions are indexed in `EnemyPositions`, so homing
+/// spells can find nearby enemies quickly.
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .init_resource::<EnemyPositions>()
+            .add_systems(Startup, setup_enemy_assets)
+            .add_systems(
+                FixedUpdate,
+                (
+                    // After the player, so a turn-based move is answered in the same step
+                    move_enemies.after(move_player_from_input),
+                    wear_off_slows.after(move_enemies),
+                )
+                    .in_set(GameplayStep),
+            )
+            .add_systems(
+                Update,
+                (
+                    setup_enemies,
+                    index_enemy_positions,
+                    shoot_at_player,
+                    damage_player_on_contact,
+                    hit_enemies_with_spells,
+                    damage_enemies_in_blast
+                        .after(index_enemy_positions)
+                        .after(hit_enemies_with_spells),
+                    hit_player_with_enemy_projectiles,
+                    burn_in_damage_fields.after(tick_despawn_timers),
+                    spawn_enemy_remains
+                        .after(hit_enemies_with_spells)
+                        .after(damage_enemies_in_blast)
+                        .after(burn_in_damage_fields),
+                    setup_enemy_assets.run_if(palette_changed),
+                ),
+            )
+            .register_ldtk_entity::<EnemyBundle>("Enemy")
+            .register_ldtk_entity::<RangedEnemyBundle>("RangedEnemy");
+    }
+}
+
+/// Sent when an enemy's health runs out.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnemyKilled {
+    pub enemy: Entity,
+}
+
+/// Sent whenever an enemy or a player loses health.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct Damaged {
+    /// The enemy or player hurt.
+    pub target: Entity,
+    /// Hit points taken away.
+    pub amount: f32,
+}
+
+/// Shared assets for enemies, built once at startup.
+#[derive(Resource)]
+pub struct EnemyAssets {
+    /// Burst shown where an enemy dies.
+    pub death_burst: Handle<EffectAsset>,
+}
+
+/// Builds the shared enemy assets, colored from the player's palette.
+fn setup_enemy_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    accessibility: Res<AccessibilitySettings>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    let texture_handle: Handle<Image> = asset_server.load("cloud.png");
+    commands.insert_resource(EnemyAssets {
+        death_burst: effects.add(spell_impact_effect(
+            texture_handle,
+            accessibility.palette().enemy_death,
+        )),
+    });
+}
+
+/// Where every enemy is this frame, for finding the enemies near a point without
+/// checking each one.
+///
+/// With at least `ENEMY_INDEX_MIN_COUNT` enemies they're also bucketed into square
+/// cells `ENEMY_INDEX_CELL_SIZE` grid cells across, and a search only looks in the
+/// cells its circle overlaps. Fewer enemies are cheaper to check one by one.
+#[derive(Resource, Default, Debug)]
+pub struct EnemyPositions {
+    enemies: Vec<(Entity, Vec2)>,
+    /// Indices into `enemies` by cell, empty when there are too few enemies to bother.
+    cells: HashMap<IVec2, Vec<usize>>,
+    /// Width of a cell, in pixels.
+    cell_size: f32,
+}
+
+impl EnemyPositions {
+    /// Indexes the given enemies and their world positions, in a level whose cells
+    /// are `grid_size` across.
+    pub fn new(enemies: Vec<(Entity, Vec2)>, grid_size: GridSize) -> Self {
+        let bucketed = enemies.len() >= ENEMY_INDEX_MIN_COUNT;
+        Self::with_cells(enemies, bucketed, grid_size)
+    }
+
+    /// Indexes the given enemies, bucketing them into cells only if `bucketed`.
+    fn with_cells(enemies: Vec<(Entity, Vec2)>, bucketed: bool, grid_size: GridSize) -> Self {
+        let mut positions = EnemyPositions {
+            enemies,
+            cells: HashMap::new(),
+            cell_size: ENEMY_INDEX_CELL_SIZE * grid_size.pixels(),
+        };
+        if bucketed {
+            for (index, (_, position)) in positions.enemies.iter().enumerate() {
+                let cell = positions.index_cell(*position);
+                positions.cells.entry(cell).or_default().push(index);
+            }
+        }
+        positions
+    }
+
+    /// The cell a world position falls in.
+    fn index_cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Finds the enemies within `radius` pixels of `center`.
+    ///
+    /// # Returns
+    /// Each enemy in range with its position, in no particular order.
+    pub fn within(&self, center: Vec2, radius: f32) -> Vec<(Entity, Vec2)> {
+        let in_range = |(_, position): &&(Entity, Vec2)| position.distance(center) <= radius;
+        if self.cells.is_empty() {
+            return self.enemies.iter().filter(in_range).copied().collect();
+        }
+        let min = self.index_cell(center - Vec2::splat(radius));
+        let max = self.index_cell(center + Vec2::splat(radius));
+        (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .map(|&index| &self.enemies[index])
+            .filter(in_range)
+            .copied()
+            .collect()
+    }
+
+    /// Finds the nearest enemy within `radius` pixels of `center`.
+    ///
+    /// # Returns
+    /// The enemy and its position, or `None` if none is in range.
+    pub fn nearest_within(&self, center: Vec2, radius: f32) -> Option<(Entity, Vec2)> {
+        self.within(center, radius)
+            .into_iter()
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(center)
+                    .total_cmp(&b.distance_squared(center))
+            })
+    }
+}
+
+/// Rebuilds `EnemyPositions` from where the enemies are this frame.
+pub(crate) fn index_enemy_positions(
+    mut enemy_positions: ResMut<EnemyPositions>,
+    enemy_query: Query<(Entity, &GlobalTransform), With<Enemy>>,
+    grid_size: Res<GridSize>,
+) {
+    *enemy_positions = EnemyPositions::new(
+        enemy_query
+            .iter()
+            .map(|(enemy, transform)| (enemy, transform.translation().truncate()))
+            .collect(),
+        *grid_size,
+    );
+}
+
+/// Rolls whether an enemy drops its loot.
+///
+/// # Returns
+/// `true` with probability `loot.chance`.
+pub fn rolls_loot<R: Rng + ?Sized>(loot: &LootDrop, rng: &mut R) -> bool {
+    rng.gen::<f32>() < loot.chance
+}
+
+/// Health and contact damage of an enemy at the given difficulty.
+pub fn enemy_stats(difficulty: Difficulty) -> (Health, ContactDamage) {
+    let multipliers = difficulty.multipliers();
+    let health = ENEMY_HEALTH_MAX * multipliers.enemy_health;
+    (
+        Health {
+            current: health,
+            max: health,
+        },
+        ContactDamage(ENEMY_CONTACT_DAMAGE * multipliers.enemy_damage),
+    )
+}
+
+/// How many copies of the `index`th enemy placed in a level should appear.
+///
+/// Spreads a fractional `enemy_count` multiplier evenly over the level's enemies:
+/// at 0.5 every other enemy is dropped, at 1.5 every other enemy is doubled.
+///
+/// # Returns
+/// `0` to remove the enemy, `1` to keep it, or more to add copies.
+pub fn enemy_copies(index: usize, enemy_count: f32) -> usize {
+    let placed = |count: usize| (count as f32 * enemy_count).floor() as usize;
+    placed(index + 1) - placed(index)
+}
+
+/// Gives newly placed enemies their stats, AI, collision layer and smoothed movement,
+/// and applies the difficulty's enemy count.
+///
+/// Each enemy's cell is remembered as its spawn origin. Extra copies are spawned
+/// in the same cell as the original, already set up.
+///
+/// # Arguments
+/// * `commands` - Used to insert stats and to despawn or copy enemies.
+/// * `difficulty` - Resource used to scale the enemies.
+/// * `query` - Query selecting enemies that haven't been set up yet.
+///
+#[allow(clippy::type_complexity)]
+fn setup_enemies(
+    mut commands: Commands,
+    difficulty: Res<Difficulty>,
+    query: Query<
+        (
+            Entity,
+            &TextureAtlasSprite,
+            &Handle<TextureAtlas>,
+            &Transform,
+            &GridCoords,
+            &LootDrop,
+            &Parent,
+        ),
+        (With<Enemy>, Without<Health>),
+    >,
+) {
+    let enemy_count = difficulty.multipliers().enemy_count;
+    for (index, (entity, sprite, texture_atlas, transform, grid_coords, loot, parent)) in
+        query.iter().enumerate()
+    {
+        let copies = enemy_copies(index, enemy_count);
+        if copies == 0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        commands.entity(entity).insert((
+            enemy_stats(*difficulty),
+            EnemyState::default(),
+            EnemyAi::new(*grid_coords),
+            Interpolated::default(),
+            CollisionLayer::Enemy.groups(),
+        ));
+        for _ in 1..copies {
+            commands
+                .spawn((
+                    Enemy,
+                    SpriteSheetBundle {
+                        sprite: sprite.clone(),
+                        texture_atlas: texture_atlas.clone(),
+                        transform: *transform,
+                        ..default()
+                    },
+                    *grid_coords,
+                    *loot,
+                    enemy_stats(*difficulty),
+                    EnemyState::default(),
+                    EnemyAi::new(*grid_coords),
+                    Interpolated::default(),
+                    CollisionLayer::Enemy.groups(),
+                ))
+                .set_parent(parent.get());
+        }
+    }
+}
+
+/// Distance between two cells, in cells.
+pub fn cell_distance(a: GridCoords, b: GridCoords) -> f32 {
+    Vec2::new((a.x - b.x) as f32, (a.y - b.y) as f32).length()
+}
+
+/// Works out what an enemy should be doing given where the player is.
+///
+/// Idle, patrolling and returning enemies give chase once the player is within
+/// `detection_radius`. A chasing enemy only gives up once the player is beyond
+/// the larger `leash_radius`, and returning enemies go idle when they're home.
+///
+/// # Arguments
+/// * `state` - What the enemy is doing now.
+/// * `ai` - The enemy's spawn origin and radii.
+/// * `enemy_cell` - The enemy's cell.
+/// * `player_cell` - The player's cell, or `None` if there's no player.
+pub fn next_enemy_state(
+    state: EnemyState,
+    ai: &EnemyAi,
+    enemy_cell: GridCoords,
+    player_cell: Option<GridCoords>,
+) -> EnemyState {
+    let player_distance = player_cell.map_or(f32::INFINITY, |player_cell| {
+        cell_distance(enemy_cell, player_cell)
+    });
+    match state {
+        EnemyState::Idle | EnemyState::Patrol | EnemyState::Return
+            if player_distance <= ai.detection_radius =>
+        {
+            EnemyState::Chase
+        }
+        EnemyState::Chase if player_distance > ai.leash_radius => EnemyState::Return,
+        EnemyState::Return if enemy_cell == ai.origin => EnemyState::Idle,
+        state => state,
+    }
+}
+
+/// Picks the next cell on the way from `from` to `to`.
+///
+/// # Returns
+/// The walkable orthogonal neighbor that gets closest to `to`, or `from` if no
+/// neighbor gets any closer.
+pub fn step_towards_cell(level_walls: &LevelWalls, from: GridCoords, to: GridCoords) -> GridCoords {
+    [(0, 1), (1, 0), (0, -1), (-1, 0)]
+        .into_iter()
+        .map(|(dx, dy)| GridCoords::new(from.x + dx, from.y + dy))
+        .filter(|cell| !level_walls.in_level_wall(cell))
+        .filter(|cell| grid_manhattan(*cell, to) < grid_manhattan(from, to))
+        .min_by(|a, b| cell_distance(*a, to).total_cmp(&cell_distance(*b, to)))
+        .unwrap_or(from)
+}
+
+/// Checks if a ranged enemy can shoot at the player: within range, with a clear
+/// line of sight.
+pub fn has_shot(
+    level_walls: &LevelWalls,
+    ranged: &RangedAttack,
+    enemy_cell: GridCoords,
+    player_cell: GridCoords,
+) -> bool {
+    cell_distance(enemy_cell, player_cell) <= ranged.range
+        && level_walls.line_of_sight(enemy_cell, player_cell)
+}
+
+/// The cells an enemy patrolling from `origin` may head for.
+///
+/// # Returns
+/// The walkable cells of the enemy's own level within `ENEMY_PATROL_RADIUS` of
+/// `origin`. Cells in a loaded neighbor are left out, since the enemy's
+/// `GridCoords` are in its own level's cells.
+pub fn patrol_cells(
+    level_walls: &LevelWalls,
+    origin: GridCoords,
+) -> impl Iterator<Item = GridCoords> + '_ {
+    (-ENEMY_PATROL_RADIUS..=ENEMY_PATROL_RADIUS)
+        .flat_map(move |dx| {
+            (-ENEMY_PATROL_RADIUS..=ENEMY_PATROL_RADIUS)
+                .map(move |dy| GridCoords::new(origin.x + dx, origin.y + dy))
+        })
+        .filter(|cell| !level_walls.in_level_wall(cell))
+}
+
+/// Keeps an enemy out of cells other enemies are in.
+///
+/// # Arguments
+/// * `level_walls` - The level's walls.
+/// * `occupied` - How many other enemies are in each cell.
+/// * `from` - The enemy's cell.
+/// * `wanted` - The cell the enemy wants to step into (or `from` to stay put).
+///
+/// # Returns
+/// `wanted` if no other enemy is there. Otherwise the free walkable cell among
+/// `from` and its orthogonal neighbors closest to `wanted`, or `from` if all of
+/// them are taken.
+pub fn unoccupied_cell(
+    level_walls: &LevelWalls,
+    occupied: &HashMap<GridCoords, usize>,
+    from: GridCoords,
+    wanted: GridCoords,
+) -> GridCoords {
+    let is_free = |cell: &GridCoords| occupied.get(cell).copied().unwrap_or(0) == 0;
+    if is_free(&wanted) {
+        return wanted;
+    }
+    [(0, 0), (0, 1), (1, 0), (0, -1), (-1, 0)]
+        .into_iter()
+        .map(|(dx, dy)| GridCoords::new(from.x + dx, from.y + dy))
+        .filter(|cell| !level_walls.in_level_wall(cell) && is_free(cell))
+        .min_by(|a, b| cell_distance(*a, wanted).total_cmp(&cell_distance(*b, wanted)))
+        .unwrap_or(from)
+}
+
+/// Steps enemies one cell at a time according to their `EnemyState`.
+///
+/// Chasing enemies head for the player, unless they're ranged and have a shot,
+/// and returning ones head for their origin.
+/// Idle enemies sometimes set off to patrol to a random walkable cell within
+/// `ENEMY_PATROL_RADIUS` of their origin, going idle again once they arrive.
+/// Enemies never step into a cell another enemy is in, so a group chasing the
+/// player spreads out around it. In turn-based mode every enemy takes one step
+/// for each of the player's `PendingTurns`, one turn per fixed step, instead of
+/// stepping on its timer. `Slowed` enemies' timers run at their slow factor, so
+/// they step less often.
+///
+/// # Arguments
+/// * `fixed_time` - Resource giving the length of the step, for the step timers.
+/// * `movement_mode` - Resource choosing timed or turn-based steps.
+/// * `pending_turns` - Resource counting the player's turns not yet answered.
+/// * `level_walls` - Resource containing information about wall locations in the level.
+/// * `player_query` - Query to access the player's grid position.
+/// * `enemy_query` - Query to access enemies' state, AI, positions and slows.
+/// * `rng` - Resource deciding when and where idle enemies patrol.
+/// * `grid_size` - Resource giving the size of a cell, to place enemies in theirs.
+///
+#[allow(clippy::type_complexity)]
+pub(crate) fn move_enemies(
+    fixed_time: Res<FixedTime>,
+    movement_mode: Res<MovementMode>,
+    mut pending_turns: ResMut<PendingTurns>,
+    level_walls: Res<LevelWalls>,
+    player_query: Query<&GridCoords, (With<Player>, Without<Enemy>)>,
+    mut enemy_query: Query<
+        (
+            &mut EnemyState,
+            &mut EnemyAi,
+            &mut GridCoords,
+            &mut Transform,
+            Option<&RangedAttack>,
+            Option<&Slowed>,
+        ),
+        (With<Enemy>, Without<Player>),
+    >,
+    mut rng: ResMut<GameRng>,
+    grid_size: Res<GridSize>,
+) {
+    let turn_taken = match *movement_mode {
+        MovementMode::TurnBased if pending_turns.0 > 0 => {
+            pending_turns.0 -= 1;
+            true
+        }
+        MovementMode::TurnBased => false,
+        // Drop turns left over from turn-based mode so switching back doesn't replay them
+        MovementMode::RealTime => {
+            pending_turns.0 = 0;
+            false
+        }
+    };
+    let player_cells: Vec<GridCoords> = player_query.iter().copied().collect();
+    // How many enemies are in each cell, kept up to date as they step
+    let mut occupied: HashMap<GridCoords, usize> = HashMap::new();
+    for (_, _, grid_coords, _, _, _) in enemy_query.iter() {
+        *occupied.entry(*grid_coords).or_default() += 1;
+    }
+    for (mut state, mut ai, mut grid_coords, mut transform, ranged, slowed) in
+        enemy_query.iter_mut()
+    {
+        let factor = slowed.map_or(1.0, |slowed| slowed.factor);
+        let stepping = match *movement_mode {
+            MovementMode::RealTime => ai
+                .step_timer
+                .tick(fixed_time.period.mul_f32(factor))
+                .just_finished(),
+            MovementMode::TurnBased => turn_taken,
+        };
+        if !stepping {
+            continue;
+        }
+
+        // With two players, each enemy goes after whichever is closer
+        let player_cell = player_cells
+            .iter()
+            .copied()
+            .min_by_key(|player_cell| grid_manhattan(*player_cell, *grid_coords));
+        let next_state = next_enemy_state(*state, &ai, *grid_coords, player_cell);
+        if next_state != *state {
+            info!(
+                "👾enemy at {:?} {:?} -> {:?}",
+                *grid_coords, *state, next_state
+            );
+            *state = next_state;
+        }
+
+        if *state == EnemyState::Idle && rng.gen::<f32>() < ENEMY_PATROL_CHANCE {
+            ai.patrol_target = patrol_cells(&level_walls, ai.origin).choose(&mut *rng);
+            if ai.patrol_target.is_some() {
+                *state = EnemyState::Patrol;
+            }
+        }
+
+        let target = match *state {
+            EnemyState::Idle => continue,
+            EnemyState::Patrol => ai.patrol_target.unwrap_or(ai.origin),
+            EnemyState::Chase => match player_cell {
+                // Ranged enemies hold their ground while they have a shot
+                Some(player_cell)
+                    if ranged.is_some_and(|ranged| {
+                        has_shot(&level_walls, ranged, *grid_coords, player_cell)
+                    }) =>
+                {
+                    *grid_coords
+                }
+                Some(player_cell) => player_cell,
+                None => *grid_coords,
+            },
+            EnemyState::Return => ai.origin,
+        };
+        if let Some(count) = occupied.get_mut(&*grid_coords) {
+            *count -= 1;
+        }
+        let next_cell = unoccupied_cell(
+            &level_walls,
+            &occupied,
+            *grid_coords,
+            step_towards_cell(&level_walls, *grid_coords, target),
+        );
+        *occupied.entry(next_cell).or_default() += 1;
+        if *state == EnemyState::Patrol && next_cell == target {
+            ai.patrol_target = None;
+            *state = EnemyState::Idle;
+        }
+        if next_cell != *grid_coords {
+            let translation = grid_size.to_translation(next_cell);
+            transform.translation.x = translation.x;
+            transform.translation.y = translation.y;
+            *grid_coords = next_cell;
+        }
+    }
+}
+
+/// Counts down enemies' slows, removing them once they wear off.
+fn wear_off_slows(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(Entity, &mut Slowed)>,
+) {
+    for (entity, mut slowed) in query.iter_mut() {
+        if slowed.timer.tick(fixed_time.period).finished() {
+            commands.entity(entity).remove::<Slowed>();
+        }
+    }
+}
+
+/// Has chasing ranged enemies shoot at the player whenever they have a shot and
+/// their cooldown allows.
+///
+/// Shots are spell projectiles tagged `EnemyProjectile`, so they fly and hit
+/// walls like the player's spells but only ever damage the player.
+///
+/// # Arguments
+/// * `commands` - Used to spawn the projectiles.
+/// * `time` - Resource to get time information for the cooldowns.
+/// * `level_walls` - Resource used to check line of sight.
+/// * `player_query` - Query to access the player's position.
+/// * `enemy_query` - Query to access ranged enemies' state, positions and attacks.
+/// * `spell_fire_assets` - Resource holding the projectile effect.
+/// * `grid_size` - Resource giving the size of a cell, for the projectile's range.
+///
+#[allow(clippy::type_complexity)]
+fn shoot_at_player(
+    mut commands: Commands,
+    time: Res<Time>,
+    level_walls: Res<LevelWalls>,
+    player_query: Query<(&Transform, &GridCoords), (With<Player>, Without<Enemy>)>,
+    mut enemy_query: Query<
+        (&EnemyState, &Transform, &GridCoords, &mut RangedAttack),
+        (With<Enemy>, Without<Player>),
+    >,
+    spell_fire_assets: Res<SpellFireAssets>,
+    grid_size: Res<GridSize>,
+) {
+    for (state, transform, grid_coords, mut ranged) in enemy_query.iter_mut() {
+        let Some((player_transform, player_cell)) = player_query.iter().min_by(|(a, _), (b, _)| {
+            let distance =
+                |player: &Transform| player.translation.distance_squared(transform.translation);
+            distance(a).total_cmp(&distance(b))
+        }) else {
+            return;
+        };
+        ranged.cooldown.tick(time.delta());
+        if *state != EnemyState::Chase
+            || !ranged.cooldown.finished()
+            || !has_shot(&level_walls, &ranged, *grid_coords, *player_cell)
+        {
+            continue;
+        }
+        ranged.cooldown.reset();
+
+        let direction = (player_transform.translation - transform.translation)
+            .truncate()
+            .normalize_or_zero();
+        info!(
+            "👾enemy at {:?} shoots at player at {:?}",
+            *grid_coords, *player_cell
+        );
+        commands.spawn((
+            EnemyProjectile,
+            SpellProjectile {
+                velocity: direction * ENEMY_PROJECTILE_SPEED,
+                damage: ranged.damage,
+                traveled: 0.0,
+                range: ENEMY_PROJECTILE_RANGE * grid_size.pixels(),
+            },
+            DespawnTimer::new(SPELL_FIRE_LIFETIME),
+            CollisionLayer::EnemySpell.groups(),
+            ParticleEffectBundle {
+                transform: Transform::from_translation(transform.translation + Vec3::Z),
+                ..ParticleEffectBundle::new(spell_fire_assets.effects[0].clone())
+            },
+            Name::new("enemy_projectile"),
+        ));
+    }
+}
+
+/// Damages the player with enemy projectiles within `PLAYER_HIT_RADIUS`, using them up.
+///
+/// # Arguments
+/// * `commands` - Used to despawn spent projectiles.
+/// * `projectile_query` - Query to access enemy projectiles and their positions.
+/// * `player_query` - Query to access players' positions and health.
+/// * `impact_events` - Event writer used to report projectiles being used up.
+/// * `damaged_events` - Event writer used to report the player being hurt.
+/// * `grid_size` - Resource giving the size of a cell, for the hit radius.
+///
+#[allow(clippy::type_complexity)]
+fn hit_player_with_enemy_projectiles(
+    mut commands: Commands,
+    projectile_query: Query<(Entity, &Transform, &SpellProjectile), With<EnemyProjectile>>,
+    mut player_query: Query<
+        (Entity, &Transform, &mut Health),
+        (With<Player>, Without<EnemyProjectile>),
+    >,
+    mut impact_events: EventWriter<SpellImpact>,
+    mut damaged_events: EventWriter<Damaged>,
+    grid_size: Res<GridSize>,
+) {
+    let hit_radius = PLAYER_HIT_RADIUS * grid_size.pixels();
+    for (projectile, projectile_transform, spell) in projectile_query.iter() {
+        let Some((player, _, mut health)) =
+            player_query
+                .iter_mut()
+                .find(|(_, player_transform, health)| {
+                    !health.is_dead()
+                        && player_transform
+                            .translation
+                            .truncate()
+                            .distance(projectile_transform.translation.truncate())
+                            <= hit_radius
+                })
+        else {
+            continue;
+        };
+        health.current -= spell.damage;
+        damaged_events.send(Damaged {
+            target: player,
+            amount: spell.damage,
+        });
+        info!(
+            "👾enemy projectile hit player for {}, {} left",
+            spell.damage, health.current
+        );
+        commands.entity(projectile).despawn_recursive();
+        impact_events.send(SpellImpact {
+            position: projectile_transform.translation,
+            kind: SpellKind::Fire,
+        });
+    }
+}
+
+/// Hurts the player when it and an enemy end up in the same cell, whichever moved.
+///
+/// # Arguments
+/// * `player_query` - Query to access players' grid positions and health.
+/// * `enemy_query` - Query to access enemies' grid positions and damage.
+/// * `damaged_events` - Event writer used to report the player being hurt.
+///
+#[allow(clippy::type_complexity)]
+fn damage_player_on_contact(
+    mut player_query: Query<(Entity, Ref<GridCoords>, &mut Health), With<Player>>,
+    enemy_query: Query<(Ref<GridCoords>, &ContactDamage), (With<Enemy>, Without<Player>)>,
+    mut damaged_events: EventWriter<Damaged>,
+) {
+    for (player, player_coords, mut health) in player_query.iter_mut() {
+        for (_, contact_damage) in enemy_query.iter().filter(|(enemy_coords, _)| {
+            **enemy_coords == *player_coords
+                && (player_coords.is_changed() || enemy_coords.is_changed())
+        }) {
+            health.current -= contact_damage.0;
+            damaged_events.send(Damaged {
+                target: player,
+                amount: contact_damage.0,
+            });
+            info!(
+                "👾enemy hit player for {} at {:?}, {} left",
+                contact_damage.0, *player_coords, health.current
+            );
+        }
+    }
+}
+
+/// Damages enemies hit by spell projectiles, despawning used up spells and killed enemies.
+///
+/// Projectiles shot by enemies are left to `hit_player_with_enemy_projectiles`.
+///
+/// A spell hits the nearest enemy within `ENEMY_HIT_RADIUS` of it and is used up,
+/// sending a `SpellImpact`. `Piercing` spells instead hit up to their remaining
+/// number of enemies, nearest first, skipping any they've already hit, and are only
+/// used up when they run out of hits. `Freezing` spells also slow the enemies they
+/// hit, stacking with any slow already on them. `Explosive` spells don't hurt the
+/// enemy they hit but go off there, sending a `SpellExplosion`.
+///
+/// # Arguments
+/// * `commands` - Used to despawn spent spells and killed enemies.
+/// * `spell_query` - Query to access spell projectiles, their positions and effects.
+/// * `enemy_query` - Query to access enemies' positions, health and slows.
+/// * `killed_events` - Event writer used to report killed enemies.
+/// * `impact_events` - Event writer used to report spells being used up.
+/// * `explosion_events` - Event writer used to set off explosive spells.
+/// * `damaged_events` - Event writer used to report enemies being hurt.
+/// * `dummy_query` - Query used to keep target dummies from being killed.
+/// * `grid_size` - Resource giving the size of a cell, for the hit radius.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn hit_enemies_with_spells(
+    mut commands: Commands,
+    mut spell_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &SpellProjectile,
+            Option<&Homing>,
+            Option<&Freezing>,
+            Option<&Explosive>,
+            Option<&mut Piercing>,
+        ),
+        Without<EnemyProjectile>,
+    >,
+    mut enemy_query: Query<
+        (Entity, &GlobalTransform, &mut Health, Option<&mut Slowed>),
+        With<Enemy>,
+    >,
+    mut killed_events: EventWriter<EnemyKilled>,
+    mut impact_events: EventWriter<SpellImpact>,
+    mut explosion_events: EventWriter<SpellExplosion>,
+    mut damaged_events: EventWriter<Damaged>,
+    dummy_query: Query<(), With<TargetDummy>>,
+    grid_size: Res<GridSize>,
+) {
+    let hit_radius = ENEMY_HIT_RADIUS * grid_size.pixels();
+    for (spell, spell_transform, projectile, homing, freezing, explosive, mut piercing) in
+        spell_query.iter_mut()
+    {
+        let spell_position = spell_transform.translation().truncate();
+        let already_hit = piercing
+            .as_ref()
+            .map_or(&[][..], |piercing| &piercing.already_hit[..]);
+        let mut targets: Vec<(Entity, f32)> = enemy_query
+            .iter()
+            .filter(|(enemy, _, health, _)| !health.is_dead() && !already_hit.contains(enemy))
+            .map(|(enemy, enemy_transform, _, _)| {
+                let distance = enemy_transform
+                    .translation()
+                    .truncate()
+                    .distance(spell_position);
+                (enemy, distance)
+            })
+            .filter(|(_, distance)| *distance <= hit_radius)
+            .collect();
+        if targets.is_empty() {
+            continue;
+        }
+        if let Some(explosive) = explosive {
+            commands.entity(spell).despawn_recursive();
+            impact_events.send(SpellImpact {
+                position: spell_transform.translation(),
+                kind: SpellKind::Explosion,
+            });
+            explosion_events.send(SpellExplosion {
+                position: spell_transform.translation(),
+                radius: explosive.radius * grid_size.pixels(),
+                damage: projectile.damage,
+            });
+            continue;
+        }
+        targets.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let max_hits = piercing
+            .as_ref()
+            .map_or(1, |piercing| piercing.hits_remaining);
+
+        for (enemy, _) in targets.into_iter().take(max_hits) {
+            let Ok((_, _, mut health, slowed)) = enemy_query.get_mut(enemy) else {
+                continue;
+            };
+            health.current -= projectile.damage;
+            damaged_events.send(Damaged {
+                target: enemy,
+                amount: projectile.damage,
+            });
+            info!(
+                "👾spell hit {:?} for {}, {} left",
+                enemy, projectile.damage, health.current
+            );
+            if health.is_dead() && !dummy_query.contains(enemy) {
+                commands.entity(enemy).despawn_recursive();
+                killed_events.send(EnemyKilled { enemy });
+            } else if let Some(freezing) = freezing {
+                match slowed {
+                    Some(mut slowed) => slowed.stack(freezing),
+                    None => {
+                        commands.entity(enemy).insert(Slowed::new(freezing));
+                    }
+                }
+            }
+            if let Some(piercing) = piercing.as_mut() {
+                piercing.hits_remaining -= 1;
+                piercing.already_hit.push(enemy);
+            }
+        }
+
+        if piercing.is_none_or(|piercing| piercing.hits_remaining == 0) {
+            commands.entity(spell).despawn_recursive();
+            impact_events.send(SpellImpact {
+                position: spell_transform.translation(),
+                kind: SpellKind::of_projectile(homing, freezing, explosive),
+            });
+        }
+    }
+}
+
+/// Damage an explosion deals to an enemy `distance` pixels from its center.
+///
+/// # Returns
+/// `damage` at the center, falling off linearly to nothing at `radius` and beyond.
+pub fn blast_damage(damage: f32, distance: f32, radius: f32) -> f32 {
+    if radius <= 0.0 {
+        return 0.0;
+    }
+    damage * (1.0 - distance / radius).clamp(0.0, 1.0)
+}
+
+/// Damages every enemy within reach of each explosion, despawning killed ones.
+///
+/// Enemies are found through `EnemyPositions`, and take `blast_damage` for how far
+/// they are from the explosion.
+///
+/// # Arguments
+/// * `commands` - Used to despawn killed enemies.
+/// * `explosion_events` - Event reader for explosive spells going off.
+/// * `enemy_positions` - Resource used to find the enemies near each explosion.
+/// * `health_query` - Query to access enemies' health.
+/// * `killed_events` - Event writer used to report killed enemies.
+/// * `damaged_events` - Event writer used to report enemies being hurt.
+/// * `dummy_query` - Query used to keep target dummies from being killed.
+///
+fn damage_enemies_in_blast(
+    mut commands: Commands,
+    mut explosion_events: EventReader<SpellExplosion>,
+    enemy_positions: Res<EnemyPositions>,
+    mut health_query: Query<&mut Health, With<Enemy>>,
+    mut killed_events: EventWriter<EnemyKilled>,
+    mut damaged_events: EventWriter<Damaged>,
+    dummy_query: Query<(), With<TargetDummy>>,
+) {
+    for explosion in explosion_events.iter() {
+        let center = explosion.position.truncate();
+        for (enemy, position) in enemy_positions.within(center, explosion.radius) {
+            let Ok(mut health) = health_query.get_mut(enemy) else {
+                continue;
+            };
+            if health.is_dead() {
+                continue;
+            }
+            let damage = blast_damage(
+                explosion.damage,
+                position.distance(center),
+                explosion.radius,
+            );
+            health.current -= damage;
+            damaged_events.send(Damaged {
+                target: enemy,
+                amount: damage,
+            });
+            info!(
+                "👾blast hit {:?} for {:.2}, {:.2} left",
+                enemy, damage, health.current
+            );
+            if health.is_dead() && !dummy_query.contains(enemy) {
+                commands.entity(enemy).despawn_recursive();
+                killed_events.send(EnemyKilled { enemy });
+            }
+        }
+    }
+}
+
+/// Hurts the enemies standing in each `DamageField` every time its tick comes
+/// round, despawning killed ones. Fields that hurt the player burn it too.
+///
+/// Fields whose `DespawnTimer` ran out this frame are going out, and no longer burn.
+///
+/// # Arguments
+/// * `commands` - Used to despawn killed enemies.
+/// * `time` - Resource giving the time since the last frame, to tick the fields.
+/// * `grid_size` - Resource used to find the cell each enemy and player stands in.
+/// * `field_query` - Query to access the fields and how long they have left.
+/// * `enemy_query` - Query to access enemies' positions and health.
+/// * `player_query` - Query to access players' positions and health.
+/// * `killed_events` - Event writer used to report killed enemies.
+/// * `damaged_events` - Event writer used to report enemies and players being hurt.
+/// * `dummy_query` - Query used to keep target dummies from being killed.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn burn_in_damage_fields(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid_size: Res<GridSize>,
+    mut field_query: Query<(&mut DamageField, Option<&DespawnTimer>)>,
+    mut enemy_query: Query<(Entity, &GlobalTransform, &mut Health), (With<Enemy>, Without<Player>)>,
+    mut player_query: Query<
+        (Entity, &GlobalTransform, &mut Health),
+        (With<Player>, Without<Enemy>),
+    >,
+    mut killed_events: EventWriter<EnemyKilled>,
+    mut damaged_events: EventWriter<Damaged>,
+    dummy_query: Query<(), With<TargetDummy>>,
+) {
+    for (mut field, despawn_timer) in field_query.iter_mut() {
+        if despawn_timer.is_some_and(|despawn_timer| despawn_timer.timer.finished()) {
+            continue;
+        }
+        let ticks = field.tick.tick(time.delta()).times_finished_this_tick();
+        if ticks == 0 {
+            continue;
+        }
+        let damage = field.damage * ticks as f32;
+        let in_field = |transform: &GlobalTransform| {
+            field.covers(grid_size.to_grid_coords(transform.translation().truncate()))
+        };
+        for (enemy, transform, mut health) in enemy_query.iter_mut() {
+            if health.is_dead() || !in_field(transform) {
+                continue;
+            }
+            health.current -= damage;
+            damaged_events.send(Damaged {
+                target: enemy,
+                amount: damage,
+            });
+            info!(
+                "👾field burned {:?} for {}, {} left",
+                enemy, damage, health.current
+            );
+            if health.is_dead() && !dummy_query.contains(enemy) {
+                commands.entity(enemy).despawn_recursive();
+                killed_events.send(EnemyKilled { enemy });
+            }
+        }
+        if !field.hurts_player {
+            continue;
+        }
+        for (player, transform, mut health) in player_query.iter_mut() {
+            if health.is_dead() || !in_field(transform) {
+                continue;
+            }
+            health.current -= damage;
+            damaged_events.send(Damaged {
+                target: player,
+                amount: damage,
+            });
+            info!(
+                "👾field burned player for {}, {} left",
+                damage, health.current
+            );
+        }
+    }
+}
+
+/// Spawns a death burst where each killed enemy was, and rolls for its loot.
+///
+/// Runs after `hit_enemies_with_spells` but before its despawns are applied, so
+/// killed enemies can still be looked up. Loot lands as a `Collectible` in the
+/// enemy's layer, on the nearest walkable cell of the current level.
+///
+/// # Arguments
+/// * `commands` - Used to spawn the bursts and collectibles.
+/// * `killed_events` - Event reader for killed enemies.
+/// * `enemy_query` - Query to access killed enemies' positions, loot and sprites.
+/// * `level_walls` - Resource used to keep loot out of walls.
+/// * `enemy_assets` - Resource holding the death burst effect.
+/// * `rng` - Resource rolling for loot.
+/// * `grid_size` - Resource giving the size of a cell, to place the loot in its cell.
+///
+#[allow(clippy::type_complexity)]
+fn spawn_enemy_remains(
+    mut commands: Commands,
+    mut killed_events: EventReader<EnemyKilled>,
+    enemy_query: Query<(
+        &GlobalTransform,
+        &Transform,
+        &GridCoords,
+        Option<&LootDrop>,
+        Option<&Handle<TextureAtlas>>,
+        Option<&Parent>,
+    )>,
+    level_walls: Res<LevelWalls>,
+    enemy_assets: Res<EnemyAssets>,
+    mut rng: ResMut<GameRng>,
+    grid_size: Res<GridSize>,
+) {
+    for killed in killed_events.iter() {
+        let Ok((global_transform, transform, grid_coords, loot, texture_atlas, parent)) =
+            enemy_query.get(killed.enemy)
+        else {
+            continue;
+        };
+
+        commands.spawn((
+            ParticleEffectBundle {
+                transform: Transform::from_translation(global_transform.translation()),
+                ..ParticleEffectBundle::new(enemy_assets.death_burst.clone())
+            },
+            ImpactBurst,
+            DespawnTimer::new(SPELL_IMPACT_SECONDS),
+            Name::new("enemy_death"),
+        ));
+
+        let Some(loot) = loot.filter(|loot| rolls_loot(loot, &mut *rng)) else {
+            continue;
+        };
+        let Some(cell) = level_walls.nearest_walkable(*grid_coords) else {
+            continue;
+        };
+        info!("👾{:?} dropped {:?} at {:?}", killed.enemy, loot, cell);
+        let translation = grid_size.to_translation(cell);
+        let mut collectible = commands.spawn((
+            Collectible { value: loot.value },
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(COLLECTIBLE_SPRITE_INDEX),
+                texture_atlas: texture_atlas.cloned().unwrap_or_default(),
+                transform: Transform::from_translation(translation.extend(transform.translation.z)),
+                ..default()
+            },
+            cell,
+            Name::new("Collectible"),
+        ));
+        if let Some(parent) = parent {
+            collectible.set_parent(parent.get());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use super::*;
+    use crate::player::end_turn_on_cast;
+    use crate::spell_fire::SpellCast;
+    use crate::test_harness::{Harness, FRAME};
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_hard_enemy_stats() {
+        let (health, contact_damage) = enemy_stats(Difficulty::Hard);
+        assert_eq!(health.max, ENEMY_HEALTH_MAX * 2.0);
+        assert_eq!(health.current, health.max);
+        assert_eq!(contact_damage, ContactDamage(ENEMY_CONTACT_DAMAGE * 2.0));
+
+        let (easy_health, _) = enemy_stats(Difficulty::Easy);
+        assert!(easy_health.max < health.max);
+    }
+
+    #[test]
+    fn test_enemy_copies() {
+        let copies = |enemy_count| {
+            (0..4)
+                .map(|i| enemy_copies(i, enemy_count))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(copies(1.0), vec![1, 1, 1, 1]);
+        assert_eq!(copies(0.5), vec![0, 1, 0, 1]);
+        assert_eq!(copies(1.5), vec![1, 2, 1, 2]);
+        assert_eq!(
+            copies(Difficulty::Hard.multipliers().enemy_count)
+                .iter()
+                .sum::<usize>(),
+            6
+        );
+    }
+
+    #[test]
+    fn test_spells_kill_enemies() {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .init_resource::<GridSize>()
+            .add_systems(Update, hit_enemies_with_spells);
+        let enemy = app
+            .world
+            .spawn((
+                Enemy,
+                GlobalTransform::from_xyz(100.0, 50.0, 0.0),
+                Health {
+                    current: 1.5,
+                    max: 1.5,
+                },
+            ))
+            .id();
+        let spell = |app: &mut App, x: f32| {
+            app.world
+                .spawn((
+                    GlobalTransform::from_xyz(x, 50.0, 0.0),
+                    SpellProjectile {
+                        velocity: Vec2::ZERO,
+                        damage: 1.0,
+                        traveled: 0.0,
+                        range: SPELL_FIRE_RANGE * GridSize::default().pixels(),
+                    },
+                ))
+                .id()
+        };
+
+        // Out of range: nothing happens
+        let miss = spell(
+            &mut app,
+            100.0 + ENEMY_HIT_RADIUS * GridSize::default().pixels() * 2.0,
+        );
+        app.update();
+        assert!(app.world.get_entity(miss).is_some());
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 1.5);
+
+        let hit = spell(&mut app, 100.0);
+        app.update();
+        assert!(app.world.get_entity(hit).is_none());
+        assert_eq!(app.world.resource::<Events<SpellImpact>>().len(), 1);
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 0.5);
+        assert!(app.world.resource::<Events<EnemyKilled>>().is_empty());
+
+        spell(&mut app, 100.0);
+        app.update();
+        assert!(app.world.get_entity(enemy).is_none());
+        let killed: Vec<EnemyKilled> = app
+            .world
+            .resource_mut::<Events<EnemyKilled>>()
+            .drain()
+            .collect();
+        assert_eq!(killed, vec![EnemyKilled { enemy }]);
+    }
+
+    #[test]
+    fn test_spells_never_kill_target_dummies() {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .init_resource::<GridSize>()
+            .add_systems(Update, hit_enemies_with_spells);
+        let dummy = app
+            .world
+            .spawn((
+                Enemy,
+                TargetDummy::default(),
+                GlobalTransform::IDENTITY,
+                Health {
+                    current: 1.0,
+                    max: 1.0,
+                },
+            ))
+            .id();
+        app.world.spawn((GlobalTransform::IDENTITY, projectile()));
+        app.update();
+        // Knocked down, but still standing for its reset
+        assert!(app.world.get::<Health>(dummy).unwrap().is_dead());
+        assert!(app.world.get_entity(dummy).is_some());
+        assert!(app.world.resource::<Events<EnemyKilled>>().is_empty());
+        assert_eq!(app.world.resource::<Events<Damaged>>().len(), 1);
+    }
+
+    /// Spawns an enemy with 5 health at each of `xs`, in a row along y = 0.
+    fn spawn_enemies_in_row<const N: usize>(app: &mut App, xs: [f32; N]) -> [Entity; N] {
+        xs.map(|x| {
+            app.world
+                .spawn((
+                    Enemy,
+                    GlobalTransform::from_xyz(x, 0.0, 0.0),
+                    Health {
+                        current: 5.0,
+                        max: 5.0,
+                    },
+                ))
+                .id()
+        })
+    }
+
+    /// Builds an app running `hit_enemies_with_spells`, with two overlapping enemies.
+    fn overlapping_enemies_app() -> (App, [Entity; 2]) {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .init_resource::<GridSize>()
+            .add_systems(Update, hit_enemies_with_spells);
+        let enemies = spawn_enemies_in_row(&mut app, [2.0, 4.0]);
+        (app, enemies)
+    }
+
+    fn projectile() -> SpellProjectile {
+        SpellProjectile {
+            velocity: Vec2::ZERO,
+            damage: 1.0,
+            traveled: 0.0,
+            range: SPELL_FIRE_RANGE * GridSize::default().pixels(),
+        }
+    }
+
+    #[test]
+    fn test_spell_hits_only_nearest_overlapping_enemy() {
+        let (mut app, [near, far]) = overlapping_enemies_app();
+        let spell = app
+            .world
+            .spawn((GlobalTransform::IDENTITY, projectile()))
+            .id();
+        app.update();
+        assert!(app.world.get_entity(spell).is_none());
+        assert_eq!(app.world.get::<Health>(near).unwrap().current, 4.0);
+        assert_eq!(app.world.get::<Health>(far).unwrap().current, 5.0);
+        assert_eq!(app.world.resource::<Events<SpellImpact>>().len(), 1);
+    }
+
+    #[test]
+    fn test_piercing_spell_hits_each_enemy_once() {
+        let (mut app, [near, far]) = overlapping_enemies_app();
+        let spell = app
+            .world
+            .spawn((GlobalTransform::IDENTITY, projectile(), Piercing::new(3)))
+            .id();
+        app.update();
+        assert_eq!(app.world.get::<Health>(near).unwrap().current, 4.0);
+        assert_eq!(app.world.get::<Health>(far).unwrap().current, 4.0);
+        assert_eq!(app.world.get::<Piercing>(spell).unwrap().hits_remaining, 1);
+
+        // Still overlapping both, but it has already hit them
+        app.update();
+        assert_eq!(app.world.get::<Health>(near).unwrap().current, 4.0);
+        assert_eq!(app.world.get::<Health>(far).unwrap().current, 4.0);
+        assert!(app.world.resource::<Events<SpellImpact>>().is_empty());
+    }
+
+    #[test]
+    fn test_piercing_spell_used_up() {
+        let (mut app, [near, far]) = overlapping_enemies_app();
+        let spell = app
+            .world
+            .spawn((GlobalTransform::IDENTITY, projectile(), Piercing::new(1)))
+            .id();
+        app.update();
+        assert!(app.world.get_entity(spell).is_none());
+        assert_eq!(app.world.get::<Health>(near).unwrap().current, 4.0);
+        assert_eq!(app.world.get::<Health>(far).unwrap().current, 5.0);
+    }
+
+    #[test]
+    fn test_freezing_spell_slows_and_stacks() {
+        let (mut app, [near, far]) = overlapping_enemies_app();
+        let freezing = Freezing {
+            factor: 0.5,
+            seconds: 2.0,
+        };
+        for _ in 0..3 {
+            app.world
+                .spawn((GlobalTransform::IDENTITY, projectile(), freezing));
+            app.update();
+        }
+        // Each hit slows further, but never past the limit
+        let slowed = app.world.get::<Slowed>(near).unwrap();
+        assert_eq!(slowed.factor, ENEMY_SLOW_MIN_FACTOR);
+        assert_eq!(slowed.timer.duration().as_secs_f32(), 2.0);
+        assert!(app.world.get::<Slowed>(far).is_none());
+    }
+
+    #[test]
+    fn test_blast_damage_falls_off() {
+        assert_eq!(blast_damage(4.0, 0.0, 40.0), 4.0);
+        assert_eq!(blast_damage(4.0, 10.0, 40.0), 3.0);
+        assert_eq!(blast_damage(4.0, 40.0, 40.0), 0.0);
+        assert_eq!(blast_damage(4.0, 50.0, 40.0), 0.0);
+    }
+
+    #[test]
+    fn test_explosion_damages_enemies_in_radius() {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .add_event::<SpellExplosion>()
+            .init_resource::<EnemyPositions>()
+            .init_resource::<GridSize>()
+            .add_systems(
+                Update,
+                (
+                    index_enemy_positions,
+                    hit_enemies_with_spells,
+                    damage_enemies_in_blast
+                        .after(index_enemy_positions)
+                        .after(hit_enemies_with_spells),
+                ),
+            );
+        // 40 pixels, in 16 pixel cells
+        let radius = 2.5;
+        let enemies = spawn_enemies_in_row(&mut app, [0.0, 10.0, 30.0, 50.0]);
+        let spell = app
+            .world
+            .spawn((
+                GlobalTransform::IDENTITY,
+                projectile(),
+                Explosive { radius },
+            ))
+            .id();
+        app.update();
+
+        assert!(app.world.get_entity(spell).is_none());
+        let health: Vec<f32> = enemies
+            .iter()
+            .map(|&enemy| app.world.get::<Health>(enemy).unwrap().current)
+            .collect();
+        // Full damage at the center, less further out, none outside the radius
+        assert_eq!(health, vec![4.0, 4.25, 4.75, 5.0]);
+        assert_eq!(app.world.resource::<Events<SpellImpact>>().len(), 1);
+    }
+
+    #[test]
+    fn test_damage_field_burns_until_it_goes_out() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<GridSize>()
+            .add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_systems(
+                Update,
+                (
+                    tick_despawn_timers,
+                    burn_in_damage_fields.after(tick_despawn_timers),
+                ),
+            );
+        let grid_size = GridSize::default();
+        let [inside, outside] = spawn_enemies_in_row(
+            &mut app,
+            [1, 4].map(|x| grid_size.to_translation(GridCoords::new(x, 0)).x),
+        );
+        app.world.spawn((
+            DamageField::new(
+                vec![GridCoords::new(1, 0), GridCoords::new(2, 0)],
+                1.0,
+                1.0,
+                false,
+            ),
+            DespawnTimer::new(2.5),
+        ));
+
+        let mut burned = Vec::new();
+        for second in 1..=4 {
+            let now = app.world.resource::<Time>().startup() + Duration::from_secs(second);
+            app.world.resource_mut::<Time>().update_with_instant(now);
+            app.update();
+            burned.push(app.world.get::<Health>(inside).unwrap().current);
+        }
+        // A burn every second while the field lasts, and none once it has gone out
+        assert_eq!(burned, vec![4.0, 3.0, 3.0, 3.0]);
+        assert_eq!(app.world.get::<Health>(outside).unwrap().current, 5.0);
+        assert!(app
+            .world
+            .query::<&DamageField>()
+            .iter(&app.world)
+            .next()
+            .is_none());
+    }
+
+    /// Runs an enemy chasing the player down a long corridor, returning the app and
+    /// the enemy.
+    fn chasing_enemy(slowed: Option<Slowed>) -> (Harness, Entity) {
+        let mut harness = Harness::new(&[], 20, 3, GridCoords::new(1, 1));
+        harness.app.insert_resource(GameRng::new(0)).add_systems(
+            FixedUpdate,
+            (move_enemies, wear_off_slows.after(move_enemies)),
+        );
+        let enemy_cell = GridCoords::new(18, 1);
+        let mut ai = EnemyAi::new(enemy_cell);
+        ai.detection_radius = 30.0;
+        ai.leash_radius = 30.0;
+        let mut enemy = harness.app.world.spawn((
+            Enemy,
+            EnemyState::default(),
+            ai,
+            enemy_cell,
+            Transform::default(),
+        ));
+        if let Some(slowed) = slowed {
+            enemy.insert(slowed);
+        }
+        let enemy = enemy.id();
+        (harness, enemy)
+    }
+
+    /// The cells the enemy has stepped since it spawned.
+    fn cells_walked(harness: &Harness, enemy: Entity) -> i32 {
+        18 - harness.app.world.get::<GridCoords>(enemy).unwrap().x
+    }
+
+    #[test]
+    fn test_slowed_enemy_steps_slower_until_it_wears_off() {
+        let (mut normal, normal_enemy) = chasing_enemy(None);
+        normal.step(120);
+        let slowed = Slowed::new(&Freezing {
+            factor: 0.5,
+            seconds: 2.0,
+        });
+        let (mut harness, enemy) = chasing_enemy(Some(slowed));
+        harness.step(120);
+        assert_eq!(cells_walked(&normal, normal_enemy), 4);
+        assert_eq!(cells_walked(&harness, enemy), 2);
+
+        // Once the slow wears off it steps at the normal rate again
+        harness.step(2);
+        assert!(harness.app.world.get::<Slowed>(enemy).is_none());
+        let before = cells_walked(&harness, enemy);
+        harness.step(120);
+        assert!(cells_walked(&harness, enemy) - before >= 4);
+    }
+
+    /// `count` enemies scattered over a square `size` pixels across, from a fixed seed.
+    fn scattered_enemies(count: u32, size: f32) -> Vec<(Entity, Vec2)> {
+        let mut rng = GameRng::new(7);
+        (0..count)
+            .map(|index| {
+                let position = Vec2::new(rng.gen_range(-size..size), rng.gen_range(-size..size));
+                (Entity::from_raw(index), position)
+            })
+            .collect()
+    }
+
+    /// The entities in `found`, sorted so results can be compared.
+    fn sorted(found: Vec<(Entity, Vec2)>) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = found.into_iter().map(|(enemy, _)| enemy).collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn test_enemies_within_radius() {
+        let near = Entity::from_raw(0);
+        let edge = Entity::from_raw(1);
+        let far = Entity::from_raw(2);
+        let enemies = vec![
+            (near, Vec2::new(10.0, 0.0)),
+            (edge, Vec2::new(0.0, -50.0)),
+            (far, Vec2::new(40.0, 40.0)), // About 56.6 away
+        ];
+        for bucketed in [false, true] {
+            let positions =
+                EnemyPositions::with_cells(enemies.clone(), bucketed, GridSize::default());
+            assert_eq!(sorted(positions.within(Vec2::ZERO, 50.0)), vec![near, edge]);
+            assert_eq!(
+                positions.nearest_within(Vec2::ZERO, 50.0),
+                Some((near, Vec2::new(10.0, 0.0)))
+            );
+            assert_eq!(positions.nearest_within(Vec2::new(500.0, 0.0), 50.0), None);
+        }
+    }
+
+    #[test]
+    fn test_bucketed_search_matches_checking_every_enemy() {
+        let enemies = scattered_enemies(500, 400.0);
+        let naive = EnemyPositions::with_cells(enemies.clone(), false, GridSize::default());
+        // The cells scale with the level's grid
+        for grid_size in [GridSize::default(), GridSize(8)] {
+            let bucketed = EnemyPositions::new(enemies.clone(), grid_size);
+            assert!(!bucketed.cells.is_empty());
+            assert_eq!(
+                bucketed.cell_size,
+                ENEMY_INDEX_CELL_SIZE * grid_size.pixels()
+            );
+            for (center, radius) in [
+                (Vec2::ZERO, 0.0),
+                (Vec2::ZERO, 37.5),
+                (Vec2::new(-123.0, 250.0), 100.0),
+                (Vec2::new(390.0, -390.0), 64.0),
+                (Vec2::ZERO, 1000.0),
+            ] {
+                assert_eq!(
+                    sorted(bucketed.within(center, radius)),
+                    sorted(naive.within(center, radius))
+                );
+            }
+        }
+    }
+
+    #[bench]
+    fn bench_enemies_within_checking_each(b: &mut test::Bencher) {
+        let positions =
+            EnemyPositions::with_cells(scattered_enemies(5000, 2000.0), false, GridSize::default());
+        b.iter(|| positions.nearest_within(Vec2::new(100.0, 100.0), SPELL_HOMING_RADIUS));
+    }
+
+    #[bench]
+    fn bench_enemies_within_bucketed(b: &mut test::Bencher) {
+        let positions =
+            EnemyPositions::with_cells(scattered_enemies(5000, 2000.0), true, GridSize::default());
+        b.iter(|| positions.nearest_within(Vec2::new(100.0, 100.0), SPELL_HOMING_RADIUS));
+    }
+
+    #[test]
+    fn test_loot_roll_seeded() {
+        let rolls = |chance: f32, seed: u64| {
+            let loot = LootDrop { chance, value: 1 };
+            let mut rng = GameRng::new(seed);
+            (0..1000).filter(|_| rolls_loot(&loot, &mut rng)).count()
+        };
+        assert_eq!(rolls(0.0, 1), 0);
+        assert_eq!(rolls(1.0, 1), 1000);
+        let quarter = rolls(0.25, 1);
+        assert!((200..300).contains(&quarter));
+        // The same seed rolls the same drops
+        assert_eq!(rolls(0.25, 1), quarter);
+    }
+
+    #[test]
+    fn test_loot_drops_on_walkable_cell() {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .insert_resource(LevelWalls::from_cells(&[(2, 2)], 5, 5))
+            .insert_resource(GameRng::new(0))
+            .init_resource::<GridSize>()
+            .insert_resource(EnemyAssets {
+                death_burst: Handle::default(),
+            })
+            .add_systems(
+                Update,
+                (
+                    hit_enemies_with_spells,
+                    spawn_enemy_remains.after(hit_enemies_with_spells),
+                ),
+            );
+        // An enemy that has ended up inside a wall
+        let enemy = app
+            .world
+            .spawn((
+                Enemy,
+                GlobalTransform::IDENTITY,
+                Transform::IDENTITY,
+                GridCoords::new(2, 2),
+                LootDrop {
+                    chance: 1.0,
+                    value: 50,
+                },
+                Health {
+                    current: 1.0,
+                    max: 1.0,
+                },
+            ))
+            .id();
+        app.world.spawn((GlobalTransform::IDENTITY, projectile()));
+        app.update();
+
+        assert!(app.world.get_entity(enemy).is_none());
+        let drops: Vec<(GridCoords, Collectible)> = app
+            .world
+            .query::<(&GridCoords, &Collectible)>()
+            .iter(&app.world)
+            .map(|(cell, collectible)| (*cell, *collectible))
+            .collect();
+        assert_eq!(drops.len(), 1);
+        let (cell, collectible) = drops[0];
+        assert_eq!(collectible, Collectible { value: 50 });
+        assert!(!app.world.resource::<LevelWalls>().in_wall(&cell));
+        assert_eq!(grid_manhattan(cell, GridCoords::new(2, 2)), 1);
+        let bursts = app
+            .world
+            .query_filtered::<(), With<ImpactBurst>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(bursts, 1);
+    }
+
+    #[test]
+    fn test_enemy_state_transitions() {
+        let ai = EnemyAi {
+            detection_radius: 3.0,
+            leash_radius: 6.0,
+            ..EnemyAi::new(GridCoords::new(0, 0))
+        };
+        let enemy = GridCoords::new(2, 0);
+        let player_at = |x| Some(GridCoords::new(x, 0));
+        let next = |state, player| next_enemy_state(state, &ai, enemy, player);
+
+        // Just outside and just inside the detection radius
+        assert_eq!(next(EnemyState::Idle, player_at(6)), EnemyState::Idle);
+        assert_eq!(next(EnemyState::Idle, player_at(5)), EnemyState::Chase);
+        assert_eq!(next(EnemyState::Patrol, player_at(6)), EnemyState::Patrol);
+        assert_eq!(next(EnemyState::Patrol, player_at(5)), EnemyState::Chase);
+
+        // Chasing continues past the detection radius, up to the leash
+        assert_eq!(next(EnemyState::Chase, player_at(8)), EnemyState::Chase);
+        assert_eq!(next(EnemyState::Chase, player_at(9)), EnemyState::Return);
+        assert_eq!(next(EnemyState::Chase, None), EnemyState::Return);
+
+        // Returning enemies can be drawn back in, and go idle once home
+        assert_eq!(next(EnemyState::Return, player_at(9)), EnemyState::Return);
+        assert_eq!(next(EnemyState::Return, player_at(4)), EnemyState::Chase);
+        assert_eq!(
+            next_enemy_state(EnemyState::Return, &ai, ai.origin, player_at(9)),
+            EnemyState::Idle
+        );
+    }
+
+    #[test]
+    fn test_step_towards_cell() {
+        // A wall at (1, 0) between (0, 0) and (2, 0)
+        let level_walls = LevelWalls::from_cells(&[(1, 0)], 4, 3);
+        let from = GridCoords::new(0, 0);
+        assert_eq!(
+            step_towards_cell(&level_walls, from, GridCoords::new(0, 2)),
+            GridCoords::new(0, 1)
+        );
+        // The direct step is blocked, and going up doesn't get any closer
+        assert_eq!(
+            step_towards_cell(&level_walls, from, GridCoords::new(2, 0)),
+            from
+        );
+        assert_eq!(step_towards_cell(&level_walls, from, from), from);
+    }
+
+    #[test]
+    fn test_enemies_stay_in_offset_level_with_open_neighbor() {
+        // level-a is 4x4 cells at (10, 10) in the world, with an open level-b east of it
+        let mut level_walls = LevelWalls::from_cells(&[], 4, 4)
+            .with_level_iid("level-a")
+            .with_origin(IVec2::new(10, 10));
+        level_walls.add_levels(
+            LevelWalls::from_cells(&[], 4, 4)
+                .with_level_iid("level-b")
+                .with_origin(IVec2::new(14, 10)),
+        );
+        let edge = GridCoords::new(3, 1);
+        assert!(!level_walls.in_wall(&GridCoords::new(4, 1)));
+        assert!(level_walls.in_level_wall(&GridCoords::new(4, 1)));
+
+        // A target past the edge doesn't pull the enemy into the neighbor
+        assert_eq!(
+            step_towards_cell(&level_walls, edge, GridCoords::new(6, 1)),
+            edge
+        );
+        // Nor does stepping aside for another enemy
+        let occupied = HashMap::from([
+            (edge, 1),
+            (GridCoords::new(3, 0), 1),
+            (GridCoords::new(3, 2), 1),
+        ]);
+        let cell = unoccupied_cell(&level_walls, &occupied, edge, edge);
+        assert_eq!(cell, GridCoords::new(2, 1));
+        // Nor does patrolling
+        let patrol: Vec<GridCoords> = patrol_cells(&level_walls, edge).collect();
+        assert!(!patrol.is_empty());
+        assert!(patrol.iter().all(|cell| level_walls.in_bounds(cell)));
+    }
+
+    #[test]
+    fn test_enemies_targeting_same_cell_separate() {
+        let player = GridCoords::new(2, 2);
+        let mut harness = Harness::new(&[], 5, 5, player);
+        // One turn, so each enemy takes exactly one step
+        harness
+            .app
+            .insert_resource(MovementMode::TurnBased)
+            .insert_resource(PendingTurns(1))
+            .insert_resource(GameRng::new(0))
+            .add_systems(FixedUpdate, move_enemies);
+        // Either side of the player, so both step toward the player's cell
+        let enemies: Vec<Entity> = [GridCoords::new(1, 2), GridCoords::new(3, 2)]
+            .into_iter()
+            .map(|cell| {
+                harness
+                    .app
+                    .world
+                    .spawn((
+                        Enemy,
+                        EnemyState::default(),
+                        EnemyAi::new(cell),
+                        cell,
+                        Transform::default(),
+                    ))
+                    .id()
+            })
+            .collect();
+        harness.step(4);
+        assert_eq!(harness.app.world.resource::<PendingTurns>().0, 0);
+
+        let destinations: Vec<GridCoords> = enemies
+            .iter()
+            .map(|&enemy| *harness.app.world.get::<GridCoords>(enemy).unwrap())
+            .collect();
+        assert!(destinations.contains(&player));
+        assert_ne!(destinations[0], destinations[1]);
+        let level_walls = harness.app.world.resource::<LevelWalls>();
+        assert!(destinations.iter().all(|cell| !level_walls.in_wall(cell)));
+
+        // Two enemies stacked in one cell: the second steps off the first
+        let level_walls = LevelWalls::from_cells(&[], 5, 5);
+        let stacked = GridCoords::new(0, 0);
+        let occupied = HashMap::from([(stacked, 1)]);
+        let cell = unoccupied_cell(&level_walls, &occupied, stacked, stacked);
+        assert_ne!(cell, stacked);
+        assert!(!level_walls.in_wall(&cell));
+    }
+
+    #[test]
+    fn test_turn_based_press_moves_player_and_enemy_once() {
+        let mut harness = Harness::new(&[], 8, 3, GridCoords::new(1, 1));
+        harness
+            .app
+            .insert_resource(MovementMode::TurnBased)
+            .insert_resource(GameRng::new(0))
+            .add_systems(FixedUpdate, move_enemies.after(move_player_from_input));
+        let enemy_cell = GridCoords::new(5, 1);
+        let enemy = harness
+            .app
+            .world
+            .spawn((
+                Enemy,
+                EnemyState::default(),
+                EnemyAi::new(enemy_cell),
+                enemy_cell,
+                Transform::default(),
+            ))
+            .id();
+
+        // Long enough for several timed steps, but it's only one press
+        harness.hold(&[KeyCode::D], 60);
+        harness.step(60);
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 1));
+        assert_eq!(
+            *harness.app.world.get::<GridCoords>(enemy).unwrap(),
+            GridCoords::new(4, 1)
+        );
+        assert_eq!(
+            *harness.app.world.get::<EnemyState>(enemy).unwrap(),
+            EnemyState::Chase
+        );
+    }
+
+    #[test]
+    fn test_turn_based_cast_between_fixed_steps_moves_enemy_once() {
+        let mut harness = Harness::new(&[], 8, 3, GridCoords::new(1, 1));
+        // Four frames to each fixed step, so most frames run no step at all
+        harness
+            .app
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME / 4))
+            .insert_resource(MovementMode::TurnBased)
+            .insert_resource(GameRng::new(0))
+            .add_event::<SpellCast>()
+            .add_systems(Update, end_turn_on_cast)
+            .add_systems(FixedUpdate, move_enemies.after(move_player_from_input));
+        let enemy_cell = GridCoords::new(5, 1);
+        let enemy = harness
+            .app
+            .world
+            .spawn((
+                Enemy,
+                EnemyState::default(),
+                EnemyAi::new(enemy_cell),
+                enemy_cell,
+                Transform::default(),
+            ))
+            .id();
+        harness.step(2);
+
+        harness.app.world.send_event(SpellCast {
+            caster: harness.player,
+            kind: SpellKind::Fire,
+        });
+        harness.step(1);
+        assert_eq!(harness.app.world.resource::<PendingTurns>().0, 1);
+        // Long enough for the cast's event to have expired, and for several steps
+        harness.step(16);
+        assert_eq!(harness.app.world.resource::<PendingTurns>().0, 0);
+        assert_eq!(
+            *harness.app.world.get::<GridCoords>(enemy).unwrap(),
+            GridCoords::new(4, 1)
+        );
+    }
+
+    #[test]
+    fn test_ranged_enemy_needs_sight_and_cooldown() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(LevelWalls::from_cells(&[(2, 0)], 5, 1))
+            .insert_resource(SpellFireAssets {
+                effects: vec![Handle::default(); SPELL_FIRE_CHARGE_LEVELS + 1],
+                trail: Handle::default(),
+                impact_fire: Handle::default(),
+                impact_homing: Handle::default(),
+                impact_ice: Handle::default(),
+                explosion: Handle::default(),
+                mesh: Handle::default(),
+                material: Handle::default(),
+            })
+            .init_resource::<GridSize>()
+            .add_systems(Update, shoot_at_player);
+        app.world.spawn((
+            Player,
+            Transform::from_xyz(72.0, 8.0, 0.0),
+            GridCoords::new(4, 0),
+        ));
+        app.world.spawn((
+            Enemy,
+            EnemyState::Chase,
+            Transform::from_xyz(8.0, 8.0, 0.0),
+            GridCoords::new(0, 0),
+            RangedAttack::default(),
+        ));
+        let shots = |app: &mut App| {
+            let count = app
+                .world
+                .query_filtered::<(), With<EnemyProjectile>>()
+                .iter(&app.world)
+                .count();
+            count
+        };
+
+        // A wall in the way
+        app.update();
+        assert_eq!(shots(&mut app), 0);
+
+        // A clear line of sight: one shot, then nothing until the cooldown is over
+        app.insert_resource(LevelWalls::from_cells(&[], 5, 1));
+        app.update();
+        assert_eq!(shots(&mut app), 1);
+        app.update();
+        assert_eq!(shots(&mut app), 1);
+
+        let now = app.world.resource::<Time>().startup()
+            + bevy::utils::Duration::from_secs_f32(ENEMY_RANGED_COOLDOWN);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        assert_eq!(shots(&mut app), 2);
+    }
+
+    #[test]
+    fn test_enemy_projectiles_only_hit_player() {
+        let mut app = App::new();
+        app.add_event::<EnemyKilled>()
+            .add_event::<Damaged>()
+            .add_event::<SpellImpact>()
+            .init_resource::<GridSize>()
+            .add_systems(
+                Update,
+                (hit_enemies_with_spells, hit_player_with_enemy_projectiles),
+            );
+        let [enemy] = spawn_enemies_in_row(&mut app, [0.0]);
+        let shot = app
+            .world
+            .spawn((
+                EnemyProjectile,
+                GlobalTransform::IDENTITY,
+                Transform::IDENTITY,
+                projectile(),
+            ))
+            .id();
+        app.update();
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 5.0);
+        assert!(app.world.get_entity(shot).is_some());
+
+        let player = app
+            .world
+            .spawn((Player, Transform::IDENTITY, Health::default()))
+            .id();
+        app.update();
+        assert_eq!(
+            app.world.get::<Health>(player).unwrap().current,
+            PLAYER_HEALTH_MAX - 1.0
+        );
+        assert!(app.world.get_entity(shot).is_none());
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 5.0);
+    }
+
+    #[test]
+    fn test_damage_player_on_contact() {
+        let mut app = App::new();
+        app.add_event::<Damaged>()
+            .add_systems(Update, damage_player_on_contact);
+        app.world
+            .spawn((Enemy, GridCoords::new(2, 1), ContactDamage(1.0)));
+        let player = app
+            .world
+            .spawn((Player, GridCoords::new(1, 1), Health::default()))
+            .id();
+        app.update();
+        assert_eq!(
+            app.world.get::<Health>(player).unwrap().current,
+            PLAYER_HEALTH_MAX
+        );
+
+        *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(2, 1);
+        app.update();
+        assert_eq!(
+            app.world.get::<Health>(player).unwrap().current,
+            PLAYER_HEALTH_MAX - 1.0
+        );
+
+        // Standing still in the cell doesn't keep hurting
+        app.update();
+        assert_eq!(
+            app.world.get::<Health>(player).unwrap().current,
+            PLAYER_HEALTH_MAX - 1.0
+        );
+    }
+}
@@ -0,0 +1,118 @@
+// entity_diagnostics.rs
+
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+
+/// EntityDiagnosticsPlugin measures how many spells, wall colliders and enemies are alive.
+///
+/// Each count is a custom diagnostic under `entities/`, measured every frame, so the
+/// `LogDiagnosticsPlugin` added in `main` logs them with the frame time. A count
+/// that keeps climbing while nothing is happening on screen is a leak: projectiles
+/// that are never despawned, or wall colliders built again each time a level spawns.
+impl Plugin for EntityDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(
+            Self::SPELL_FIRE_COUNT,
+            "entities/spell_fire",
+            ENTITY_DIAGNOSTIC_HISTORY,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            Self::WALL_COLLIDER_COUNT,
+            "entities/wall_colliders",
+            ENTITY_DIAGNOSTIC_HISTORY,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            Self::ENEMY_COUNT,
+            "entities/enemies",
+            ENTITY_DIAGNOSTIC_HISTORY,
+        ))
+        .add_systems(Update, measure_entity_counts);
+    }
+}
+
+impl EntityDiagnosticsPlugin {
+    /// Live `SpellFire` projectiles.
+    pub const SPELL_FIRE_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5a1e_3c1f_0d6b_4e0a_9f6e_1b2c_7d4a_0001);
+    /// Wall colliders, merged, breakable and moving.
+    pub const WALL_COLLIDER_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5a1e_3c1f_0d6b_4e0a_9f6e_1b2c_7d4a_0002);
+    /// Live enemies.
+    pub const ENEMY_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5a1e_3c1f_0d6b_4e0a_9f6e_1b2c_7d4a_0003);
+}
+
+/// Records how many spells, wall colliders and enemies there are this frame.
+///
+/// # Arguments
+/// * `diagnostics` - Used to add this frame's measurements.
+/// * `spell_fire_query` - Query to count the live spells.
+/// * `wall_collider_query` - Query to count the wall colliders.
+/// * `enemy_query` - Query to count the live enemies.
+///
+fn measure_entity_counts(
+    mut diagnostics: Diagnostics,
+    spell_fire_query: Query<(), With<SpellFire>>,
+    wall_collider_query: Query<(), With<WallCollider>>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    diagnostics.add_measurement(EntityDiagnosticsPlugin::SPELL_FIRE_COUNT, || {
+        spell_fire_query.iter().count() as f64
+    });
+    diagnostics.add_measurement(EntityDiagnosticsPlugin::WALL_COLLIDER_COUNT, || {
+        wall_collider_query.iter().count() as f64
+    });
+    diagnostics.add_measurement(EntityDiagnosticsPlugin::ENEMY_COUNT, || {
+        enemy_query.iter().count() as f64
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::diagnostic::DiagnosticsStore;
+
+    /// The latest measurement of the diagnostic `id`.
+    fn measured(app: &App, id: DiagnosticId) -> Option<f64> {
+        app.world
+            .resource::<DiagnosticsStore>()
+            .get(id)
+            .and_then(|diagnostic| diagnostic.value())
+    }
+
+    #[test]
+    fn test_counts_reflect_spawned_entities() {
+        let mut app = App::new();
+        app.add_plugins(EntityDiagnosticsPlugin);
+        let spells: Vec<Entity> = (0..3).map(|_| app.world.spawn(SpellFire).id()).collect();
+        app.world.spawn(WallCollider);
+        app.world.spawn(WallCollider);
+        app.world.spawn(Enemy);
+        app.update();
+        assert_eq!(
+            measured(&app, EntityDiagnosticsPlugin::SPELL_FIRE_COUNT),
+            Some(3.0)
+        );
+        assert_eq!(
+            measured(&app, EntityDiagnosticsPlugin::WALL_COLLIDER_COUNT),
+            Some(2.0)
+        );
+        assert_eq!(
+            measured(&app, EntityDiagnosticsPlugin::ENEMY_COUNT),
+            Some(1.0)
+        );
+
+        // Despawned spells drop out of the next measurement
+        for spell in spells {
+            app.world.despawn(spell);
+        }
+        app.update();
+        assert_eq!(
+            measured(&app, EntityDiagnosticsPlugin::SPELL_FIRE_COUNT),
+            Some(0.0)
+        );
+    }
+}
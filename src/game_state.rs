@@ -0,0 +1,940 @@
+// game_state.rs
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::door::{start_teleport, PendingTeleport, ScreenFade, TeleportTarget};
+use crate::hud::score_text;
+use crate::map::LevelWalls;
+use crate::menu_navigation::navigate_menu;
+use crate::player::{input_unlocked, InputLocked, Lives, SpawnPoint};
+use crate::score::{HighScore, Score};
+use crate::settings::{Action, KeyBindings};
+use crate::settings_menu::SettingsState;
+use crate::spell_fire::Mana;
+
+/// GameStatePlugin moves the game between playing, the game over screen and the
+/// main menu, and pauses it while playing.
+///
+/// Entering `Playing` resets the run and spawns the LDtk world; leaving it
+/// despawns the world again. P pauses and resumes. While paused the game clock and
+/// the physics pipeline are stopped, so animations, cooldowns, fixed steps and
+/// bodies all hold still until play resumes.
+///
+/// R (rebindable) restarts the level the player is in: it's respawned from LDtk
+/// with its enemies and wall colliders, spells in flight are cleared, and the
+/// player goes back to the spawn point. The run itself, score and lives, carries on.
+///
+/// The map is `MAP_FILENAME` unless another is given with a `--map=<path>`
+/// command-line argument, like `--map=practice.ldtk` for the practice range. If
+/// the LDtk map is missing or corrupt, no level ever spawns. Rather than leave a
+/// blank screen, a failed load is logged and reported with an error screen leading
+/// back to the main menu.
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        let map_path = map_from_args(std::env::args()).map_or_else(MapPath::default, MapPath);
+        info!("🗺️map: {}", map_path.0);
+        app.insert_resource(map_path)
+            .add_state::<GameState>()
+            .add_state::<PauseState>()
+            .add_event::<MapLoadFailed>()
+            .add_systems(OnEnter(GameState::Playing), (reset_run, spawn_world))
+            .add_systems(
+                OnExit(GameState::Playing),
+                (despawn_world, unpause, despawn_menu_screen),
+            )
+            .add_systems(
+                OnEnter(PauseState::Paused),
+                (setup_pause_screen, stop_clock),
+            )
+            .add_systems(
+                OnExit(PauseState::Paused),
+                (despawn_pause_screen, start_clock),
+            )
+            .add_systems(OnEnter(GameState::GameOver), setup_game_over_screen)
+            .add_systems(OnExit(GameState::GameOver), despawn_menu_screen)
+            .add_systems(OnEnter(GameState::MainMenu), setup_main_menu)
+            .add_systems(OnExit(GameState::MainMenu), despawn_menu_screen)
+            .add_systems(
+                Update,
+                (
+                    handle_menu_buttons.after(navigate_menu),
+                    toggle_pause
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(in_state(SettingsState::Closed)),
+                    restart_level
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(input_unlocked),
+                    check_map_load.run_if(in_state(GameState::Playing)),
+                    show_map_load_error.after(check_map_load),
+                ),
+            );
+    }
+}
+
+/// The top-level state of the game.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Playing,
+    GameOver,
+    MainMenu,
+}
+
+/// Whether play is paused. Only changes while `Playing`.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Component for the buttons on the game over screen, main menu and pause screen.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    /// Start a fresh run from the game over screen.
+    Retry,
+    /// Go to the main menu.
+    Menu,
+    /// Start a fresh run from the main menu.
+    Play,
+    /// Carry on playing from the pause screen.
+    Resume,
+    /// Open the settings menu.
+    Settings,
+}
+
+/// Path of the LDtk map to play, relative to the assets folder.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct MapPath(pub String);
+
+impl Default for MapPath {
+    fn default() -> Self {
+        MapPath(MAP_FILENAME.to_string())
+    }
+}
+
+/// Finds a `--map=<path>` argument.
+///
+/// # Returns
+/// The path given by the last such argument, or `None` if there isn't one.
+pub fn map_from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    args.into_iter()
+        .filter_map(|arg| Some(arg.strip_prefix("--map=")?.to_string()))
+        .filter(|path| !path.is_empty())
+        .last()
+}
+
+/// Sent when the LDtk map can't be loaded, because it's missing or corrupt.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct MapLoadFailed {
+    /// The map's asset path.
+    pub path: String,
+}
+
+/// Marker for an LDtk world whose map failed to load, so it's only reported once.
+#[derive(Component)]
+struct MapLoadReported;
+
+/// Marker for the root of the game over screen or main menu.
+#[derive(Component)]
+struct MenuScreen;
+
+/// Marker for the root of the pause screen.
+#[derive(Component)]
+struct PauseScreen;
+
+/// Resets the resources belonging to a run, so a retry starts fresh.
+///
+/// # Arguments
+/// * `commands` - Used to forget the spawn point, which is recorded again when the level spawns.
+/// * `score` - Resource reset to zero.
+/// * `lives` - Resource reset to the difficulty's starting lives.
+/// * `difficulty` - Resource giving the starting lives.
+/// * `mana` - Resource refilled.
+/// * `level_selection` - Resource pointed back at the starting level.
+/// * `pending_teleport` - Resource cleared of any teleport in progress.
+///
+fn reset_run(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    difficulty: Res<Difficulty>,
+    mut mana: ResMut<Mana>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+) {
+    *score = Score::default();
+    *lives = Lives(difficulty.starting_lives());
+    *mana = Mana::default();
+    *level_selection = LevelSelection::default();
+    pending_teleport.0 = None;
+    commands.remove_resource::<SpawnPoint>();
+}
+
+/// Spawns the LDtk world, which brings in the starting level and the player.
+fn spawn_world(mut commands: Commands, asset_server: Res<AssetServer>, map_path: Res<MapPath>) {
+    commands.spawn(LdtkWorldBundle {
+        ldtk_handle: asset_server.load(map_path.0.as_str()),
+        ..Default::default()
+    });
+}
+
+/// Sends a `MapLoadFailed` for each LDtk world whose map has failed to load.
+///
+/// # Arguments
+/// * `commands` - Used to mark the world as reported.
+/// * `asset_server` - Resource giving the load state of the map.
+/// * `map_path` - Resource giving the map's path, if the asset server doesn't know it.
+/// * `world_query` - Query to access the LDtk worlds not yet reported.
+/// * `failed_events` - Event writer used to report the failure.
+///
+fn check_map_load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map_path: Res<MapPath>,
+    world_query: Query<(Entity, &Handle<LdtkAsset>), Without<MapLoadReported>>,
+    mut failed_events: EventWriter<MapLoadFailed>,
+) {
+    for (world, handle) in world_query.iter() {
+        if asset_server.get_load_state(handle) != LoadState::Failed {
+            continue;
+        }
+        commands.entity(world).insert(MapLoadReported);
+        let path = asset_server.get_handle_path(handle).map_or_else(
+            || map_path.0.clone(),
+            |path| path.path().display().to_string(),
+        );
+        failed_events.send(MapLoadFailed { path });
+    }
+}
+
+/// Logs each map that failed to load and shows an error screen with a Menu button.
+fn show_map_load_error(mut commands: Commands, mut failed_events: EventReader<MapLoadFailed>) {
+    for failed in failed_events.iter() {
+        error!(
+            "🗺️could not load the map {}: it's missing or corrupt",
+            failed.path
+        );
+        spawn_menu_screen(
+            &mut commands,
+            MenuScreen,
+            &[
+                "Could not load the map".to_string(),
+                format!("{} is missing or corrupt", failed.path),
+            ],
+            &[("Menu", MenuButton::Menu)],
+        );
+    }
+}
+
+/// Restarts the level the player is in when the restart key is pressed.
+///
+/// Spells and enemy projectiles in flight are despawned, and the level gets a
+/// `Respawn`, so bevy_ecs_ldtk despawns everything under it, wall colliders
+/// included, and spawns it afresh. Its walls are dropped from `LevelWalls`, so the
+/// player, who is detached from the level by the teleport, is only placed at the
+/// `SpawnPoint` once the fresh level's walls are cached. Without a spawn point the
+/// player is put back on the cell it's standing on. In local co-op both players go
+/// together, to player one's cell if there's no spawn point.
+///
+/// # Arguments
+/// * `commands` - Used to despawn projectiles and respawn the level.
+/// * `input_res` - Resource to check for the restart key.
+/// * `keys` - Resource giving the key bound to `Action::RestartLevel`.
+/// * `level_walls` - Resource giving the current level, whose walls are dropped.
+/// * `level_entities` - Query used to find the current level's entity.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+/// * `player_query` - Query to access the players' ids, cells, health and velocity.
+/// * `projectile_query` - Query selecting spells and enemy projectiles.
+/// * `spawn_point` - Resource holding where the player goes back to, if known.
+/// * `level_selection` - Resource switched to the spawn point's level.
+/// * `pending_teleport` - Resource recording the teleport to the spawn point.
+/// * `screen_fade` - Resource driving the fade overlay.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn restart_level(
+    mut commands: Commands,
+    input_res: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut level_walls: ResMut<LevelWalls>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    mut player_query: Query<
+        (
+            Entity,
+            Option<&PlayerId>,
+            &GridCoords,
+            &mut Health,
+            &mut Velocity2d,
+        ),
+        With<Player>,
+    >,
+    projectile_query: Query<Entity, Or<(With<SpellFire>, With<EnemyProjectile>)>>,
+    spawn_point: Option<Res<SpawnPoint>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut screen_fade: ResMut<ScreenFade>,
+) {
+    if !input_res.just_pressed(keys.key(Action::RestartLevel)) {
+        return;
+    }
+    let level_iid = level_walls.level_iid().to_string();
+    let Some(level_entity) = level_entities.iter().find_map(|(entity, handle)| {
+        level_assets
+            .get(handle)
+            .filter(|ldtk_level| ldtk_level.level.iid == level_iid)
+            .map(|_| entity)
+    }) else {
+        return;
+    };
+
+    info!("🔄restarting level {}", level_iid);
+    for entity in projectile_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.entity(level_entity).insert(Respawn);
+    level_walls.remove_level(&level_iid);
+
+    for (_, _, _, mut health, mut velocity) in player_query.iter_mut() {
+        health.current = health.max;
+        velocity.0 = Vec2::ZERO;
+    }
+    let Some((player, _, cell, ..)) = player_query
+        .iter()
+        .min_by_key(|(_, player_id, ..)| PlayerId::of(*player_id).0)
+    else {
+        return;
+    };
+    let target = match &spawn_point {
+        Some(spawn_point) => TeleportTarget {
+            player,
+            level_iid: spawn_point.level_iid.clone(),
+            cell: spawn_point.cell,
+        },
+        None => TeleportTarget {
+            player,
+            level_iid: level_iid.clone(),
+            cell: *cell,
+        },
+    };
+    start_teleport(
+        &mut commands,
+        target,
+        player_query.iter().map(|(entity, ..)| entity),
+        &mut level_selection,
+        &mut pending_teleport,
+        &mut screen_fade,
+    );
+}
+
+/// Despawns the LDtk world along with anything left behind by the run.
+///
+/// Players and spells aren't always children of the world (a player mid-teleport
+/// or a projectile in flight), so they're despawned separately.
+#[allow(clippy::type_complexity)]
+fn despawn_world(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        Or<(
+            With<Handle<LdtkAsset>>,
+            With<Player>,
+            With<SpellFire>,
+            With<EnemyProjectile>,
+        )>,
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Spawns a full-screen column of lines of text followed by buttons, with `marker`
+/// on its root.
+fn spawn_menu_screen(
+    commands: &mut Commands,
+    marker: impl Component,
+    lines: &[String],
+    buttons: &[(&str, MenuButton)],
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(HUD_MARGIN),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+                ..default()
+            },
+            marker,
+            Name::new("Menu screen"),
+        ))
+        .with_children(|screen| {
+            for line in lines {
+                screen.spawn(TextBundle::from_section(
+                    line.clone(),
+                    TextStyle {
+                        font_size: HUD_FONT_SIZE * 2.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+            }
+            for &(label, button) in buttons {
+                screen
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(HUD_MARGIN)),
+                                ..default()
+                            },
+                            background_color: Color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font_size: HUD_FONT_SIZE,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// Shows the final score with Retry and Menu buttons.
+fn setup_game_over_screen(mut commands: Commands, score: Res<Score>, high_score: Res<HighScore>) {
+    info!("💀game over with {:?}", *score);
+    spawn_menu_screen(
+        &mut commands,
+        MenuScreen,
+        &["Game Over".to_string(), score_text(&score, &high_score)],
+        &[("Retry", MenuButton::Retry), ("Menu", MenuButton::Menu)],
+    );
+}
+
+/// Shows the title and best score with Play and Settings buttons.
+fn setup_main_menu(mut commands: Commands, high_score: Res<HighScore>) {
+    spawn_menu_screen(
+        &mut commands,
+        MenuScreen,
+        &[
+            "Exterminator Wizard".to_string(),
+            format!("Best {}", high_score.best),
+        ],
+        &[
+            ("Play", MenuButton::Play),
+            ("Settings", MenuButton::Settings),
+        ],
+    );
+}
+
+/// Pauses or resumes play when P is pressed.
+fn toggle_pause(
+    input_res: Res<Input<KeyCode>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if !input_res.just_pressed(KeyCode::P) {
+        return;
+    }
+    next_pause_state.set(match pause_state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+    });
+}
+
+/// Resumes play when the run ends, so the next one doesn't start paused.
+fn unpause(mut next_pause_state: ResMut<NextState<PauseState>>) {
+    next_pause_state.set(PauseState::Running);
+}
+
+/// Locks gameplay input and shows the pause screen.
+fn setup_pause_screen(mut commands: Commands, mut input_locked: ResMut<InputLocked>) {
+    info!("⏸️paused");
+    input_locked.paused = true;
+    spawn_menu_screen(
+        &mut commands,
+        PauseScreen,
+        &["Paused".to_string()],
+        &[
+            ("Resume", MenuButton::Resume),
+            ("Settings", MenuButton::Settings),
+            ("Menu", MenuButton::Menu),
+        ],
+    );
+}
+
+/// Unlocks gameplay input and despawns the pause screen.
+fn despawn_pause_screen(
+    mut commands: Commands,
+    mut input_locked: ResMut<InputLocked>,
+    query: Query<Entity, With<PauseScreen>>,
+) {
+    input_locked.paused = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Stops game time and physics, so nothing driven by `Time` advances while paused.
+fn stop_clock(mut time: ResMut<Time>, rapier_config: Option<ResMut<RapierConfiguration>>) {
+    time.pause();
+    if let Some(mut rapier_config) = rapier_config {
+        rapier_config.physics_pipeline_active = false;
+    }
+}
+
+/// Restarts game time and physics when play resumes.
+fn start_clock(mut time: ResMut<Time>, rapier_config: Option<ResMut<RapierConfiguration>>) {
+    time.unpause();
+    if let Some(mut rapier_config) = rapier_config {
+        rapier_config.physics_pipeline_active = true;
+    }
+}
+
+/// Despawns the game over screen, main menu or map error screen.
+fn despawn_menu_screen(mut commands: Commands, query: Query<Entity, With<MenuScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Switches state when a menu button is pressed.
+fn handle_menu_buttons(
+    query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut next_settings_state: ResMut<NextState<SettingsState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            MenuButton::Retry | MenuButton::Play => next_state.set(GameState::Playing),
+            MenuButton::Menu => next_state.set(GameState::MainMenu),
+            MenuButton::Resume => next_pause_state.set(PauseState::Running),
+            MenuButton::Settings => next_settings_state.set(SettingsState::Open),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::door::finish_teleport;
+    use crate::map::GridSize;
+    use crate::menu_navigation::MenuSelection;
+    use crate::player::{
+        animate_sprites, player_translation, AnimationFinished, AnimationFrameReached,
+    };
+    use crate::settings_menu::RebindCapture;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::utils::Duration;
+    use bevy::utils::HashSet;
+
+    #[test]
+    fn test_restart_level_returns_player_to_spawn() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<LevelWalls>()
+            .init_resource::<GridSize>()
+            .init_resource::<LevelSelection>()
+            .init_resource::<PendingTeleport>()
+            .init_resource::<ScreenFade>()
+            .init_resource::<crate::player::CameraSnap>()
+            .insert_resource(SpawnPoint {
+                level_iid: "level-a".to_string(),
+                cell: GridCoords::new(1, 1),
+            })
+            .add_systems(
+                Update,
+                (restart_level, finish_teleport.after(restart_level)),
+            );
+        let handle = app
+            .world
+            .resource_mut::<Assets<LdtkLevel>>()
+            .add(LdtkLevel {
+                level: bevy_ecs_ldtk::ldtk::Level {
+                    iid: "level-a".to_string(),
+                    ..default()
+                },
+                background_image: None,
+            });
+        let level = app.world.spawn((SpatialBundle::default(), handle)).id();
+        let level_walls = || LevelWalls::new(HashSet::new(), 8, 8).with_level_iid("level-a");
+        app.world
+            .resource_mut::<LevelWalls>()
+            .add_levels(level_walls());
+        app.world
+            .resource_mut::<LevelWalls>()
+            .select_level("level-a");
+        let player = app
+            .world
+            .spawn((
+                Player,
+                Transform::from_xyz(100.0, 100.0, 0.0),
+                GridCoords::new(6, 6),
+                Health {
+                    current: 1.0,
+                    ..default()
+                },
+                Velocity2d(Vec2::X),
+            ))
+            .set_parent(level)
+            .id();
+        // A co-op partner elsewhere in the level goes along
+        let player_two = app
+            .world
+            .spawn((
+                Player,
+                PlayerId::TWO,
+                Transform::from_xyz(40.0, 40.0, 0.0),
+                GridCoords::new(2, 2),
+                Health::default(),
+                Velocity2d::default(),
+            ))
+            .set_parent(level)
+            .id();
+        let spell = app.world.spawn(SpellFire).id();
+        let enemy_shot = app.world.spawn(EnemyProjectile).id();
+        app.update();
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::R);
+        app.update();
+        // Projectiles are cleared and the level respawns, with the player lifted out
+        assert!(app.world.get_entity(spell).is_none());
+        assert!(app.world.get_entity(enemy_shot).is_none());
+        assert!(app.world.get::<Respawn>(level).is_some());
+        assert!(app.world.get::<Parent>(player).is_none());
+        assert!(app.world.get::<Parent>(player_two).is_none());
+        assert_eq!(
+            app.world.get::<Health>(player).unwrap().current,
+            PLAYER_HEALTH_MAX
+        );
+        assert_eq!(
+            app.world.get::<GridCoords>(player),
+            Some(&GridCoords::new(6, 6))
+        );
+
+        // Once the fresh level's walls are cached the player is back at the spawn point
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::R);
+        app.world
+            .resource_mut::<LevelWalls>()
+            .add_levels(level_walls());
+        app.update();
+        let spawn_cell = GridCoords::new(1, 1);
+        let translation = player_translation(spawn_cell, GridSize::default());
+        for player in [player, player_two] {
+            let transform = app.world.get::<Transform>(player).unwrap();
+            assert_eq!(transform.translation.truncate(), translation);
+            assert_eq!(app.world.get::<GridCoords>(player), Some(&spawn_cell));
+            assert_eq!(app.world.get::<Parent>(player).unwrap().get(), level);
+        }
+        assert!(app.world.resource::<PendingTeleport>().0.is_none());
+    }
+
+    #[test]
+    fn test_retry_resets_run() {
+        let mut app = App::new();
+        app.init_resource::<Score>()
+            .init_resource::<Difficulty>()
+            .init_resource::<Lives>()
+            .init_resource::<Mana>()
+            .init_resource::<LevelSelection>()
+            .init_resource::<PendingTeleport>()
+            .add_state::<GameState>()
+            .add_state::<PauseState>()
+            .add_state::<SettingsState>()
+            .add_systems(OnEnter(GameState::Playing), reset_run)
+            .add_systems(Update, handle_menu_buttons);
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().get(),
+            GameState::GameOver
+        );
+
+        // Leftovers from the run that just ended
+        app.world.insert_resource(Score(700));
+        app.world.insert_resource(Lives(0));
+        app.world.resource_mut::<Mana>().current = 0.0;
+        app.world
+            .insert_resource(LevelSelection::Iid("level-b".to_string()));
+        app.world.insert_resource(SpawnPoint {
+            level_iid: "level-b".to_string(),
+            cell: GridCoords::new(1, 1),
+        });
+
+        app.world.spawn((Interaction::Pressed, MenuButton::Retry));
+        app.update(); // The button queues the transition
+        app.update(); // The transition runs
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().get(),
+            GameState::Playing
+        );
+        assert_eq!(*app.world.resource::<Score>(), Score(0));
+        assert_eq!(
+            *app.world.resource::<Lives>(),
+            Lives(Difficulty::Normal.starting_lives())
+        );
+        assert_eq!(*app.world.resource::<Mana>(), Mana::default());
+        assert_eq!(
+            *app.world.resource::<LevelSelection>(),
+            LevelSelection::default()
+        );
+        assert!(app.world.get_resource::<SpawnPoint>().is_none());
+    }
+
+    #[test]
+    fn test_animation_holds_while_paused() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_resource(RapierConfiguration::default())
+            .add_state::<PauseState>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameReached>()
+            .add_systems(OnEnter(PauseState::Paused), stop_clock)
+            .add_systems(OnExit(PauseState::Paused), start_clock)
+            .add_systems(Update, animate_sprites);
+        let entity = app
+            .world
+            .spawn((
+                Animation {
+                    frames: vec![1, 2, 3],
+                    timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+                    ..default()
+                },
+                TextureAtlasSprite::new(1),
+            ))
+            .id();
+        let elapsed = |app: &App| app.world.get::<Animation>(entity).unwrap().timer.elapsed();
+        app.update(); // The first frame takes no time
+        app.update();
+        assert_eq!(elapsed(&app), Duration::from_millis(100));
+
+        app.world
+            .resource_mut::<NextState<PauseState>>()
+            .set(PauseState::Paused);
+        app.update(); // This frame's time has already passed when the pause starts
+        let paused_at = elapsed(&app);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(elapsed(&app), paused_at);
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(entity).unwrap().index,
+            1
+        );
+        assert!(
+            !app.world
+                .resource::<RapierConfiguration>()
+                .physics_pipeline_active
+        );
+
+        app.world
+            .resource_mut::<NextState<PauseState>>()
+            .set(PauseState::Running);
+        app.update(); // Resumes at the end of the frame's state transitions
+        app.update();
+        assert!(elapsed(&app) > paused_at);
+        assert!(
+            app.world
+                .resource::<RapierConfiguration>()
+                .physics_pipeline_active
+        );
+    }
+
+    #[test]
+    fn test_menu_button() {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .add_state::<PauseState>()
+            .add_state::<SettingsState>()
+            .add_systems(Update, handle_menu_buttons);
+        app.world.spawn((Interaction::Pressed, MenuButton::Menu));
+        app.update();
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().get(),
+            GameState::MainMenu
+        );
+    }
+
+    #[test]
+    fn test_keyboard_presses_highlighted_button() {
+        let mut app = App::new();
+        app.add_state::<GameState>()
+            .add_state::<PauseState>()
+            .add_state::<SettingsState>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<RebindCapture>()
+            .init_resource::<MenuSelection>()
+            .add_systems(
+                Update,
+                (navigate_menu, handle_menu_buttons.after(navigate_menu)),
+            );
+        let [_, settings] = [MenuButton::Play, MenuButton::Settings]
+            .into_iter()
+            .enumerate()
+            .map(|(row, button)| {
+                let y = row as f32 * 40.0;
+                app.world
+                    .spawn((
+                        ButtonBundle {
+                            global_transform: GlobalTransform::from_translation(Vec3::Y * y),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        app.update();
+
+        let press = |app: &mut App, key: KeyCode| {
+            app.world.resource_mut::<Input<KeyCode>>().press(key);
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().clear();
+        };
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Return);
+        assert_eq!(
+            *app.world.get::<Interaction>(settings).unwrap(),
+            Interaction::Pressed
+        );
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<SettingsState>>().get(),
+            SettingsState::Open
+        );
+        // The button is let go again, as a click would be
+        assert_eq!(
+            *app.world.get::<Interaction>(settings).unwrap(),
+            Interaction::None
+        );
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().get(),
+            GameState::Playing
+        );
+    }
+
+    #[test]
+    fn test_map_from_args() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            map_from_args(args(&["game", "--map=practice.ldtk"])),
+            Some("practice.ldtk".to_string())
+        );
+        assert_eq!(
+            map_from_args(args(&["--map=a.ldtk", "--map=b.ldtk"])),
+            Some("b.ldtk".to_string())
+        );
+        assert_eq!(map_from_args(args(&["--map="])), None);
+        assert_eq!(map_from_args(args(&["game", "--seed=7"])), None);
+    }
+
+    #[test]
+    fn test_missing_map_sends_map_load_failed() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkAsset>()
+            .insert_resource(MapPath("no-such-map.ldtk".to_string()))
+            .add_event::<MapLoadFailed>()
+            .add_systems(Startup, spawn_world)
+            .add_systems(Update, check_map_load);
+
+        // The load fails on the asset server's own thread, so give it a moment
+        let mut failed = Vec::new();
+        for _ in 0..200 {
+            app.update();
+            failed.extend(app.world.resource_mut::<Events<MapLoadFailed>>().drain());
+            if !failed.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            failed,
+            vec![MapLoadFailed {
+                path: "no-such-map.ldtk".to_string()
+            }]
+        );
+
+        // Reported once, not every frame
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world.resource::<Events<MapLoadFailed>>().is_empty());
+    }
+
+    #[test]
+    fn test_map_load_failure_shows_error() {
+        let mut app = App::new();
+        app.add_event::<MapLoadFailed>()
+            .add_systems(Update, show_map_load_error);
+        app.world.send_event(MapLoadFailed {
+            path: MAP_FILENAME.to_string(),
+        });
+        app.update();
+
+        let lines: Vec<String> = app
+            .world
+            .query::<&Text>()
+            .iter(&app.world)
+            .map(|text| text.sections[0].value.clone())
+            .collect();
+        assert!(lines.contains(&"Could not load the map".to_string()));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains(MAP_FILENAME) && line.contains("missing")));
+        let buttons: Vec<MenuButton> = app
+            .world
+            .query::<&MenuButton>()
+            .iter(&app.world)
+            .copied()
+            .collect();
+        assert_eq!(buttons, vec![MenuButton::Menu]);
+
+        // Reported once, not every frame
+        app.update();
+        let screens = app
+            .world
+            .query_filtered::<(), With<MenuScreen>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(screens, 1);
+    }
+}
@@ -0,0 +1,184 @@
+// hit_stop.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::enemy::EnemyKilled;
+use crate::interpolation::TimeScale;
+use crate::spell_fire::SpellExplosion;
+
+/// HitStopPlugin freezes the game for a moment when a hit lands hard.
+///
+/// A spell killing an enemy, or an explosion going off, drops the `TimeScale` to
+/// `HIT_STOP_TIME_SCALE` for `HitStopSettings::seconds`, then puts it back. The
+/// freeze is timed in real time, since game time barely moves while it lasts.
+/// Another big hit during a freeze starts it over rather than stacking. The length
+/// is read from the `GameConfig`; zero turns hit-stop off.
+impl Plugin for HitStopPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HitStopSettings>()
+            .init_resource::<HitStop>()
+            .add_systems(Update, (end_hit_stop, start_hit_stop.after(end_hit_stop)));
+    }
+}
+
+/// How long big hits freeze the game, chosen in the `GameConfig`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HitStopSettings {
+    /// Length of the freeze, in seconds of real time. 0.0 turns hit-stop off.
+    pub seconds: f32,
+}
+
+impl Default for HitStopSettings {
+    fn default() -> Self {
+        HitStopSettings {
+            seconds: HIT_STOP_SECONDS,
+        }
+    }
+}
+
+/// The freeze in progress, if any.
+#[derive(Resource, Debug, Default)]
+pub struct HitStop {
+    /// Real time left in the freeze, or `None` when the game isn't frozen.
+    timer: Option<Timer>,
+    /// The `TimeScale` to go back to once the freeze is over.
+    resume_scale: f32,
+}
+
+impl HitStop {
+    /// Checks if the game is frozen by a hit.
+    pub fn active(&self) -> bool {
+        self.timer.is_some()
+    }
+}
+
+/// Freezes the game when an enemy is killed or an explosion goes off.
+///
+/// # Arguments
+/// * `killed_events` - Event reader for killed enemies.
+/// * `explosion_events` - Event reader for explosive spells going off.
+/// * `settings` - Resource giving how long to freeze for.
+/// * `hit_stop` - Resource recording the freeze and the scale to resume at.
+/// * `time_scale` - Resource dropped to freeze the game.
+///
+fn start_hit_stop(
+    mut killed_events: EventReader<EnemyKilled>,
+    mut explosion_events: EventReader<SpellExplosion>,
+    settings: Res<HitStopSettings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    let hits = killed_events.iter().count() + explosion_events.iter().count();
+    if hits == 0 || settings.seconds <= 0.0 {
+        return;
+    }
+    if !hit_stop.active() {
+        hit_stop.resume_scale = time_scale.0;
+        time_scale.0 = HIT_STOP_TIME_SCALE;
+    }
+    hit_stop.timer = Some(Timer::from_seconds(settings.seconds, TimerMode::Once));
+}
+
+/// Puts the `TimeScale` back once the freeze has lasted its real time.
+///
+/// If something else set the `TimeScale` during the freeze, like a pause or a slow
+/// motion effect, its scale is left as it is instead.
+fn end_hit_stop(time: Res<Time>, mut hit_stop: ResMut<HitStop>, mut time_scale: ResMut<TimeScale>) {
+    let Some(timer) = hit_stop.timer.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.raw_delta()).finished() {
+        return;
+    }
+    hit_stop.timer = None;
+    if time_scale.0 == HIT_STOP_TIME_SCALE {
+        time_scale.0 = hit_stop.resume_scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::GridSize;
+    use bevy::utils::Duration;
+
+    /// Lets `seconds` of real time pass, then runs the app.
+    fn run_after(app: &mut App, seconds: f32) {
+        let time = app.world.resource::<Time>();
+        let now =
+            time.last_update().unwrap_or_else(|| time.startup()) + Duration::from_secs_f32(seconds);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+    }
+
+    #[test]
+    fn test_kill_freezes_then_resumes() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<TimeScale>()
+            .insert_resource(HitStopSettings { seconds: 0.1 })
+            .add_event::<EnemyKilled>()
+            .add_event::<SpellExplosion>()
+            .add_plugins(HitStopPlugin);
+        run_after(&mut app, 0.0);
+        assert_eq!(app.world.resource::<TimeScale>().0, 1.0);
+
+        let enemy = app.world.spawn_empty().id();
+        app.world.send_event(EnemyKilled { enemy });
+        run_after(&mut app, 0.02);
+        assert_eq!(app.world.resource::<TimeScale>().0, HIT_STOP_TIME_SCALE);
+        run_after(&mut app, 0.05);
+        assert!(app.world.resource::<HitStop>().active());
+        assert_eq!(app.world.resource::<TimeScale>().0, HIT_STOP_TIME_SCALE);
+
+        // Over after the configured real time, back at the scale it froze from
+        run_after(&mut app, 0.06);
+        assert!(!app.world.resource::<HitStop>().active());
+        assert_eq!(app.world.resource::<TimeScale>().0, 1.0);
+    }
+
+    #[test]
+    fn test_scale_set_during_freeze_is_kept() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<TimeScale>()
+            .insert_resource(HitStopSettings { seconds: 0.1 })
+            .add_event::<EnemyKilled>()
+            .add_event::<SpellExplosion>()
+            .add_plugins(HitStopPlugin);
+        run_after(&mut app, 0.0);
+        let enemy = app.world.spawn_empty().id();
+        app.world.send_event(EnemyKilled { enemy });
+        run_after(&mut app, 0.02);
+        assert_eq!(app.world.resource::<TimeScale>().0, HIT_STOP_TIME_SCALE);
+
+        // Something else slows the game down while it's frozen
+        app.world.resource_mut::<TimeScale>().0 = 0.5;
+        run_after(&mut app, 0.11);
+        assert!(!app.world.resource::<HitStop>().active());
+        assert_eq!(app.world.resource::<TimeScale>().0, 0.5);
+    }
+
+    #[test]
+    fn test_zero_length_hit_stop_is_off() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<TimeScale>()
+            .insert_resource(HitStopSettings { seconds: 0.0 })
+            .add_event::<EnemyKilled>()
+            .add_event::<SpellExplosion>()
+            .add_plugins(HitStopPlugin);
+        app.world.send_event(SpellExplosion {
+            position: Vec3::ZERO,
+            radius: SPELL_EXPLOSION_RADIUS * GridSize::default().pixels(),
+            damage: 1.0,
+        });
+        run_after(&mut app, 0.02);
+        assert!(!app.world.resource::<HitStop>().active());
+        assert_eq!(app.world.resource::<TimeScale>().0, 1.0);
+    }
+}
@@ -0,0 +1,476 @@
+// hud.rs
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::{FieldValue, Level};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::map::LevelWalls;
+use crate::player::Lives;
+use crate::score::{HighScore, Score};
+use crate::spell_fire::{Mana, NoMana, SpellCooldown};
+use crate::util::ldtk_field;
+
+/// HudPlugin draws the heads-up display in the top-left corner of the screen.
+///
+/// Below the text, a bar fills back up as the `SpellCooldown` from the last cast
+/// runs out, turning bright once the player can cast again. The mana line turns
+/// red for `HUD_NO_MANA_SECONDS` whenever a cast fails for lack of mana.
+///
+/// Whenever the player enters a level, its name is shown as a title card across
+/// the middle of the screen for `LEVEL_TITLE_SECONDS`, fading out over the last
+/// `LEVEL_TITLE_FADE_SECONDS` before it's despawned. The name is the level's
+/// `display_name` field in LDtk, or its identifier if that's empty.
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NoManaFlash>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(
+                Update,
+                (
+                    update_score_text,
+                    update_lives_text,
+                    update_mana_text,
+                    update_cooldown_bar,
+                    show_level_title,
+                    fade_level_title.after(show_level_title),
+                ),
+            );
+    }
+}
+
+/// Marker for the HUD text showing the score.
+#[derive(Component)]
+struct ScoreText;
+
+/// Marker for the HUD text showing the lives left.
+#[derive(Component)]
+struct LivesText;
+
+/// Marker for the HUD text showing the player's mana.
+#[derive(Component)]
+struct ManaText;
+
+/// Time left showing the mana text in red after a cast failed for lack of mana.
+#[derive(Resource)]
+struct NoManaFlash(Timer);
+
+impl Default for NoManaFlash {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(HUD_NO_MANA_SECONDS, TimerMode::Once);
+        // Nothing has failed yet, so start out not flashing
+        timer.tick(timer.duration());
+        NoManaFlash(timer)
+    }
+}
+
+/// Marker for the filled part of the HUD's spell cooldown bar.
+#[derive(Component)]
+struct CooldownFill;
+
+/// Title card showing the name of the level just entered, until its timer runs out.
+#[derive(Component)]
+struct LevelTitle {
+    timer: Timer,
+}
+
+/// Text shown for the lives left.
+pub fn lives_text(lives: &Lives) -> String {
+    format!("Lives {}", lives.0)
+}
+
+/// Text shown for the mana left, rounded down to whole points.
+pub fn mana_text(mana: &Mana) -> String {
+    format!("Mana {}/{}", mana.current.floor(), mana.max.floor())
+}
+
+/// How full the cooldown bar is drawn for a cooldown timer.
+///
+/// # Returns
+/// The share of the cooldown that has passed: 0.0 just after a cast, 1.0 once the
+/// player can cast again.
+pub fn cooldown_fill(timer: &Timer) -> f32 {
+    if timer.duration().is_zero() {
+        return 1.0;
+    }
+    timer.percent().clamp(0.0, 1.0)
+}
+
+/// The name shown on entering a level.
+///
+/// # Returns
+/// The level's `display_name` field, or its LDtk identifier if the field is missing
+/// or empty.
+pub fn level_display_name(level: &Level) -> String {
+    match ldtk_field(&level.field_instances, LEVEL_DISPLAY_NAME_FIELD) {
+        Some(FieldValue::String(Some(name))) if !name.is_empty() => name.clone(),
+        _ => level.identifier.clone(),
+    }
+}
+
+/// Opacity of a level title card with `timer` ticking down its display time.
+///
+/// # Returns
+/// 1.0 until the last `LEVEL_TITLE_FADE_SECONDS`, then falling to 0.0 as the timer
+/// finishes.
+pub fn level_title_alpha(timer: &Timer) -> f32 {
+    (timer.remaining_secs() / LEVEL_TITLE_FADE_SECONDS).clamp(0.0, 1.0)
+}
+
+/// Text shown for the current and best score.
+pub fn score_text(score: &Score, high_score: &HighScore) -> String {
+    format!("Score {}  Best {}", score.0, high_score.best)
+}
+
+/// Spawns the HUD text, one line per stat.
+fn setup_hud(mut commands: Commands) {
+    let text = || {
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: HUD_FONT_SIZE,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(HUD_MARGIN),
+                    top: Val::Px(HUD_MARGIN),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            Name::new("HUD"),
+        ))
+        .with_children(|hud| {
+            hud.spawn((text(), ScoreText, Name::new("Score text")));
+            hud.spawn((text(), LivesText, Name::new("Lives text")));
+            hud.spawn((text(), ManaText, Name::new("Mana text")));
+            hud.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(HUD_COOLDOWN_BAR_WIDTH),
+                        height: Val::Px(HUD_COOLDOWN_BAR_HEIGHT),
+                        margin: UiRect::top(Val::Px(HUD_MARGIN / 2.0)),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                },
+                Name::new("Cooldown bar"),
+            ))
+            .with_children(|bar| {
+                bar.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::ORANGE.into(),
+                        ..default()
+                    },
+                    CooldownFill,
+                ));
+            });
+        });
+}
+
+/// Updates the score text whenever the score or high score changes.
+fn update_score_text(
+    score: Res<Score>,
+    high_score: Res<HighScore>,
+    mut query: Query<&mut Text, With<ScoreText>>,
+) {
+    if !score.is_changed() && !high_score.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = score_text(&score, &high_score);
+    }
+}
+
+/// Updates the lives text whenever the lives change.
+fn update_lives_text(lives: Res<Lives>, mut query: Query<&mut Text, With<LivesText>>) {
+    if !lives.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = lives_text(&lives);
+    }
+}
+
+/// Updates the mana text, turning it red for a moment whenever a cast fails for
+/// lack of mana.
+fn update_mana_text(
+    time: Res<Time>,
+    mana: Res<Mana>,
+    mut no_mana_events: EventReader<NoMana>,
+    mut flash: ResMut<NoManaFlash>,
+    mut query: Query<&mut Text, With<ManaText>>,
+) {
+    if no_mana_events.iter().last().is_some() {
+        flash.0.reset();
+    } else {
+        flash.0.tick(time.delta());
+    }
+    let color = if flash.0.finished() {
+        Color::WHITE
+    } else {
+        Color::RED
+    };
+    let value = mana_text(&mana);
+    for mut text in query.iter_mut() {
+        let section = &mut text.sections[0];
+        if section.value != value {
+            section.value = value.clone();
+        }
+        if section.style.color != color {
+            section.style.color = color;
+        }
+    }
+}
+
+/// Shows a title card with the level's name when the player enters a level.
+///
+/// A level is entered when `LevelWalls` switches to it, so neighbors loading in the
+/// background don't get a card. Any card still showing is replaced.
+///
+/// # Arguments
+/// * `commands` - Used to spawn the new card and despawn the old one.
+/// * `level_walls` - Resource giving the level the player is in.
+/// * `shown_iid` - IID of the level the last card was shown for.
+/// * `level_entities` - Query used to find the entered level's asset.
+/// * `level_assets` - Loaded LDtk levels, holding each level's name.
+/// * `title_query` - Query to find a card still showing.
+///
+fn show_level_title(
+    mut commands: Commands,
+    level_walls: Res<LevelWalls>,
+    mut shown_iid: Local<String>,
+    level_entities: Query<&Handle<LdtkLevel>>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    title_query: Query<Entity, With<LevelTitle>>,
+) {
+    let level_iid = level_walls.level_iid();
+    if level_iid.is_empty() || level_iid == shown_iid.as_str() {
+        return;
+    }
+    let Some(level) = level_entities
+        .iter()
+        .filter_map(|handle| level_assets.get(handle))
+        .map(|ldtk_level| &ldtk_level.level)
+        .find(|level| level.iid == level_iid)
+    else {
+        return;
+    };
+    *shown_iid = level_iid.to_string();
+
+    for entity in title_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let name = level_display_name(level);
+    info!("🏷️entered level {}", name);
+    commands.spawn((
+        TextBundle::from_section(
+            name,
+            TextStyle {
+                font_size: LEVEL_TITLE_FONT_SIZE,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Percent(LEVEL_TITLE_TOP_PERCENT),
+            ..default()
+        }),
+        LevelTitle {
+            timer: Timer::from_seconds(LEVEL_TITLE_SECONDS, TimerMode::Once),
+        },
+        Name::new("Level title"),
+    ));
+}
+
+/// Fades level title cards out as their time runs out, despawning them once gone.
+fn fade_level_title(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut LevelTitle, &mut Text)>,
+) {
+    for (entity, mut title, mut text) in query.iter_mut() {
+        title.timer.tick(time.delta());
+        if title.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = level_title_alpha(&title.timer);
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+/// Fills the cooldown bar by how much of the spell cooldown has passed.
+///
+/// The cooldown ticks every frame, so the bar is only written when its width or
+/// color actually changes, leaving the UI layout alone once the spell is ready.
+fn update_cooldown_bar(
+    cooldown: Res<SpellCooldown>,
+    mut query: Query<(&mut Style, &mut BackgroundColor), With<CooldownFill>>,
+) {
+    let width = Val::Percent(cooldown_fill(&cooldown.timer) * 100.0);
+    let color = if cooldown.ready() {
+        Color::ORANGE
+    } else {
+        Color::GRAY
+    };
+    for (mut style, mut background) in query.iter_mut() {
+        if style.width != width {
+            style.width = width;
+        }
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_text() {
+        assert_eq!(
+            score_text(&Score(300), &HighScore { best: 1200 }),
+            "Score 300  Best 1200"
+        );
+    }
+
+    #[test]
+    fn test_cooldown_fill() {
+        let mut timer = Timer::from_seconds(1.0, TimerMode::Once);
+        // Just cast
+        assert_eq!(cooldown_fill(&timer), 0.0);
+        timer.tick(bevy::utils::Duration::from_secs_f32(0.5));
+        assert_eq!(cooldown_fill(&timer), 0.5);
+        // Ready, and staying full however long it's left
+        timer.tick(bevy::utils::Duration::from_secs(2));
+        assert_eq!(cooldown_fill(&timer), 1.0);
+        assert_eq!(
+            cooldown_fill(&Timer::from_seconds(0.0, TimerMode::Once)),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_level_display_name() {
+        let fields: Vec<bevy_ecs_ldtk::ldtk::FieldInstance> = serde_json::from_str(
+            r#"[
+                { "__identifier": "display_name", "__type": "String", "__value": "The Cellar",
+                  "__tile": null, "defUid": 1, "realEditorValues": [] }
+            ]"#,
+        )
+        .unwrap();
+        let mut level = Level {
+            identifier: "Level_0".to_string(),
+            field_instances: fields,
+            ..default()
+        };
+        assert_eq!(level_display_name(&level), "The Cellar");
+
+        // Without a display name the identifier is shown
+        level.field_instances[0].value = FieldValue::String(Some(String::new()));
+        assert_eq!(level_display_name(&level), "Level_0");
+        level.field_instances.clear();
+        assert_eq!(level_display_name(&level), "Level_0");
+    }
+
+    #[test]
+    fn test_level_title_fades_then_despawns() {
+        let mut timer = Timer::from_seconds(LEVEL_TITLE_SECONDS, TimerMode::Once);
+        assert_eq!(level_title_alpha(&timer), 1.0);
+        // Fully shown until the fade starts, then halfway through the fade
+        timer.tick(bevy::utils::Duration::from_secs_f32(
+            LEVEL_TITLE_SECONDS - LEVEL_TITLE_FADE_SECONDS,
+        ));
+        assert_eq!(level_title_alpha(&timer), 1.0);
+        timer.tick(bevy::utils::Duration::from_secs_f32(
+            LEVEL_TITLE_FADE_SECONDS / 2.0,
+        ));
+        assert!((level_title_alpha(&timer) - 0.5).abs() < 1e-4);
+
+        // The card is despawned once its time is up
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+                bevy::utils::Duration::from_secs_f32(LEVEL_TITLE_SECONDS / 4.0),
+            ))
+            .add_systems(Update, fade_level_title);
+        let title = app
+            .world
+            .spawn((
+                TextBundle::from_section("Level_0", TextStyle::default()),
+                LevelTitle {
+                    timer: Timer::from_seconds(LEVEL_TITLE_SECONDS, TimerMode::Once),
+                },
+            ))
+            .id();
+        app.update(); // The first frame takes no time
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world.get_entity(title).is_some());
+        app.update();
+        assert!(app.world.get_entity(title).is_none());
+    }
+
+    #[test]
+    fn test_lives_text() {
+        assert_eq!(lives_text(&Lives(2)), "Lives 2");
+    }
+
+    #[test]
+    fn test_mana_text_flashes_on_no_mana() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(
+                bevy::utils::Duration::from_secs_f32(HUD_NO_MANA_SECONDS / 2.0),
+            ))
+            .init_resource::<Mana>()
+            .init_resource::<NoManaFlash>()
+            .add_event::<NoMana>()
+            .add_systems(Update, update_mana_text);
+        let text = app
+            .world
+            .spawn((TextBundle::from_section("", TextStyle::default()), ManaText))
+            .id();
+        let color = |app: &App| app.world.get::<Text>(text).unwrap().sections[0].style.color;
+        app.world.resource_mut::<Mana>().current = 7.5;
+        app.update();
+        assert_eq!(
+            app.world.get::<Text>(text).unwrap().sections[0].value,
+            "Mana 7/100"
+        );
+        assert_eq!(color(&app), Color::WHITE);
+
+        app.world.send_event(NoMana {
+            kind: crate::spell_fire::SpellKind::Homing,
+            cost: SPELL_HOMING_MANA_COST,
+        });
+        app.update();
+        assert_eq!(color(&app), Color::RED);
+        app.update();
+        app.update();
+        assert_eq!(color(&app), Color::WHITE);
+    }
+}
@@ -0,0 +1,73 @@
+// inspectable.rs
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::map::{LevelGrid, LevelWalls};
+use crate::physics::PhysicsSettings;
+
+/// InspectablePlugin registers the game's own types for reflection.
+///
+/// The world inspector (backquote by default) can only show and edit the fields of registered
+/// types, so animations can be tweaked, the level's dimensions read and gravity tried
+/// out while the game runs. Types are registered along with their `ReflectComponent` or
+/// `ReflectResource`, which the inspector uses to find them on entities. The types of
+/// their fields are registered too, so the inspector can open them up.
+impl Plugin for InspectablePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Player>()
+            .register_type::<PlayerId>()
+            .register_type::<Animation>()
+            .register_type::<Vec<usize>>()
+            .register_type::<Wall>()
+            .register_type::<SpellFire>()
+            .register_type::<LevelWalls>()
+            .register_type::<LevelGrid>()
+            .register_type::<HashMap<String, LevelGrid>>()
+            .register_type::<PhysicsSettings>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::reflect::TypeData;
+    use std::any::{type_name, TypeId};
+
+    /// Checks that `T` is registered along with `D`, its component or resource data.
+    fn assert_registered<T: 'static, D: TypeData>(app: &App) {
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        assert!(
+            registry.get_type_data::<D>(TypeId::of::<T>()).is_some(),
+            "{} not registered",
+            type_name::<T>()
+        );
+    }
+
+    /// Checks that `T` is registered, for types that are only ever fields.
+    fn assert_field_registered<T: 'static>(app: &App) {
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        assert!(
+            registry.get(TypeId::of::<T>()).is_some(),
+            "{} not registered",
+            type_name::<T>()
+        );
+    }
+
+    #[test]
+    fn test_types_registered() {
+        let mut app = App::new();
+        app.add_plugins(InspectablePlugin);
+        assert_registered::<Player, ReflectComponent>(&app);
+        assert_registered::<Animation, ReflectComponent>(&app);
+        assert_registered::<Wall, ReflectComponent>(&app);
+        assert_registered::<SpellFire, ReflectComponent>(&app);
+        assert_registered::<LevelWalls, ReflectResource>(&app);
+        assert_registered::<PhysicsSettings, ReflectResource>(&app);
+        assert_field_registered::<Vec<usize>>(&app);
+        assert_field_registered::<LevelGrid>(&app);
+        assert_field_registered::<HashMap<String, LevelGrid>>(&app);
+    }
+}
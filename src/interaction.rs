@@ -0,0 +1,287 @@
+// interaction.rs
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::map::GridSize;
+use crate::player::{input_unlocked, player_cell};
+use crate::settings::{Action, KeyBindings};
+use crate::util::grid_manhattan;
+
+/// InteractionPlugin lets the player use the `Interactable` it's standing on or next to.
+///
+/// Pressing the interact key (E by default) sends an `InteractEvent` for the nearest
+/// one, which doors, levers and the like read to do their thing. A prompt at the
+/// bottom of the screen shows the key and what it would do while one is in reach.
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InteractEvent>()
+            .add_systems(Startup, setup_interact_prompt)
+            .add_systems(
+                Update,
+                (
+                    interact_from_input.run_if(input_unlocked),
+                    update_interact_prompt,
+                ),
+            );
+    }
+}
+
+/// Sent when a player uses an `Interactable`.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteractEvent {
+    pub player: Entity,
+    pub target: Entity,
+}
+
+/// Marker for the UI text showing the interaction prompt.
+#[derive(Component)]
+struct InteractPrompt;
+
+/// Checks if something in cell `b` is in reach of a player in cell `a`.
+///
+/// # Returns
+/// `true` for the same cell or one of its four neighbors.
+pub fn is_adjacent(a: GridCoords, b: GridCoords) -> bool {
+    grid_manhattan(a, b) <= 1
+}
+
+/// Picks the interactable a player in `player_cell` would use.
+///
+/// # Arguments
+/// * `player_cell` - The player's cell.
+/// * `interactables` - Each interactable and its cell.
+///
+/// # Returns
+/// The nearest one in reach, preferring the player's own cell, or `None` if none
+/// is in reach.
+pub fn nearest_interactable(
+    player_cell: GridCoords,
+    interactables: impl IntoIterator<Item = (Entity, GridCoords)>,
+) -> Option<Entity> {
+    interactables
+        .into_iter()
+        .filter(|(_, cell)| is_adjacent(player_cell, *cell))
+        .min_by_key(|(entity, cell)| (grid_manhattan(player_cell, *cell), *entity))
+        .map(|(entity, _)| entity)
+}
+
+/// The world cell an entity stands in. Cells are compared in world space, so
+/// interactables in neighboring levels are found too.
+fn world_cell(transform: &GlobalTransform, grid_size: GridSize) -> GridCoords {
+    grid_size.to_grid_coords(transform.translation().truncate())
+}
+
+/// The world cell a player stands in, measured from its feet like `player_cell`.
+fn player_world_cell(transform: &GlobalTransform, grid_size: GridSize) -> GridCoords {
+    player_cell(transform.translation().truncate(), grid_size)
+}
+
+/// Text of the prompt for an interactable used with `key`.
+pub fn interact_prompt_text(key: KeyCode, interactable: &Interactable) -> String {
+    format!("[{:?}] {}", key, interactable.prompt)
+}
+
+/// Spawns the (initially hidden) interaction prompt.
+fn setup_interact_prompt(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: HUD_FONT_SIZE,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(HUD_MARGIN),
+            left: Val::Percent(50.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        InteractPrompt,
+        Name::new("Interact prompt"),
+    ));
+}
+
+/// Sends an `InteractEvent` for the interactable nearest player one when the
+/// interact key is pressed.
+fn interact_from_input(
+    input_res: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    grid_size: Res<GridSize>,
+    player_query: Query<(Entity, &GlobalTransform, Option<&PlayerId>), With<Player>>,
+    interactable_query: Query<(Entity, &GlobalTransform), With<Interactable>>,
+    mut interact_events: EventWriter<InteractEvent>,
+) {
+    if !input_res.just_pressed(keys.key(Action::Interact)) {
+        return;
+    }
+    for (player, player_transform, player_id) in player_query.iter() {
+        if PlayerId::of(player_id) != PlayerId::ONE {
+            continue;
+        }
+        let player_cell = player_world_cell(player_transform, *grid_size);
+        let nearest = nearest_interactable(
+            player_cell,
+            interactable_query
+                .iter()
+                .map(|(entity, transform)| (entity, world_cell(transform, *grid_size))),
+        );
+        if let Some(target) = nearest {
+            info!("🖐️player at {:?} interacts with {:?}", player_cell, target);
+            interact_events.send(InteractEvent { player, target });
+        }
+    }
+}
+
+/// Shows the prompt for the interactable in the player's reach, or hides it when
+/// there's none.
+fn update_interact_prompt(
+    keys: Res<KeyBindings>,
+    grid_size: Res<GridSize>,
+    player_query: Query<(&GlobalTransform, Option<&PlayerId>), With<Player>>,
+    interactable_query: Query<(Entity, &GlobalTransform, &Interactable)>,
+    mut prompt_query: Query<(&mut Text, &mut Visibility), With<InteractPrompt>>,
+) {
+    // Interacting is player one's
+    let player_one = player_query
+        .iter()
+        .find(|(_, player_id)| PlayerId::of(*player_id) == PlayerId::ONE);
+    let nearest = player_one.and_then(|(player_transform, _)| {
+        nearest_interactable(
+            player_world_cell(player_transform, *grid_size),
+            interactable_query
+                .iter()
+                .map(|(entity, transform, _)| (entity, world_cell(transform, *grid_size))),
+        )
+    });
+    let prompt = nearest
+        .and_then(|target| interactable_query.get(target).ok())
+        .map(|(_, _, interactable)| interact_prompt_text(keys.key(Action::Interact), interactable));
+    for (mut text, mut visibility) in prompt_query.iter_mut() {
+        let wanted = match prompt {
+            Some(_) => Visibility::Inherited,
+            None => Visibility::Hidden,
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        if let Some(prompt) = &prompt {
+            if text.sections[0].value != *prompt {
+                text.sections[0].value = prompt.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::{player_translation, InputLocked};
+
+    #[test]
+    fn test_is_adjacent() {
+        let cell = GridCoords::new(3, 3);
+        assert!(is_adjacent(cell, cell));
+        assert!(is_adjacent(cell, GridCoords::new(3, 4)));
+        assert!(is_adjacent(cell, GridCoords::new(2, 3)));
+        assert!(!is_adjacent(cell, GridCoords::new(4, 4)));
+        assert!(!is_adjacent(cell, GridCoords::new(5, 3)));
+    }
+
+    /// Spawns an entity standing in `cell`.
+    fn spawn_in_cell(app: &mut App, cell: GridCoords, bundle: impl Bundle) -> Entity {
+        let translation = GridSize::default().to_translation(cell).extend(0.0);
+        app.world
+            .spawn((GlobalTransform::from_translation(translation), bundle))
+            .id()
+    }
+
+    /// Spawns a player standing in `cell`, its sprite reaching up into the cell above.
+    fn spawn_player_in_cell(app: &mut App, cell: GridCoords) -> Entity {
+        let translation = player_translation(cell, GridSize::default()).extend(0.0);
+        app.world
+            .spawn((GlobalTransform::from_translation(translation), Player))
+            .id()
+    }
+
+    /// Builds an app running `interact_from_input`.
+    fn interact_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<GridSize>()
+            .init_resource::<InputLocked>()
+            .add_event::<InteractEvent>()
+            .add_systems(Update, interact_from_input.run_if(input_unlocked));
+        app
+    }
+
+    /// Presses and lets go of the interact key, returning the `InteractEvent`s sent.
+    fn press_interact(app: &mut App) -> Vec<InteractEvent> {
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::E);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().reset_all();
+        app.world
+            .resource_mut::<Events<InteractEvent>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn test_interact_fires_for_nearest_only() {
+        let mut app = interact_app();
+        let player = spawn_player_in_cell(&mut app, GridCoords::new(2, 2));
+        spawn_in_cell(&mut app, GridCoords::new(3, 2), Interactable::default());
+        let under_player = spawn_in_cell(&mut app, GridCoords::new(2, 2), Interactable::default());
+        spawn_in_cell(&mut app, GridCoords::new(5, 2), Interactable::default());
+
+        // Nothing is sent without the key
+        app.update();
+        assert!(app.world.resource::<Events<InteractEvent>>().is_empty());
+
+        assert_eq!(
+            press_interact(&mut app),
+            vec![InteractEvent {
+                player,
+                target: under_player,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_interact_measures_player_from_its_feet() {
+        let mut app = interact_app();
+        let player = spawn_player_in_cell(&mut app, GridCoords::new(2, 2));
+        // Next to the top of the sprite, but two cells from where the player stands
+        spawn_in_cell(&mut app, GridCoords::new(2, 4), Interactable::default());
+        assert!(press_interact(&mut app).is_empty());
+
+        let below = spawn_in_cell(&mut app, GridCoords::new(2, 1), Interactable::default());
+        assert_eq!(
+            press_interact(&mut app),
+            vec![InteractEvent {
+                player,
+                target: below,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_nothing_in_reach() {
+        let far = (Entity::from_raw(1), GridCoords::new(4, 4));
+        assert_eq!(nearest_interactable(GridCoords::new(0, 0), [far]), None);
+    }
+
+    #[test]
+    fn test_interact_prompt_text() {
+        let door = Interactable {
+            prompt: DOOR_INTERACT_PROMPT.to_string(),
+        };
+        assert_eq!(interact_prompt_text(KeyCode::E, &door), "[E] Enter");
+    }
+}
@@ -0,0 +1,218 @@
+// interpolation.rs
+
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+use bevy::transform::TransformSystem;
+
+use crate::components::*;
+use crate::constants::*;
+
+/// InterpolationPlugin runs gameplay in fixed steps and smooths what's drawn between them.
+///
+/// Movement and enemy AI run in `FixedUpdate` in the `GameplayStep` set, every
+/// `FIXED_TIMESTEP` seconds, so the game plays the same at any frame rate. Bevy runs
+/// as many steps each frame as time calls for: none on some frames, several on others.
+/// Drawn as-is, an entity would jump on frames with a step and stand still on frames
+/// without one.
+///
+/// Instead, each `Interpolated` entity remembers its translation after the last two
+/// steps. Every frame, just before transforms are propagated, its `Transform` is set
+/// partway between them, by how far time has got toward the next step. What's drawn
+/// trails the game by up to one step but moves smoothly. Before each step the
+/// `Transform` is put back where the last step left it, so the drawn position never
+/// feeds back into gameplay.
+///
+/// A `Transform` that no longer holds the last drawn translation has been moved
+/// outside the fixed steps, by a teleport or a level hand-over, and snaps there
+/// instead of sliding across the level.
+///
+/// `TimeScale` sets how fast game time runs against the wall clock. It scales the
+/// `Time` delta itself, so animations, cooldowns, the fixed steps and Rapier, which
+/// all run on that delta, slow down or speed up together.
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FixedTime::new_from_secs(FIXED_TIMESTEP))
+            .init_resource::<TimeScale>()
+            .add_systems(First, apply_time_scale.before(TimeSystem))
+            .add_systems(
+                FixedUpdate,
+                (
+                    restore_step_translation.before(GameplayStep),
+                    record_step_translation.after(GameplayStep),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                interpolate_translation.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Systems that step the game in `FixedUpdate`.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameplayStep;
+
+/// How fast game time runs, as a multiple of real time: 0.5 for slow motion, 2.0
+/// to fast-forward. Clamped to 0.0..=`TIME_SCALE_MAX`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+impl TimeScale {
+    /// The scale to run time at, kept within range.
+    pub fn clamped(self) -> f32 {
+        if self.0.is_finite() {
+            self.0.clamp(0.0, TIME_SCALE_MAX)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Runs `Time` at the `TimeScale`, before this frame's delta is measured.
+fn apply_time_scale(time_scale: Res<TimeScale>, mut time: ResMut<Time>) {
+    if !time_scale.is_changed() {
+        return;
+    }
+    time.set_relative_speed(time_scale.clamped());
+}
+
+/// How far time has got from the last fixed step toward the next.
+///
+/// # Returns
+/// A fraction from 0.0, just after a step, up to 1.0 when the next is due.
+pub fn step_fraction(fixed_time: &FixedTime) -> f32 {
+    (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).clamp(0.0, 1.0)
+}
+
+/// Puts `Interpolated` entities back where the last fixed step left them.
+fn restore_step_translation(mut query: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        if transform.translation != interpolated.rendered {
+            interpolated.snap_to(transform.translation);
+        }
+        transform.translation = interpolated.current;
+    }
+}
+
+/// Records where the fixed step left `Interpolated` entities.
+fn record_step_translation(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+        interpolated.rendered = transform.translation;
+    }
+}
+
+/// Places `Interpolated` entities between their last two steps for drawing.
+///
+/// # Arguments
+/// * `fixed_time` - Resource giving how far time has got toward the next step.
+/// * `query` - Query to access the entities' transforms and step translations.
+///
+fn interpolate_translation(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let fraction = step_fraction(&fixed_time);
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        if transform.translation != interpolated.rendered {
+            interpolated.snap_to(transform.translation);
+        }
+        transform.translation = interpolated.translation_at(fraction);
+        interpolated.rendered = transform.translation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::utils::Duration;
+
+    /// Moves every `Interpolated` entity one cell right.
+    fn walk_right(mut query: Query<&mut Transform, With<Interpolated>>) {
+        for mut transform in query.iter_mut() {
+            transform.translation.x += GRID_SIZE as f32;
+        }
+    }
+
+    /// Builds an app drawing four frames per one-second fixed step, spawning an
+    /// entity that walks one cell right each step.
+    fn interpolation_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InterpolationPlugin))
+            .insert_resource(FixedTime::new(Duration::from_secs(1)))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                250,
+            )))
+            .add_systems(FixedUpdate, walk_right.in_set(GameplayStep));
+        let entity = app
+            .world
+            .spawn((Transform::default(), Interpolated::default()))
+            .id();
+        app.update(); // The first frame takes no time
+        (app, entity)
+    }
+
+    /// Runs `frames` frames, returning the drawn x position after each.
+    fn drawn_x(app: &mut App, entity: Entity, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|_| {
+                app.update();
+                app.world.get::<Transform>(entity).unwrap().translation.x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_drawn_between_steps() {
+        let (mut app, entity) = interpolation_app();
+        // The first step lands on the fourth frame, then each frame draws a quarter of the way on
+        assert_eq!(
+            drawn_x(&mut app, entity, 8),
+            vec![0.0, 0.0, 0.0, 0.0, 4.0, 8.0, 12.0, 16.0]
+        );
+        // Gameplay sees where the last step left it, not the drawn position
+        assert_eq!(
+            app.world.get::<Interpolated>(entity).unwrap().current.x,
+            32.0
+        );
+    }
+
+    #[test]
+    fn test_moves_outside_steps_snap() {
+        let (mut app, entity) = interpolation_app();
+        drawn_x(&mut app, entity, 5);
+
+        // Teleported between steps: drawn there straight away, and stepped on from there
+        app.world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x = 100.0;
+        assert_eq!(drawn_x(&mut app, entity, 3), vec![100.0, 100.0, 100.0]);
+        assert_eq!(drawn_x(&mut app, entity, 1), vec![104.0]);
+    }
+
+    #[test]
+    fn test_time_scale_halves_movement() {
+        let (mut app, entity) = interpolation_app();
+        app.insert_resource(TimeScale(0.5));
+        // Steps come every eight frames instead of four, so each frame draws half as far
+        assert_eq!(
+            drawn_x(&mut app, entity, 12),
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 4.0, 6.0, 8.0]
+        );
+
+        // Out of range scales are clamped
+        assert_eq!(TimeScale(-1.0).clamped(), 0.0);
+        assert_eq!(TimeScale(100.0).clamped(), TIME_SCALE_MAX);
+        assert_eq!(TimeScale(f32::NAN).clamped(), 1.0);
+    }
+}
@@ -1,14 +1,15 @@
 // main.rs
 // github.com/algrym/exterminator_wizard
 
+#![cfg_attr(test, feature(test))]
+
+use std::path::Path;
+
 use bevy::diagnostic::{
     FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin, SystemInformationDiagnosticsPlugin,
 };
 use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings, clear_color::ClearColorConfig, tonemapping::Tonemapping,
-    },
-    input::common_conditions::input_toggle_active,
+    core_pipeline::clear_color::ClearColorConfig,
     prelude::*,
     render::{render_resource::WgpuFeatures, settings::WgpuSettings, RenderPlugin},
 };
@@ -20,13 +21,47 @@ use bevy_rapier2d::prelude::*;
 pub use components::*;
 
 use crate::constants::*;
+use crate::player::inspector_open;
+use crate::settings::{load_config, view_scale, GameConfig};
+use crate::visuals::VisualSettings;
 
+mod accessibility;
+mod combat_log;
 mod components;
 mod constants;
+mod despawn;
+mod difficulty;
+mod door;
+mod enemy;
+mod entity_diagnostics;
+mod game_state;
+mod hit_stop;
+mod hud;
+mod inspectable;
+mod interaction;
+mod interpolation;
 mod map;
+mod menu_navigation;
+mod minimap;
+mod moving_wall;
+mod physics;
 mod player;
+mod replay;
+mod rng;
+mod save;
+mod score;
+mod screenshot;
+mod settings;
+mod settings_menu;
+mod sound;
 mod spell_fire;
+mod target_dummy;
+#[cfg(test)]
+mod test_harness;
+mod tile_animation;
 mod util;
+mod vignette;
+mod visuals;
 
 /// This function is the entry point of the "Exterminator Wizard" game.
 fn main() {
@@ -35,17 +70,24 @@ fn main() {
         .features
         .set(WgpuFeatures::VERTEX_WRITABLE_STORAGE, true);
 
-    let primary_window = Window {
-        title: format!(
-            "Exterminator Wizard v{} - ajw@ajw.io",
-            env!("CARGO_PKG_VERSION")
-        ),
-        resolution: (WINDOW_WIDTH, WINDOW_HEIGHT).into(),
-        resizable: false,
-        ..Default::default()
-    };
+    let config = load_config(Path::new(SETTINGS_FILENAME));
+    let primary_window = config.window();
+    let physics_scale = config.physics.physics_scale();
 
     App::new()
+        // Inserted before the plugins so they can read the saved settings while building
+        .insert_resource(config.audio.clone())
+        .insert_resource(config.visuals.clone())
+        .insert_resource(config.accessibility.clone())
+        .insert_resource(config.keys.clone())
+        .insert_resource(config.coop_keys.clone())
+        .insert_resource(config.movement_mode)
+        .insert_resource(config.movement_feel)
+        .insert_resource(config.hit_stop)
+        .insert_resource(config.physics)
+        .insert_resource(config.player_collision)
+        .insert_resource(config.debug)
+        .insert_resource(config)
         .add_plugins((
             DefaultPlugins
                 .set(RenderPlugin { wgpu_settings })
@@ -55,19 +97,54 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
             LdtkPlugin,
+            DifficultyPlugin,
+            GameStatePlugin,
             PlayerPlugin,
             SpellFirePlugin,
+            DoorPlugin,
+            EnemyPlugin,
+            MinimapPlugin,
+            ScreenshotPlugin,
+            ScorePlugin,
+            HudPlugin,
             HanabiPlugin,
             MapPlugin,
-            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(GRID_SIZE as f32),
-            RapierDebugRenderPlugin::default(),
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(physics_scale),
         ))
         .add_plugins((
-            WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Escape)),
+            // Collider outlines are toggled with F2
+            RapierDebugRenderPlugin {
+                enabled: false,
+                ..default()
+            },
+            SettingsPlugin,
+            SettingsMenuPlugin,
+            SoundPlugin,
+            InterpolationPlugin,
+            RngPlugin,
+            VisualsPlugin,
+            InteractionPlugin,
+            MovingWallPlugin,
+            DespawnPlugin,
+            InspectablePlugin,
+            WorldInspectorPlugin::default().run_if(inspector_open),
             SystemInformationDiagnosticsPlugin,
             LogDiagnosticsPlugin::default(),
             FrameTimeDiagnosticsPlugin,
         ))
+        .add_plugins((
+            TileAnimationPlugin,
+            VignettePlugin,
+            AccessibilityPlugin,
+            EntityDiagnosticsPlugin,
+            HitStopPlugin,
+            CombatLogPlugin,
+            MenuNavigationPlugin,
+            PhysicsPlugin,
+            TargetDummyPlugin,
+            SavePlugin,
+            ReplayPlugin,
+        ))
         .insert_resource(LevelSelection::default())
         .insert_resource(LdtkSettings {
             level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
@@ -76,27 +153,20 @@ fn main() {
             set_clear_color: SetClearColor::FromLevelBackground,
             ..Default::default()
         })
-        .insert_resource(RapierConfiguration {
-            gravity: Vec2::ZERO,
-            ..Default::default()
-        })
         .add_systems(Startup, setup)
         .run();
 }
 
-/// This function initializes the camera and spawns the LDtk world.
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// This function initializes the camera, scaled to the configured window height
+/// and with the configured bloom and tonemapping.
+/// The LDtk world is spawned by `GameStatePlugin`.
+fn setup(mut commands: Commands, config: Res<GameConfig>, visuals: Res<VisualSettings>) {
     let mut camera = Camera2dBundle::default();
-    camera.projection.scale = CAMERA_SCALE;
+    camera.projection.scale = view_scale(config.window_height);
     camera.camera_2d.clear_color = ClearColorConfig::Custom(Color::BLACK);
     camera.camera.hdr = true;
-    camera.tonemapping = Tonemapping::default();
+    camera.tonemapping = visuals.tonemapping.tonemapping();
 
     info!("spawn {:?}", camera.camera);
-    commands.spawn((camera, BloomSettings::default()));
-
-    commands.spawn(LdtkWorldBundle {
-        ldtk_handle: asset_server.load(MAP_FILENAME),
-        ..Default::default()
-    });
+    commands.spawn((camera, visuals.bloom_settings()));
 }
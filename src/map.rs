@@ -1,118 +1,1256 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::utils::{grid_coords_to_translation, translation_to_grid_coords};
 use bevy_rapier2d::prelude::*;
+use rand::seq::IteratorRandom;
+use rand::Rng;
 
 use crate::components::*;
 use crate::constants::*;
+use crate::player::player_cell;
+use crate::util::grid_chebyshev;
 
 /// This plugin is responsible for handling map-related functionalities
-/// in the game, including processing and caching wall locations.
+/// in the game, including processing and caching wall locations, and reading
+/// the grid size from the LDtk project. Once a spawned level's walls are cached,
+/// or a level already cached is selected, a `LevelReady` event is sent, so systems
+/// that need them, like the check for a player stuck in a wall, run on that instead
+/// of racing the cache. Breakable walls (int-cell value 2) get colliders of their
+/// own instead of being merged, so a spell can break one down without touching the
+/// walls around it.
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_int_cell::<WallBundle>(1)
+            .register_ldtk_int_cell::<BreakableWallBundle>(2)
+            .init_resource::<GridSize>()
             .init_resource::<LevelWalls>()
+            .init_resource::<WallColliderDebug>()
+            .add_event::<LevelEdgeReached>()
+            .add_event::<WallDamaged>()
+            .add_event::<LevelReady>()
             .add_systems(
                 Update,
-                (setup_wall_colliders, cache_wall_locations, display_events),
+                (
+                    read_grid_size,
+                    setup_wall_colliders.after(read_grid_size),
+                    setup_breakable_wall_colliders.after(read_grid_size),
+                    damage_breakable_walls.after(cache_wall_locations),
+                    cache_wall_locations.after(read_grid_size),
+                    transition_level_at_edge,
+                    display_events,
+                    toggle_wall_collider_gizmos,
+                    toggle_physics_debug_render,
+                    draw_wall_collider_gizmos.run_if(wall_collider_gizmos_visible),
+                    dump_ascii_map,
+                ),
             );
     }
 }
 
-/// This plugin is responsible for handling map-related functionalities
-/// in the game, including processing and caching wall locations.
-#[derive(Default, Resource)]
+/// Size of a grid cell, in pixels.
+///
+/// Starts at `GRID_SIZE` and is replaced by the LDtk project's default grid size
+/// once the project loads, so maps authored at another tile size line up. Convert
+/// between cells and translations with this rather than with the constant.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridSize(pub i32);
+
+impl Default for GridSize {
+    fn default() -> Self {
+        GridSize(GRID_SIZE)
+    }
+}
+
+impl GridSize {
+    /// The size of a cell as a float, for working in translations.
+    pub fn pixels(self) -> f32 {
+        self.0 as f32
+    }
+
+    /// The translation of the center of `grid_coords`, relative to its level.
+    pub fn to_translation(self, grid_coords: GridCoords) -> Vec2 {
+        grid_coords_to_translation(grid_coords, IVec2::splat(self.0))
+    }
+
+    /// The cell containing `translation`, relative to its level.
+    pub fn to_grid_coords(self, translation: Vec2) -> GridCoords {
+        translation_to_grid_coords(translation, IVec2::splat(self.0))
+    }
+}
+
+/// Run condition for rebuilding anything sized in cells: true when the `GridSize`
+/// changes after startup, when it was first built at the default size.
+pub fn grid_size_changed(grid_size: Res<GridSize>) -> bool {
+    grid_size.is_changed() && !grid_size.is_added()
+}
+
+/// Picks up the grid size of the LDtk project whenever it loads or changes.
+///
+/// # Arguments
+/// * `project_events` - Event reader for LDtk projects loading and reloading.
+/// * `ldtk_project_assets` - Loaded LDtk projects, to read the grid size from.
+/// * `grid_size` - Resource receiving the project's grid size.
+///
+fn read_grid_size(
+    mut project_events: EventReader<AssetEvent<LdtkAsset>>,
+    ldtk_project_assets: Res<Assets<LdtkAsset>>,
+    mut grid_size: ResMut<GridSize>,
+) {
+    for event in project_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(ldtk_project) = ldtk_project_assets.get(handle) else {
+            continue;
+        };
+        let project_grid_size = ldtk_project.project.default_grid_size;
+        if project_grid_size > 0 && grid_size.0 != project_grid_size {
+            info!("🗺️grid size: {}px", project_grid_size);
+            grid_size.0 = project_grid_size;
+        }
+    }
+}
+
+/// Wall locations and bounds of every spawned level, keyed by level IID, with one
+/// level selected.
+///
+/// Each level also knows where its bottom-left cell sits in a world-space grid
+/// shared by all levels, so cells can be checked across level boundaries. Cell
+/// checks take the selected level's grid cells (the level the player is in), and a
+/// cell past its edge is answered by whichever loaded neighbor it falls in.
+#[derive(Default, Resource, Reflect)]
+#[reflect(Resource)]
 pub struct LevelWalls {
+    levels: HashMap<String, LevelGrid>,
+    level_iid: String,
+    /// World-space cells covered by moving walls as of the last fixed step. Hidden
+    /// from reflection, as the inspector can't list a set of cells.
+    #[reflect(ignore)]
+    moving_wall_cells: HashSet<GridCoords>,
+}
+
+/// Walls and bounds of a single level.
+#[derive(Default, Debug, Clone, Reflect)]
+pub(crate) struct LevelGrid {
+    /// Hidden from reflection, as the inspector can't list a set of cells.
+    #[reflect(ignore)]
     wall_locations: HashSet<GridCoords>,
-    level_width: i32,
-    level_height: i32,
+    width: i32,
+    height: i32,
+    /// World-space grid position of the level's bottom-left cell.
+    origin: IVec2,
+}
+
+impl LevelGrid {
+    /// Converts a world-space cell to this level's cells.
+    fn to_local(&self, world: GridCoords) -> GridCoords {
+        GridCoords::new(world.x - self.origin.x, world.y - self.origin.y)
+    }
+
+    /// Checks if a world-space cell lies within this level.
+    fn contains(&self, world: GridCoords) -> bool {
+        let local = self.to_local(world);
+        (0..self.width).contains(&local.x) && (0..self.height).contains(&local.y)
+    }
+}
+
+/// Sent when the player tries to move beyond the edge of the current level.
+#[derive(Event, Debug)]
+pub struct LevelEdgeReached {
+    /// The player entity that hit the edge.
+    pub player: Entity,
+    /// Where the player wanted to go, in world space.
+    pub destination: Vec2,
+}
+
+/// Sent once a spawned level's walls are cached in `LevelWalls`, and it's selected
+/// if the `LevelSelection` points at it. Also sent when a level whose walls were
+/// already cached, like a loaded neighbor, becomes the selected one.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct LevelReady {
+    pub level_iid: String,
+}
+
+/// Sent when a spell hits a `Breakable` wall.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct WallDamaged {
+    pub wall: Entity,
+    pub damage: f32,
+}
+
+/// Debug record of the wall rectangles built by `setup_wall_colliders`.
+///
+/// When `enabled`, the collider pass stores every wall cell (pre-merge) and every
+/// merged plate rectangle (post-merge) in world space, so they can be drawn with
+/// gizmos independently of Rapier's debug render. Rectangles are kept per level IID.
+#[derive(Resource)]
+pub struct WallColliderDebug {
+    /// Whether the collider pass records rectangles at all.
+    pub enabled: bool,
+    /// Which set of rectangles (if any) is currently drawn.
+    pub mode: WallGizmoMode,
+    /// One rectangle per wall cell, as they were before merging.
+    pub pre_merge: HashMap<String, Vec<Rect>>,
+    /// The merged rectangles that colliders were actually built from.
+    pub post_merge: HashMap<String, Vec<Rect>>,
+}
+
+impl Default for WallColliderDebug {
+    fn default() -> Self {
+        WallColliderDebug {
+            enabled: cfg!(debug_assertions),
+            mode: WallGizmoMode::Off,
+            pre_merge: HashMap::new(),
+            post_merge: HashMap::new(),
+        }
+    }
+}
+
+/// Which wall rectangles the gizmo overlay draws.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallGizmoMode {
+    #[default]
+    Off,
+    PreMerge,
+    PostMerge,
+}
+
+impl WallGizmoMode {
+    /// Returns the next mode in the F3 toggle cycle.
+    pub fn next(self) -> Self {
+        match self {
+            WallGizmoMode::Off => WallGizmoMode::PostMerge,
+            WallGizmoMode::PostMerge => WallGizmoMode::PreMerge,
+            WallGizmoMode::PreMerge => WallGizmoMode::Off,
+        }
+    }
 }
 
 impl LevelWalls {
+    /// Builds the walls of a level that isn't tied to an LDtk level.
+    ///
+    /// # Arguments
+    /// * `walls` - The wall cells.
+    /// * `width` - Level width in cells.
+    /// * `height` - Level height in cells.
+    pub fn new(walls: HashSet<GridCoords>, width: i32, height: i32) -> Self {
+        let grid = LevelGrid {
+            wall_locations: walls,
+            width,
+            height,
+            origin: IVec2::ZERO,
+        };
+        LevelWalls {
+            levels: HashMap::from([(String::new(), grid)]),
+            level_iid: String::new(),
+            moving_wall_cells: HashSet::new(),
+        }
+    }
+
+    /// Ties the walls of the selected level to the LDtk level with the given IID.
+    pub fn with_level_iid(mut self, level_iid: impl Into<String>) -> Self {
+        let level_iid = level_iid.into();
+        if let Some(grid) = self.levels.remove(&self.level_iid) {
+            self.levels.insert(level_iid.clone(), grid);
+        }
+        self.level_iid = level_iid;
+        self
+    }
+
+    /// Places the selected level's bottom-left cell at `origin` in the world-space grid.
+    pub fn with_origin(mut self, origin: IVec2) -> Self {
+        if let Some(grid) = self.levels.get_mut(&self.level_iid) {
+            grid.origin = origin;
+        }
+        self
+    }
+
+    /// Adds the levels of `other`, replacing any walls already cached for the same
+    /// levels and keeping the rest. The selection is left alone.
+    pub fn add_levels(&mut self, other: LevelWalls) {
+        self.levels.extend(other.levels);
+    }
+
+    /// Forgets the walls of a level that has been despawned.
+    pub fn remove_level(&mut self, level_iid: &str) {
+        self.levels.remove(level_iid);
+    }
+
+    /// Checks if the walls of the level with the given IID are cached.
+    pub fn has_level(&self, level_iid: &str) -> bool {
+        self.levels.contains_key(level_iid)
+    }
+
+    /// Makes the level with the given IID the one cell checks answer for.
+    pub fn select_level(&mut self, level_iid: impl Into<String>) {
+        self.level_iid = level_iid.into();
+    }
+
+    /// The selected level's walls, if they're cached.
+    fn selected(&self) -> Option<&LevelGrid> {
+        self.levels.get(&self.level_iid)
+    }
+
+    /// Builds a level from a list of wall cells, for tests.
+    #[cfg(test)]
+    pub(crate) fn from_cells(cells: &[(i32, i32)], width: i32, height: i32) -> Self {
+        LevelWalls::new(
+            cells.iter().map(|&(x, y)| GridCoords::new(x, y)).collect(),
+            width,
+            height,
+        )
+    }
+
     /// Checks if the given grid coordinates are within a wall.
     ///
+    /// Cells past the edge of the selected level are checked in the loaded
+    /// neighbor they fall in.
+    ///
     /// # Arguments
-    /// * `grid_coords` - The grid coordinates to check.
+    /// * `grid_coords` - The grid coordinates to check, in the selected level's cells.
     ///
     /// # Returns
-    /// `true` if the coordinates are within a wall, `false` otherwise.
+    /// `true` if the coordinates are within a wall or outside every loaded level,
+    /// `false` otherwise.
     pub fn in_wall(&self, grid_coords: &GridCoords) -> bool {
-        grid_coords.x < 0
-            || grid_coords.y < 0
-            || grid_coords.x >= self.level_width
-            || grid_coords.y >= self.level_height
-            || self.wall_locations.contains(grid_coords)
+        self.in_wall_world(self.to_world(grid_coords))
+    }
+
+    /// Checks if the given grid coordinates are within a wall of the selected level
+    /// itself, for things like enemies that never leave it.
+    ///
+    /// # Returns
+    /// `true` if the coordinates are within a wall or past the edge of the selected
+    /// level, even where a loaded neighbor is open, `false` otherwise.
+    pub fn in_level_wall(&self, grid_coords: &GridCoords) -> bool {
+        !self.in_bounds(grid_coords) || self.in_wall(grid_coords)
+    }
+
+    /// Checks if a cell in world-space grid coordinates is within a wall, in
+    /// whichever loaded level it falls.
+    ///
+    /// # Arguments
+    /// * `world` - The world-space grid coordinates to check.
+    ///
+    /// # Returns
+    /// `true` if the cell is a wall or outside every loaded level, `false` otherwise.
+    pub fn in_wall_world(&self, world: GridCoords) -> bool {
+        // Most checks are in the selected level, so try it before searching the rest
+        self.selected()
+            .filter(|grid| grid.contains(world))
+            .or_else(|| self.levels.values().find(|grid| grid.contains(world)))
+            .map_or(true, |grid| {
+                grid.wall_locations.contains(&grid.to_local(world))
+            })
+    }
+
+    /// Checks if a point in world space is solid, in whichever loaded level it falls.
+    ///
+    /// The point is checked in the cell it falls in, with points on a cell's edge
+    /// belonging to the cell above or to the right. A sprite's translation is a cell
+    /// above where it stands, so check a player's feet, or use `player_cell`.
+    ///
+    /// # Arguments
+    /// * `pos` - The world-space position to check, in pixels.
+    /// * `grid_size` - The size of a cell, in pixels.
+    ///
+    /// # Returns
+    /// `true` if the point is in a wall or outside every loaded level, `false` otherwise.
+    pub fn solid_at_world(&self, pos: Vec2, grid_size: i32) -> bool {
+        let cell = (pos / grid_size as f32).floor().as_ivec2();
+        self.in_wall_world(GridCoords::new(cell.x, cell.y))
+    }
+
+    /// Opens up a wall cell, in world-space grid coordinates, in whichever loaded
+    /// level it falls.
+    pub fn open_world(&mut self, world: GridCoords) {
+        if let Some(grid) = self.levels.values_mut().find(|grid| grid.contains(world)) {
+            let local = grid.to_local(world);
+            grid.wall_locations.remove(&local);
+        }
+    }
+
+    /// Converts a cell of the selected level to world-space grid coordinates.
+    pub fn to_world(&self, grid_coords: &GridCoords) -> GridCoords {
+        let origin = self.selected().map_or(IVec2::ZERO, |grid| grid.origin);
+        GridCoords::new(grid_coords.x + origin.x, grid_coords.y + origin.y)
+    }
+
+    /// Converts world-space grid coordinates to a cell of the selected level.
+    pub fn to_local(&self, world: &GridCoords) -> GridCoords {
+        let origin = self.selected().map_or(IVec2::ZERO, |grid| grid.origin);
+        GridCoords::new(world.x - origin.x, world.y - origin.y)
+    }
+
+    /// Checks if the given grid coordinates are inside the level boundaries.
+    ///
+    /// # Arguments
+    /// * `grid_coords` - The grid coordinates to check.
+    ///
+    /// # Returns
+    /// `true` if the coordinates are within the level, `false` if they are past an edge.
+    pub fn in_bounds(&self, grid_coords: &GridCoords) -> bool {
+        grid_coords.x >= 0
+            && grid_coords.y >= 0
+            && grid_coords.x < self.width()
+            && grid_coords.y < self.height()
+    }
+
+    /// Checks if a straight line between the centers of two cells is clear of walls.
+    ///
+    /// Every cell the line passes through is checked, including both ends. A line
+    /// passing exactly through a corner is blocked if either cell beside the
+    /// corner is a wall, so nothing is seen through a diagonal gap.
+    ///
+    /// # Arguments
+    /// * `from` - The cell looking.
+    /// * `to` - The cell being looked at.
+    ///
+    /// # Returns
+    /// `true` if no cell on the line is a wall or past the edge of the level.
+    pub fn line_of_sight(&self, from: GridCoords, to: GridCoords) -> bool {
+        let (steps_x, steps_y) = ((to.x - from.x).abs(), (to.y - from.y).abs());
+        let (sign_x, sign_y) = ((to.x - from.x).signum(), (to.y - from.y).signum());
+        let mut cell = from;
+        let (mut taken_x, mut taken_y) = (0, 0);
+        loop {
+            if self.in_wall(&cell) {
+                return false;
+            }
+            if taken_x == steps_x && taken_y == steps_y {
+                return true;
+            }
+            // Which cell border the line crosses next, compared without dividing
+            let next_x_vs_y = (1 + 2 * taken_x) * steps_y - (1 + 2 * taken_y) * steps_x;
+            if next_x_vs_y == 0 {
+                let beside_x = GridCoords::new(cell.x + sign_x, cell.y);
+                let beside_y = GridCoords::new(cell.x, cell.y + sign_y);
+                if self.in_wall(&beside_x) || self.in_wall(&beside_y) {
+                    return false;
+                }
+                cell = GridCoords::new(cell.x + sign_x, cell.y + sign_y);
+                taken_x += 1;
+                taken_y += 1;
+            } else if next_x_vs_y < 0 {
+                cell.x += sign_x;
+                taken_x += 1;
+            } else {
+                cell.y += sign_y;
+                taken_y += 1;
+            }
+        }
+    }
+
+    /// Checks if a step between neighboring cells squeezes diagonally between two
+    /// walls that touch at a corner.
+    ///
+    /// # Arguments
+    /// * `from` - The cell being left.
+    /// * `to` - The cell being entered.
+    ///
+    /// # Returns
+    /// `true` if the step is diagonal and both cells beside it are walls.
+    pub fn diagonal_squeeze(&self, from: &GridCoords, to: &GridCoords) -> bool {
+        grid_chebyshev(*from, *to) == 1
+            && from.x != to.x
+            && from.y != to.y
+            && self.in_wall(&GridCoords::new(to.x, from.y))
+            && self.in_wall(&GridCoords::new(from.x, to.y))
     }
+
+    /// Checks if something in `from` may step into `to`: it isn't a wall, the step
+    /// doesn't squeeze between two walls meeting at a corner, and it doesn't walk
+    /// into a moving wall. Something already on a moving wall may step off it.
+    pub fn can_step(&self, from: &GridCoords, to: &GridCoords) -> bool {
+        !self.in_wall(to) && !self.diagonal_squeeze(from, to) && !self.into_moving_wall(from, to)
+    }
+
+    /// Checks if a step from `from` enters a moving wall it wasn't already on.
+    fn into_moving_wall(&self, from: &GridCoords, to: &GridCoords) -> bool {
+        self.moving_wall_cells.contains(&self.to_world(to))
+            && !self.moving_wall_cells.contains(&self.to_world(from))
+    }
+
+    /// Replaces the world-space cells covered by moving walls, which `can_step`
+    /// keeps things from walking into.
+    pub fn set_moving_wall_cells(&mut self, cells: HashSet<GridCoords>) {
+        self.moving_wall_cells = cells;
+    }
+
+    /// Returns the width of the selected level, in grid cells, or 0 if its walls aren't cached.
+    pub fn width(&self) -> i32 {
+        self.selected().map_or(0, |grid| grid.width)
+    }
+
+    /// Returns the height of the selected level, in grid cells, or 0 if its walls aren't cached.
+    pub fn height(&self) -> i32 {
+        self.selected().map_or(0, |grid| grid.height)
+    }
+
+    /// Returns the IID of the selected level.
+    pub fn level_iid(&self) -> &str {
+        &self.level_iid
+    }
+
+    /// Iterates over every in-bounds cell of the level that is not a wall.
+    ///
+    /// # Returns
+    /// The walkable cells in row-major order, starting from `(0, 0)`.
+    pub fn walkable_cells(&self) -> impl Iterator<Item = GridCoords> + '_ {
+        let width = self.width();
+        (0..self.height())
+            .flat_map(move |y| (0..width).map(move |x| GridCoords::new(x, y)))
+            .filter(move |grid_coords| !self.in_wall(grid_coords))
+    }
+
+    /// Picks a random walkable cell of the level.
+    ///
+    /// # Arguments
+    /// * `rng` - The random number generator to draw from.
+    ///
+    /// # Returns
+    /// A walkable cell, or `None` if the level has no walkable cells.
+    pub fn random_walkable<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<GridCoords> {
+        self.walkable_cells().choose(rng)
+    }
+
+    /// Finds the walkable cell closest to `target`.
+    ///
+    /// Does a breadth-first search outward from `target` (clamped into the level bounds)
+    /// over the four orthogonal neighbors, so the result is the nearest open cell by
+    /// walking distance.
+    ///
+    /// # Arguments
+    /// * `target` - The cell something wants to be placed at.
+    ///
+    /// # Returns
+    /// `target` itself if it is walkable, the closest walkable cell otherwise,
+    /// or `None` if the whole level is walls.
+    pub fn nearest_walkable(&self, target: GridCoords) -> Option<GridCoords> {
+        let (width, height) = (self.width(), self.height());
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let start = GridCoords::new(target.x.clamp(0, width - 1), target.y.clamp(0, height - 1));
+
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            if !self.in_wall(&cell) {
+                return Some(cell);
+            }
+            for (dx, dy) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+                let next = GridCoords::new(cell.x + dx, cell.y + dy);
+                if self.in_bounds(&next) && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Caches the locations of walls for every spawned level, and selects the level
+/// the `LevelSelection` points at.
+///
+/// Each `LevelEvent::Spawned` adds only that level's walls to `LevelWalls`, sized
+/// from the level's own asset, and `LevelEvent::Despawned` drops them again, so
+/// loading a neighbor leaves the levels already cached alone. Each level is placed
+/// in the world-space grid by its `Transform`. The LDtk project is
+/// only consulted to turn a new `LevelSelection` into a level IID, once per change,
+/// and not at all when the selection is already an IID. The selected level is
+/// switched to as soon as its walls are cached. A `LevelReady` is sent for each
+/// level cached, and for a level cached earlier when it's selected, after the
+/// selection is updated.
+///
+/// A level's walls are found by walking down from the level to its layers and
+/// their children, so caching a level costs only as much as its own walls, however
+/// many neighbors are loaded.
+///
+/// # Arguments
+/// * `level_walls` - Resource the walls are cached in.
+/// * `level_events` - Event reader for levels being spawned and despawned.
+/// * `ready_events` - Event writer used to report levels whose walls are cached.
+/// * `level_selection` - Resource choosing the level the player is in.
+/// * `selected_iid` - The IID the current selection was last looked up as.
+/// * `walls` - Query to access walls' grid positions.
+/// * `children` - Query used to walk from a level down to its layers and their walls.
+/// * `level_entities` - Query used to find the spawned level's entity and position.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+/// * `ldtk_project_entities` - Query to access the LDtk project handle.
+/// * `ldtk_project_assets` - Loaded LDtk projects, used to look up the selected level.
+/// * `grid_size` - Resource giving the size of a cell, to size and place levels.
+///
+/// Collects the walls of one level.
+///
+/// Walls are children of a layer, which is a child of the level, so they're found
+/// by walking down from the level rather than checking every wall in the world.
+///
+/// # Arguments
+/// * `level` - The level entity.
+/// * `walls` - Query to access walls' grid positions.
+/// * `children` - Query used to walk from the level down to its layers and their walls.
+///
+/// # Returns
+/// The level's wall cells.
+fn level_wall_locations(
+    level: Entity,
+    walls: &Query<&GridCoords, With<Wall>>,
+    children: &Query<&Children>,
+) -> HashSet<GridCoords> {
+    let layer_children: Vec<&Children> = children
+        .get(level)
+        .into_iter()
+        .flat_map(|layers| children.iter_many(layers.iter()))
+        .collect();
+    let mut wall_locations =
+        HashSet::with_capacity(layer_children.iter().map(|layer| layer.len()).sum());
+    for layer in layer_children {
+        wall_locations.extend(walls.iter_many(layer.iter()).copied());
+    }
+    wall_locations
 }
 
-/// Caches the locations of walls whenever a level is spawned.
-/// This function listens for `LevelEvent::Spawned` events and updates
-/// the `LevelWalls` resource with the wall locations for the current level.
-fn cache_wall_locations(
+#[allow(clippy::too_many_arguments)]
+pub fn cache_wall_locations(
     mut level_walls: ResMut<LevelWalls>,
     mut level_events: EventReader<LevelEvent>,
+    mut ready_events: EventWriter<LevelReady>,
+    level_selection: Res<LevelSelection>,
+    mut selected_iid: Local<Option<String>>,
     walls: Query<&GridCoords, With<Wall>>,
+    children: Query<&Children>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>, &Transform)>,
+    level_assets: Res<Assets<LdtkLevel>>,
     ldtk_project_entities: Query<&Handle<LdtkAsset>>,
     ldtk_project_assets: Res<Assets<LdtkAsset>>,
+    grid_size: Res<GridSize>,
 ) {
+    let mut ready = Vec::new();
     for level_event in level_events.iter() {
-        if let LevelEvent::Spawned(level_iid) = level_event {
-            let ldtk_project = ldtk_project_assets
-                .get(ldtk_project_entities.single())
-                .expect("ERROR: LdtkProject should be loaded when level is spawned");
-            let level = ldtk_project
-                .get_level(&LevelSelection::Iid(level_iid.to_string()))
-                .expect("ERROR: spawned level should exist in project");
-
-            let wall_locations = walls.iter().copied().collect();
-
-            let new_level_walls = LevelWalls {
-                wall_locations,
-                level_width: level.px_wid / GRID_SIZE,
-                level_height: level.px_hei / GRID_SIZE,
-            };
+        match level_event {
+            LevelEvent::Spawned(level_iid) => {
+                let Some((level_entity, ldtk_level, level_transform)) = level_entities
+                    .iter()
+                    .find_map(|(entity, handle, transform)| {
+                        level_assets
+                            .get(handle)
+                            .filter(|ldtk_level| ldtk_level.level.iid == *level_iid)
+                            .map(|ldtk_level| (entity, ldtk_level, transform))
+                    })
+                else {
+                    continue;
+                };
+
+                let wall_locations = level_wall_locations(level_entity, &walls, &children);
+                level_walls.add_levels(
+                    LevelWalls::new(
+                        wall_locations,
+                        ldtk_level.level.px_wid / grid_size.0,
+                        ldtk_level.level.px_hei / grid_size.0,
+                    )
+                    .with_level_iid(level_iid.clone())
+                    .with_origin(
+                        (level_transform.translation.truncate() / grid_size.pixels())
+                            .round()
+                            .as_ivec2(),
+                    ),
+                );
+                ready.push(LevelReady {
+                    level_iid: level_iid.clone(),
+                });
+            }
+            LevelEvent::Despawned(level_iid) => level_walls.remove_level(level_iid),
+            _ => {}
+        }
+    }
+
+    if level_selection.is_changed() {
+        *selected_iid = None;
+    }
+    if selected_iid.is_none() {
+        // The project may still be loading, e.g. right after a retry respawns the
+        // world; the lookup is tried again next frame.
+        *selected_iid = match level_selection.as_ref() {
+            LevelSelection::Iid(level_iid) => Some(level_iid.clone()),
+            other => ldtk_project_entities
+                .get_single()
+                .ok()
+                .and_then(|handle| ldtk_project_assets.get(handle))
+                .and_then(|ldtk_project| ldtk_project.get_level(other))
+                .map(|level| level.iid.clone()),
+        };
+    }
+
+    // The selected level may not have spawned yet; it's selected once its walls are cached.
+    if let Some(level_iid) = selected_iid.as_ref() {
+        if level_walls.level_iid() != level_iid && level_walls.has_level(level_iid) {
+            level_walls.select_level(level_iid.clone());
+            // A neighbor cached earlier had its `LevelReady` before it was selected
+            if !ready.iter().any(|event| event.level_iid == *level_iid) {
+                ready.push(LevelReady {
+                    level_iid: level_iid.clone(),
+                });
+            }
+        }
+    }
+    ready_events.send_batch(ready);
+}
+
+/// Finds the level, other than `current_iid`, whose world-space bounds contain `point`.
+///
+/// Bounds are treated as half-open (`min <= point < max`) so a point on a shared
+/// edge belongs to exactly one level. A point past a corner only matches a level
+/// placed diagonally; if there is none, the player stays blocked.
+///
+/// # Arguments
+/// * `levels` - IID and world-space bounds of each loaded level.
+/// * `current_iid` - The level the player is leaving.
+/// * `point` - The world-space point the player is moving to.
+pub fn level_at_point<'a>(
+    levels: &'a [(String, Rect)],
+    current_iid: &str,
+    point: Vec2,
+) -> Option<&'a str> {
+    levels
+        .iter()
+        .filter(|(iid, _)| iid != current_iid)
+        .find(|(_, bounds)| {
+            point.x >= bounds.min.x
+                && point.y >= bounds.min.y
+                && point.x < bounds.max.x
+                && point.y < bounds.max.y
+        })
+        .map(|(iid, _)| iid.as_str())
+}
+
+/// Moves the player into a neighboring level when it walks off the current one.
+///
+/// Listens for `LevelEdgeReached`, looks for a loaded neighbor under the player's feet,
+/// and if one exists, reparents the player onto that level, moves it to its destination
+/// and selects the neighbor. Without a neighbor, or with a wall in the neighbor where
+/// the player would land, the event is ignored and the player stays blocked by the
+/// level boundary. In local co-op the other player is brought along to the same
+/// spot, since the level left behind may be unloaded, taking its children with it.
+fn transition_level_at_edge(
+    mut commands: Commands,
+    mut edge_events: EventReader<LevelEdgeReached>,
+    mut level_selection: ResMut<LevelSelection>,
+    level_walls: Res<LevelWalls>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>, &GlobalTransform)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    mut player_query: Query<
+        (Entity, &mut Transform, &GlobalTransform, &mut GridCoords),
+        With<Player>,
+    >,
+    grid_size: Res<GridSize>,
+) {
+    let Some(edge_event) = edge_events.iter().last() else {
+        return;
+    };
+
+    let mut loaded = Vec::new();
+    let mut levels = Vec::new();
+    for (entity, handle, level_transform) in level_entities.iter() {
+        if let Some(ldtk_level) = level_assets.get(handle) {
+            let origin = level_transform.translation();
+            let size = Vec2::new(
+                ldtk_level.level.px_wid as f32,
+                ldtk_level.level.px_hei as f32,
+            );
+            let min = origin.truncate();
+            levels.push((
+                ldtk_level.level.iid.clone(),
+                Rect::from_corners(min, min + size),
+            ));
+            loaded.push((entity, origin));
+        }
+    }
+
+    // Measure from the lower half of the player sprite, like the wall check does
+    let feet = edge_event.destination - Vec2::new(0.0, grid_size.pixels());
+    if level_walls.solid_at_world(feet, grid_size.0) {
+        return;
+    }
+    let Some(index) = level_at_point(&levels, level_walls.level_iid(), feet)
+        .and_then(|iid| levels.iter().position(|(level_iid, _)| level_iid == iid))
+    else {
+        return;
+    };
+    let (level_entity, level_origin) = loaded[index];
+
+    if !player_query.contains(edge_event.player) {
+        return;
+    }
+    info!(
+        "player crossed into level {} at {:?}",
+        levels[index].0, edge_event.destination
+    );
+    for (player, mut player_transform, player_global, mut player_grid_coords) in
+        player_query.iter_mut()
+    {
+        player_transform.translation = Vec3::new(
+            edge_event.destination.x - level_origin.x,
+            edge_event.destination.y - level_origin.y,
+            player_global.translation().z - level_origin.z,
+        );
+        *player_grid_coords = player_cell(player_transform.translation.truncate(), *grid_size);
+        commands.entity(player).set_parent(level_entity);
+    }
+    *level_selection = LevelSelection::Iid(levels[index].0.clone());
+}
+
+/// Represents a wide wall that is 1 tile tall
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+/// A simple rectangle type representing a wall of any size, in grid cells.
+/// Both edges are inclusive: a single tile has `left == right` and `bottom == top`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct WallRect {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl WallRect {
+    /// Checks if this rectangle shares at least one cell with `other`.
+    pub fn overlaps(&self, other: &WallRect) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.bottom <= other.top
+            && other.bottom <= self.top
+    }
+}
+
+/// Finds rectangles that share cells, which the plate method should never produce.
+///
+/// # Returns
+/// Each overlapping pair once, as indices into `rects` with the lower index first.
+pub fn overlapping_rects(rects: &[WallRect]) -> Vec<(usize, usize)> {
+    let mut overlaps = Vec::new();
+    for (i, a) in rects.iter().enumerate() {
+        for (j, b) in rects.iter().enumerate().skip(i + 1) {
+            if a.overlaps(b) {
+                overlaps.push((i, j));
+            }
+        }
+    }
+    overlaps
+}
+
+/// Finds the runs ("plates") of wall along each row and combines plates that repeat
+/// in consecutive rows into rectangles. With `column_major` the grid is scanned
+/// column by column instead, which merges vertical corridors into fewer rects.
+fn plate_rects(
+    walls: &HashSet<GridCoords>,
+    width: i32,
+    height: i32,
+    column_major: bool,
+) -> Vec<WallRect> {
+    let cell = |x: i32, y: i32| {
+        if column_major {
+            GridCoords { x: y, y: x }
+        } else {
+            GridCoords { x, y }
+        }
+    };
+    let (width, height) = if column_major {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    // Find the horizontal runs ("plates") of wall in each row
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+    for y in 0..height {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width so the algorithm "terminates" plates that touch the right edge
+        for x in 0..width + 1 {
+            match (plate_start, walls.contains(&cell(x, y))) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: x - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+
+        plate_stack.push(row_plates);
+    }
+
+    // Combine plates into rectangles across multiple rows
+    let mut rect_builder: HashMap<Plate, WallRect> = HashMap::new();
+    let mut prev_row: Vec<Plate> = Vec::new();
+    let mut wall_rects: Vec<WallRect> = Vec::new();
+
+    // An extra empty row so the algorithm "finishes" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (y, current_row) in plate_stack.into_iter().enumerate() {
+        for prev_plate in &prev_row {
+            if !current_row.contains(prev_plate) {
+                // Remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_plate) {
+                    wall_rects.push(rect);
+                }
+            }
+        }
+        for plate in &current_row {
+            rect_builder
+                .entry(plate.clone())
+                .and_modify(|e| e.top += 1)
+                .or_insert(WallRect {
+                    bottom: y as i32,
+                    top: y as i32,
+                    left: plate.left,
+                    right: plate.right,
+                });
+        }
+        prev_row = current_row;
+    }
 
-            *level_walls = new_level_walls;
+    if column_major {
+        // Swap the axes back so the rects are in grid space
+        for rect in wall_rects.iter_mut() {
+            *rect = WallRect {
+                left: rect.bottom,
+                right: rect.top,
+                bottom: rect.left,
+                top: rect.right,
+            };
         }
     }
+
+    wall_rects
+}
+
+/// Merges wall cells into as few rectangles as the plate method finds.
+///
+/// Plates merge well along one axis only, so the cells are merged both row by row and
+/// column by column, and the smaller set of rectangles is kept. The rectangles are
+/// sorted bottom to top, then left to right, so the same walls always give the same
+/// rectangles in the same order.
+///
+/// # Arguments
+/// * `cells` - The wall cells to merge.
+/// * `width` - Number of columns to scan; walls at or past it are left out.
+/// * `height` - Number of rows to scan; walls at or past it are left out.
+///
+/// # Returns
+/// Rectangles covering every wall cell in range exactly once.
+pub fn merge_walls(cells: &HashSet<GridCoords>, width: i32, height: i32) -> Vec<WallRect> {
+    let row_major = plate_rects(cells, width, height, false);
+    let column_major = plate_rects(cells, width, height, true);
+    debug!(
+        "wall merge: {} cells -> {} row-major / {} column-major rects",
+        cells.len(),
+        row_major.len(),
+        column_major.len()
+    );
+    let mut wall_rects = if column_major.len() < row_major.len() {
+        column_major
+    } else {
+        row_major
+    };
+    wall_rects.sort_by_key(|r| (r.bottom, r.left, r.top, r.right));
+    wall_rects
 }
 
-/// Sets up collision components for newly added wall entities.
+/// Builds merged wall colliders for each level once it has finished spawning.
 ///
-/// This system is designed to run for each entity that has a `Wall` component,
-/// but not a `Collider` component. It triggers only when a `Wall` component is newly added
-/// to an entity. The system adds a `Collider` component to these entities to handle
-/// physical interactions in the game world. Additionally, a `RigidBody::Fixed` component
-/// is added to ensure that the walls are stationary and do not move in response to collisions.
+/// Runs on `LevelEvent::Spawned`, when all of the level's walls exist, so each
+/// level's walls are merged exactly once rather than piecemeal as wall tiles are added.
+/// The level's walls are found through their parents (wall -> layer -> level) and
+/// merged into rectangles by `merge_walls`. One fixed `Collider` is spawned per
+/// rectangle as a child of the level, instead of one per wall tile.
+/// `Breakable` walls are left out, as `setup_breakable_wall_colliders` handles them.
+/// Cells and rectangles are sorted bottom to top, then left to right, so the same
+/// walls always give the same colliders in the same order.
 ///
-/// The `Collider` is a cuboid with dimensions based on the wall sprite's width and height,
-/// providing an accurate collision area that matches the wall's visual representation.
+/// If `WallColliderDebug` is enabled, the merged rectangles are checked for overlaps,
+/// which are logged as errors, and the per-cell (pre-merge) and merged (post-merge)
+/// rectangles are recorded in world space for `draw_wall_collider_gizmos`.
 ///
 /// # Arguments
-/// * `commands` - Provides the functionality to perform various operations on entities,
-///   such as adding or removing components.
-/// * `query` - Query that selects wall entities requiring collider components.
+/// * `commands` - Used to spawn the collider entities under each level.
+/// * `level_events` - Event reader for levels being spawned.
+/// * `level_entities` - Query used to find the spawned level's entity and position.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+/// * `wall_query` - Query selecting walls with their grid position and parent layer.
+/// * `parent_query` - Query used to walk from a wall's layer up to its level.
+/// * `wall_debug` - Debug resource receiving the pre- and post-merge rectangles.
+/// * `grid_size` - Resource giving the size of a cell, to size the colliders.
 ///
-#[allow(clippy::type_complexity)]
 fn setup_wall_colliders(
     mut commands: Commands,
-    query: Query<Entity, (With<Wall>, Without<Collider>, Added<Wall>)>,
+    mut level_events: EventReader<LevelEvent>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>, &Transform)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    wall_query: Query<(&GridCoords, &Parent), (With<Wall>, Without<Breakable>)>,
+    parent_query: Query<&Parent, Without<Wall>>,
+    mut wall_debug: ResMut<WallColliderDebug>,
+    grid_size: Res<GridSize>,
+) {
+    for level_event in level_events.iter() {
+        let LevelEvent::Spawned(level_iid) = level_event else {
+            continue;
+        };
+        let Some((level_entity, level_transform)) =
+            level_entities
+                .iter()
+                .find_map(|(entity, handle, transform)| {
+                    level_assets
+                        .get(handle)
+                        .filter(|ldtk_level| ldtk_level.level.iid == *level_iid)
+                        .map(|_| (entity, transform))
+                })
+        else {
+            continue;
+        };
+
+        let level_walls: HashSet<GridCoords> = wall_query
+            .iter()
+            .filter(|(_, parent)| {
+                parent_query
+                    .get(parent.get())
+                    .is_ok_and(|layer_parent| layer_parent.get() == level_entity)
+            })
+            .map(|(grid_coords, _)| *grid_coords)
+            .collect();
+        let width = level_walls.iter().map(|c| c.x).max().unwrap_or(0) + 1;
+        let height = level_walls.iter().map(|c| c.y).max().unwrap_or(0) + 1;
+
+        let wall_rects = merge_walls(&level_walls, width, height);
+
+        if wall_debug.enabled {
+            for (i, j) in overlapping_rects(&wall_rects) {
+                error!(
+                    "🧱overlapping wall colliders in level {}: {:?} and {:?}",
+                    level_iid, wall_rects[i], wall_rects[j]
+                );
+            }
+            let offset = level_transform.translation.truncate();
+            let grid = grid_size.pixels();
+            let mut cells: Vec<&GridCoords> = level_walls.iter().collect();
+            cells.sort_by_key(|c| (c.y, c.x));
+            let pre_merge = cells
+                .into_iter()
+                .map(|c| {
+                    let min = offset + Vec2::new(c.x as f32, c.y as f32) * grid;
+                    bevy::math::Rect::from_corners(min, min + Vec2::splat(grid))
+                })
+                .collect();
+            let post_merge = wall_rects
+                .iter()
+                .map(|r| {
+                    bevy::math::Rect::from_corners(
+                        offset + Vec2::new(r.left as f32, r.bottom as f32) * grid,
+                        offset + Vec2::new((r.right + 1) as f32, (r.top + 1) as f32) * grid,
+                    )
+                })
+                .collect();
+            // A level spawning again replaces its old rectangles
+            wall_debug.pre_merge.insert(level_iid.clone(), pre_merge);
+            wall_debug.post_merge.insert(level_iid.clone(), post_merge);
+        }
+
+        info!(
+            "built {} colliders via plate method from {} wall cells in level {}",
+            wall_rects.len(),
+            level_walls.len(),
+            level_iid
+        );
+
+        let grid = grid_size.pixels();
+        commands.entity(level_entity).with_children(|level| {
+            for wall_rect in wall_rects {
+                level
+                    .spawn_empty()
+                    .insert(Collider::cuboid(
+                        (wall_rect.right - wall_rect.left + 1) as f32 * grid / 2.0,
+                        (wall_rect.top - wall_rect.bottom + 1) as f32 * grid / 2.0,
+                    ))
+                    .insert(RigidBody::Fixed)
+                    .insert(ActiveEvents::COLLISION_EVENTS)
+                    .insert(CollisionLayer::Wall.groups())
+                    .insert(WallCollider)
+                    .insert(TransformBundle::from_transform(Transform::from_xyz(
+                        (wall_rect.left + wall_rect.right + 1) as f32 * grid / 2.0,
+                        (wall_rect.bottom + wall_rect.top + 1) as f32 * grid / 2.0,
+                        0.0,
+                    )))
+                    .insert(Name::new(format!(
+                        "Wall ({},{})-({},{})",
+                        wall_rect.left, wall_rect.bottom, wall_rect.right, wall_rect.top
+                    )));
+            }
+        });
+    }
+}
+
+/// Gives each newly spawned breakable wall a one-cell collider of its own, so it
+/// goes away with the wall.
+fn setup_breakable_wall_colliders(
+    mut commands: Commands,
+    query: Query<Entity, Added<Breakable>>,
+    grid_size: Res<GridSize>,
 ) {
     for entity in query.iter() {
-        commands
-            .entity(entity)
-            .insert(Collider::cuboid(
-                WALL_SPRITE_WIDTH / 2.0,
-                WALL_SPRITE_HEIGHT / 2.0,
-            ))
-            .insert(ActiveEvents::COLLISION_EVENTS)
-            .insert(Ccd::enabled())
-            .insert(Name::new(format!("Wall {:?}", entity)))
-            .insert(RigidBody::Fixed);
+        commands.entity(entity).insert((
+            Collider::cuboid(grid_size.pixels() / 2.0, grid_size.pixels() / 2.0),
+            RigidBody::Fixed,
+            ActiveEvents::COLLISION_EVENTS,
+            CollisionLayer::Wall.groups(),
+            WallCollider,
+        ));
     }
-    if query.iter().count() > 0 {
-        info!("built {} colliders via naïve method", query.iter().count());
+}
+
+/// Damages breakable walls hit by spells, breaking those with no health left.
+///
+/// A broken wall is despawned along with its tile and collider, and its cell is
+/// opened up in `LevelWalls` so the player and spells can pass.
+///
+/// # Arguments
+/// * `commands` - Used to despawn broken walls.
+/// * `damage_events` - Event reader for spells hitting breakable walls.
+/// * `wall_query` - Query to access breakable walls' health and positions.
+/// * `level_walls` - Resource the broken walls' cells are opened up in.
+/// * `grid_size` - Resource giving the size of a cell, to find the walls' cells.
+///
+fn damage_breakable_walls(
+    mut commands: Commands,
+    mut damage_events: EventReader<WallDamaged>,
+    mut wall_query: Query<(&mut Breakable, &GlobalTransform)>,
+    mut level_walls: ResMut<LevelWalls>,
+    grid_size: Res<GridSize>,
+) {
+    for damaged in damage_events.iter() {
+        let Ok((mut breakable, transform)) = wall_query.get_mut(damaged.wall) else {
+            continue;
+        };
+        // Already broken by an earlier hit this frame
+        if breakable.health <= 0.0 {
+            continue;
+        }
+        breakable.health -= damaged.damage;
+        let cell = grid_size.to_grid_coords(transform.translation().truncate());
+        if breakable.health > 0.0 {
+            info!("🧱wall at {:?} hit, {} left", cell, breakable.health);
+            continue;
+        }
+        info!("🧱wall at {:?} broken", cell);
+        commands.entity(damaged.wall).despawn_recursive();
+        level_walls.open_world(cell);
+    }
+}
+
+/// Cycles the wall gizmo overlay (off -> post-merge -> pre-merge) when F3 is pressed.
+fn toggle_wall_collider_gizmos(
+    input_res: Res<Input<KeyCode>>,
+    mut wall_debug: ResMut<WallColliderDebug>,
+) {
+    if input_res.just_pressed(KeyCode::F3) {
+        wall_debug.mode = wall_debug.mode.next();
+        info!("wall collider gizmos: {:?}", wall_debug.mode);
+    }
+}
+
+/// Shows or hides Rapier's collider outlines when F2 is pressed.
+///
+/// Does nothing if `RapierDebugRenderPlugin` isn't added.
+fn toggle_physics_debug_render(
+    input_res: Res<Input<KeyCode>>,
+    debug_render: Option<ResMut<DebugRenderContext>>,
+) {
+    let Some(mut debug_render) = debug_render else {
+        return;
+    };
+    if input_res.just_pressed(KeyCode::F2) {
+        debug_render.enabled = !debug_render.enabled;
+        info!("physics debug render: {}", debug_render.enabled);
+    }
+}
+
+/// Renders the level as ASCII art, one line per row with the top row first.
+///
+/// Walls are `#`, open cells `.` and the player `@`.
+///
+/// # Arguments
+/// * `level_walls` - The level to render.
+/// * `player` - The player's cell, if it should be marked.
+///
+/// # Returns
+/// The rows joined with newlines, with no trailing newline.
+pub fn render_ascii_map(level_walls: &LevelWalls, player: Option<GridCoords>) -> String {
+    (0..level_walls.height())
+        .rev()
+        .map(|y| {
+            (0..level_walls.width())
+                .map(|x| {
+                    let cell = GridCoords::new(x, y);
+                    if player == Some(cell) {
+                        '@'
+                    } else if level_walls.in_wall(&cell) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Logs the current level as ASCII art when F4 is pressed.
+fn dump_ascii_map(
+    input_res: Res<Input<KeyCode>>,
+    level_walls: Res<LevelWalls>,
+    player_query: Query<&GridCoords, With<Player>>,
+) {
+    if input_res.just_pressed(KeyCode::F4) {
+        let player = player_query.iter().next().copied();
+        info!(
+            "level {} ({}x{}):\n{}",
+            level_walls.level_iid(),
+            level_walls.width(),
+            level_walls.height(),
+            render_ascii_map(&level_walls, player)
+        );
+    }
+}
+
+/// Run condition: true when the wall gizmo overlay should be drawn.
+fn wall_collider_gizmos_visible(wall_debug: Res<WallColliderDebug>) -> bool {
+    wall_debug.mode != WallGizmoMode::Off
+}
+
+/// Draws the recorded wall rectangles with gizmos.
+///
+/// Pre-merge cells are drawn in yellow and merged rectangles in green, so the
+/// output of the plate algorithm can be compared against the raw wall layout.
+fn draw_wall_collider_gizmos(wall_debug: Res<WallColliderDebug>, mut gizmos: Gizmos) {
+    let (rects, color) = match wall_debug.mode {
+        WallGizmoMode::Off => return,
+        WallGizmoMode::PreMerge => (&wall_debug.pre_merge, Color::YELLOW),
+        WallGizmoMode::PostMerge => (&wall_debug.post_merge, Color::GREEN),
+    };
+    for rect in rects.values().flatten() {
+        gizmos.rect_2d(rect.center(), 0.0, rect.size(), color);
     }
 }
 
@@ -132,20 +1270,1050 @@ fn display_events(
 
 #[cfg(test)]
 mod tests {
+    extern crate test;
+
     use super::*;
+    use crate::player::player_translation;
+    use crate::test_harness::Harness;
+    use crate::util::grid_manhattan;
+    use bevy::ecs::system::SystemState;
+
+    #[test]
+    fn test_new_level_walls() {
+        let walls = HashSet::from([GridCoords::new(1, 2), GridCoords::new(3, 0)]);
+        let level_walls = LevelWalls::new(walls.clone(), 4, 3);
+        assert_eq!(level_walls.width(), 4);
+        assert_eq!(level_walls.height(), 3);
+        assert_eq!(level_walls.level_iid(), "");
+        for x in -1..=4 {
+            for y in -1..=3 {
+                let cell = GridCoords::new(x, y);
+                let in_bounds = (0..4).contains(&x) && (0..3).contains(&y);
+                assert_eq!(
+                    level_walls.in_wall(&cell),
+                    !in_bounds || walls.contains(&cell)
+                );
+            }
+        }
+
+        let level_walls = level_walls.with_level_iid("level-a");
+        assert_eq!(level_walls.level_iid(), "level-a");
+        assert!(level_walls.in_wall(&GridCoords::new(1, 2)));
+    }
 
     #[test]
     fn test_in_wall() {
-        let mut level_walls = LevelWalls {
-            wall_locations: HashSet::new(),
-            level_width: 10,
-            level_height: 10,
-        };
-        level_walls.wall_locations.insert(GridCoords::new(5, 5));
+        let level_walls = LevelWalls::from_cells(&[(5, 5)], 10, 10);
 
         assert!(!level_walls.in_wall(&GridCoords::new(1, 1))); // Inside the level and not a wall
         assert!(level_walls.in_wall(&GridCoords::new(5, 5))); // Wall location
         assert!(level_walls.in_wall(&GridCoords::new(-1, 0))); // Outside the level boundaries
         assert!(level_walls.in_wall(&GridCoords::new(10, 10))); // Outside the level boundaries
     }
+
+    #[test]
+    fn test_walkable_cells() {
+        let level_walls = LevelWalls::from_cells(&[(1, 0), (2, 1)], 3, 2);
+
+        let walkable: Vec<GridCoords> = level_walls.walkable_cells().collect();
+        assert_eq!(
+            walkable,
+            vec![
+                GridCoords::new(0, 0),
+                GridCoords::new(2, 0),
+                GridCoords::new(0, 1),
+                GridCoords::new(1, 1),
+            ]
+        );
+        assert!(walkable.iter().all(|c| !level_walls.in_wall(c)));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..10 {
+            let cell = level_walls.random_walkable(&mut rng).unwrap();
+            assert!(walkable.contains(&cell));
+        }
+    }
+
+    #[test]
+    fn test_random_walkable_all_walls() {
+        let level_walls = LevelWalls::from_cells(&[(0, 0)], 1, 1);
+        assert_eq!(level_walls.walkable_cells().count(), 0);
+        assert_eq!(level_walls.random_walkable(&mut rand::thread_rng()), None);
+    }
+
+    #[test]
+    fn test_nearest_walkable() {
+        let level_walls = LevelWalls::from_cells(&[(2, 2), (2, 3), (3, 2)], 5, 5);
+
+        // Already walkable
+        assert_eq!(
+            level_walls.nearest_walkable(GridCoords::new(1, 1)),
+            Some(GridCoords::new(1, 1))
+        );
+
+        // Inside a wall: resolves to an orthogonally adjacent open cell
+        let nearest = level_walls.nearest_walkable(GridCoords::new(2, 2)).unwrap();
+        assert!(!level_walls.in_wall(&nearest));
+        assert_eq!(grid_manhattan(nearest, GridCoords::new(2, 2)), 1);
+
+        // Outside the level: clamped back in bounds
+        assert_eq!(
+            level_walls.nearest_walkable(GridCoords::new(-3, 0)),
+            Some(GridCoords::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn test_nearest_walkable_all_walls() {
+        let level_walls = LevelWalls::new(
+            (0..2)
+                .flat_map(|x| (0..2).map(move |y| GridCoords::new(x, y)))
+                .collect(),
+            2,
+            2,
+        );
+        assert_eq!(level_walls.nearest_walkable(GridCoords::new(0, 0)), None);
+    }
+
+    #[test]
+    fn test_diagonal_squeeze() {
+        // Two walls touching at a corner, with the player below-left of the gap
+        let level_walls = LevelWalls::from_cells(&[(1, 2), (2, 1)], 4, 4);
+        let from = GridCoords::new(1, 1);
+        assert!(level_walls.diagonal_squeeze(&from, &GridCoords::new(2, 2)));
+        assert!(!level_walls.can_step(&from, &GridCoords::new(2, 2)));
+
+        // One clear side is enough to get round the corner
+        let level_walls = LevelWalls::from_cells(&[(1, 2)], 4, 4);
+        assert!(!level_walls.diagonal_squeeze(&from, &GridCoords::new(2, 2)));
+        assert!(level_walls.can_step(&from, &GridCoords::new(2, 2)));
+
+        // Straight steps are never a squeeze
+        assert!(level_walls.can_step(&from, &GridCoords::new(2, 1)));
+        assert!(!level_walls.can_step(&from, &GridCoords::new(1, 2)));
+
+        // Walking diagonally into the gap leaves the player where it started
+        let mut harness = Harness::new(&[(1, 2), (2, 1)], 4, 4, from);
+        harness.hold(&[KeyCode::W, KeyCode::D], Harness::frames_to_walk(2));
+        assert_eq!(harness.player_coords(), from);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        // A wall in the middle of a 5x5 level
+        let level_walls = LevelWalls::from_cells(&[(2, 2)], 5, 5);
+        let los = |from: (i32, i32), to: (i32, i32)| {
+            level_walls.line_of_sight(GridCoords::new(from.0, from.1), GridCoords::new(to.0, to.1))
+        };
+        assert!(los((0, 0), (0, 0)));
+        assert!(los((0, 0), (4, 0)));
+        assert!(los((0, 3), (4, 4)));
+        assert!(!los((0, 1), (4, 3))); // Cuts through the wall cell
+        assert!(!los((0, 2), (4, 2)));
+        assert!(!los((2, 0), (2, 4)));
+        assert!(!los((0, 0), (4, 4)));
+        assert!(!los((1, 2), (2, 2))); // Looking at the wall itself
+        assert!(!los((0, 0), (5, 0))); // Past the edge of the level
+
+        // A diagonal gap between two walls touching at a corner
+        let level_walls = LevelWalls::from_cells(&[(1, 0), (0, 1)], 3, 3);
+        assert!(!level_walls.line_of_sight(GridCoords::new(0, 0), GridCoords::new(2, 2)));
+    }
+
+    #[test]
+    fn test_in_bounds_detects_edges() {
+        let level_walls = LevelWalls::from_cells(&[], 4, 3);
+        assert!(level_walls.in_bounds(&GridCoords::new(0, 0)));
+        assert!(level_walls.in_bounds(&GridCoords::new(3, 2)));
+        assert!(!level_walls.in_bounds(&GridCoords::new(4, 1))); // East edge
+        assert!(!level_walls.in_bounds(&GridCoords::new(1, -1))); // South edge
+        assert!(!level_walls.in_bounds(&GridCoords::new(-1, 3))); // North-west corner
+    }
+
+    #[test]
+    fn test_in_wall_across_level_boundary() {
+        // level-b sits directly east of level-a
+        let mut level_walls = LevelWalls::from_cells(&[(3, 1)], 4, 4).with_level_iid("level-a");
+        level_walls.add_levels(
+            LevelWalls::from_cells(&[(0, 2)], 4, 4)
+                .with_level_iid("level-b")
+                .with_origin(IVec2::new(4, 0)),
+        );
+
+        assert!(level_walls.in_wall(&GridCoords::new(4, 2))); // The neighbor's wall
+        assert!(!level_walls.in_wall(&GridCoords::new(4, 1))); // Open in the neighbor
+        assert!(level_walls.in_wall(&GridCoords::new(3, 1))); // Still our own wall
+        assert!(level_walls.in_wall(&GridCoords::new(8, 1))); // Past both levels
+        assert!(level_walls.in_wall(&GridCoords::new(4, -1))); // Below the neighbor
+        assert!(!level_walls.in_bounds(&GridCoords::new(4, 1))); // Open, but not our level
+
+        // The same cells, seen from the neighbor
+        level_walls.select_level("level-b".to_string());
+        assert_eq!(
+            level_walls.to_world(&GridCoords::new(0, 2)),
+            GridCoords::new(4, 2)
+        );
+        assert!(level_walls.in_wall(&GridCoords::new(0, 2)));
+        assert!(level_walls.in_wall(&GridCoords::new(-1, 1)));
+        assert!(!level_walls.in_wall(&GridCoords::new(-1, 2)));
+        assert!(level_walls.in_wall_world(GridCoords::new(3, 1)));
+    }
+
+    #[test]
+    fn test_solid_at_world() {
+        // A 4x4 level whose bottom-left cell sits at (-2, -2) in the world-space grid
+        let level_walls =
+            LevelWalls::from_cells(&[(1, 1), (3, 2)], 4, 4).with_origin(IVec2::new(-2, -2));
+        let grid = GRID_SIZE as f32;
+        let center = |x: i32, y: i32| (Vec2::new(x as f32, y as f32) + 0.5) * grid;
+
+        // Cell centers
+        assert!(level_walls.solid_at_world(center(-1, -1), GRID_SIZE));
+        assert!(level_walls.solid_at_world(center(1, 0), GRID_SIZE));
+        assert!(!level_walls.solid_at_world(center(0, 0), GRID_SIZE));
+        assert!(!level_walls.solid_at_world(center(-2, -2), GRID_SIZE));
+
+        // A point on an edge belongs to the cell above and to the right
+        assert!(level_walls.solid_at_world(Vec2::new(-grid, -grid), GRID_SIZE));
+        assert!(!level_walls.solid_at_world(Vec2::new(0.0, 0.0), GRID_SIZE));
+        assert!(level_walls.solid_at_world(Vec2::new(-0.1, -0.1), GRID_SIZE));
+
+        // Past the level, even just below its bottom edge
+        assert!(level_walls.solid_at_world(Vec2::new(-grid, -2.0 * grid - 0.1), GRID_SIZE));
+        assert!(level_walls.solid_at_world(center(2, 0), GRID_SIZE));
+
+        // The cell size comes from the caller, not the constant
+        assert!(level_walls.solid_at_world(Vec2::new(-5.0, -5.0), 8));
+        assert!(!level_walls.solid_at_world(Vec2::new(5.0, 5.0), 8));
+    }
+
+    #[test]
+    fn test_level_at_point() {
+        let square = |x: f32, y: f32| Rect::new(x, y, x + 100.0, y + 100.0);
+        let levels = vec![
+            ("current".to_string(), square(0.0, 0.0)),
+            ("east".to_string(), square(100.0, 0.0)),
+            ("north".to_string(), square(0.0, 100.0)),
+        ];
+
+        assert_eq!(
+            level_at_point(&levels, "current", Vec2::new(101.0, 50.0)),
+            Some("east")
+        );
+        assert_eq!(
+            level_at_point(&levels, "current", Vec2::new(50.0, 100.0)),
+            Some("north")
+        );
+        // Shared edge belongs to the neighbor, never back to the current level
+        assert_eq!(
+            level_at_point(&levels, "current", Vec2::new(100.0, 0.0)),
+            Some("east")
+        );
+        // Corner with no diagonal neighbor, and open space: stay blocked
+        assert_eq!(
+            level_at_point(&levels, "current", Vec2::new(150.0, 150.0)),
+            None
+        );
+        assert_eq!(
+            level_at_point(&levels, "current", Vec2::new(-10.0, 50.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_ascii_map() {
+        let level_walls = LevelWalls::from_cells(&[(0, 0), (1, 0), (2, 0), (0, 1), (2, 2)], 3, 3);
+        assert_eq!(
+            render_ascii_map(&level_walls, Some(GridCoords::new(1, 1))),
+            "..#\n#@.\n###"
+        );
+        assert_eq!(render_ascii_map(&level_walls, None), "..#\n#..\n###");
+        assert_eq!(render_ascii_map(&LevelWalls::default(), None), "");
+    }
+
+    #[test]
+    fn test_harness_wall_blocks_player() {
+        let mut harness = Harness::new(&[(3, 1)], 5, 5, GridCoords::new(1, 1));
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(3));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 1));
+
+        // Pushing past the level edge leaves the player in place and reports it
+        harness.hold(&[KeyCode::A], Harness::frames_to_walk(4));
+        assert_eq!(harness.player_coords(), GridCoords::new(0, 1));
+        assert!(!harness
+            .app
+            .world
+            .resource::<Events<LevelEdgeReached>>()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_coop_partner_crosses_into_neighbor_with_player() {
+        let mut app = wall_cache_app();
+        app.add_event::<LevelEdgeReached>()
+            .add_systems(Update, transition_level_at_edge.after(cache_wall_locations));
+        let grid_size = *app.world.resource::<GridSize>();
+        let level_a = spawn_level(&mut app, "level-a", &[]);
+        // level-b is 8 cells east of level-a
+        let level_b = spawn_level(&mut app, "level-b", &[]);
+        let origin_b = Vec3::new(8.0 * grid_size.pixels(), 0.0, 0.0);
+        app.world.entity_mut(level_b).insert((
+            Transform::from_translation(origin_b),
+            GlobalTransform::from_translation(origin_b),
+        ));
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+
+        let mut spawn_player = |cell: GridCoords| {
+            let translation = player_translation(cell, grid_size).extend(0.0);
+            app.world
+                .spawn((
+                    Player,
+                    Transform::from_translation(translation),
+                    GlobalTransform::from_translation(translation),
+                    cell,
+                ))
+                .set_parent(level_a)
+                .id()
+        };
+        // Player one steps east off level-a while player two hangs back
+        let player_one = spawn_player(GridCoords::new(7, 1));
+        let player_two = spawn_player(GridCoords::new(2, 3));
+        let destination = player_translation(GridCoords::new(8, 1), grid_size);
+        app.world.send_event(LevelEdgeReached {
+            player: player_one,
+            destination,
+        });
+        app.update();
+
+        assert_eq!(
+            *app.world.resource::<LevelSelection>(),
+            LevelSelection::Iid("level-b".to_string())
+        );
+        for player in [player_one, player_two] {
+            assert_eq!(app.world.get::<Parent>(player).unwrap().get(), level_b);
+            assert_eq!(
+                *app.world.get::<GridCoords>(player).unwrap(),
+                GridCoords::new(0, 1)
+            );
+        }
+    }
+
+    /// Builds an app running the wall collider pass with the given debug settings.
+    fn wall_collider_app(wall_debug: WallColliderDebug) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_event::<LevelEvent>()
+            .init_resource::<GridSize>()
+            .insert_resource(wall_debug)
+            .add_systems(Update, setup_wall_colliders);
+        app
+    }
+
+    /// Spawns an 8x8 cell level -> layer -> walls hierarchy like bevy_ecs_ldtk does,
+    /// without announcing that the level has spawned.
+    fn spawn_level(app: &mut App, level_iid: &str, cells: &[(i32, i32)]) -> Entity {
+        spawn_sized_level(app, level_iid, 8, cells)
+    }
+
+    /// Spawns a `size` x `size` level with the given walls, without announcing it.
+    fn spawn_sized_level(
+        app: &mut App,
+        level_iid: &str,
+        size: i32,
+        cells: &[(i32, i32)],
+    ) -> Entity {
+        let grid_size = app.world.resource::<GridSize>().0;
+        let handle = app
+            .world
+            .resource_mut::<Assets<LdtkLevel>>()
+            .add(LdtkLevel {
+                level: bevy_ecs_ldtk::ldtk::Level {
+                    iid: level_iid.to_string(),
+                    px_wid: size * grid_size,
+                    px_hei: size * grid_size,
+                    ..default()
+                },
+                background_image: None,
+            });
+        let level = app.world.spawn((SpatialBundle::default(), handle)).id();
+        let layer = app.world.spawn_empty().set_parent(level).id();
+        spawn_walls(app, layer, cells);
+        level
+    }
+
+    fn spawn_walls(app: &mut App, layer: Entity, cells: &[(i32, i32)]) {
+        for &(x, y) in cells {
+            app.world
+                .spawn((Wall, GridCoords::new(x, y)))
+                .set_parent(layer);
+        }
+    }
+
+    /// Spawns a level with the given walls and announces it, like bevy_ecs_ldtk does.
+    fn spawn_walls_level(app: &mut App, cells: &[(i32, i32)]) -> Entity {
+        let level = spawn_level(app, "level-a", cells);
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        level
+    }
+
+    /// The number of wall colliders that are children of `level`.
+    fn level_collider_count(app: &mut App, level: Entity) -> usize {
+        app.world
+            .query::<(&Collider, &Parent)>()
+            .iter(&app.world)
+            .filter(|(_, parent)| parent.get() == level)
+            .count()
+    }
+
+    #[test]
+    fn test_wall_collider_debug_rects() {
+        let mut app = wall_collider_app(WallColliderDebug {
+            enabled: true,
+            ..default()
+        });
+
+        // A 3x1 horizontal wall and a separate single tile
+        spawn_walls_level(&mut app, &[(0, 0), (1, 0), (2, 0), (5, 3)]);
+        app.update();
+
+        let wall_debug = app.world.resource::<WallColliderDebug>();
+        assert_eq!(wall_debug.pre_merge["level-a"].len(), 4);
+        assert_eq!(wall_debug.post_merge["level-a"].len(), 2);
+        assert_eq!(app.world.query::<&Collider>().iter(&app.world).count(), 2);
+    }
+
+    #[test]
+    fn test_wall_merge_output_reproducible() {
+        let cells = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (5, 3),
+            (5, 4),
+            (7, 7),
+            (3, 6),
+            (4, 6),
+        ];
+        let rects = |cells: &[(i32, i32)]| {
+            let mut app = wall_collider_app(WallColliderDebug {
+                enabled: true,
+                ..default()
+            });
+            spawn_walls_level(&mut app, cells);
+            app.update();
+            let mut wall_debug = app.world.resource_mut::<WallColliderDebug>();
+            (
+                wall_debug.pre_merge.remove("level-a").unwrap(),
+                wall_debug.post_merge.remove("level-a").unwrap(),
+            )
+        };
+
+        // Each run hashes the cells differently, and the walls may spawn in any order
+        let first = rects(&cells);
+        for _ in 0..5 {
+            assert_eq!(rects(&cells), first);
+        }
+        let mut reversed = cells;
+        reversed.reverse();
+        assert_eq!(rects(&reversed), first);
+
+        // Bottom to top, then left to right
+        let bottoms: Vec<f32> = first.1.iter().map(|rect| rect.min.y).collect();
+        assert!(bottoms.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_physics_debug_render_toggle() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .insert_resource(DebugRenderContext {
+                enabled: false,
+                ..default()
+            })
+            .add_systems(Update, toggle_physics_debug_render);
+        app.update();
+        assert!(!app.world.resource::<DebugRenderContext>().enabled);
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::F2);
+        app.update();
+        assert!(app.world.resource::<DebugRenderContext>().enabled);
+
+        // Held down, it doesn't flip back
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+        app.update();
+        assert!(app.world.resource::<DebugRenderContext>().enabled);
+
+        app.world.resource_mut::<Input<KeyCode>>().release_all();
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::F2);
+        app.update();
+        assert!(!app.world.resource::<DebugRenderContext>().enabled);
+    }
+
+    #[test]
+    fn test_wall_collider_debug_disabled() {
+        let mut app = wall_collider_app(WallColliderDebug {
+            enabled: false,
+            ..default()
+        });
+
+        spawn_walls_level(&mut app, &[(0, 0), (0, 1)]);
+        app.update();
+
+        let wall_debug = app.world.resource::<WallColliderDebug>();
+        assert!(wall_debug.pre_merge.is_empty());
+        assert!(wall_debug.post_merge.is_empty());
+    }
+
+    #[test]
+    fn test_wall_colliders_built_once_per_level_spawn() {
+        let mut app = wall_collider_app(WallColliderDebug::default());
+
+        // Walls streaming in before the level has finished spawning are left alone
+        let level_a = spawn_level(&mut app, "level-a", &[(0, 0), (1, 0)]);
+        app.update();
+        assert_eq!(level_collider_count(&mut app, level_a), 0);
+
+        // The whole level is merged once it has spawned, and only then
+        let layer_a = app.world.get::<Children>(level_a).unwrap()[0];
+        spawn_walls(&mut app, layer_a, &[(2, 0), (5, 3)]);
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+        assert_eq!(level_collider_count(&mut app, level_a), 2);
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(level_collider_count(&mut app, level_a), 2);
+
+        // A neighbor spawning later gets its own colliders without rebuilding the first level's
+        let level_b = spawn_level(&mut app, "level-b", &[(0, 0), (0, 1), (0, 2)]);
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+        assert_eq!(level_collider_count(&mut app, level_a), 2);
+        assert_eq!(level_collider_count(&mut app, level_b), 1);
+    }
+
+    #[test]
+    fn test_second_level_adds_walls_without_clobbering_first() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_asset::<LdtkAsset>()
+            .add_event::<LevelEvent>()
+            .add_event::<LevelReady>()
+            .init_resource::<GridSize>()
+            .init_resource::<LevelWalls>()
+            .insert_resource(LevelSelection::Iid("level-a".to_string()))
+            .add_systems(Update, cache_wall_locations);
+        let wall_a = GridCoords::new(1, 1);
+        let wall_b = GridCoords::new(2, 2);
+
+        spawn_level(&mut app, "level-a", &[(1, 1)]);
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+        let level_walls = app.world.resource::<LevelWalls>();
+        assert_eq!(level_walls.level_iid(), "level-a");
+        assert_eq!((level_walls.width(), level_walls.height()), (8, 8));
+        assert!(level_walls.in_wall(&wall_a));
+        assert!(!level_walls.in_wall(&wall_b));
+
+        // A neighbor loading adds its walls but leaves the selected level's alone
+        spawn_level(&mut app, "level-b", &[(2, 2)]);
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+        let level_walls = app.world.resource::<LevelWalls>();
+        assert_eq!(level_walls.level_iid(), "level-a");
+        assert!(level_walls.has_level("level-b"));
+        assert!(level_walls.in_wall(&wall_a));
+        assert!(!level_walls.in_wall(&wall_b));
+
+        // Walking into the neighbor switches to its walls, and back again
+        app.insert_resource(LevelSelection::Iid("level-b".to_string()));
+        app.update();
+        let level_walls = app.world.resource::<LevelWalls>();
+        assert_eq!(level_walls.level_iid(), "level-b");
+        assert!(!level_walls.in_wall(&wall_a));
+        assert!(level_walls.in_wall(&wall_b));
+
+        app.insert_resource(LevelSelection::Iid("level-a".to_string()));
+        app.update();
+        let level_walls = app.world.resource::<LevelWalls>();
+        assert!(level_walls.in_wall(&wall_a));
+        assert!(!level_walls.in_wall(&wall_b));
+
+        // A despawned level is forgotten
+        app.world
+            .send_event(LevelEvent::Despawned("level-b".to_string()));
+        app.update();
+        assert!(!app.world.resource::<LevelWalls>().has_level("level-b"));
+    }
+
+    /// Builds an app caching the walls of the levels spawned in it.
+    fn wall_cache_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_asset::<LdtkAsset>()
+            .add_event::<LevelEvent>()
+            .add_event::<LevelReady>()
+            .init_resource::<GridSize>()
+            .init_resource::<LevelWalls>()
+            .insert_resource(LevelSelection::Iid("level-a".to_string()))
+            .add_systems(Update, cache_wall_locations);
+        app
+    }
+
+    /// Every other cell of a `size` x `size` block, with a few solid runs.
+    fn large_wall_set(size: i32) -> Vec<(i32, i32)> {
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| (x, y)))
+            .filter(|(x, y)| (x + y) % 2 == 0 || y % 7 == 0)
+            .collect()
+    }
+
+    #[test]
+    fn test_cache_large_level_in_wall_unchanged() {
+        let mut app = wall_cache_app();
+        let cells = large_wall_set(8);
+        spawn_level(&mut app, "level-a", &cells);
+        // A neighbor with walls everywhere, none of which may leak into level-a
+        let everywhere: Vec<(i32, i32)> =
+            (0..8).flat_map(|x| (0..8).map(move |y| (x, y))).collect();
+        spawn_level(&mut app, "level-b", &everywhere);
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+
+        let level_walls = app.world.resource::<LevelWalls>();
+        for x in 0..8 {
+            for y in 0..8 {
+                assert_eq!(
+                    level_walls.in_wall(&GridCoords::new(x, y)),
+                    cells.contains(&(x, y)),
+                    "({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    /// Levels reported ready, with whether their walls were cached and selected by then.
+    #[derive(Resource, Default)]
+    struct Ready(Vec<(String, bool)>);
+
+    /// Records each `LevelReady` along with what `LevelWalls` held when it arrived.
+    fn record_ready(
+        mut ready_events: EventReader<LevelReady>,
+        level_walls: Res<LevelWalls>,
+        mut ready: ResMut<Ready>,
+    ) {
+        for event in ready_events.iter() {
+            let cached = level_walls.has_level(&event.level_iid)
+                && level_walls.level_iid() == event.level_iid
+                && level_walls.in_wall(&GridCoords::new(1, 1));
+            ready.0.push((event.level_iid.clone(), cached));
+        }
+    }
+
+    #[test]
+    fn test_level_ready_after_walls_cached() {
+        let mut app = wall_cache_app();
+        app.init_resource::<Ready>()
+            .add_systems(Update, record_ready.after(cache_wall_locations));
+
+        // A level that exists but hasn't been reported spawned isn't ready
+        spawn_level(&mut app, "level-a", &[(1, 1)]);
+        app.update();
+        assert!(app.world.resource::<Ready>().0.is_empty());
+        assert!(!app.world.resource::<LevelWalls>().has_level("level-a"));
+
+        // Nor is one reported spawned that can't be found
+        app.world
+            .send_event(LevelEvent::Spawned("level-missing".to_string()));
+        app.update();
+        assert!(app.world.resource::<Ready>().0.is_empty());
+
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Ready>().0,
+            vec![("level-a".to_string(), true)]
+        );
+
+        // Sent once per spawn, not every frame
+        app.update();
+        assert_eq!(app.world.resource::<Ready>().0.len(), 1);
+
+        // A neighbor cached before it's selected is ready again once selected
+        spawn_level(&mut app, "level-b", &[(1, 1)]);
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Ready>().0[1..],
+            [("level-b".to_string(), false)]
+        );
+        app.insert_resource(LevelSelection::Iid("level-b".to_string()));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Ready>().0[2..],
+            [("level-b".to_string(), true)]
+        );
+        app.update();
+        assert_eq!(app.world.resource::<Ready>().0.len(), 3);
+    }
+
+    #[test]
+    fn test_cached_neighbor_placed_by_its_transform() {
+        // Levels authored on an 8 pixel grid
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_asset::<LdtkAsset>()
+            .add_event::<LevelEvent>()
+            .add_event::<LevelReady>()
+            .insert_resource(GridSize(8))
+            .init_resource::<LevelWalls>()
+            .insert_resource(LevelSelection::Iid("level-a".to_string()))
+            .add_systems(Update, cache_wall_locations);
+
+        spawn_level(&mut app, "level-a", &[]);
+        // level-b is 8 cells east of level-a, with a wall in its second row
+        let level_b = spawn_level(&mut app, "level-b", &[(0, 1)]);
+        app.world
+            .get_mut::<Transform>(level_b)
+            .unwrap()
+            .translation
+            .x = 64.0;
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+
+        let level_walls = app.world.resource::<LevelWalls>();
+        assert_eq!(level_walls.level_iid(), "level-a");
+        assert!(level_walls.in_wall(&GridCoords::new(8, 1)));
+        assert!(!level_walls.in_wall(&GridCoords::new(8, 0)));
+        assert!(level_walls.in_wall(&GridCoords::new(-1, 1))); // Nothing loaded to the west
+        assert_eq!((level_walls.width(), level_walls.height()), (8, 8));
+    }
+
+    #[test]
+    fn test_wall_colliders_sized_by_grid_size() {
+        let mut app = wall_collider_app(WallColliderDebug {
+            enabled: true,
+            ..default()
+        });
+        app.insert_resource(GridSize(8));
+
+        // A 3x1 horizontal wall
+        spawn_walls_level(&mut app, &[(0, 0), (1, 0), (2, 0)]);
+        app.update();
+
+        let wall_debug = app.world.resource::<WallColliderDebug>();
+        assert_eq!(
+            wall_debug.post_merge["level-a"],
+            vec![bevy::math::Rect::new(0.0, 0.0, 24.0, 8.0)]
+        );
+        let centers: Vec<Vec3> = app
+            .world
+            .query_filtered::<&Transform, With<Collider>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation)
+            .collect();
+        assert_eq!(centers, vec![Vec3::new(12.0, 4.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_grid_size_conversions() {
+        let grid_size = GridSize(8);
+        assert_eq!(
+            grid_size.to_translation(GridCoords::new(2, 1)),
+            Vec2::new(20.0, 12.0)
+        );
+        assert_eq!(
+            grid_size.to_grid_coords(Vec2::new(20.0, 12.0)),
+            GridCoords::new(2, 1)
+        );
+        assert_eq!(
+            grid_size.to_grid_coords(Vec2::new(23.9, 8.0)),
+            GridCoords::new(2, 1)
+        );
+        assert_eq!(
+            GridSize::default().to_translation(GridCoords::new(2, 1)),
+            Vec2::new(40.0, 24.0)
+        );
+    }
+
+    #[test]
+    fn test_repeated_hits_break_wall() {
+        let mut app = App::new();
+        app.init_resource::<GridSize>()
+            .insert_resource(LevelWalls::from_cells(&[(2, 0)], 5, 1))
+            .add_event::<WallDamaged>()
+            .add_systems(Update, damage_breakable_walls);
+        let cell = GridCoords::new(2, 0);
+        let wall = app
+            .world
+            .spawn((
+                Wall,
+                Breakable { health: 2.0 },
+                GlobalTransform::from_translation(
+                    GridSize::default().to_translation(cell).extend(0.0),
+                ),
+            ))
+            .id();
+        let hit = |app: &mut App| {
+            app.world.send_event(WallDamaged { wall, damage: 1.0 });
+            app.update();
+        };
+
+        // One hit only chips it
+        hit(&mut app);
+        assert_eq!(app.world.get::<Breakable>(wall).unwrap().health, 1.0);
+        assert!(app.world.resource::<LevelWalls>().in_wall(&cell));
+
+        // The second breaks it, opening the cell
+        hit(&mut app);
+        assert!(app.world.get_entity(wall).is_none());
+        assert!(!app.world.resource::<LevelWalls>().in_wall(&cell));
+    }
+
+    fn collider_count(cells: &[(i32, i32)]) -> usize {
+        let mut app = wall_collider_app(WallColliderDebug::default());
+        spawn_walls_level(&mut app, cells);
+        app.update();
+        let count = app.world.query::<&Collider>().iter(&app.world).count();
+        count
+    }
+
+    #[test]
+    fn test_vertical_wall_merges_to_one_collider() {
+        let cells: Vec<(i32, i32)> = (0..6).map(|y| (3, y)).collect();
+        assert_eq!(collider_count(&cells), 1);
+    }
+
+    #[test]
+    fn test_vertical_wall_with_stubs_merges_column_wise() {
+        // A 1-wide wall with stubs off one side: every row's plate differs from the
+        // next, so rows alone need 7 rects, but columns need the wall plus the stubs
+        let cells = [
+            (0, 0),
+            (0, 1),
+            (1, 1),
+            (0, 2),
+            (0, 3),
+            (1, 3),
+            (0, 4),
+            (0, 5),
+            (1, 5),
+            (0, 6),
+        ];
+        assert_eq!(plate_rects(&wall_cells(&cells), 2, 7, false).len(), 7);
+        assert_eq!(collider_count(&cells), 4);
+    }
+
+    #[test]
+    fn test_vertical_corridor_uses_column_merge() {
+        // A 1-wide column with a bump: row-major plates need 3 rects, columns need 2
+        assert_eq!(collider_count(&[(0, 0), (0, 1), (1, 1), (0, 2)]), 2);
+    }
+
+    fn wall_cells(cells: &[(i32, i32)]) -> HashSet<GridCoords> {
+        cells.iter().map(|&(x, y)| GridCoords::new(x, y)).collect()
+    }
+
+    fn wall_rect(left: i32, bottom: i32, right: i32, top: i32) -> WallRect {
+        WallRect {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_merge_walls_single_tile() {
+        assert_eq!(
+            merge_walls(&wall_cells(&[(2, 3)]), 4, 4),
+            vec![wall_rect(2, 3, 2, 3)]
+        );
+        assert!(merge_walls(&HashSet::new(), 4, 4).is_empty());
+    }
+
+    #[test]
+    fn test_merge_walls_l_shape() {
+        // A floor three wide with a wall rising two more from its left end
+        let cells = wall_cells(&[(0, 0), (1, 0), (2, 0), (0, 1), (0, 2)]);
+        assert_eq!(
+            merge_walls(&cells, 3, 3),
+            vec![wall_rect(0, 0, 2, 0), wall_rect(0, 1, 0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_rects_flagged() {
+        // Two 2x2 blocks sharing the cell (1, 1), and one beside them touching but apart
+        let rects = [
+            wall_rect(0, 0, 1, 1),
+            wall_rect(1, 1, 2, 2),
+            wall_rect(3, 0, 3, 2),
+        ];
+        assert!(rects[0].overlaps(&rects[1]));
+        assert!(!rects[1].overlaps(&rects[2]));
+        assert_eq!(overlapping_rects(&rects), vec![(0, 1)]);
+
+        // The merge pass itself never overlaps
+        let cells: Vec<(i32, i32)> = (0..8)
+            .flat_map(|x| (0..8).map(move |y| (x, y)))
+            .filter(|(x, y)| (x * 3 + y) % 4 != 0)
+            .collect();
+        assert!(overlapping_rects(&merge_walls(&wall_cells(&cells), 8, 8)).is_empty());
+    }
+
+    #[test]
+    fn test_merge_walls_hollow_square() {
+        // A 4x4 ring: the top and bottom span the width, the sides fill in between
+        let cells = wall_cells(&[
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (3, 0),
+            (0, 1),
+            (3, 1),
+            (0, 2),
+            (3, 2),
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (3, 3),
+        ]);
+        let rects = merge_walls(&cells, 4, 4);
+        assert_eq!(
+            rects,
+            vec![
+                wall_rect(0, 0, 3, 0),
+                wall_rect(0, 1, 0, 2),
+                wall_rect(3, 1, 3, 2),
+                wall_rect(0, 3, 3, 3),
+            ]
+        );
+        // Every wall cell is covered exactly once, and the hole is left open
+        let area: i32 = rects
+            .iter()
+            .map(|r| (r.right - r.left + 1) * (r.top - r.bottom + 1))
+            .sum();
+        assert_eq!(area, cells.len() as i32);
+    }
+
+    /// Builds an app with two 256x256 levels of walls loaded side by side, returning
+    /// it and the first level.
+    fn large_levels_app() -> (App, Entity) {
+        let mut app = wall_cache_app();
+        let cells = large_wall_set(256);
+        let level = spawn_sized_level(&mut app, "level-a", 256, &cells);
+        spawn_sized_level(&mut app, "level-b", 256, &cells);
+        (app, level)
+    }
+
+    /// Collects the walls of `level` the way `cache_wall_locations` used to, by
+    /// checking every wall's layer, kept to compare against `level_wall_locations`.
+    fn level_wall_locations_by_parent(
+        level: Entity,
+        walls: &Query<(&GridCoords, &Parent), With<Wall>>,
+        layers: &Query<&Parent, Without<Wall>>,
+    ) -> HashSet<GridCoords> {
+        walls
+            .iter()
+            .filter(|(_, parent)| {
+                layers
+                    .get(parent.get())
+                    .is_ok_and(|layer_parent| layer_parent.get() == level)
+            })
+            .map(|(grid_coords, _)| *grid_coords)
+            .collect()
+    }
+
+    #[test]
+    fn test_level_wall_locations_match_checking_every_wall() {
+        let mut app = wall_cache_app();
+        let cells = large_wall_set(8);
+        let level = spawn_level(&mut app, "level-a", &cells);
+        spawn_level(&mut app, "level-b", &[(0, 0), (1, 1)]);
+        let mut before = SystemState::<(
+            Query<(&GridCoords, &Parent), With<Wall>>,
+            Query<&Parent, Without<Wall>>,
+        )>::new(&mut app.world);
+        let mut after =
+            SystemState::<(Query<&GridCoords, With<Wall>>, Query<&Children>)>::new(&mut app.world);
+        let (walls, layers) = before.get(&app.world);
+        let expected = level_wall_locations_by_parent(level, &walls, &layers);
+        let (walls, children) = after.get(&app.world);
+        assert_eq!(level_wall_locations(level, &walls, &children), expected);
+        assert_eq!(expected.len(), cells.len());
+    }
+
+    /// Before: every wall of both levels is checked to find the first level's.
+    #[bench]
+    fn bench_level_wall_locations_by_parent(b: &mut test::Bencher) {
+        let (mut app, level) = large_levels_app();
+        let mut state = SystemState::<(
+            Query<(&GridCoords, &Parent), With<Wall>>,
+            Query<&Parent, Without<Wall>>,
+        )>::new(&mut app.world);
+        b.iter(|| {
+            let (walls, layers) = state.get(&app.world);
+            level_wall_locations_by_parent(level, &walls, &layers)
+        });
+    }
+
+    /// After: only the first level's own walls are visited.
+    #[bench]
+    fn bench_level_wall_locations_by_children(b: &mut test::Bencher) {
+        let (mut app, level) = large_levels_app();
+        let mut state =
+            SystemState::<(Query<&GridCoords, With<Wall>>, Query<&Children>)>::new(&mut app.world);
+        b.iter(|| {
+            let (walls, children) = state.get(&app.world);
+            level_wall_locations(level, &walls, &children)
+        });
+    }
+
+    #[bench]
+    fn bench_cache_wall_locations_large(b: &mut test::Bencher) {
+        // A 256x256 level next to a loaded neighbor of the same size
+        let (mut app, _) = large_levels_app();
+        b.iter(|| {
+            app.world
+                .send_event(LevelEvent::Spawned("level-a".to_string()));
+            app.update();
+        });
+    }
+
+    #[bench]
+    fn bench_setup_wall_colliders_dense(b: &mut test::Bencher) {
+        // A 64x64 checkerboard of 2x2 blocks is a worst case for plate merging
+        let cells: Vec<(i32, i32)> = (0..64)
+            .flat_map(|x| (0..64).map(move |y| (x, y)))
+            .filter(|(x, y)| (x / 2 + y / 2) % 2 == 0)
+            .collect();
+        b.iter(|| collider_count(&cells));
+    }
 }
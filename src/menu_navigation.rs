@@ -0,0 +1,221 @@
+// menu_navigation.rs
+
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::game_state::{GameState, PauseState};
+use crate::settings_menu::{RebindCapture, SettingsButton, SettingsState};
+
+/// MenuNavigationPlugin lets the menus be used without a mouse.
+///
+/// The up and down arrows, or a gamepad's d-pad, move a highlight through the
+/// buttons on screen, top to bottom, wrapping around at either end. Enter, or the
+/// gamepad's south button, presses the highlighted button by setting its
+/// `Interaction` to `Pressed`, just as a click would, so the menus' own button
+/// handlers do the rest; it's let go again on the next frame. While the settings
+/// menu is open only its buttons are picked from, since it covers the menu it was
+/// opened from, and navigation waits while a rebind is listening for a key. It only
+/// runs while a menu is showing: on the main menu or game over screen, while paused,
+/// or with the settings menu open.
+impl Plugin for MenuNavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuSelection>().add_systems(
+            Update,
+            navigate_menu.run_if(
+                in_state(GameState::MainMenu)
+                    .or_else(in_state(GameState::GameOver))
+                    .or_else(in_state(PauseState::Paused))
+                    .or_else(in_state(SettingsState::Open)),
+            ),
+        );
+    }
+}
+
+/// The button highlighted for keyboard and gamepad navigation.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuSelection {
+    /// Position of the highlighted button among the buttons on screen, top to bottom.
+    pub index: usize,
+    /// Button pressed from the keyboard or gamepad, let go on the next frame.
+    pressed: Option<Entity>,
+    /// Button last drawn highlighted, so the buttons are only recolored when it changes.
+    highlighted: Option<Entity>,
+}
+
+/// Moves a selection `steps` places through `len` buttons, wrapping around at
+/// either end.
+///
+/// # Returns
+/// The new position, or 0 if there are no buttons.
+pub fn step_selection(index: usize, len: usize, steps: i32) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    (index as i64 + steps as i64).rem_euclid(len as i64) as usize
+}
+
+/// Moves the menu highlight and presses the highlighted button from the keyboard or
+/// gamepad.
+///
+/// The buttons are only recolored when the highlight moves or a menu opens.
+///
+/// # Arguments
+/// * `input_res` - Resource giving the keys pressed this frame.
+/// * `gamepad_res` - Resource giving the gamepad buttons pressed this frame.
+/// * `capture` - Resource telling whether a rebind is waiting for a key.
+/// * `selection` - Resource holding the highlighted button.
+/// * `button_query` - Query to access the buttons, their positions and colors.
+/// * `added_query` - Query used to start each newly opened menu at its top button.
+///
+#[allow(clippy::type_complexity)]
+pub fn navigate_menu(
+    input_res: Res<Input<KeyCode>>,
+    gamepad_res: Res<Input<GamepadButton>>,
+    capture: Res<RebindCapture>,
+    mut selection: ResMut<MenuSelection>,
+    mut button_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut Interaction,
+            &mut BackgroundColor,
+            Option<&SettingsButton>,
+        ),
+        With<Button>,
+    >,
+    added_query: Query<(), Added<Button>>,
+) {
+    if let Some(entity) = selection.pressed {
+        selection.pressed = None;
+        if let Ok((_, _, mut interaction, _, _)) = button_query.get_mut(entity) {
+            if *interaction == Interaction::Pressed {
+                *interaction = Interaction::None;
+            }
+        }
+    }
+    if capture.0.is_some() {
+        return;
+    }
+
+    let settings_open = button_query
+        .iter()
+        .any(|(_, _, _, _, settings_button)| settings_button.is_some());
+    let mut buttons: Vec<(Entity, Vec2)> = button_query
+        .iter()
+        .filter(|(_, _, _, _, settings_button)| settings_button.is_some() == settings_open)
+        .map(|(entity, transform, _, _, _)| (entity, transform.translation().truncate()))
+        .collect();
+    if buttons.is_empty() {
+        return;
+    }
+    // UI positions run down the screen, so this is reading order
+    buttons.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let gamepad_pressed = |button_type: GamepadButtonType| {
+        gamepad_res
+            .get_just_pressed()
+            .any(|button| button.button_type == button_type)
+    };
+    let mut steps = 0;
+    if input_res.just_pressed(KeyCode::Up) || gamepad_pressed(GamepadButtonType::DPadUp) {
+        steps -= 1;
+    }
+    if input_res.just_pressed(KeyCode::Down) || gamepad_pressed(GamepadButtonType::DPadDown) {
+        steps += 1;
+    }
+    let index = if !added_query.is_empty() {
+        0
+    } else {
+        selection.index.min(buttons.len() - 1)
+    };
+    let index = step_selection(index, buttons.len(), steps);
+    if selection.index != index {
+        selection.index = index;
+    }
+    let (selected, _) = buttons[index];
+
+    if selection.highlighted != Some(selected) || !added_query.is_empty() {
+        selection.highlighted = Some(selected);
+        for (entity, _, _, mut color, _) in button_query.iter_mut() {
+            color.0 = if entity == selected {
+                Color::ORANGE
+            } else {
+                Color::DARK_GRAY
+            };
+        }
+    }
+    if input_res.just_pressed(KeyCode::Return) || gamepad_pressed(GamepadButtonType::South) {
+        if let Ok((_, _, mut interaction, _, _)) = button_query.get_mut(selected) {
+            *interaction = Interaction::Pressed;
+            selection.pressed = Some(selected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_wraps_around() {
+        assert_eq!(step_selection(0, 3, 1), 1);
+        assert_eq!(step_selection(2, 3, 1), 0);
+        assert_eq!(step_selection(0, 3, -1), 2);
+        assert_eq!(step_selection(1, 3, 0), 1);
+        assert_eq!(step_selection(1, 3, -4), 0);
+        assert_eq!(step_selection(0, 0, 1), 0);
+    }
+
+    #[test]
+    fn test_arrows_move_highlight_through_buttons() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<RebindCapture>()
+            .init_resource::<MenuSelection>()
+            .add_systems(Update, navigate_menu);
+        // Spawned out of order; they're navigated top to bottom
+        let buttons = [20.0, 0.0, 10.0].map(|y| {
+            app.world
+                .spawn(ButtonBundle {
+                    global_transform: GlobalTransform::from_translation(Vec3::new(0.0, y, 0.0)),
+                    ..default()
+                })
+                .id()
+        });
+        let highlighted = |app: &App| {
+            buttons
+                .iter()
+                .position(|&button| {
+                    app.world.get::<BackgroundColor>(button).unwrap().0 == Color::ORANGE
+                })
+                .unwrap()
+        };
+        app.update();
+        assert_eq!(highlighted(&app), 1);
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        assert_eq!(highlighted(&app), 0);
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+
+        let gamepad_down = GamepadButton::new(Gamepad::new(0), GamepadButtonType::DPadDown);
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(gamepad_down);
+        app.update();
+        assert_eq!(highlighted(&app), 1);
+        assert_eq!(app.world.resource::<MenuSelection>().index, 0);
+        app.world.resource_mut::<Input<GamepadButton>>().clear();
+
+        // With the highlight left where it is, the buttons aren't recolored
+        app.world.get_mut::<BackgroundColor>(buttons[0]).unwrap().0 = Color::BLUE;
+        app.update();
+        assert_eq!(
+            app.world.get::<BackgroundColor>(buttons[0]).unwrap().0,
+            Color::BLUE
+        );
+    }
+}
@@ -0,0 +1,414 @@
+// minimap.rs
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::accessibility::{palette_changed, AccessibilitySettings};
+use crate::components::*;
+use crate::constants::*;
+use crate::map::LevelWalls;
+
+/// MinimapPlugin is responsible for the minimap in the corner of the screen.
+/// The level's walls are drawn into a texture whenever `LevelWalls` changes,
+/// and the player dot is moved over it every frame. Cells the player hasn't
+/// explored yet are hidden under fog. M toggles it. The player dot takes its color
+/// from the `AccessibilitySettings` palette.
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VisitedCells>()
+            .add_systems(Startup, setup_minimap)
+            .add_systems(
+                Update,
+                (
+                    toggle_minimap,
+                    track_visited_cells,
+                    draw_minimap_walls.after(track_visited_cells),
+                    move_minimap_player_dot,
+                    recolor_minimap_player_dot.run_if(palette_changed),
+                ),
+            );
+    }
+}
+
+/// Cells of the current level the player has been on or next to.
+#[derive(Default, Resource, Debug)]
+pub struct VisitedCells(pub HashSet<GridCoords>);
+
+impl VisitedCells {
+    /// Checks if the player has explored the given cell.
+    pub fn contains(&self, grid_coords: &GridCoords) -> bool {
+        self.0.contains(grid_coords)
+    }
+
+    /// Returns the cells around `center` (including itself) that haven't been visited yet.
+    pub fn unvisited_around(&self, center: GridCoords) -> Vec<GridCoords> {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| GridCoords::new(center.x + dx, center.y + dy)))
+            .filter(|cell| !self.0.contains(cell))
+            .collect()
+    }
+}
+
+/// Handle to the texture the minimap walls are drawn into.
+#[derive(Resource)]
+pub struct Minimap {
+    image: Handle<Image>,
+}
+
+/// Marker for the root UI node of the minimap.
+#[derive(Component)]
+struct MinimapRoot;
+
+/// Marker for the UI node showing the player on the minimap.
+#[derive(Component)]
+struct MinimapPlayerDot;
+
+/// RGBA colors used when drawing the minimap.
+const MINIMAP_WALL_COLOR: [u8; 4] = [200, 200, 200, 255];
+const MINIMAP_FLOOR_COLOR: [u8; 4] = [40, 40, 50, 200];
+const MINIMAP_FOG_COLOR: [u8; 4] = [10, 10, 15, 200];
+const MINIMAP_EMPTY_COLOR: [u8; 4] = [0, 0, 0, 0];
+
+/// Number of minimap pixels per level cell, keeping the level's aspect ratio.
+pub fn minimap_scale(level_size: IVec2, minimap_size: UVec2) -> f32 {
+    if level_size.x <= 0 || level_size.y <= 0 {
+        return 0.0;
+    }
+    (minimap_size.x as f32 / level_size.x as f32).min(minimap_size.y as f32 / level_size.y as f32)
+}
+
+/// Maps a level cell to the minimap pixel at the top-left of that cell.
+///
+/// The minimap is an image, so its y axis points down while `GridCoords` point up.
+///
+/// # Returns
+/// The pixel, or `None` if the cell is outside the level.
+pub fn cell_to_minimap_pixel(
+    cell: GridCoords,
+    level_size: IVec2,
+    minimap_size: UVec2,
+) -> Option<UVec2> {
+    if cell.x < 0 || cell.y < 0 || cell.x >= level_size.x || cell.y >= level_size.y {
+        return None;
+    }
+    let scale = minimap_scale(level_size, minimap_size);
+    Some(UVec2::new(
+        (cell.x as f32 * scale) as u32,
+        ((level_size.y - 1 - cell.y) as f32 * scale) as u32,
+    ))
+}
+
+/// Maps a minimap pixel back to the level cell it shows.
+///
+/// # Returns
+/// The cell, or `None` if the pixel is in the unused margin of the minimap.
+pub fn minimap_pixel_to_cell(
+    pixel: UVec2,
+    level_size: IVec2,
+    minimap_size: UVec2,
+) -> Option<GridCoords> {
+    let scale = minimap_scale(level_size, minimap_size);
+    if scale <= 0.0 {
+        return None;
+    }
+    let x = (pixel.x as f32 / scale) as i32;
+    let row = (pixel.y as f32 / scale) as i32;
+    if x >= level_size.x || row >= level_size.y {
+        return None;
+    }
+    Some(GridCoords::new(x, level_size.y - 1 - row))
+}
+
+/// Draws the walls of a level into RGBA pixel data of the given size.
+///
+/// # Arguments
+/// * `level_size` - Width and height of the level, in cells.
+/// * `minimap_size` - Width and height of the minimap, in pixels.
+/// * `is_wall` - Returns whether a cell of the level is a wall.
+/// * `is_explored` - Returns whether the player has explored a cell; others are drawn as fog.
+pub fn render_minimap_pixels(
+    level_size: IVec2,
+    minimap_size: UVec2,
+    is_wall: impl Fn(&GridCoords) -> bool,
+    is_explored: impl Fn(&GridCoords) -> bool,
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity((minimap_size.x * minimap_size.y * 4) as usize);
+    for y in 0..minimap_size.y {
+        for x in 0..minimap_size.x {
+            let color = match minimap_pixel_to_cell(UVec2::new(x, y), level_size, minimap_size) {
+                Some(cell) if !is_explored(&cell) => MINIMAP_FOG_COLOR,
+                Some(cell) if is_wall(&cell) => MINIMAP_WALL_COLOR,
+                Some(_) => MINIMAP_FLOOR_COLOR,
+                None => MINIMAP_EMPTY_COLOR,
+            };
+            data.extend_from_slice(&color);
+        }
+    }
+    data
+}
+
+/// Creates the minimap texture and its UI nodes in the top-right corner.
+fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    accessibility: Res<AccessibilitySettings>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: MINIMAP_SIZE,
+            height: MINIMAP_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &MINIMAP_EMPTY_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+
+    commands
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(MINIMAP_MARGIN),
+                    right: Val::Px(MINIMAP_MARGIN),
+                    width: Val::Px(MINIMAP_SIZE as f32),
+                    height: Val::Px(MINIMAP_SIZE as f32),
+                    ..default()
+                },
+                image: UiImage::new(image.clone()),
+                ..default()
+            },
+            MinimapRoot,
+            Name::new("Minimap"),
+        ))
+        .with_children(|minimap| {
+            minimap.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(3.0),
+                        height: Val::Px(3.0),
+                        ..default()
+                    },
+                    background_color: accessibility.palette().minimap_player.into(),
+                    ..default()
+                },
+                MinimapPlayerDot,
+            ));
+        });
+
+    commands.insert_resource(Minimap { image });
+}
+
+/// Shows or hides the minimap when M is pressed.
+fn toggle_minimap(
+    input_res: Res<Input<KeyCode>>,
+    mut root_query: Query<&mut Visibility, With<MinimapRoot>>,
+) {
+    if input_res.just_pressed(KeyCode::M) {
+        for mut visibility in root_query.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Records the cells on and around the player as explored.
+///
+/// The set is cleared whenever the level selection changes, so each level
+/// starts out unexplored. It is only touched when a new cell is explored, so
+/// the minimap isn't redrawn every frame.
+fn track_visited_cells(
+    level_selection: Res<LevelSelection>,
+    mut visited_cells: ResMut<VisitedCells>,
+    player_query: Query<&GridCoords, With<Player>>,
+) {
+    if level_selection.is_changed() {
+        visited_cells.0.clear();
+    }
+    for player_coords in player_query.iter() {
+        let unvisited = visited_cells.unvisited_around(*player_coords);
+        if !unvisited.is_empty() {
+            visited_cells.0.extend(unvisited);
+        }
+    }
+}
+
+/// Redraws the minimap whenever the cached level walls or the explored cells change.
+fn draw_minimap_walls(
+    level_walls: Res<LevelWalls>,
+    visited_cells: Res<VisitedCells>,
+    minimap: Option<Res<Minimap>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(minimap) = minimap else {
+        return;
+    };
+    if !level_walls.is_changed() && !visited_cells.is_changed() {
+        return;
+    }
+    if let Some(image) = images.get_mut(&minimap.image) {
+        image.data = render_minimap_pixels(
+            IVec2::new(level_walls.width(), level_walls.height()),
+            UVec2::splat(MINIMAP_SIZE),
+            |cell| level_walls.in_wall(cell),
+            |cell| visited_cells.contains(cell),
+        );
+    }
+}
+
+/// Recolors the player dot when the palette changes.
+fn recolor_minimap_player_dot(
+    accessibility: Res<AccessibilitySettings>,
+    mut query: Query<&mut BackgroundColor, With<MinimapPlayerDot>>,
+) {
+    for mut color in query.iter_mut() {
+        color.0 = accessibility.palette().minimap_player;
+    }
+}
+
+/// Moves the player dot to the player's cell every frame.
+fn move_minimap_player_dot(
+    level_walls: Res<LevelWalls>,
+    player_query: Query<(&GridCoords, Option<&PlayerId>), With<Player>>,
+    mut dot_query: Query<&mut Style, With<MinimapPlayerDot>>,
+) {
+    let Some((player_coords, _)) = player_query
+        .iter()
+        .find(|(_, player_id)| PlayerId::of(*player_id) == PlayerId::ONE)
+    else {
+        return;
+    };
+    let level_size = IVec2::new(level_walls.width(), level_walls.height());
+    let minimap_size = UVec2::splat(MINIMAP_SIZE);
+    let Some(pixel) = cell_to_minimap_pixel(*player_coords, level_size, minimap_size) else {
+        return;
+    };
+    let half_cell = minimap_scale(level_size, minimap_size) / 2.0;
+    for mut style in dot_query.iter_mut() {
+        style.left = Val::Px(pixel.x as f32 + half_cell - 1.0);
+        style.top = Val::Px(pixel.y as f32 + half_cell - 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_to_minimap_pixel() {
+        // A 4x2 level on an 8x8 minimap is drawn 2 pixels per cell, top-aligned
+        let level_size = IVec2::new(4, 2);
+        let minimap_size = UVec2::new(8, 8);
+        assert_eq!(minimap_scale(level_size, minimap_size), 2.0);
+        assert_eq!(
+            cell_to_minimap_pixel(GridCoords::new(0, 0), level_size, minimap_size),
+            Some(UVec2::new(0, 2))
+        );
+        assert_eq!(
+            cell_to_minimap_pixel(GridCoords::new(3, 1), level_size, minimap_size),
+            Some(UVec2::new(6, 0))
+        );
+        assert_eq!(
+            cell_to_minimap_pixel(GridCoords::new(4, 0), level_size, minimap_size),
+            None
+        );
+    }
+
+    #[test]
+    fn test_minimap_pixel_to_cell() {
+        let level_size = IVec2::new(4, 2);
+        let minimap_size = UVec2::new(8, 8);
+        assert_eq!(
+            minimap_pixel_to_cell(UVec2::new(1, 3), level_size, minimap_size),
+            Some(GridCoords::new(0, 0))
+        );
+        assert_eq!(
+            minimap_pixel_to_cell(UVec2::new(7, 0), level_size, minimap_size),
+            Some(GridCoords::new(3, 1))
+        );
+        // Below the level: unused margin
+        assert_eq!(
+            minimap_pixel_to_cell(UVec2::new(0, 4), level_size, minimap_size),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_minimap_pixels() {
+        let walls = HashSet::from([GridCoords::new(1, 0)]);
+        let pixels = render_minimap_pixels(
+            IVec2::new(2, 1),
+            UVec2::new(2, 2),
+            |cell| walls.contains(cell),
+            |_| true,
+        );
+        assert_eq!(pixels.len(), 2 * 2 * 4);
+        assert_eq!(pixels[0..4], MINIMAP_FLOOR_COLOR);
+        assert_eq!(pixels[4..8], MINIMAP_WALL_COLOR);
+        assert_eq!(
+            pixels[8..16],
+            [MINIMAP_EMPTY_COLOR, MINIMAP_EMPTY_COLOR].concat()
+        );
+    }
+
+    #[test]
+    fn test_render_minimap_fog() {
+        let pixels = render_minimap_pixels(
+            IVec2::new(2, 1),
+            UVec2::new(2, 1),
+            |_| false,
+            |cell| cell.x == 0,
+        );
+        assert_eq!(pixels[0..4], MINIMAP_FLOOR_COLOR);
+        assert_eq!(pixels[4..8], MINIMAP_FOG_COLOR);
+    }
+
+    fn visited_cells_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(LevelSelection::default())
+            .init_resource::<VisitedCells>()
+            .add_systems(Update, track_visited_cells);
+        app
+    }
+
+    #[test]
+    fn test_visited_cells_accumulate() {
+        let mut app = visited_cells_app();
+        let player = app.world.spawn((Player, GridCoords::new(5, 5))).id();
+        app.update();
+        assert_eq!(app.world.resource::<VisitedCells>().0.len(), 9);
+
+        // Walking two cells east uncovers two new columns of three
+        for x in 6..=7 {
+            *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(x, 5);
+            app.update();
+        }
+        let visited = app.world.resource::<VisitedCells>();
+        assert_eq!(visited.0.len(), 15);
+        assert!(visited.contains(&GridCoords::new(4, 4)));
+        assert!(visited.contains(&GridCoords::new(8, 6)));
+        assert!(!visited.contains(&GridCoords::new(9, 5)));
+    }
+
+    #[test]
+    fn test_visited_cells_reset_on_level_change() {
+        let mut app = visited_cells_app();
+        let player = app.world.spawn((Player, GridCoords::new(5, 5))).id();
+        app.update();
+        *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(20, 20);
+        app.update();
+        assert_eq!(app.world.resource::<VisitedCells>().0.len(), 18);
+
+        *app.world.resource_mut::<LevelSelection>() = LevelSelection::Iid("next".to_string());
+        app.update();
+        let visited = app.world.resource::<VisitedCells>();
+        assert_eq!(visited.0.len(), 9);
+        assert!(!visited.contains(&GridCoords::new(5, 5)));
+    }
+}
@@ -0,0 +1,256 @@
+// moving_wall.rs
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components::*;
+use crate::interpolation::GameplayStep;
+use crate::map::{GridSize, LevelWalls};
+use crate::player::{move_player_from_input, player_cell};
+
+/// MovingWallPlugin moves the LDtk "MovingWall" entities along their waypoints.
+///
+/// Moving walls are kinematic Rapier bodies rather than part of `LevelWalls`' cached
+/// cells, which never change once a level is cached. Each fixed step they move
+/// before the player does, carrying along a player standing on them or in their
+/// way, unless that would push the player into a wall. The cells they cover are
+/// then handed to `LevelWalls`, so the player can't walk into them.
+impl Plugin for MovingWallPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, setup_moving_wall_colliders)
+            .add_systems(
+                FixedUpdate,
+                move_walls
+                    .in_set(GameplayStep)
+                    .before(move_player_from_input),
+            )
+            .register_ldtk_entity::<MovingWallBundle>("MovingWall");
+    }
+}
+
+/// Gives newly spawned moving walls a one-cell kinematic collider.
+fn setup_moving_wall_colliders(
+    mut commands: Commands,
+    query: Query<Entity, Added<MovingWall>>,
+    grid_size: Res<GridSize>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert((
+            Collider::cuboid(grid_size.pixels() / 2.0, grid_size.pixels() / 2.0),
+            RigidBody::KinematicPositionBased,
+            CollisionLayer::Wall.groups(),
+            WallCollider,
+            Name::new("Moving wall"),
+        ));
+    }
+}
+
+/// Checks if `point` lies within the cell-sized square centered on `center`.
+fn covers(center: Vec2, point: Vec2, cell_size: f32) -> bool {
+    (point - center)
+        .abs()
+        .cmplt(Vec2::splat(cell_size / 2.0))
+        .all()
+}
+
+/// The world-space cells a cell-sized wall centered on `center` overlaps: one
+/// when it sits in a cell, and up to four while it's between them.
+fn covered_cells(center: Vec2, cell_size: f32) -> impl Iterator<Item = GridCoords> {
+    // Shrunk a little, so a wall sitting exactly in a cell doesn't touch its neighbors
+    let half = cell_size / 2.0 - 0.01;
+    let min = ((center - half) / cell_size).floor().as_ivec2();
+    let max = ((center + half) / cell_size).floor().as_ivec2();
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| GridCoords::new(x, y)))
+}
+
+/// Moves each wall one fixed step along its waypoints, carrying players with it.
+///
+/// A player whose feet are on the wall before or after it moves is moved by the
+/// same amount, so it rides the wall or is pushed ahead of it. Only players in the
+/// wall's level are carried, and never into a wall of that level. The cells every
+/// wall covers afterwards are stored in `LevelWalls`, to block the player's steps.
+///
+/// # Arguments
+/// * `fixed_time` - Resource giving the length of the step.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `level_walls` - Resource used to keep carried players out of walls, and given
+///   the cells the walls cover.
+/// * `parent_query` - Query used to walk from a wall's layer up to its level.
+/// * `level_query` - Query to access the position of a wall's level in the world.
+/// * `wall_query` - Query to access moving walls, their positions and layers.
+/// * `player_query` - Query to access players' positions, cells and levels.
+///
+#[allow(clippy::type_complexity)]
+fn move_walls(
+    fixed_time: Res<FixedTime>,
+    grid_size: Res<GridSize>,
+    mut level_walls: ResMut<LevelWalls>,
+    parent_query: Query<&Parent, (Without<MovingWall>, Without<Player>)>,
+    level_query: Query<&Transform, (Without<MovingWall>, Without<Player>)>,
+    mut wall_query: Query<(&mut MovingWall, &mut Transform, Option<&Parent>), Without<Player>>,
+    mut player_query: Query<
+        (&mut Transform, &mut GridCoords, Option<&Parent>),
+        (With<Player>, Without<MovingWall>),
+    >,
+) {
+    let cell_size = grid_size.pixels();
+    let mut covered = HashSet::new();
+    for (mut wall, mut wall_transform, layer) in wall_query.iter_mut() {
+        let position = wall_transform.translation.truncate();
+        let start = *wall.start.get_or_insert(position);
+        let distance = wall.speed * cell_size * fixed_time.period.as_secs_f32();
+        let moved_to = wall.advance(start, position, distance, cell_size);
+        let delta = moved_to - position;
+
+        // Walls are children of the Entities layer, which is a child of the level
+        let wall_level = layer
+            .and_then(|layer| parent_query.get(layer.get()).ok())
+            .map(|level| level.get());
+        let level_origin = wall_level
+            .and_then(|level| level_query.get(level).ok())
+            .map_or(Vec2::ZERO, |level| level.translation.truncate());
+        covered.extend(covered_cells(level_origin + moved_to, cell_size));
+        if delta == Vec2::ZERO {
+            continue;
+        }
+        wall_transform.translation.x = moved_to.x;
+        wall_transform.translation.y = moved_to.y;
+
+        for (mut player_transform, mut player_grid_coords, player_level) in player_query.iter_mut()
+        {
+            if player_level.map(|level| level.get()) != wall_level {
+                continue;
+            }
+            let translation = player_transform.translation.truncate();
+            let feet = translation - Vec2::new(0.0, cell_size);
+            if !covers(position, feet, cell_size) && !covers(moved_to, feet, cell_size) {
+                continue;
+            }
+            let destination = translation + delta;
+            let destination_cell = player_cell(destination, *grid_size);
+            if level_walls.in_bounds(&destination_cell) && level_walls.in_wall(&destination_cell) {
+                continue;
+            }
+            player_transform.translation.x = destination.x;
+            player_transform.translation.y = destination.y;
+            *player_grid_coords = destination_cell;
+        }
+    }
+    level_walls.set_moving_wall_cells(covered);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::Harness;
+
+    #[test]
+    fn test_waypoint_traversal() {
+        // A loop around a rectangle two cells wide and one tall
+        let mut wall = MovingWall {
+            waypoints: vec![
+                IVec2::ZERO,
+                IVec2::new(2, 0),
+                IVec2::new(2, 1),
+                IVec2::new(0, 1),
+            ],
+            next: 1,
+            ..default()
+        };
+        let start = Vec2::ZERO;
+        let mut position = start;
+        let mut advance = |distance: f32| {
+            position = wall.advance(start, position, distance, 16.0);
+            position
+        };
+        assert_eq!(advance(16.0), Vec2::new(16.0, 0.0));
+        // Turns the corner partway through a step
+        assert_eq!(advance(24.0), Vec2::new(32.0, 8.0));
+        assert_eq!(advance(32.0), Vec2::new(8.0, 16.0));
+        // And loops back around to the start
+        assert_eq!(advance(24.0), Vec2::ZERO);
+        assert_eq!(advance(8.0), Vec2::new(8.0, 0.0));
+    }
+
+    #[test]
+    fn test_wall_without_path_stays_put() {
+        let mut wall = MovingWall::default();
+        let position = Vec2::new(40.0, 8.0);
+        assert_eq!(wall.advance(position, position, 10.0, 16.0), position);
+    }
+
+    #[test]
+    fn test_covered_cells() {
+        let cells = |center: Vec2| covered_cells(center, 16.0).collect::<Vec<_>>();
+        assert_eq!(cells(Vec2::new(24.0, 8.0)), vec![GridCoords::new(1, 0)]);
+        assert_eq!(
+            cells(Vec2::new(32.0, 8.0)),
+            vec![GridCoords::new(1, 0), GridCoords::new(2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_player_cannot_walk_into_wall() {
+        let mut harness = Harness::new(&[], 10, 3, GridCoords::new(1, 1));
+        harness.app.add_systems(
+            FixedUpdate,
+            move_walls
+                .in_set(GameplayStep)
+                .before(move_player_from_input),
+        );
+        // A wall with nowhere to go, standing in the player's way
+        let grid_size = GridSize::default();
+        let wall_cell = GridCoords::new(4, 1);
+        harness.app.world.spawn((
+            MovingWall::default(),
+            Transform::from_translation(grid_size.to_translation(wall_cell).extend(0.0)),
+        ));
+
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(6));
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 1));
+    }
+
+    #[test]
+    fn test_wall_carries_player_without_level_walls() {
+        let player_cell = GridCoords::new(1, 1);
+        let mut harness = Harness::new(&[], 10, 3, player_cell);
+        harness.app.add_systems(
+            FixedUpdate,
+            move_walls
+                .in_set(GameplayStep)
+                .before(move_player_from_input),
+        );
+        let grid_size = GridSize::default();
+        let wall = harness
+            .app
+            .world
+            .spawn((
+                MovingWall {
+                    waypoints: vec![IVec2::ZERO, IVec2::new(3, 0)],
+                    speed: 6.0,
+                    next: 1,
+                    start: None,
+                },
+                Transform::from_translation(grid_size.to_translation(player_cell).extend(0.0)),
+            ))
+            .id();
+
+        // 20 steps at 6 cells per second is two cells
+        harness.step(21);
+        let wall_x = harness
+            .app
+            .world
+            .get::<Transform>(wall)
+            .unwrap()
+            .translation
+            .x;
+        assert_eq!(grid_size.to_grid_coords(Vec2::new(wall_x, 0.0)).x, 3);
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 1));
+        // The wall moved through cells the level's cached walls know nothing about
+        let level_walls = harness.app.world.resource::<LevelWalls>();
+        assert!((1..=4).all(|x| !level_walls.in_wall(&GridCoords::new(x, 1))));
+    }
+}
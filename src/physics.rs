@@ -0,0 +1,123 @@
+// physics.rs
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::constants::*;
+
+/// PhysicsPlugin applies the `PhysicsSettings` to Rapier.
+///
+/// `PhysicsSettings` is loaded with the rest of the `GameConfig` in `main`. Its
+/// pixels per meter are fixed when the `RapierPhysicsPlugin` is built, so a change
+/// takes a restart. Its gravity is copied into the `RapierConfiguration` at startup
+/// and again whenever it changes, so it can be tried out from the world inspector
+/// while the game runs. The game is top-down, so there's no gravity by default.
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsSettings>()
+            .add_systems(Update, apply_physics_settings);
+    }
+}
+
+/// Rapier's world settings, chosen in the `GameConfig`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    /// Pull on every dynamic body, in pixels per second squared.
+    pub gravity: Vec2,
+    /// How many pixels make up one of Rapier's meters.
+    pub pixels_per_meter: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings {
+            gravity: Vec2::ZERO,
+            pixels_per_meter: GRID_SIZE as f32,
+        }
+    }
+}
+
+impl PhysicsSettings {
+    /// The pixels per meter to build the `RapierPhysicsPlugin` with.
+    ///
+    /// # Returns
+    /// `pixels_per_meter`, or `GRID_SIZE` if it isn't a positive number.
+    pub fn physics_scale(&self) -> f32 {
+        if self.pixels_per_meter.is_finite() && self.pixels_per_meter > 0.0 {
+            self.pixels_per_meter
+        } else {
+            GRID_SIZE as f32
+        }
+    }
+}
+
+/// Copies the gravity into the `RapierConfiguration` when the settings change, or
+/// when Rapier's configuration is first inserted.
+fn apply_physics_settings(
+    settings: Res<PhysicsSettings>,
+    rapier_config: Option<ResMut<RapierConfiguration>>,
+) {
+    let Some(mut rapier_config) = rapier_config else {
+        return;
+    };
+    if !settings.is_changed() && !rapier_config.is_added() {
+        return;
+    }
+    rapier_config.gravity = settings.gravity;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_applied_to_rapier() {
+        let mut app = App::new();
+        app.init_resource::<PhysicsSettings>()
+            .insert_resource(RapierConfiguration::default())
+            .add_systems(Update, apply_physics_settings);
+        app.update();
+        // Top-down: nothing falls
+        assert_eq!(
+            app.world.resource::<RapierConfiguration>().gravity,
+            Vec2::ZERO
+        );
+
+        app.world.resource_mut::<PhysicsSettings>().gravity = Vec2::new(0.0, -300.0);
+        app.update();
+        assert_eq!(
+            app.world.resource::<RapierConfiguration>().gravity,
+            Vec2::new(0.0, -300.0)
+        );
+
+        // Other settings, like pausing the pipeline, are left alone
+        app.world
+            .resource_mut::<RapierConfiguration>()
+            .physics_pipeline_active = false;
+        app.update();
+        assert!(
+            !app.world
+                .resource::<RapierConfiguration>()
+                .physics_pipeline_active
+        );
+    }
+
+    #[test]
+    fn test_physics_scale_falls_back_to_grid_size() {
+        let scale = |pixels_per_meter: f32| {
+            PhysicsSettings {
+                pixels_per_meter,
+                ..default()
+            }
+            .physics_scale()
+        };
+        assert_eq!(scale(32.0), 32.0);
+        assert_eq!(scale(0.0), GRID_SIZE as f32);
+        assert_eq!(scale(-8.0), GRID_SIZE as f32);
+        assert_eq!(scale(f32::NAN), GRID_SIZE as f32);
+    }
+}
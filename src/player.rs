@@ -1,33 +1,329 @@
 // player.rs
 
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::render::view::VisibilitySystems;
 use bevy::time::common_conditions::on_timer;
-use bevy::utils::Duration;
+use bevy::transform::TransformSystem;
+use bevy::utils::{Duration, HashMap};
+use bevy::window::PrimaryWindow;
 use bevy_ecs_ldtk::prelude::*;
-use bevy_ecs_ldtk::utils::translation_to_grid_coords;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::components::*;
 use crate::constants::*;
-use crate::map::LevelWalls;
+use crate::difficulty::Difficulty;
+use crate::door::{start_teleport, PendingTeleport, ScreenFade, TeleportTarget};
+use crate::game_state::GameState;
+use crate::interpolation::GameplayStep;
+use crate::map::{cache_wall_locations, GridSize, LevelEdgeReached, LevelReady, LevelWalls};
+use crate::settings::{view_scale, Action, CoopBindings, KeyBindings};
+use crate::spell_fire::SpellCast;
 use crate::util::convert_vec3_to_vec2;
 
 /// PlayerPlugin is responsible for handling player-related functionalities
 /// in the game. This includes processing player input for movement
-/// and animating the player sprite.
+/// and animating the player sprite, including the cast animation and the death
+/// animation played before the player respawns. In turn-based mode each move or
+/// cast ends the player's turn. Movement keys are read every frame, but the player
+/// moves in the fixed gameplay step, and the camera follows where the player is
+/// drawn. A `CameraSnap` is asked for when a level spawns or the player teleports.
+/// With `PlayerCollision::Physics`, real-time movement is swept through Rapier's
+/// colliders instead of checked against the wall cells.
+///
+/// For local co-op, F5 brings in a second player beside the first, or sends it
+/// away again. Each player moves with its own keys, player one with `KeyBindings`
+/// and player two with `CoopBindings`, and the camera frames both. Casting and
+/// interacting stay with player one.
+///
+/// When `DebugSettings::no_clip` is on, F1 toggles no-clip: the player walks
+/// through walls, with its collider disabled, until F1 is pressed again. The level's
+/// bounds still hand the player over to its neighbors. It's off in normal play.
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                move_player_from_input,
-                animate_player,
-                dbg_player.run_if(on_timer(Duration::from_secs(1))),
-                setup_player_animation,
-                setup_player_collision,
-            ),
-        )
-        .register_ldtk_entity::<PlayerBundle>("Player");
+        app.init_resource::<InputLocked>()
+            .init_resource::<Lives>()
+            .init_resource::<MovementMode>()
+            .init_resource::<MovementFeel>()
+            .init_resource::<PlayerCollision>()
+            .init_resource::<DebugSettings>()
+            .init_resource::<NoClip>()
+            .init_resource::<MoveInput>()
+            .init_resource::<CameraSnap>()
+            .init_resource::<FacingFrames>()
+            .init_resource::<PendingTurns>()
+            .init_resource::<LevelPlayerStats>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameReached>()
+            .add_systems(
+                PreUpdate,
+                read_move_input.run_if(input_unlocked).after(InputSystem),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    move_player_from_input
+                        .run_if(input_unlocked)
+                        .run_if(not(physics_movement)),
+                    move_player_with_physics
+                        .run_if(input_unlocked)
+                        .run_if(physics_movement),
+                    apply_recoil
+                        .after(move_player_from_input)
+                        .after(move_player_with_physics),
+                )
+                    .in_set(GameplayStep),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_player_with_camera
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::UpdateOrthographicFrusta),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_inspector_input_lock,
+                    animate_sprites,
+                    dbg_player.run_if(on_timer(Duration::from_secs(1))),
+                    setup_player_animation,
+                    setup_player_collision,
+                    record_spawn_point,
+                    place_player_at_spawn.after(record_spawn_point),
+                    unstick_player
+                        .after(place_player_at_spawn)
+                        .after(cache_wall_locations),
+                    start_player_death,
+                    end_turn_on_cast,
+                    start_cast_animation.before(animate_sprites),
+                    finish_cast_animation.after(animate_sprites),
+                    respawn_player.after(animate_sprites),
+                    snap_camera_on_level_spawn,
+                    despawn_extra_players,
+                    apply_level_player_stats.after(despawn_extra_players),
+                    join_player_two,
+                    toggle_no_clip.run_if(input_unlocked),
+                    disable_colliders_in_no_clip.after(toggle_no_clip),
+                    apply_facing_frames.before(animate_sprites),
+                    restart_animation_on_state_change
+                        .after(start_cast_animation)
+                        .after(start_player_death)
+                        .before(animate_sprites),
+                ),
+            )
+            .register_ldtk_entity::<PlayerBundle>("Player")
+            .register_ldtk_entity::<PlayerSpawnBundle>("PlayerSpawn");
+    }
+}
+
+/// How the player moves through the level.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementMode {
+    /// The player moves smoothly for as long as a key is held, and enemies act on
+    /// their own timers.
+    #[default]
+    RealTime,
+    /// Each key press moves the player exactly one cell, and enemies take one step
+    /// per move or cast.
+    TurnBased,
+}
+
+/// What stops the player at walls in real-time mode.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerCollision {
+    /// Each step is checked against the wall cells in `LevelWalls`.
+    #[default]
+    Grid,
+    /// Each step is swept through Rapier's colliders, sliding along the walls built
+    /// by `setup_wall_colliders` and anything else solid, like moving walls.
+    Physics,
+}
+
+/// Run condition for moving the player through Rapier: true in real-time mode with
+/// `PlayerCollision::Physics`. Turn-based moves are whole cells, so they stay on the grid,
+/// and so does no-clip, which has nothing to sweep against.
+pub fn physics_movement(
+    collision: Res<PlayerCollision>,
+    movement_mode: Res<MovementMode>,
+    no_clip: Res<NoClip>,
+) -> bool {
+    *collision == PlayerCollision::Physics && *movement_mode == MovementMode::RealTime && !no_clip.0
+}
+
+/// Debug tools, off in normal play, chosen in the `GameConfig`.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugSettings {
+    /// Whether F1 toggles no-clip movement.
+    pub no_clip: bool,
+}
+
+/// Whether the player walks through walls. Toggled with F1 when
+/// `DebugSettings::no_clip` allows it.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoClip(pub bool);
+
+/// How the player speeds up and slows down in real-time mode.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MovementFeel {
+    /// Rate the player speeds up toward full speed, in pixels per second squared.
+    pub acceleration: f32,
+    /// Rate the player coasts to a stop with no key held, in pixels per second squared.
+    pub friction: f32,
+}
+
+impl Default for MovementFeel {
+    fn default() -> Self {
+        MovementFeel {
+            acceleration: PLAYER_ACCELERATION,
+            friction: PLAYER_FRICTION,
+        }
+    }
+}
+
+impl MovementFeel {
+    /// Reaches full speed and stops dead within a single frame.
+    #[cfg(test)]
+    pub(crate) fn instant() -> Self {
+        MovementFeel {
+            acceleration: f32::MAX,
+            friction: f32::MAX,
+        }
+    }
+}
+
+/// Moves `velocity` toward `target` by at most one frame's worth of acceleration,
+/// or of friction when there's no input.
+///
+/// # Arguments
+/// * `velocity` - Current velocity, in pixels per second.
+/// * `target` - Velocity the input asks for; zero when no key is held.
+/// * `feel` - Acceleration and friction to use.
+/// * `delta_seconds` - Length of the frame.
+pub fn step_velocity(
+    velocity: Vec2,
+    target: Vec2,
+    feel: &MovementFeel,
+    delta_seconds: f32,
+) -> Vec2 {
+    let rate = if target == Vec2::ZERO {
+        feel.friction
+    } else {
+        feel.acceleration
+    };
+    let max_change = rate * delta_seconds;
+    let change = target - velocity;
+    if change.length() <= max_change {
+        target
+    } else {
+        velocity + change.normalize() * max_change
+    }
+}
+
+/// One player's movement keys read since the last fixed step, as directions on the grid.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct PlayerMoveInput {
+    /// Direction of the keys held down, for real-time movement.
+    pub held: Vec2,
+    /// Direction of the keys pressed since a fixed step last moved the player, for
+    /// turn-based movement.
+    pub pressed: Vec2,
+}
+
+/// Each player's movement keys read since the last fixed step, by `PlayerId`.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct MoveInput(pub HashMap<PlayerId, PlayerMoveInput>);
+
+impl MoveInput {
+    /// Takes the input for a fixed step, leaving the held keys for the next step
+    /// but using up the presses.
+    ///
+    /// # Returns
+    /// Every player's input as it was before the presses were cleared.
+    pub fn take_step(&mut self) -> HashMap<PlayerId, PlayerMoveInput> {
+        let inputs = self.0.clone();
+        for input in self.0.values_mut() {
+            input.pressed = Vec2::ZERO;
+        }
+        inputs
+    }
+}
+
+/// The `PlayerStats` each level's LDtk "Player" entity sets, by level IID and
+/// `PlayerId`, held until that level is the one being played.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct LevelPlayerStats(pub HashMap<(String, PlayerId), PlayerStats>);
+
+/// Turns the player has finished in turn-based mode, by moving or casting, that the
+/// enemies haven't answered yet.
+///
+/// Casts end turns in `Update` and moves in the fixed step, and a frame can run no
+/// fixed step at all, so turns are counted here and carried into the next step
+/// rather than sent as events that could expire before it runs.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingTurns(pub u32);
+
+/// Suspends gameplay input while another part of the game owns the keyboard.
+#[derive(Default, Resource, Debug)]
+pub struct InputLocked {
+    /// The world inspector is open, so keys typed into its fields must not move the player.
+    pub inspector: bool,
+    /// The game is paused.
+    pub paused: bool,
+}
+
+impl InputLocked {
+    /// Checks if anything is holding the input lock.
+    pub fn locked(&self) -> bool {
+        self.inspector || self.paused
+    }
+}
+
+/// Where the player appears at the start of the game and after dying.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SpawnPoint {
+    /// IID of the level holding the spawn point.
+    pub level_iid: String,
+    /// Cell the player appears in.
+    pub cell: GridCoords,
+}
+
+/// How many more times the player can die before the game is over.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lives(pub u32);
+
+impl FromWorld for Lives {
+    fn from_world(world: &mut World) -> Self {
+        let difficulty = world
+            .get_resource::<Difficulty>()
+            .copied()
+            .unwrap_or_default();
+        Lives(difficulty.starting_lives())
+    }
+}
+
+/// Run condition for gameplay input systems: true unless `InputLocked` is held.
+pub fn input_unlocked(input_locked: Res<InputLocked>) -> bool {
+    !input_locked.locked()
+}
+
+/// Run condition for the world inspector: true while it's toggled open.
+pub fn inspector_open(input_locked: Res<InputLocked>) -> bool {
+    input_locked.inspector
+}
+
+/// Toggles the world inspector, and with it the input lock, when the inspector key
+/// (backquote by default) is pressed.
+fn toggle_inspector_input_lock(
+    input_res: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    mut input_locked: ResMut<InputLocked>,
+) {
+    if input_res.just_pressed(keys.key(Action::ToggleInspector)) {
+        input_locked.inspector = !input_locked.inspector;
+        info!("🔍inspector open: {}", input_locked.inspector);
     }
 }
 
@@ -35,169 +331,2636 @@ impl Plugin for PlayerPlugin {
 ///
 /// This system runs for each entity that has a `Player` component but not an `Animation` component.
 /// It is triggered only when a `Player` component is newly added to an entity.
-/// The system adds an `Animation` component with predefined frames to these entities.
+/// The system adds an `Animation` component with predefined frames to these entities,
+/// dropping any frames that fall outside the player's texture atlas.
 ///
 /// # Arguments
 /// * `commands` - Used to perform commands on entities such as adding components.
 /// * `query` - Query to select entities that are players and require an animation component.
+/// * `texture_atlases` - Loaded texture atlases, used to validate the animation frames.
 ///
 #[allow(clippy::type_complexity)]
 fn setup_player_animation(
     mut commands: Commands,
-    query: Query<Entity, (With<Player>, Without<Animation>, Added<Player>)>,
+    query: Query<
+        (Entity, Option<&Handle<TextureAtlas>>),
+        (With<Player>, Without<Animation>, Added<Player>),
+    >,
+    texture_atlases: Res<Assets<TextureAtlas>>,
 ) {
-    for entity in query.iter() {
+    for (entity, atlas_handle) in query.iter() {
         info!("Adding animation to player entity: {:?}", entity);
-        commands.entity(entity).insert(Animation {
-            frames: PLAYER_SPRITE_FRAMES.to_vec(),
-            ..default()
-        });
+        let mut animation = idle_animation();
+        match atlas_handle.and_then(|handle| texture_atlases.get(handle)) {
+            Some(atlas) => animation.frames = valid_frames(&animation.frames, atlas.len()),
+            None => warn!(
+                "Player {:?} has no texture atlas, frames not validated",
+                entity
+            ),
+        }
+        commands.entity(entity).insert(animation);
     }
 }
 
+/// Drops the frames of `animation` that aren't in the player's texture atlas.
+///
+/// # Arguments
+/// * `animation` - Animation about to be played.
+/// * `atlas_handle` - The player's texture atlas, if it has one.
+/// * `texture_atlases` - Loaded texture atlases.
+///
+/// # Returns
+/// The animation with only the frames in the atlas, or unchanged if the atlas
+/// isn't loaded.
+fn fit_to_atlas(
+    mut animation: Animation,
+    atlas_handle: Option<&Handle<TextureAtlas>>,
+    texture_atlases: &Assets<TextureAtlas>,
+) -> Animation {
+    if let Some(atlas) = atlas_handle.and_then(|handle| texture_atlases.get(handle)) {
+        animation.frames = valid_frames(&animation.frames, atlas.len());
+    }
+    animation
+}
+
+/// Drops animation frames that don't exist in a texture atlas.
+///
+/// # Arguments
+/// * `frames` - Sprite indices making up the animation.
+/// * `atlas_len` - Number of sprites in the texture atlas.
+///
+/// # Returns
+/// The frames that are within the atlas, in their original order. Each dropped
+/// frame is logged.
+pub fn valid_frames(frames: &[usize], atlas_len: usize) -> Vec<usize> {
+    frames
+        .iter()
+        .copied()
+        .filter(|&frame| {
+            let valid = frame < atlas_len;
+            if !valid {
+                warn!(
+                    "Dropping animation frame {} outside texture atlas of {} sprites",
+                    frame, atlas_len
+                );
+            }
+            valid
+        })
+        .collect()
+}
+
+/// An upright capsule filling a sprite frame of `frame_size` pixels.
+///
+/// For a 16x32 frame the capsule has a radius of 8 (half the width) and a straight
+/// middle section 16 tall, so the rounded ends take up the top and bottom 8 pixels.
+/// A frame wider than it is tall gets a circle as wide as the frame is tall.
+pub fn frame_collider(frame_size: Vec2) -> Collider {
+    let radius = frame_size.x.min(frame_size.y) / 2.0;
+    Collider::capsule_y((frame_size.y / 2.0 - radius).max(0.0), radius)
+}
+
+/// The player's collider when its sprite frame size isn't known: a capsule one cell
+/// wide and two tall, the size of the stock sprite.
+pub fn player_collider(grid_size: GridSize) -> Collider {
+    frame_collider(Vec2::new(grid_size.pixels(), grid_size.pixels() * 2.0))
+}
+
+/// Size of frame `index` of `atlas`, in pixels, if the atlas has that frame.
+pub fn atlas_frame_size(atlas: &TextureAtlas, index: usize) -> Option<Vec2> {
+    atlas.textures.get(index).map(|rect| rect.size())
+}
+
 /// Sets up the collision component for newly added player entities.
 ///
 /// This system adds a `Collider` component to entities that have a `Player` component
-/// but do not yet have a `Collider`. It is triggered only when a `Player` component
-/// is newly added to an entity. The collider is a cuboid shaped based on the player sprite's
-/// width and height, ensuring the collision area accurately matches the player's visual representation.
+/// but do not yet have a `Collider`. The collider is a `frame_collider` capsule
+/// filling the sprite's current frame, read from its `TextureAtlas`, whose rounded
+/// ends slide past wall corners instead of snagging on them. A player whose atlas
+/// hasn't loaded yet is tried again next frame, and one without an atlas gets the
+/// stock `player_collider`.
 ///
 /// # Arguments
 /// * `commands` - Used to perform commands on entities such as adding components.
-/// * `query` - Query to select entities that are players and require a collider component.
+/// * `query` - Query to select players that require a collider component, with their sprites.
+/// * `texture_atlases` - Loaded atlases, giving the size of the sprite's frames.
+/// * `grid_size` - Resource giving the size of a cell, for players without an atlas.
 ///
 #[allow(clippy::type_complexity)]
 fn setup_player_collision(
     mut commands: Commands,
-    query: Query<Entity, (With<Player>, Without<Collider>, Added<Player>)>,
+    query: Query<
+        (
+            Entity,
+            Option<&Handle<TextureAtlas>>,
+            Option<&TextureAtlasSprite>,
+        ),
+        (With<Player>, Without<Collider>),
+    >,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    grid_size: Res<GridSize>,
 ) {
-    for entity in query.iter() {
+    for (entity, atlas_handle, sprite) in query.iter() {
+        let collider = match atlas_handle {
+            Some(handle) => {
+                let Some(atlas) = texture_atlases.get(handle) else {
+                    continue; // Sized once the atlas has loaded
+                };
+                let index = sprite.map_or(0, |sprite| sprite.index);
+                match atlas_frame_size(atlas, index) {
+                    Some(frame_size) => frame_collider(frame_size),
+                    None => player_collider(*grid_size),
+                }
+            }
+            None => player_collider(*grid_size),
+        };
         info!("Adding collision to player entity: {:?}", entity);
         commands
             .entity(entity)
-            .insert(Collider::cuboid(
-                PLAYER_SPRITE_WIDTH / 2.0,
-                PLAYER_SPRITE_HEIGHT / 2.0,
-            ))
+            .insert(collider)
             .insert(ActiveEvents::COLLISION_EVENTS)
             .insert(KinematicCharacterController::default())
+            .insert(CollisionLayer::Player.groups())
             .insert(Sleeping::disabled())
             .insert(Ccd::enabled())
             .insert(Name::new("Player"));
     }
 }
 
-/// Processes player input for movement.
+/// Reads each player's movement keys into `MoveInput` for the next fixed step.
 ///
-/// This function updates the player's position and orientation based on keyboard inputs.
-/// It ensures that the player does not move into walls and updates the camera position
-/// to follow the player.
+/// Presses are kept until a fixed step uses them, so a tap on a frame without a
+/// step still takes a turn, and a frame with two steps doesn't take it twice.
 ///
 /// # Arguments
-/// * `player_query` - Query to access player entities' transforms, sprites, and grid coordinates.
-/// * `time` - Resource to get time information for frame delta calculation.
-/// * `camera_query` - Query to access and update the camera's transform.
 /// * `input_res` - Resource to get the current input state.
-/// * `level_walls` - Resource containing information about wall locations in the level.
+/// * `keys` - Resource giving player one's keys for each direction.
+/// * `coop_keys` - Resource giving player two's keys for each direction.
+/// * `move_input` - Resource the directions are stored in.
 ///
-fn move_player_from_input(
-    mut player_query: Query<
-        (&mut Transform, &mut TextureAtlasSprite, &mut GridCoords),
-        With<Player>,
-    >,
-    time: Res<Time>,
-    mut camera_query: Query<(&mut OrthographicProjection, &mut Transform), Without<Player>>,
+pub(crate) fn read_move_input(
     input_res: Res<Input<KeyCode>>,
-    level_walls: Res<LevelWalls>,
+    keys: Res<KeyBindings>,
+    coop_keys: Res<CoopBindings>,
+    mut move_input: ResMut<MoveInput>,
 ) {
-    let speed = PLAYER_SPRITE_SPEED * time.delta_seconds();
-    let mut move_vec = Vec2::ZERO;
+    for (player_id, move_keys) in [
+        (PlayerId::ONE, keys.move_keys()),
+        (PlayerId::TWO, coop_keys.move_keys()),
+    ] {
+        let input = move_input.0.entry(player_id).or_default();
+        input.held = input_direction(move_keys, |key| input_res.pressed(key));
+        let pressed = input.pressed + input_direction(move_keys, |key| input_res.just_pressed(key));
+        input.pressed = pressed.clamp(Vec2::NEG_ONE, Vec2::ONE);
+    }
+}
 
-    // Convert input to change in GridCoords
-    if input_res.pressed(KeyCode::W) {
-        move_vec.y += speed;
+/// The direction on the grid of the movement keys `key_down` reports as down.
+///
+/// # Arguments
+/// * `move_keys` - The keys for up, left, down and right, in that order.
+/// * `key_down` - Whether a key counts as down.
+fn input_direction(move_keys: [KeyCode; 4], key_down: impl Fn(KeyCode) -> bool) -> Vec2 {
+    let [up, left, down, right] = move_keys;
+    let mut direction = Vec2::ZERO;
+    if key_down(up) {
+        direction.y += 1.0;
     }
-    if input_res.pressed(KeyCode::A) {
-        move_vec.x -= speed;
+    if key_down(left) {
+        direction.x -= 1.0;
     }
-    if input_res.pressed(KeyCode::S) {
-        move_vec.y -= speed;
+    if key_down(down) {
+        direction.y -= 1.0;
     }
-    if input_res.pressed(KeyCode::D) {
-        move_vec.x += speed;
+    if key_down(right) {
+        direction.x += 1.0;
     }
-    // If we didn't move the player, we don't need to continue.
-    // We need to run the rest of this ONE TIME to fix the camera.
+    direction
+}
+
+/// Moves the player by one fixed step of input.
+///
+/// This function updates each player's position and orientation from its own
+/// `MoveInput`.
+/// It ensures that the player does not move into walls. Moves past the edge of the
+/// level are reported as `LevelEdgeReached` so the map can hand the player over to
+/// a neighboring level. In real-time mode held keys accelerate the player's
+/// `Velocity2d` and the player coasts to a stop, stopping dead against walls. In
+/// turn-based mode each key press moves a whole cell and adds to `PendingTurns`.
+///
+/// # Arguments
+/// * `player_query` - Query to access player entities' transforms, sprites, velocities,
+///   grid coordinates, levels and stats.
+/// * `level_query` - Query to access the position of the player's level in the world.
+/// * `fixed_time` - Resource giving the length of the step.
+/// * `difficulty` - Resource scaling the players' speed.
+/// * `move_input` - Resource holding the movement keys read since the last step.
+/// * `movement_mode` - Resource choosing real-time or turn-based movement.
+/// * `feel` - Resource giving the player's acceleration and friction.
+/// * `level_walls` - Resource containing information about wall locations in the level.
+/// * `no_clip` - Resource telling whether walls are ignored.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `edge_events` - Event writer used to report moves past the edge of the level.
+/// * `pending_turns` - Resource counting the turns enemies still have to answer.
+///
+/// Dying players ignore input until they respawn.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn move_player_from_input(
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut TextureAtlasSprite,
+            &mut GridCoords,
+            &mut Velocity2d,
+            &AnimationState,
+            Option<&Parent>,
+            Option<&PlayerId>,
+            Option<&mut Facing>,
+            Option<&PlayerStats>,
+        ),
+        With<Player>,
+    >,
+    level_query: Query<&GlobalTransform, Without<Player>>,
+    fixed_time: Res<FixedTime>,
+    difficulty: Res<Difficulty>,
+    mut move_input: ResMut<MoveInput>,
+    movement_mode: Res<MovementMode>,
+    feel: Res<MovementFeel>,
+    level_walls: Res<LevelWalls>,
+    no_clip: Res<NoClip>,
+    grid_size: Res<GridSize>,
+    mut edge_events: EventWriter<LevelEdgeReached>,
+    mut pending_turns: ResMut<PendingTurns>,
+) {
+    let speed_multiplier = difficulty.multipliers().player_speed;
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    // Presses are used up by this step either way, so old ones don't carry over
+    let inputs = move_input.take_step();
 
     // Assign the new destination to the player
-    for (mut player_transform, mut player_sprite, mut player_grid_coords) in player_query.iter_mut()
+    for (
+        player_entity,
+        mut player_transform,
+        mut player_sprite,
+        mut player_grid_coords,
+        mut velocity,
+        animation_state,
+        parent,
+        player_id,
+        facing,
+        stats,
+    ) in player_query.iter_mut()
     {
+        let max_speed = stats.map_or(PLAYER_SPRITE_SPEED, |stats| stats.speed) * speed_multiplier;
+        let input = inputs
+            .get(&PlayerId::of(player_id))
+            .copied()
+            .unwrap_or_default();
+        // Turn-based moves are a whole cell per press rather than a distance per step held
+        let input_dir = match *movement_mode {
+            MovementMode::RealTime => input.held,
+            MovementMode::TurnBased => input.pressed,
+        };
+        let move_vec = match (animation_state, *movement_mode) {
+            (AnimationState::Dying, _) => {
+                velocity.0 = Vec2::ZERO;
+                Vec2::ZERO
+            }
+            (_, MovementMode::TurnBased) => {
+                velocity.0 = Vec2::ZERO;
+                input_dir * grid_size.pixels()
+            }
+            (_, MovementMode::RealTime) => {
+                velocity.0 = step_velocity(velocity.0, input_dir * max_speed, &feel, delta_seconds);
+                velocity.0 * delta_seconds
+            }
+        };
+
         // Where is the player's planned destination, in transform domain?
         let player_dest_trans =
             convert_vec3_to_vec2(player_transform.translation + move_vec.extend(0.0));
 
         // Where is the player's planned destination, in coordinate domain?
-        let mut player_dest_coords =
-            translation_to_grid_coords(player_dest_trans, IVec2::splat(GRID_SIZE));
-        player_dest_coords.y -= 1; // Measure from the lower half of the player sprite
+        let player_dest_coords = player_cell(player_dest_trans, *grid_size);
 
-        // If there's no collision, then copy the plans into the actual
-        if !level_walls.in_wall(&player_dest_coords) {
+        // If there's no collision, then copy the plans into the actual. Steps into a
+        // neighboring level are left to the hand-over, even where it's open.
+        let in_bounds = level_walls.in_bounds(&player_dest_coords);
+        if in_bounds
+            && (no_clip.0 || level_walls.can_step(&player_grid_coords, &player_dest_coords))
+        {
             *player_grid_coords = player_dest_coords;
             player_transform.translation.x = player_dest_trans.x;
             player_transform.translation.y = player_dest_trans.y;
+            if *movement_mode == MovementMode::TurnBased && move_vec != Vec2::ZERO {
+                pending_turns.0 += 1;
+            }
+        } else {
+            // Walls stop the player dead rather than letting speed build up against them
+            velocity.0 = Vec2::ZERO;
+            if move_vec != Vec2::ZERO && !in_bounds {
+                // The player is a child of its level, so offset by the level's position
+                let level_origin = parent
+                    .and_then(|parent| level_query.get(parent.get()).ok())
+                    .map_or(Vec2::ZERO, |level| {
+                        convert_vec3_to_vec2(level.translation())
+                    });
+                edge_events.send(LevelEdgeReached {
+                    player: player_entity,
+                    destination: level_origin + player_dest_trans,
+                });
+            }
+        }
+
+        face_along(&mut player_sprite, facing, move_vec);
+    }
+}
+
+/// Makes the player sprite face the way it's moving, leaving the flip be when
+/// moving straight up or down, and records the way it faces in `Facing`.
+fn face_along(player_sprite: &mut TextureAtlasSprite, facing: Option<Mut<Facing>>, move_vec: Vec2) {
+    let Some(new_facing) = Facing::from_direction(move_vec) else {
+        return; // No change on zero
+    };
+    match new_facing {
+        Facing::Left => player_sprite.flip_x = true,
+        Facing::Right => player_sprite.flip_x = false,
+        Facing::Up | Facing::Down => {}
+    }
+    if let Some(mut facing) = facing {
+        // Only mark it changed when it did, so the frames aren't swapped every step
+        facing.set_if_neq(new_facing);
+    }
+}
+
+/// Frames the player animates through while facing up or down.
+///
+/// Left empty, the side-on frames are kept for every direction, as the stock
+/// sprite sheet has no others. Filling one in switches to those frames whenever
+/// the player turns that way, instead of flipping.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct FacingFrames {
+    /// Frames shown while facing up.
+    pub up: Vec<usize>,
+    /// Frames shown while facing down.
+    pub down: Vec<usize>,
+}
+
+impl FacingFrames {
+    /// The regular animation frames for a player facing `facing`.
+    pub fn frames(&self, facing: Facing) -> &[usize] {
+        let frames = match facing {
+            Facing::Up => &self.up,
+            Facing::Down => &self.down,
+            Facing::Left | Facing::Right => return &PLAYER_SPRITE_FRAMES,
+        };
+        if frames.is_empty() {
+            &PLAYER_SPRITE_FRAMES[..]
+        } else {
+            frames.as_slice()
+        }
+    }
+}
+
+/// Switches a player's regular animation to the frames for the way it now faces.
+///
+/// Cast and death animations are left to finish; the player picks up its facing's
+/// frames the next time it turns.
+#[allow(clippy::type_complexity)]
+fn apply_facing_frames(
+    facing_frames: Res<FacingFrames>,
+    mut query: Query<
+        (
+            &Facing,
+            &AnimationState,
+            &mut Animation,
+            &mut TextureAtlasSprite,
+        ),
+        (With<Player>, Changed<Facing>),
+    >,
+) {
+    for (facing, animation_state, mut animation, mut sprite) in query.iter_mut() {
+        let frames = facing_frames.frames(*facing);
+        if *animation_state != AnimationState::Idle || animation.frames == frames {
+            continue;
+        }
+        animation.frames = frames.to_vec();
+        if let Some(index) = animation.restart() {
+            sprite.index = index;
+        }
+    }
+}
+
+/// Moves the player by one fixed step of input, letting Rapier stop it at walls.
+///
+/// The step is swept through the physics world with the player's collider, the same
+/// way a `KinematicCharacterController` moves, so the player slides along walls
+/// instead of stopping dead. A kinematic body given a velocity would pass straight
+/// through the fixed wall colliders, since Rapier doesn't push kinematic bodies
+/// back. This happens within the fixed step rather than in Rapier's own schedule,
+/// so the player stays interpolated and `GridCoords` is synced from where the sweep
+/// left it. `Velocity2d` is cut to what the player
+/// actually moved, so speed doesn't build up against a wall. As with grid movement,
+/// moves past the edge of the level are reported as `LevelEdgeReached` instead.
+///
+/// # Arguments
+/// * `player_query` - Query to access player entities' transforms, colliders, sprites,
+///   velocities, grid coordinates, levels and stats.
+/// * `level_query` - Query to access the position of the player's level in the world.
+/// * `rapier_context` - Rapier's physics world, swept for walls.
+/// * `fixed_time` - Resource giving the length of the step.
+/// * `difficulty` - Resource scaling the players' speed.
+/// * `move_input` - Resource holding the movement keys read since the last step.
+/// * `feel` - Resource giving the player's acceleration and friction.
+/// * `level_walls` - Resource giving the bounds of the level.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `edge_events` - Event writer used to report moves past the edge of the level.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn move_player_with_physics(
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &Collider,
+            &mut TextureAtlasSprite,
+            &mut GridCoords,
+            &mut Velocity2d,
+            &AnimationState,
+            Option<&Parent>,
+            Option<&PlayerId>,
+            Option<&mut Facing>,
+            Option<&PlayerStats>,
+        ),
+        With<Player>,
+    >,
+    level_query: Query<&GlobalTransform, Without<Player>>,
+    mut rapier_context: ResMut<RapierContext>,
+    fixed_time: Res<FixedTime>,
+    difficulty: Res<Difficulty>,
+    mut move_input: ResMut<MoveInput>,
+    feel: Res<MovementFeel>,
+    level_walls: Res<LevelWalls>,
+    grid_size: Res<GridSize>,
+    mut edge_events: EventWriter<LevelEdgeReached>,
+) {
+    let speed_multiplier = difficulty.multipliers().player_speed;
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    let inputs = move_input.take_step();
+    let (groups, _) = CollisionLayer::Player.groups();
+
+    for (
+        player_entity,
+        mut player_transform,
+        collider,
+        mut player_sprite,
+        mut player_grid_coords,
+        mut velocity,
+        animation_state,
+        parent,
+        player_id,
+        facing,
+        stats,
+    ) in player_query.iter_mut()
+    {
+        let max_speed = stats.map_or(PLAYER_SPRITE_SPEED, |stats| stats.speed) * speed_multiplier;
+        if *animation_state == AnimationState::Dying {
+            velocity.0 = Vec2::ZERO;
+            continue;
+        }
+        let held = inputs
+            .get(&PlayerId::of(player_id))
+            .map_or(Vec2::ZERO, |input| input.held);
+        velocity.0 = step_velocity(velocity.0, held * max_speed, &feel, delta_seconds);
+        let move_vec = velocity.0 * delta_seconds;
+        if move_vec == Vec2::ZERO {
+            continue;
         }
 
-        // Make the player sprite face the right direction
-        match move_vec.x {
-            x if x < 0.0 => player_sprite.flip_x = true,
-            x if x > 0.0 => player_sprite.flip_x = false,
-            _ => {} // No change on zero
+        // The player is a child of its level, so offset by the level's position
+        let level_origin = parent
+            .and_then(|parent| level_query.get(parent.get()).ok())
+            .map_or(Vec2::ZERO, |level| {
+                convert_vec3_to_vec2(level.translation())
+            });
+        let position = convert_vec3_to_vec2(player_transform.translation);
+        let moved = rapier_context.move_shape(
+            move_vec,
+            collider,
+            level_origin + position,
+            0.0,
+            0.0,
+            &MoveShapeOptions {
+                autostep: None,
+                snap_to_ground: None,
+                ..default()
+            },
+            QueryFilter::new()
+                .exclude_collider(player_entity)
+                .exclude_sensors()
+                .groups(groups),
+            |_| {},
+        );
+        let dest = position + moved.effective_translation;
+
+        let dest_coords = player_cell(dest, *grid_size);
+        if !level_walls.in_bounds(&dest_coords) {
+            velocity.0 = Vec2::ZERO;
+            edge_events.send(LevelEdgeReached {
+                player: player_entity,
+                destination: level_origin + position + move_vec,
+            });
+            continue;
+        }
+        player_transform.translation.x = dest.x;
+        player_transform.translation.y = dest.y;
+        *player_grid_coords = dest_coords;
+        velocity.0 = moved.effective_translation / delta_seconds;
+        face_along(&mut player_sprite, facing, move_vec);
+    }
+}
+
+/// Set for one frame to put the camera straight onto the player, so a new level or
+/// a teleport never pans across the whole map, however the camera follows.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CameraSnap(pub bool);
+
+/// Asks for a camera snap whenever a level spawns.
+fn snap_camera_on_level_spawn(
+    mut level_events: EventReader<LevelEvent>,
+    mut camera_snap: ResMut<CameraSnap>,
+) {
+    if level_events
+        .iter()
+        .any(|level_event| matches!(level_event, LevelEvent::Spawned(_)))
+    {
+        camera_snap.0 = true;
+    }
+}
+
+/// The box around the players' world positions.
+///
+/// # Returns
+/// The lowest and highest corners of the box, or `None` if there are no players.
+fn players_bounds(positions: impl IntoIterator<Item = Vec3>) -> Option<(Vec3, Vec3)> {
+    let mut positions = positions.into_iter();
+    let first = positions.next()?;
+    Some(positions.fold((first, first), |(min, max), position| {
+        (min.min(position), max.max(position))
+    }))
+}
+
+/// The point to frame so every player is in view: the middle of the box around them.
+///
+/// # Returns
+/// The center of the players' world positions, or `None` if there are no players.
+pub fn players_center(positions: impl IntoIterator<Item = Vec3>) -> Option<Vec3> {
+    players_bounds(positions).map(|(min, max)| (min + max) / 2.0)
+}
+
+/// How far the camera zooms out so every player fits in view.
+///
+/// # Arguments
+/// * `positions` - The players' world positions.
+/// * `view` - Size of the world shown at the normal zoom, in pixels.
+///
+/// # Returns
+/// The factor to multiply the view scale by: 1.0 while the players fit with
+/// `CAMERA_FIT_MARGIN` to spare, growing as they spread apart, up to
+/// `CAMERA_MAX_ZOOM_OUT`.
+pub fn camera_zoom(positions: impl IntoIterator<Item = Vec3>, view: Vec2) -> f32 {
+    let Some((min, max)) = players_bounds(positions) else {
+        return 1.0;
+    };
+    if view.x <= 0.0 || view.y <= 0.0 {
+        return 1.0;
+    }
+    let needed = (max - min).truncate() + Vec2::splat(2.0 * CAMERA_FIT_MARGIN);
+    (needed / view)
+        .max_element()
+        .clamp(1.0, CAMERA_MAX_ZOOM_OUT)
+}
+
+/// Where the camera centers to keep the player at `player_world` in view.
+pub fn camera_target(player_world: Vec3) -> Vec2 {
+    // The view scale keeps the visible world the same size, so the offset is fixed
+    Vec2::new(
+        player_world.x,
+        player_world.y - (WINDOW_HEIGHT / CAMERA_HEIGHT_OFFSET),
+    )
+}
+
+/// Keeps the camera on the players.
+/// With two players the camera frames the middle of the pair, per `players_center`,
+/// and zooms out as they spread apart so both stay in view, per `camera_zoom`.
+///
+/// Runs once transforms have been propagated, so the camera follows where the player
+/// is drawn between fixed steps rather than jumping with each step. The camera's
+/// `GlobalTransform` is updated here too so the view doesn't trail a frame behind.
+/// A `CameraSnap` is cleared once the camera has been put on the player.
+///
+/// # Arguments
+/// * `camera_snap` - Resource asking for a snap this frame.
+/// * `player_query` - Query to access the players' positions in the world.
+/// * `window_query` - Query to access the primary window, to size the view.
+/// * `camera_query` - Query to access and update the camera's transforms and zoom.
+///
+#[allow(clippy::type_complexity)]
+fn follow_player_with_camera(
+    mut camera_snap: ResMut<CameraSnap>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<
+        (
+            &mut Transform,
+            &mut GlobalTransform,
+            Option<&mut OrthographicProjection>,
+        ),
+        (With<Camera2d>, Without<Player>),
+    >,
+) {
+    let positions: Vec<Vec3> = player_query
+        .iter()
+        .map(|global| global.translation())
+        .collect();
+    let Some(center) = players_center(positions.iter().copied()) else {
+        return;
+    };
+    let target = camera_target(center);
+    // The normal view scale, and how much of the world it shows
+    let view = window_query.get_single().ok().map(|window| {
+        let scale = view_scale(window.height());
+        (scale, Vec2::new(window.width(), window.height()) * scale)
+    });
+    for (mut camera_transform, mut camera_global, projection) in camera_query.iter_mut() {
+        // Assign x and y of the target to the camera (not z)
+        camera_transform.translation.x = target.x;
+        camera_transform.translation.y = target.y;
+        *camera_global = GlobalTransform::from(*camera_transform);
+
+        if let (Some(mut projection), Some((scale, view_size))) = (projection, view) {
+            let zoomed = scale * camera_zoom(positions.iter().copied(), view_size);
+            if projection.scale != zoomed {
+                projection.scale = zoomed;
+            }
         }
+    }
+    if camera_snap.0 {
+        info!("📷camera snapped to {:?}", target);
+        camera_snap.0 = false;
+    }
+}
+
+/// Sent once when a one-shot animation finishes on its last frame.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFinished(pub Entity);
+
+/// Sent when an animation steps onto one of its `event_frames`, carrying the
+/// frame's position in the animation.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFrameReached(pub Entity, pub usize);
 
-        // Assign x and y of player transform to the camera (not z)
-        let (_orthographic_projection, mut camera_transform) = camera_query.single_mut();
-        camera_transform.translation.x = player_transform.translation.x;
-        camera_transform.translation.y =
-            player_transform.translation.y - (WINDOW_HEIGHT / CAMERA_HEIGHT_OFFSET);
+/// The grid cell a player at `translation` occupies.
+///
+/// The cell is measured from the lower half of the player sprite.
+pub fn player_cell(translation: Vec2, grid_size: GridSize) -> GridCoords {
+    let mut grid_coords = grid_size.to_grid_coords(translation);
+    grid_coords.y -= 1;
+    grid_coords
+}
+
+/// Where a player standing in `cell` is placed, the reverse of `player_cell`.
+pub fn player_translation(cell: GridCoords, grid_size: GridSize) -> Vec2 {
+    grid_size.to_translation(cell) + Vec2::new(0.0, grid_size.pixels())
+}
+
+/// Moves a recoiling player for one step without entering walls.
+///
+/// If the full move would end in a wall or squeeze between two walls meeting at a
+/// corner, the horizontal and vertical parts are tried on their own so the player
+/// slides along the wall instead of stopping.
+///
+/// # Arguments
+/// * `translation` - The player's position.
+/// * `velocity` - The recoil velocity, in pixels per second.
+/// * `delta_seconds` - Length of the step.
+/// * `level_walls` - The level's walls.
+/// * `grid_size` - The size of a cell.
+///
+/// # Returns
+/// The new position, which is `translation` if every move is blocked.
+pub fn recoil_step(
+    translation: Vec2,
+    velocity: Vec2,
+    delta_seconds: f32,
+    level_walls: &LevelWalls,
+    grid_size: GridSize,
+) -> Vec2 {
+    let step = velocity * delta_seconds;
+    let from = player_cell(translation, grid_size);
+    [step, Vec2::new(step.x, 0.0), Vec2::new(0.0, step.y)]
+        .into_iter()
+        .map(|step| translation + step)
+        .find(|dest| level_walls.can_step(&from, &player_cell(*dest, grid_size)))
+        .unwrap_or(translation)
+}
+
+/// Moves recoiling players and decays their recoil, one fixed step at a time.
+///
+/// # Arguments
+/// * `commands` - Used to remove recoil once it has died down.
+/// * `fixed_time` - Resource giving the length of the step.
+/// * `level_walls` - Resource containing information about wall locations in the level.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `query` - Query to access recoiling players' transforms and grid coordinates.
+///
+fn apply_recoil(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    level_walls: Res<LevelWalls>,
+    grid_size: Res<GridSize>,
+    mut query: Query<(Entity, &mut Transform, &mut GridCoords, &mut Recoil), With<Player>>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    for (entity, mut transform, mut grid_coords, mut recoil) in query.iter_mut() {
+        let dest = recoil_step(
+            transform.translation.truncate(),
+            recoil.velocity,
+            delta_seconds,
+            &level_walls,
+            *grid_size,
+        );
+        transform.translation.x = dest.x;
+        transform.translation.y = dest.y;
+        *grid_coords = player_cell(dest, *grid_size);
+
+        recoil.velocity *= (1.0 - PLAYER_RECOIL_DECAY * delta_seconds).max(0.0);
+        if recoil.velocity.length() < PLAYER_RECOIL_MIN_SPEED {
+            commands.entity(entity).remove::<Recoil>();
+        }
     }
 }
 
-/// Animates the player sprite based on the defined animation frames.
+/// Animates sprites based on their defined animation frames.
 ///
-/// This function cycles through a series of sprite indices to animate the player sprite.
-/// It uses a timer to control the animation speed.
+/// This function cycles through a series of sprite indices to animate each sprite.
+/// It uses a timer to control the animation speed. One-shot animations hold their
+/// last frame once they finish and send a single `AnimationFinished`. Stepping onto
+/// an event frame sends `AnimationFrameReached`; the frame shown before the first
+/// step doesn't.
 ///
 /// # Arguments
 /// * `time` - Resource to get time information for the animation timer.
-/// * `query` - Query to access player entities' animations and texture atlas sprites.
-fn animate_player(
+/// * `query` - Query to access entities' animations and texture atlas sprites.
+/// * `finished_events` - Event writer used to report finished one-shot animations.
+/// * `frame_events` - Event writer used to report event frames being shown.
+pub(crate) fn animate_sprites(
     time: Res<Time>,
-    mut query: Query<(&mut Animation, &mut TextureAtlasSprite), With<Player>>,
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
+    mut finished_events: EventWriter<AnimationFinished>,
+    mut frame_events: EventWriter<AnimationFrameReached>,
 ) {
-    for (mut animation, mut sprite) in query.iter_mut() {
+    for (entity, mut animation, mut sprite) in query.iter_mut() {
         animation.timer.tick(time.delta());
-        if animation.timer.just_finished() {
-            // Cycle through the list of animation frames
-            if !animation.frames.is_empty() {
-                let next_frame = (animation
-                    .frames
-                    .iter()
-                    .position(|&f| f == sprite.index)
-                    .unwrap_or(0)
-                    + 1)
-                    % animation.frames.len();
-                sprite.index = animation.frames[next_frame];
-            }
+        if !animation.timer.just_finished() || animation.finished {
+            continue;
+        }
+        if let Some(index) = animation.advance() {
+            sprite.index = index;
+        }
+        if animation.finished {
+            finished_events.send(AnimationFinished(entity));
+        } else if animation.on_event_frame() {
+            frame_events.send(AnimationFrameReached(entity, animation.current));
         }
     }
 }
 
-pub fn dbg_player(mut query: Query<(&Transform, &GridCoords, &Collider, &Player)>) {
-    for (transform, grid_coords, collider, _player) in &mut query {
+/// Restarts the animation of entities whose `AnimationState` just changed.
+///
+/// The new animation starts on its first frame with a whole frame's time to show,
+/// instead of wherever the old timer had got to in its cycle.
+///
+/// # Arguments
+/// * `query` - Query to access the animations and sprites of entities that changed state.
+///
+fn restart_animation_on_state_change(
+    mut query: Query<(&mut Animation, &mut TextureAtlasSprite), Changed<AnimationState>>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        if let Some(index) = animation.restart() {
+            sprite.index = index;
+        }
+    }
+}
+
+/// Starts the death animation for players whose health has run out.
+///
+/// The player switches to the one-shot `PLAYER_DEATH_FRAMES` sequence and its
+/// `AnimationState` to `Dying`, which disables movement and casting until
+/// `respawn_player` brings it back. Frames outside the player's texture atlas are
+/// dropped.
+///
+/// # Arguments
+/// * `query` - Query to access players whose health just changed.
+/// * `texture_atlases` - Loaded texture atlases, used to validate the animation frames.
+///
+#[allow(clippy::type_complexity)]
+fn start_player_death(
+    mut query: Query<
+        (
+            &Health,
+            &mut AnimationState,
+            &mut Animation,
+            &mut TextureAtlasSprite,
+            Option<&Handle<TextureAtlas>>,
+        ),
+        (With<Player>, Changed<Health>),
+    >,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+) {
+    for (health, mut animation_state, mut animation, mut sprite, atlas_handle) in query.iter_mut() {
+        if !health.is_dead() || *animation_state == AnimationState::Dying {
+            continue;
+        }
+        info!("💀player died");
+        *animation_state = AnimationState::Dying;
+        *animation = fit_to_atlas(
+            Animation::once(PLAYER_DEATH_FRAMES.to_vec()),
+            atlas_handle,
+            &texture_atlases,
+        );
+        if let Some(&first) = animation.frames.first() {
+            sprite.index = first;
+        }
+    }
+}
+
+/// Respawns players once their death animation has finished.
+///
+/// Each death costs a life; when the last one is gone the game is over. Otherwise
+/// health is restored, the regular animation cycle resumes and the player is
+/// teleported back to the `SpawnPoint`, taking any co-op partner along, or respawns
+/// where it died if there isn't one.
+///
+/// # Arguments
+/// * `commands` - Used to detach the players from their level when teleporting.
+/// * `query` - Query to access players' health and animation state.
+/// * `lives` - Resource counting the lives left.
+/// * `spawn_point` - Resource holding where to respawn, if known.
+/// * `level_selection` - Resource switched to the spawn point's level.
+/// * `pending_teleport` - Resource recording the teleport to the spawn point.
+/// * `screen_fade` - Resource driving the fade overlay.
+/// * `next_state` - Used to switch to `GameState::GameOver`.
+/// * `texture_atlases` - Loaded texture atlases, used to validate the animation frames.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn respawn_player(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &mut Health,
+            &mut AnimationState,
+            &mut Animation,
+            &mut TextureAtlasSprite,
+            Option<&Handle<TextureAtlas>>,
+        ),
+        With<Player>,
+    >,
+    mut lives: ResMut<Lives>,
+    spawn_point: Option<Res<SpawnPoint>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut pending_teleport: ResMut<PendingTeleport>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut next_state: ResMut<NextState<GameState>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+) {
+    // A co-op partner goes along to the spawn point, so it isn't left in a level
+    // that's no longer loaded
+    let players: Vec<Entity> = query.iter().map(|(entity, ..)| entity).collect();
+    for (entity, mut health, mut animation_state, mut animation, mut sprite, atlas_handle) in
+        query.iter_mut()
+    {
+        if *animation_state != AnimationState::Dying || !animation.finished {
+            continue;
+        }
+        lives.0 = lives.0.saturating_sub(1);
+        if lives.0 == 0 {
+            next_state.set(GameState::GameOver);
+            continue;
+        }
+
         info!(
-            "loc@{:?}=({},{}) collider={:?}",
-            &transform.translation, &grid_coords.x, &grid_coords.y, &collider
+            "💀player respawned at {:?}, {} lives left",
+            spawn_point.as_deref(),
+            lives.0
         );
+        health.current = health.max;
+        *animation_state = AnimationState::Idle;
+        *animation = fit_to_atlas(idle_animation(), atlas_handle, &texture_atlases);
+        if let Some(&first) = animation.frames.first() {
+            sprite.index = first;
+        }
+        if let Some(spawn_point) = &spawn_point {
+            start_teleport(
+                &mut commands,
+                TeleportTarget {
+                    player: entity,
+                    level_iid: spawn_point.level_iid.clone(),
+                    cell: spawn_point.cell,
+                },
+                players.iter().copied(),
+                &mut level_selection,
+                &mut pending_teleport,
+                &mut screen_fade,
+            );
+        }
+    }
+}
+
+/// Keeps a single player per `PlayerId`: a `Player` spawned while one with its id
+/// already exists, by a level that holds the LDtk "Player" entity being
+/// respawned, is despawned straight away.
+///
+/// The new player's `PlayerStats`, read from its level's LDtk fields, are kept in
+/// `LevelPlayerStats` until that level is selected, since a neighbor is spawned
+/// before the player walks into it.
+///
+/// # Arguments
+/// * `commands` - Commands to despawn the extra players.
+/// * `level_stats` - Resource holding each level's player stats.
+/// * `player_query` - Query to access the players, their stats and parent layers.
+/// * `layers` - Query used to walk from a player's layer up to its level.
+/// * `level_entities` - Query used to find the level a player was spawned in.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+///
+#[allow(clippy::type_complexity)]
+fn despawn_extra_players(
+    mut commands: Commands,
+    mut level_stats: ResMut<LevelPlayerStats>,
+    player_query: Query<(
+        Entity,
+        Option<&PlayerId>,
+        Ref<Player>,
+        Option<&PlayerStats>,
+        Option<&Parent>,
+    )>,
+    layers: Query<&Parent, Without<Player>>,
+    level_entities: Query<&Handle<LdtkLevel>>,
+    level_assets: Res<Assets<LdtkLevel>>,
+) {
+    for (entity, player_id, player, stats, layer) in player_query.iter() {
+        if !player.is_added() {
+            continue;
+        }
+        let player_id = PlayerId::of(player_id);
+        if !player_query.iter().any(|(_, other_id, other, ..)| {
+            PlayerId::of(other_id) == player_id && !other.is_added()
+        }) {
+            continue;
+        }
+        info!("🧙dropped extra player {:?}", entity);
+        // LDtk entities are children of the Entities layer, which is a child of the level
+        let level_iid = layer
+            .and_then(|layer| layers.get(layer.get()).ok())
+            .and_then(|level| level_entities.get(level.get()).ok())
+            .and_then(|handle| level_assets.get(handle))
+            .map(|ldtk_level| ldtk_level.level.iid.clone());
+        if let (Some(level_iid), Some(stats)) = (level_iid, stats) {
+            level_stats.0.insert((level_iid, player_id), *stats);
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Gives the players the `PlayerStats` of the level just selected, if its LDtk
+/// "Player" entity set any.
+///
+/// Maximum health follows the level's stats, and current health is kept, only
+/// clamped to the new maximum.
+///
+/// # Arguments
+/// * `ready_events` - Event reader for levels whose walls have just been cached.
+/// * `level_walls` - Resource giving the level being played.
+/// * `level_stats` - Resource holding each level's player stats.
+/// * `player_query` - Query to access the players' stats and health.
+///
+fn apply_level_player_stats(
+    mut ready_events: EventReader<LevelReady>,
+    level_walls: Res<LevelWalls>,
+    level_stats: Res<LevelPlayerStats>,
+    mut player_query: Query<(Option<&PlayerId>, &mut PlayerStats, &mut Health), With<Player>>,
+) {
+    // Read every event so none are left for the next frame
+    let selected_ready = ready_events
+        .iter()
+        .filter(|ready| ready.level_iid == level_walls.level_iid())
+        .count();
+    if selected_ready == 0 {
+        return;
+    }
+    for (player_id, mut stats, mut health) in player_query.iter_mut() {
+        let key = (level_walls.level_iid().to_string(), PlayerId::of(player_id));
+        let Some(level_stats) = level_stats.0.get(&key) else {
+            continue;
+        };
+        *stats = *level_stats;
+        health.max = level_stats.health;
+        health.current = health.current.min(health.max);
+    }
+}
+
+/// Brings player two in beside player one when F5 is pressed, or sends it away if
+/// it's already playing.
+///
+/// Player two shares player one's sprite sheet, tinted `PLAYER_TWO_TINT`, and
+/// starts on player one's cell in player one's level. It's given its collider and
+/// animation like any new player. Player two can't join while any of its
+/// `CoopBindings` are also in `KeyBindings`.
+#[allow(clippy::type_complexity)]
+fn join_player_two(
+    mut commands: Commands,
+    input_res: Res<Input<KeyCode>>,
+    keys: Res<KeyBindings>,
+    coop_keys: Res<CoopBindings>,
+    player_query: Query<
+        (
+            Entity,
+            Option<&PlayerId>,
+            &Transform,
+            &GridCoords,
+            Option<&Handle<TextureAtlas>>,
+            Option<&Parent>,
+        ),
+        With<Player>,
+    >,
+) {
+    if !input_res.just_pressed(KeyCode::F5) {
+        return;
+    }
+    if let Some((player_two, ..)) = player_query
+        .iter()
+        .find(|(_, player_id, ..)| PlayerId::of(*player_id) == PlayerId::TWO)
+    {
+        info!("🧙player two left");
+        commands.entity(player_two).despawn_recursive();
+        return;
+    }
+    let Some((_, _, transform, grid_coords, atlas, parent)) = player_query
+        .iter()
+        .find(|(_, player_id, ..)| PlayerId::of(*player_id) == PlayerId::ONE)
+    else {
+        return;
+    };
+    let conflicts = coop_keys.conflicts(&keys);
+    if !conflicts.is_empty() {
+        warn!(
+            "🧙player two can't join, its keys {:?} are already bound",
+            conflicts
+        );
+        return;
+    }
+
+    let [r, g, b] = PLAYER_TWO_TINT;
+    let mut player_two = commands.spawn(PlayerBundle {
+        player_id: PlayerId::TWO,
+        sprite_bundle: SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index: PLAYER_SPRITE_FRAMES[0],
+                color: Color::rgb(r, g, b),
+                ..default()
+            },
+            texture_atlas: atlas.cloned().unwrap_or_default(),
+            transform: *transform,
+            ..default()
+        },
+        grid_coords: *grid_coords,
+        ..default()
+    });
+    if let Some(parent) = parent {
+        player_two.set_parent(parent.get());
+    }
+    info!("🧙player two joined at {:?}", grid_coords);
+}
+
+/// Toggles no-clip when F1 is pressed, if the `DebugSettings` allow it.
+///
+/// Turning it off moves any player left inside a wall out to the nearest open cell,
+/// so walls stop it again from there.
+///
+/// # Arguments
+/// * `input_res` - Resource giving the keys pressed this frame.
+/// * `debug` - Resource telling whether no-clip is allowed.
+/// * `no_clip` - Resource holding whether no-clip is on.
+/// * `level_walls` - Resource used to find an open cell for a player in a wall.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `player_query` - Query to access the players' positions.
+///
+fn toggle_no_clip(
+    input_res: Res<Input<KeyCode>>,
+    debug: Res<DebugSettings>,
+    mut no_clip: ResMut<NoClip>,
+    level_walls: Res<LevelWalls>,
+    grid_size: Res<GridSize>,
+    mut player_query: Query<(&mut Transform, &mut GridCoords), With<Player>>,
+) {
+    if !debug.no_clip || !input_res.just_pressed(KeyCode::F1) {
+        return;
+    }
+    no_clip.0 = !no_clip.0;
+    info!("🧙no-clip: {}", no_clip.0);
+    if no_clip.0 {
+        return;
+    }
+    for (mut transform, mut grid_coords) in player_query.iter_mut() {
+        if !level_walls.in_wall(&grid_coords) {
+            continue;
+        }
+        let Some(cell) = level_walls.nearest_walkable(*grid_coords) else {
+            continue;
+        };
+        let translation = player_translation(cell, *grid_size);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        *grid_coords = cell;
+    }
+}
+
+/// Disables the players' colliders while no-clip is on, and enables them again
+/// once it's off.
+fn disable_colliders_in_no_clip(
+    mut commands: Commands,
+    no_clip: Res<NoClip>,
+    player_query: Query<(Entity, Option<&ColliderDisabled>), (With<Player>, With<Collider>)>,
+) {
+    for (player, disabled) in player_query.iter() {
+        match (no_clip.0, disabled.is_some()) {
+            (true, false) => {
+                commands.entity(player).insert(ColliderDisabled);
+            }
+            (false, true) => {
+                commands.entity(player).remove::<ColliderDisabled>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Records the `SpawnPoint` from a `PlayerSpawn` marker when its level spawns.
+///
+/// Only the first marker found is used, so neighboring levels loading later don't
+/// move the spawn point.
+///
+/// # Arguments
+/// * `commands` - Used to insert the `SpawnPoint` resource.
+/// * `spawn_point` - The current spawn point, if one has been recorded.
+/// * `level_events` - Event reader for levels being spawned.
+/// * `markers` - Query to access spawn markers, their grid positions and parent layers.
+/// * `layers` - Query used to walk from a marker's layer up to its level.
+/// * `level_entities` - Query used to find the spawned level's entity.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+///
+fn record_spawn_point(
+    mut commands: Commands,
+    spawn_point: Option<Res<SpawnPoint>>,
+    mut level_events: EventReader<LevelEvent>,
+    markers: Query<(&GridCoords, &Parent), With<PlayerSpawn>>,
+    layers: Query<&Parent, Without<PlayerSpawn>>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+) {
+    if spawn_point.is_some() {
+        return;
+    }
+    for level_event in level_events.iter() {
+        let LevelEvent::Spawned(level_iid) = level_event else {
+            continue;
+        };
+        let Some(level_entity) = level_entities.iter().find_map(|(entity, handle)| {
+            level_assets
+                .get(handle)
+                .filter(|ldtk_level| ldtk_level.level.iid == *level_iid)
+                .map(|_| entity)
+        }) else {
+            continue;
+        };
+
+        // Markers are children of the Entities layer, which is a child of the level
+        let Some((cell, _)) = markers.iter().find(|(_, layer)| {
+            layers
+                .get(layer.get())
+                .is_ok_and(|level| level.get() == level_entity)
+        }) else {
+            continue;
+        };
+
+        info!("🏁spawn point at {} {:?}", level_iid, cell);
+        commands.insert_resource(SpawnPoint {
+            level_iid: level_iid.clone(),
+            cell: *cell,
+        });
+        return;
+    }
+}
+
+/// Moves the player to the `SpawnPoint` as soon as it's first recorded.
+///
+/// # Arguments
+/// * `commands` - Used to move the player into the spawn point's level.
+/// * `spawn_point` - Resource holding where the player appears.
+/// * `player_query` - Query to access players' transforms and grid positions.
+/// * `level_entities` - Query used to find the spawn point's level entity.
+/// * `level_assets` - Loaded LDtk levels, used to match level entities to IIDs.
+/// * `grid_size` - Resource giving the size of a cell.
+///
+fn place_player_at_spawn(
+    mut commands: Commands,
+    spawn_point: Option<Res<SpawnPoint>>,
+    mut player_query: Query<(Entity, &mut Transform, &mut GridCoords), With<Player>>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    grid_size: Res<GridSize>,
+) {
+    let Some(spawn_point) = spawn_point.filter(|spawn_point| spawn_point.is_added()) else {
+        return;
+    };
+    let Some(level_entity) = level_entities.iter().find_map(|(entity, handle)| {
+        level_assets
+            .get(handle)
+            .filter(|ldtk_level| ldtk_level.level.iid == spawn_point.level_iid)
+            .map(|_| entity)
+    }) else {
+        return;
+    };
+
+    for (player, mut player_transform, mut player_grid_coords) in player_query.iter_mut() {
+        let translation = player_translation(spawn_point.cell, *grid_size);
+        player_transform.translation.x = translation.x;
+        player_transform.translation.y = translation.y;
+        *player_grid_coords = spawn_point.cell;
+        commands.entity(player).set_parent(level_entity);
+    }
+}
+
+/// Moves a player found inside a wall to the nearest walkable cell.
+///
+/// Runs on `LevelReady` for the selected level, once its walls are cached, so a
+/// player left in a wall by a level change isn't soft-locked.
+///
+/// # Arguments
+/// * `ready_events` - Event reader for levels whose walls have just been cached.
+/// * `level_walls` - Resource holding the wall locations of the selected level.
+/// * `grid_size` - Resource giving the size of a cell.
+/// * `player_query` - Query to access players' transforms and grid positions.
+///
+fn unstick_player(
+    mut ready_events: EventReader<LevelReady>,
+    level_walls: Res<LevelWalls>,
+    grid_size: Res<GridSize>,
+    mut player_query: Query<(&mut Transform, &mut GridCoords), With<Player>>,
+) {
+    // Read every event so none are left for the next frame
+    let selected_ready = ready_events
+        .iter()
+        .filter(|ready| ready.level_iid == level_walls.level_iid())
+        .count();
+    if selected_ready == 0 {
+        return;
+    }
+    for (mut transform, mut grid_coords) in player_query.iter_mut() {
+        // A player past the edge is being handed over to the next level
+        if !level_walls.in_bounds(&grid_coords) || !level_walls.in_wall(&grid_coords) {
+            continue;
+        }
+        let Some(open) = level_walls.nearest_walkable(*grid_coords) else {
+            continue;
+        };
+        warn!(
+            "🧱player stuck in wall at {:?}, moved to {:?}",
+            *grid_coords, open
+        );
+        let translation = player_translation(open, *grid_size);
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        *grid_coords = open;
+    }
+}
+
+/// The player's regular, looping animation.
+fn idle_animation() -> Animation {
+    Animation {
+        frames: PLAYER_SPRITE_FRAMES.to_vec(),
+        ..default()
+    }
+}
+
+/// Ends the player's turn whenever they cast a spell in turn-based mode.
+pub(crate) fn end_turn_on_cast(
+    movement_mode: Res<MovementMode>,
+    mut cast_events: EventReader<SpellCast>,
+    mut pending_turns: ResMut<PendingTurns>,
+) {
+    // Read the casts either way so switching modes doesn't end a turn for an old one
+    let casts = cast_events.iter().count();
+    if *movement_mode == MovementMode::TurnBased && casts > 0 {
+        pending_turns.0 += 1;
+    }
+}
+
+/// Plays the one-shot cast animation on players that just cast a spell.
+///
+/// Casting again mid-animation restarts it. Dying players are left alone. Frames
+/// outside the player's texture atlas are dropped.
+///
+/// # Arguments
+/// * `cast_events` - Event reader for successful spell casts.
+/// * `query` - Query to access players' animation state and sprites.
+/// * `texture_atlases` - Loaded texture atlases, used to validate the animation frames.
+///
+#[allow(clippy::type_complexity)]
+fn start_cast_animation(
+    mut cast_events: EventReader<SpellCast>,
+    mut query: Query<
+        (
+            &mut AnimationState,
+            &mut Animation,
+            &mut TextureAtlasSprite,
+            Option<&Handle<TextureAtlas>>,
+        ),
+        With<Player>,
+    >,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+) {
+    for cast in cast_events.iter() {
+        let Ok((mut animation_state, mut animation, mut sprite, atlas_handle)) =
+            query.get_mut(cast.caster)
+        else {
+            continue;
+        };
+        if *animation_state == AnimationState::Dying {
+            continue;
+        }
+        *animation_state = AnimationState::Casting;
+        *animation = fit_to_atlas(
+            Animation::once(PLAYER_CAST_FRAMES.to_vec()),
+            atlas_handle,
+            &texture_atlases,
+        );
+        if let Some(&first) = animation.frames.first() {
+            sprite.index = first;
+        }
+    }
+}
+
+/// Returns casting players to their regular animation once the cast animation finishes.
+///
+/// # Arguments
+/// * `finished_events` - Event reader for finished one-shot animations.
+/// * `query` - Query to access players' animation state and sprites.
+/// * `texture_atlases` - Loaded texture atlases, used to validate the animation frames.
+///
+#[allow(clippy::type_complexity)]
+fn finish_cast_animation(
+    mut finished_events: EventReader<AnimationFinished>,
+    mut query: Query<
+        (
+            &mut AnimationState,
+            &mut Animation,
+            &mut TextureAtlasSprite,
+            Option<&Handle<TextureAtlas>>,
+        ),
+        With<Player>,
+    >,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+) {
+    for AnimationFinished(entity) in finished_events.iter() {
+        let Ok((mut animation_state, mut animation, mut sprite, atlas_handle)) =
+            query.get_mut(*entity)
+        else {
+            continue;
+        };
+        if *animation_state != AnimationState::Casting {
+            continue;
+        }
+        *animation_state = AnimationState::Idle;
+        *animation = fit_to_atlas(idle_animation(), atlas_handle, &texture_atlases);
+        if let Some(&first) = animation.frames.first() {
+            sprite.index = first;
+        }
+    }
+}
+
+pub fn dbg_player(mut query: Query<(&Transform, &GridCoords, &Collider, &Player)>) {
+    for (transform, grid_coords, collider, _player) in &mut query {
+        info!(
+            "loc@{:?}=({},{}) collider={:?}",
+            &transform.translation, &grid_coords.x, &grid_coords.y, &collider
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spell_fire::SpellKind;
+    use crate::test_harness::{Harness, FRAME};
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_ecs_ldtk::ldtk::{EntityInstance, FieldInstance};
+
+    #[test]
+    fn test_velocity_ramps_up_and_coasts_to_a_stop() {
+        let feel = MovementFeel::default();
+        let delta_seconds = 1.0 / 60.0;
+        let full_speed = Vec2::new(PLAYER_SPRITE_SPEED, 0.0);
+
+        // Several frames to reach full speed, getting faster each one
+        let mut velocity = Vec2::ZERO;
+        let mut frames = 0;
+        while velocity != full_speed {
+            let next = step_velocity(velocity, full_speed, &feel, delta_seconds);
+            assert!(next.x > velocity.x);
+            assert!(next.x <= full_speed.x);
+            velocity = next;
+            frames += 1;
+        }
+        assert!(frames > 1);
+        assert_eq!(
+            frames,
+            (PLAYER_SPRITE_SPEED / (PLAYER_ACCELERATION * delta_seconds)).ceil() as usize
+        );
+
+        // With no input it slows every frame until it stops
+        let mut frames = 0;
+        while velocity != Vec2::ZERO {
+            let next = step_velocity(velocity, Vec2::ZERO, &feel, delta_seconds);
+            assert!(next.x < velocity.x);
+            assert!(next.x >= 0.0);
+            velocity = next;
+            frames += 1;
+        }
+        assert!(frames > 1);
+    }
+
+    #[test]
+    fn test_looping_animation_wraps() {
+        let mut animation = Animation {
+            frames: vec![1, 2, 3],
+            ..default()
+        };
+        assert_eq!(animation.advance(), Some(2));
+        assert_eq!(animation.advance(), Some(3));
+        assert_eq!(animation.advance(), Some(1));
+        assert!(!animation.finished);
+    }
+
+    #[test]
+    fn test_one_shot_animation_stops_on_last_frame() {
+        let mut animation = Animation::once(vec![1, 2, 3]);
+        assert_eq!(animation.advance(), Some(2));
+        assert_eq!(animation.advance(), Some(3));
+        assert!(!animation.finished);
+        assert_eq!(animation.advance(), Some(3));
+        assert!(animation.finished);
+        assert_eq!(animation.advance(), Some(3));
+    }
+
+    #[test]
+    fn test_one_shot_animation_with_repeated_frames() {
+        let mut animation = Animation::once(vec![5, 5, 5]);
+        assert_eq!(animation.advance(), Some(5));
+        assert_eq!(animation.advance(), Some(5));
+        assert!(!animation.finished);
+        assert_eq!(animation.advance(), Some(5));
+        assert!(animation.finished);
+    }
+
+    #[test]
+    fn test_valid_frames_against_small_atlas() {
+        let atlas = TextureAtlas::from_grid(Handle::default(), Vec2::splat(16.0), 4, 1, None, None);
+        assert_eq!(atlas.len(), 4);
+        assert_eq!(valid_frames(&[0, 3, 4, 1, 136], atlas.len()), vec![0, 3, 1]);
+        assert_eq!(
+            valid_frames(&PLAYER_SPRITE_FRAMES, atlas.len()),
+            Vec::<usize>::new()
+        );
+        assert_eq!(valid_frames(&[], atlas.len()), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_empty_animation() {
+        assert_eq!(Animation::default().advance(), None);
+    }
+
+    #[test]
+    fn test_empty_one_shot_animation_finishes() {
+        let mut animation = Animation::once(Vec::new());
+        assert_eq!(animation.advance(), None);
+        assert!(animation.finished);
+    }
+
+    /// Builds an app that only animates players, spawning one with `animation`.
+    fn animation_app(animation: Animation) -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameReached>()
+            .add_systems(Update, animate_sprites);
+        let first_frame = animation.frames[0];
+        let player = app
+            .world
+            .spawn((Player, animation, TextureAtlasSprite::new(first_frame)))
+            .id();
+        app.update();
+        (app, player)
+    }
+
+    /// Advances time by one animation frame and runs the app, returning the
+    /// sprite index and any `AnimationFinished` events sent during the update.
+    fn step_animation(app: &mut App, player: Entity) -> (usize, Vec<AnimationFinished>) {
+        let now = app
+            .world
+            .resource::<Time>()
+            .last_update()
+            .unwrap_or_else(|| app.world.resource::<Time>().startup())
+            + Duration::from_secs_f32(SPRITE_ANIMATION_SPEED);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        let events = app
+            .world
+            .resource_mut::<Events<AnimationFinished>>()
+            .drain()
+            .collect();
+        let index = app.world.get::<TextureAtlasSprite>(player).unwrap().index;
+        (index, events)
+    }
+
+    #[test]
+    fn test_state_change_restarts_animation() {
+        let (mut app, player) = animation_app(Animation {
+            frames: vec![1, 2, 3],
+            ..default()
+        });
+        app.add_systems(
+            Update,
+            restart_animation_on_state_change.before(animate_sprites),
+        );
+        app.world.entity_mut(player).insert(AnimationState::Idle);
+        assert_eq!(step_animation(&mut app, player).0, 2);
+
+        // Partway into the next frame, the player starts casting
+        let mut animation = app.world.get_mut::<Animation>(player).unwrap();
+        animation
+            .timer
+            .tick(Duration::from_secs_f32(SPRITE_ANIMATION_SPEED / 2.0));
+        animation.frames = vec![7, 8, 9];
+        *app.world.get_mut::<AnimationState>(player).unwrap() = AnimationState::Casting;
+        let now = app.world.resource::<Time>().last_update().unwrap();
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(player).unwrap().index,
+            7
+        );
+        let animation = app.world.get::<Animation>(player).unwrap();
+        assert_eq!(animation.current, 0);
+        assert_eq!(animation.timer.elapsed(), Duration::ZERO);
+        // The first frame gets its full time before the next is shown
+        assert_eq!(step_animation(&mut app, player).0, 8);
+    }
+
+    #[test]
+    fn test_animate_looping() {
+        let (mut app, player) = animation_app(Animation {
+            frames: vec![1, 2, 3],
+            ..default()
+        });
+        let indices: Vec<usize> = (0..5)
+            .map(|_| {
+                let (index, events) = step_animation(&mut app, player);
+                assert!(events.is_empty());
+                index
+            })
+            .collect();
+        assert_eq!(indices, vec![2, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_animate_one_shot_sends_finished_once() {
+        let (mut app, player) = animation_app(Animation::once(vec![1, 2, 3]));
+        assert_eq!(step_animation(&mut app, player), (2, vec![]));
+        assert_eq!(step_animation(&mut app, player), (3, vec![]));
+        assert_eq!(
+            step_animation(&mut app, player),
+            (3, vec![AnimationFinished(player)])
+        );
+        assert_eq!(step_animation(&mut app, player), (3, vec![]));
+    }
+
+    /// Drains the `AnimationFrameReached` events sent so far.
+    fn frame_events(app: &mut App) -> Vec<AnimationFrameReached> {
+        app.world
+            .resource_mut::<Events<AnimationFrameReached>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn test_event_frames_fire_once_per_loop() {
+        let (mut app, player) = animation_app(Animation {
+            frames: vec![1, 2, 3, 4],
+            event_frames: vec![0, 2],
+            ..default()
+        });
+        let mut reached = Vec::new();
+        // Two full loops
+        for _ in 0..8 {
+            step_animation(&mut app, player);
+            reached.extend(frame_events(&mut app));
+        }
+        assert_eq!(
+            reached,
+            vec![
+                AnimationFrameReached(player, 2),
+                AnimationFrameReached(player, 0),
+                AnimationFrameReached(player, 2),
+                AnimationFrameReached(player, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_one_shot_last_event_frame_fires_once() {
+        let (mut app, player) = animation_app(Animation {
+            event_frames: vec![2],
+            ..Animation::once(vec![1, 2, 3])
+        });
+        let mut reached = Vec::new();
+        let mut finished = Vec::new();
+        for _ in 0..5 {
+            finished.extend(step_animation(&mut app, player).1);
+            reached.extend(frame_events(&mut app));
+        }
+        assert_eq!(reached, vec![AnimationFrameReached(player, 2)]);
+        assert_eq!(finished, vec![AnimationFinished(player)]);
+    }
+
+    #[test]
+    fn test_recoil_step_clamped_by_walls() {
+        // Walls down the x = 2 column; the player stands in (1, 1)
+        let level_walls = LevelWalls::from_cells(&[(2, 0), (2, 1), (2, 2), (2, 3)], 5, 5);
+        let start = Vec2::new(24.0, 40.0);
+        let grid_size = GridSize::default();
+        assert_eq!(player_cell(start, grid_size), GridCoords::new(1, 1));
+        assert_eq!(player_translation(GridCoords::new(1, 1), grid_size), start);
+
+        // Open space: the full step is taken
+        assert_eq!(
+            recoil_step(start, Vec2::new(-80.0, 0.0), 0.1, &level_walls, grid_size),
+            Vec2::new(16.0, 40.0)
+        );
+        // Straight into the wall: no movement
+        assert_eq!(
+            recoil_step(start, Vec2::new(100.0, 0.0), 0.1, &level_walls, grid_size),
+            start
+        );
+        // Diagonally into the wall: slides along it
+        assert_eq!(
+            recoil_step(start, Vec2::new(100.0, 50.0), 0.1, &level_walls, grid_size),
+            Vec2::new(24.0, 45.0)
+        );
+    }
+
+    #[test]
+    fn test_player_collider_capsule_fills_sprite() {
+        let collider = player_collider(GridSize::default());
+        let capsule = collider.as_capsule().unwrap();
+        let grid = GRID_SIZE as f32;
+        assert_eq!(capsule.radius(), grid / 2.0);
+        assert_eq!(capsule.half_height(), grid / 2.0);
+        // One cell wide and two tall, like the sprite
+        assert_eq!(capsule.radius() * 2.0, grid);
+        assert_eq!((capsule.half_height() + capsule.radius()) * 2.0, grid * 2.0);
+
+        // Scales with the level's grid
+        let capsule = player_collider(GridSize(8)).as_capsule().unwrap();
+        assert_eq!(capsule.radius(), 4.0);
+        assert_eq!(capsule.half_height(), 4.0);
+    }
+
+    #[test]
+    fn test_player_collider_sized_from_atlas() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<TextureAtlas>()
+            .init_resource::<GridSize>()
+            .add_systems(Update, setup_player_collision);
+        // A 24x40 sprite, not the stock 16x32
+        let atlas =
+            TextureAtlas::from_grid(Handle::default(), Vec2::new(24.0, 40.0), 4, 1, None, None);
+        assert_eq!(atlas_frame_size(&atlas, 2), Some(Vec2::new(24.0, 40.0)));
+        assert_eq!(atlas_frame_size(&atlas, 4), None);
+        let handle = app.world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+        let player = app
+            .world
+            .spawn((Player, handle, TextureAtlasSprite::new(1)))
+            .id();
+        app.update();
+
+        let collider = app.world.get::<Collider>(player).unwrap();
+        let capsule = collider.as_capsule().unwrap();
+        assert_eq!(capsule.radius(), 12.0);
+        assert_eq!(capsule.half_height(), 8.0);
+        // Fills the frame: as wide and as tall as the sprite
+        assert_eq!(capsule.radius() * 2.0, 24.0);
+        assert_eq!((capsule.half_height() + capsule.radius()) * 2.0, 40.0);
+
+        // A squat frame gets a circle
+        let capsule = frame_collider(Vec2::new(32.0, 16.0));
+        let capsule = capsule.as_capsule().unwrap();
+        assert_eq!(capsule.radius(), 8.0);
+        assert_eq!(capsule.half_height(), 0.0);
+    }
+
+    #[test]
+    fn test_physics_movement_stops_at_wall_collider() {
+        // No wall cells, so only the collider can stop the player
+        let mut harness = Harness::new(&[], 8, 8, GridCoords::new(1, 1));
+        harness
+            .app
+            .add_plugins((
+                TransformPlugin,
+                HierarchyPlugin,
+                AssetPlugin::default(),
+                bevy::scene::ScenePlugin,
+                RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(GRID_SIZE as f32),
+            ))
+            .add_asset::<Mesh>()
+            .insert_resource(PlayerCollision::Physics)
+            .add_systems(
+                FixedUpdate,
+                move_player_with_physics
+                    .run_if(physics_movement)
+                    .in_set(GameplayStep),
+            );
+        harness.app.world.entity_mut(harness.player).insert((
+            GlobalTransform::default(),
+            player_collider(GridSize::default()),
+            CollisionLayer::Player.groups(),
+        ));
+        // A wall one cell wide across the whole level, its left face at x = 64
+        let grid = GRID_SIZE as f32;
+        harness.app.world.spawn((
+            RigidBody::Fixed,
+            Collider::cuboid(grid / 2.0, grid * 4.0),
+            CollisionLayer::Wall.groups(),
+            TransformBundle::from(Transform::from_xyz(grid * 4.5, grid * 4.0, 0.0)),
+        ));
+        harness.step(1); // The first frame takes no time
+
+        // Long enough to walk well past the wall
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(6));
+        let translation = harness
+            .app
+            .world
+            .get::<Transform>(harness.player)
+            .unwrap()
+            .translation;
+        let start = player_translation(GridCoords::new(1, 1), GridSize::default());
+        assert!(translation.x > start.x);
+        // The capsule's right side rests against the wall's left face, not inside it
+        assert!(translation.x + grid / 2.0 <= grid * 4.0 + 0.5);
+        assert_eq!(translation.y, start.y);
+        assert_eq!(
+            harness.player_coords(),
+            player_cell(convert_vec3_to_vec2(translation), GridSize::default())
+        );
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 1));
+    }
+
+    #[test]
+    fn test_recoil_decays_and_is_removed() {
+        let mut harness = Harness::new(&[], 5, 5, GridCoords::new(2, 2));
+        harness.app.add_systems(FixedUpdate, apply_recoil);
+        harness.app.world.entity_mut(harness.player).insert(Recoil {
+            velocity: Vec2::new(PLAYER_RECOIL_SPEED, 0.0),
+        });
+        harness.step(1);
+        let recoil = harness.app.world.get::<Recoil>(harness.player).unwrap();
+        assert!(recoil.velocity.x < PLAYER_RECOIL_SPEED);
+
+        harness.step(60);
+        assert!(harness.app.world.get::<Recoil>(harness.player).is_none());
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 2));
+    }
+
+    #[test]
+    fn test_player_in_wall_nudged_out() {
+        let mut harness = Harness::new(&[(2, 2), (2, 3)], 5, 5, GridCoords::new(2, 2));
+        harness
+            .app
+            .add_event::<LevelReady>()
+            .add_systems(Update, unstick_player);
+        // Left alone until the level is ready
+        harness.step(1);
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 2));
+
+        let level_iid = harness
+            .app
+            .world
+            .resource::<LevelWalls>()
+            .level_iid()
+            .to_string();
+        harness.app.world.send_event(LevelReady { level_iid });
+        harness.step(1);
+        // The nearest open cell is right of the wall, the up neighbor being a wall too
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 2));
+        let translation = harness
+            .app
+            .world
+            .get::<Transform>(harness.player)
+            .unwrap()
+            .translation;
+        assert_eq!(
+            translation.truncate(),
+            player_translation(GridCoords::new(3, 2), GridSize::default())
+        );
+    }
+
+    #[test]
+    fn test_harness_walk_one_cell() {
+        let mut harness = Harness::new(&[], 5, 5, GridCoords::new(1, 1));
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 1));
+
+        harness.hold(&[KeyCode::W], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 2));
+    }
+
+    #[test]
+    fn test_no_clip_ignores_walls() {
+        let mut harness = Harness::new(&[(2, 1)], 6, 3, GridCoords::new(1, 1));
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(2));
+        assert_eq!(harness.player_coords(), GridCoords::new(1, 1));
+
+        harness.app.insert_resource(NoClip(true));
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(2));
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 1));
+
+        // Walls stop the player again once it's off
+        harness.app.insert_resource(NoClip(false));
+        harness.hold(&[KeyCode::A], Harness::frames_to_walk(2));
+        assert_eq!(harness.player_coords(), GridCoords::new(3, 1));
+    }
+
+    #[test]
+    fn test_no_clip_toggle_needs_debug_settings() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<DebugSettings>()
+            .init_resource::<NoClip>()
+            .init_resource::<GridSize>()
+            .insert_resource(LevelWalls::from_cells(&[(2, 1)], 5, 3))
+            .add_systems(
+                Update,
+                (
+                    toggle_no_clip,
+                    disable_colliders_in_no_clip.after(toggle_no_clip),
+                ),
+            );
+        let player = app
+            .world
+            .spawn((
+                Player,
+                Transform::default(),
+                GridCoords::new(1, 1),
+                Collider::ball(4.0),
+            ))
+            .id();
+        let press_f1 = |app: &mut App| {
+            app.world
+                .resource_mut::<Input<KeyCode>>()
+                .press(KeyCode::F1);
+            app.update();
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.release(KeyCode::F1);
+            input.clear();
+        };
+
+        // Unavailable in normal play
+        press_f1(&mut app);
+        assert!(!app.world.resource::<NoClip>().0);
+        assert!(app.world.get::<ColliderDisabled>(player).is_none());
+
+        app.world.resource_mut::<DebugSettings>().no_clip = true;
+        press_f1(&mut app);
+        assert!(app.world.resource::<NoClip>().0);
+        assert!(app.world.get::<ColliderDisabled>(player).is_some());
+
+        // Turned off inside a wall, the player is put back in the open
+        *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(2, 1);
+        press_f1(&mut app);
+        assert!(!app.world.resource::<NoClip>().0);
+        assert!(app.world.get::<ColliderDisabled>(player).is_none());
+        let cell = *app.world.get::<GridCoords>(player).unwrap();
+        assert!(!app.world.resource::<LevelWalls>().in_wall(&cell));
+        assert_eq!(
+            app.world
+                .get::<Transform>(player)
+                .unwrap()
+                .translation
+                .truncate(),
+            player_translation(cell, GridSize::default())
+        );
+    }
+
+    #[test]
+    fn test_vertical_move_keeps_flip_and_faces() {
+        let mut harness = Harness::new(&[], 5, 5, GridCoords::new(2, 1));
+        harness
+            .app
+            .world
+            .entity_mut(harness.player)
+            .insert(Facing::default());
+        let flip_x = |harness: &Harness| {
+            harness
+                .app
+                .world
+                .get::<TextureAtlasSprite>(harness.player)
+                .unwrap()
+                .flip_x
+        };
+        let facing = |harness: &Harness| *harness.app.world.get::<Facing>(harness.player).unwrap();
+
+        harness.hold(&[KeyCode::A], Harness::frames_to_walk(1));
+        assert!(flip_x(&harness));
+        assert_eq!(facing(&harness), Facing::Left);
+
+        // Straight up or down turns the player without flipping the sprite back
+        harness.hold(&[KeyCode::W], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(1, 2));
+        assert!(flip_x(&harness));
+        assert_eq!(facing(&harness), Facing::Up);
+        harness.hold(&[KeyCode::S], Harness::frames_to_walk(1));
+        assert!(flip_x(&harness));
+        assert_eq!(facing(&harness), Facing::Down);
+
+        // Any sideways part faces sideways
+        harness.hold(&[KeyCode::W, KeyCode::D], Harness::frames_to_walk(1));
+        assert!(!flip_x(&harness));
+        assert_eq!(facing(&harness), Facing::Right);
+    }
+
+    #[test]
+    fn test_facing_frames() {
+        let mut facing_frames = FacingFrames::default();
+        // Without frames of their own, up and down use the side-on ones
+        assert_eq!(facing_frames.frames(Facing::Up), &PLAYER_SPRITE_FRAMES);
+        assert_eq!(facing_frames.frames(Facing::Left), &PLAYER_SPRITE_FRAMES);
+
+        facing_frames.up = vec![1, 2];
+        assert_eq!(facing_frames.frames(Facing::Up), &[1, 2]);
+        assert_eq!(facing_frames.frames(Facing::Down), &PLAYER_SPRITE_FRAMES);
+
+        let (mut app, player) = animation_app(idle_animation());
+        app.insert_resource(facing_frames)
+            .add_systems(Update, apply_facing_frames);
+        app.world
+            .entity_mut(player)
+            .insert((AnimationState::Idle, Facing::Up));
+        app.update();
+        assert_eq!(
+            app.world.get::<Animation>(player).unwrap().frames,
+            vec![1, 2]
+        );
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(player).unwrap().index,
+            1
+        );
+    }
+
+    #[test]
+    fn test_coop_players_move_independently() {
+        let mut harness = Harness::new(&[], 6, 6, GridCoords::new(1, 1));
+        let cell = GridCoords::new(3, 1);
+        let translation = player_translation(cell, GridSize::default());
+        let player_two = harness
+            .app
+            .world
+            .spawn((
+                Player,
+                PlayerId::TWO,
+                AnimationState::default(),
+                Transform::from_translation(translation.extend(0.0)),
+                TextureAtlasSprite::default(),
+                cell,
+                Velocity2d::default(),
+            ))
+            .id();
+        let player_two_coords =
+            |harness: &Harness| *harness.app.world.get::<GridCoords>(player_two).unwrap();
+
+        // Player one's keys move only player one
+        harness.hold(&[KeyCode::D], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 1));
+        assert_eq!(player_two_coords(&harness), GridCoords::new(3, 1));
+
+        // Player two's keys move only player two
+        harness.hold(&[KeyCode::I], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 1));
+        assert_eq!(player_two_coords(&harness), GridCoords::new(3, 2));
+
+        // Both at once, in different directions
+        harness.hold(&[KeyCode::W, KeyCode::L], Harness::frames_to_walk(1));
+        assert_eq!(harness.player_coords(), GridCoords::new(2, 2));
+        assert_eq!(player_two_coords(&harness), GridCoords::new(4, 2));
+
+        // The camera frames the middle of the pair
+        assert_eq!(
+            players_center([Vec3::new(0.0, 10.0, 0.0), Vec3::new(40.0, 30.0, 0.0)]),
+            Some(Vec3::new(20.0, 20.0, 0.0))
+        );
+        assert_eq!(players_center([]), None);
+    }
+
+    #[test]
+    fn test_camera_zooms_out_to_fit_players() {
+        let view = Vec2::new(640.0, 360.0);
+        let at = |x: f32, y: f32| Vec3::new(x, y, 0.0);
+        assert_eq!(camera_zoom([], view), 1.0);
+        assert_eq!(camera_zoom([at(500.0, 500.0)], view), 1.0);
+        // Close together there's no need to zoom out
+        assert_eq!(camera_zoom([at(0.0, 0.0), at(100.0, 50.0)], view), 1.0);
+        // Spread apart, the wider of the two directions decides, margin included
+        let zoom = camera_zoom([at(0.0, 0.0), at(1000.0, 100.0)], view);
+        assert_eq!(zoom, (1000.0 + 2.0 * CAMERA_FIT_MARGIN) / 640.0);
+        let zoom = camera_zoom([at(0.0, 0.0), at(100.0, 600.0)], view);
+        assert_eq!(zoom, (600.0 + 2.0 * CAMERA_FIT_MARGIN) / 360.0);
+        // But only so far
+        assert_eq!(
+            camera_zoom([at(0.0, 0.0), at(10000.0, 0.0)], view),
+            CAMERA_MAX_ZOOM_OUT
+        );
+    }
+
+    #[test]
+    fn test_player_two_needs_keys_of_its_own_to_join() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<CoopBindings>()
+            .add_systems(Update, join_player_two);
+        app.world
+            .spawn((Player, Transform::default(), GridCoords::new(1, 1)));
+        let player_count = |app: &mut App| {
+            app.world
+                .query_filtered::<(), With<Player>>()
+                .iter(&app.world)
+                .count()
+        };
+        let press_f5 = |app: &mut App| {
+            app.world
+                .resource_mut::<Input<KeyCode>>()
+                .press(KeyCode::F5);
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().reset_all();
+        };
+
+        press_f5(&mut app);
+        assert_eq!(player_count(&mut app), 2);
+        press_f5(&mut app);
+        assert_eq!(player_count(&mut app), 1);
+
+        // Player one moving up with I would move player two too
+        app.world
+            .resource_mut::<KeyBindings>()
+            .bind(Action::MoveUp, KeyCode::I);
+        press_f5(&mut app);
+        assert_eq!(player_count(&mut app), 1);
+    }
+
+    #[test]
+    fn test_fixed_step_move_independent_of_frame_rate() {
+        // Half a second of holding D, drawn at 20, 60 and 120 frames per second
+        let walks: Vec<(GridCoords, Vec3)> = [FRAME * 3, FRAME, FRAME / 2]
+            .into_iter()
+            .map(|frame| {
+                let mut harness = Harness::new(&[], 10, 5, GridCoords::new(1, 1));
+                harness
+                    .app
+                    .add_plugins(InterpolationPlugin)
+                    .insert_resource(FixedTime::new(FRAME))
+                    .insert_resource(TimeUpdateStrategy::ManualDuration(frame));
+                harness
+                    .app
+                    .world
+                    .entity_mut(harness.player)
+                    .insert(Interpolated::default());
+                harness.step(1); // The first frame takes no time
+                let frames = (FRAME * 30).as_nanos() / frame.as_nanos();
+                harness.hold(&[KeyCode::D], frames as usize);
+                harness.step(3);
+                let interpolated = harness.app.world.get::<Interpolated>(harness.player);
+                (harness.player_coords(), interpolated.unwrap().current)
+            })
+            .collect();
+        assert_eq!(walks[0].0, GridCoords::new(4, 1));
+        assert_eq!(walks[0], walks[1]);
+        assert_eq!(walks[1], walks[2]);
+    }
+
+    #[test]
+    fn test_move_player_skipped_while_input_locked() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<CoopBindings>()
+            .init_resource::<MovementMode>()
+            .init_resource::<MovementFeel>()
+            .init_resource::<MoveInput>()
+            .init_resource::<FixedTime>()
+            .init_resource::<InputLocked>()
+            .init_resource::<Difficulty>()
+            .init_resource::<LevelWalls>()
+            .init_resource::<NoClip>()
+            .init_resource::<GridSize>()
+            .add_event::<LevelEdgeReached>()
+            .init_resource::<PendingTurns>()
+            .add_systems(
+                Update,
+                (
+                    toggle_inspector_input_lock,
+                    read_move_input
+                        .run_if(input_unlocked)
+                        .after(toggle_inspector_input_lock),
+                    move_player_from_input
+                        .run_if(input_unlocked)
+                        .after(read_move_input),
+                ),
+            );
+        let player = app
+            .world
+            .spawn((
+                Player,
+                AnimationState::default(),
+                Transform::default(),
+                TextureAtlasSprite::default(),
+                GridCoords::default(),
+                Velocity2d::default(),
+            ))
+            .id();
+
+        let step = |app: &mut App, key: KeyCode| {
+            app.world.resource_mut::<Input<KeyCode>>().press(key);
+            app.update();
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.release(key);
+            input.clear();
+        };
+
+        // Escape is left for menus
+        step(&mut app, KeyCode::Escape);
+        assert!(!app.world.resource::<InputLocked>().locked());
+
+        // Opening the inspector locks input, so pressing A doesn't turn the wizard around
+        step(&mut app, KeyCode::Grave);
+        assert!(app.world.resource::<InputLocked>().locked());
+        step(&mut app, KeyCode::A);
+        assert!(!app.world.get::<TextureAtlasSprite>(player).unwrap().flip_x);
+
+        // Closing it hands input back
+        step(&mut app, KeyCode::Grave);
+        assert!(!app.world.resource::<InputLocked>().locked());
+        step(&mut app, KeyCode::A);
+        assert!(app.world.get::<TextureAtlasSprite>(player).unwrap().flip_x);
+
+        // The key can be rebound
+        app.world
+            .resource_mut::<KeyBindings>()
+            .bind(Action::ToggleInspector, KeyCode::F10);
+        step(&mut app, KeyCode::F10);
+        assert!(app.world.resource::<InputLocked>().locked());
+    }
+
+    /// Builds an app running the death and respawn systems, spawning a living player.
+    fn death_app() -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<TextureAtlas>>()
+            .init_resource::<LevelSelection>()
+            .init_resource::<PendingTeleport>()
+            .init_resource::<ScreenFade>()
+            .insert_resource(Lives(3))
+            .add_state::<GameState>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationFrameReached>()
+            .add_systems(
+                Update,
+                (
+                    start_player_death,
+                    animate_sprites,
+                    respawn_player.after(animate_sprites),
+                ),
+            );
+        let player = app
+            .world
+            .spawn((
+                Player,
+                Health::default(),
+                AnimationState::default(),
+                idle_animation(),
+                TextureAtlasSprite::new(PLAYER_SPRITE_FRAMES[0]),
+            ))
+            .id();
+        app.update();
+        (app, player)
+    }
+
+    /// Kills the player and plays the death animation through to the end.
+    fn play_death(app: &mut App, player: Entity) {
+        app.world.get_mut::<Health>(player).unwrap().current = 0.0;
+        app.update();
+        assert!(!app.world.get::<Animation>(player).unwrap().looping);
+
+        // Step through every death frame, plus one tick holding the last frame
+        for _ in 0..PLAYER_DEATH_FRAMES.len() {
+            assert_eq!(
+                *app.world.get::<AnimationState>(player).unwrap(),
+                AnimationState::Dying
+            );
+            step_animation(app, player);
+        }
+    }
+
+    #[test]
+    fn test_death_animation_then_respawn() {
+        let (mut app, player) = death_app();
+        let spawn_point = SpawnPoint {
+            level_iid: "level-a".to_string(),
+            cell: GridCoords::new(2, 3),
+        };
+        app.world.insert_resource(spawn_point.clone());
+        play_death(&mut app, player);
+
+        assert_eq!(
+            *app.world.get::<AnimationState>(player).unwrap(),
+            AnimationState::Idle
+        );
+        let health = app.world.get::<Health>(player).unwrap();
+        assert_eq!(health.current, health.max);
+        assert!(app.world.get::<Animation>(player).unwrap().looping);
+        assert_eq!(*app.world.resource::<Lives>(), Lives(2));
+        assert_eq!(
+            app.world.resource::<PendingTeleport>().0,
+            Some(TeleportTarget {
+                player,
+                level_iid: spawn_point.level_iid,
+                cell: spawn_point.cell,
+            })
+        );
+    }
+
+    #[test]
+    fn test_death_with_frames_outside_atlas_still_respawns() {
+        let (mut app, player) = death_app();
+        // Too small for any of the death or idle frames
+        let atlas = TextureAtlas::from_grid(Handle::default(), Vec2::splat(16.0), 4, 1, None, None);
+        let atlas = app.world.resource_mut::<Assets<TextureAtlas>>().add(atlas);
+        app.world.entity_mut(player).insert(atlas);
+        app.world.get_mut::<Health>(player).unwrap().current = 0.0;
+        app.update();
+        assert!(app
+            .world
+            .get::<Animation>(player)
+            .unwrap()
+            .frames
+            .is_empty());
+
+        step_animation(&mut app, player);
+        assert_eq!(
+            *app.world.get::<AnimationState>(player).unwrap(),
+            AnimationState::Idle
+        );
+        assert_eq!(*app.world.resource::<Lives>(), Lives(2));
+        assert!(app
+            .world
+            .get::<Animation>(player)
+            .unwrap()
+            .frames
+            .is_empty());
+    }
+
+    #[test]
+    fn test_death_without_spawn_point_respawns_in_place() {
+        let (mut app, player) = death_app();
+        play_death(&mut app, player);
+        assert_eq!(
+            *app.world.get::<AnimationState>(player).unwrap(),
+            AnimationState::Idle
+        );
+        assert_eq!(*app.world.resource::<Lives>(), Lives(2));
+        assert_eq!(app.world.resource::<PendingTeleport>().0, None);
+    }
+
+    #[test]
+    fn test_last_life_is_game_over() {
+        let (mut app, player) = death_app();
+        app.world.insert_resource(Lives(1));
+        play_death(&mut app, player);
+        app.update(); // The transition runs
+        assert_eq!(*app.world.resource::<Lives>(), Lives(0));
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().get(),
+            GameState::GameOver
+        );
+    }
+
+    #[test]
+    fn test_starting_lives_from_difficulty() {
+        let mut world = World::new();
+        assert_eq!(Lives::from_world(&mut world), Lives(3));
+        world.insert_resource(Difficulty::Hard);
+        assert_eq!(Lives::from_world(&mut world), Lives(1));
+    }
+
+    #[test]
+    fn test_camera_snaps_to_player_on_level_spawn() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<CameraSnap>()
+            .add_event::<LevelEvent>()
+            .add_systems(Update, snap_camera_on_level_spawn)
+            .add_systems(PostUpdate, follow_player_with_camera);
+        let player_world = Vec3::new(500.0, 300.0, 0.0);
+        app.world
+            .spawn((Player, GlobalTransform::from_translation(player_world)));
+        let camera = app
+            .world
+            .spawn((
+                Camera2d::default(),
+                Transform::default(),
+                GlobalTransform::default(),
+            ))
+            .id();
+        let camera_xy = |app: &App| {
+            app.world
+                .get::<Transform>(camera)
+                .unwrap()
+                .translation
+                .truncate()
+        };
+        let target = camera_target(player_world);
+        app.update();
+        assert!(!app.world.resource::<CameraSnap>().0);
+
+        // A level spawning asks for a snap, which puts the camera exactly on the
+        // player that frame and is then cleared
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+        assert_eq!(camera_xy(&app), target);
+        assert_eq!(
+            app.world
+                .get::<GlobalTransform>(camera)
+                .unwrap()
+                .translation(),
+            target.extend(0.0)
+        );
+        assert!(!app.world.resource::<CameraSnap>().0);
+    }
+
+    #[test]
+    fn test_cast_animation_then_idle() {
+        let (mut app, player) = animation_app(idle_animation());
+        app.init_resource::<Assets<TextureAtlas>>()
+            .add_event::<SpellCast>()
+            .add_systems(
+                Update,
+                (
+                    start_cast_animation.before(animate_sprites),
+                    finish_cast_animation.after(animate_sprites),
+                ),
+            )
+            .world
+            .entity_mut(player)
+            .insert(AnimationState::default());
+
+        app.world.send_event(SpellCast {
+            caster: player,
+            kind: SpellKind::Fire,
+        });
+        app.update();
+        assert_eq!(
+            *app.world.get::<AnimationState>(player).unwrap(),
+            AnimationState::Casting
+        );
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(player).unwrap().index,
+            PLAYER_CAST_FRAMES[0]
+        );
+
+        // Every cast frame, then one tick holding the last
+        for _ in 0..PLAYER_CAST_FRAMES.len() - 1 {
+            step_animation(&mut app, player);
+            assert_eq!(
+                *app.world.get::<AnimationState>(player).unwrap(),
+                AnimationState::Casting
+            );
+        }
+        step_animation(&mut app, player);
+        assert_eq!(
+            *app.world.get::<AnimationState>(player).unwrap(),
+            AnimationState::Idle
+        );
+        assert!(app.world.get::<Animation>(player).unwrap().looping);
+    }
+
+    #[test]
+    fn test_spawn_point_recorded_from_marker() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_event::<LevelEvent>()
+            .add_systems(Update, record_spawn_point);
+
+        // Two levels, each with an Entities layer; only level-b has a spawn marker
+        let mut level_entities = Vec::new();
+        for iid in ["level-a", "level-b"] {
+            let handle = app
+                .world
+                .resource_mut::<Assets<LdtkLevel>>()
+                .add(LdtkLevel {
+                    level: bevy_ecs_ldtk::ldtk::Level {
+                        iid: iid.to_string(),
+                        ..default()
+                    },
+                    background_image: None,
+                });
+            let level = app.world.spawn(handle).id();
+            let layer = app.world.spawn_empty().set_parent(level).id();
+            level_entities.push((level, layer));
+        }
+        app.world
+            .spawn((PlayerSpawn, GridCoords::new(4, 2)))
+            .set_parent(level_entities[1].1);
+
+        app.world
+            .send_event(LevelEvent::Spawned("level-a".to_string()));
+        app.update();
+        assert!(app.world.get_resource::<SpawnPoint>().is_none());
+
+        app.world
+            .send_event(LevelEvent::Spawned("level-b".to_string()));
+        app.update();
+        assert_eq!(
+            app.world.get_resource::<SpawnPoint>(),
+            Some(&SpawnPoint {
+                level_iid: "level-b".to_string(),
+                cell: GridCoords::new(4, 2),
+            })
+        );
+    }
+
+    /// A Player entity instance with the given custom fields, as LDtk JSON.
+    fn player_entity_instance(fields: &str) -> EntityInstance {
+        let fields: Vec<FieldInstance> = serde_json::from_str(fields).unwrap();
+        EntityInstance {
+            identifier: "Player".to_string(),
+            field_instances: fields,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_player_stats_from_fields() {
+        let stats = PlayerStats::from(&player_entity_instance(
+            r#"[
+                { "__identifier": "speed", "__type": "Float", "__value": 150.0,
+                  "__tile": null, "defUid": 1, "realEditorValues": [] },
+                { "__identifier": "health", "__type": "Float", "__value": 5.0,
+                  "__tile": null, "defUid": 2, "realEditorValues": [] }
+            ]"#,
+        ));
+        assert_eq!(
+            stats,
+            PlayerStats {
+                speed: 150.0,
+                health: 5.0
+            }
+        );
+        assert_eq!(
+            stats.starting_health(),
+            Health {
+                current: 5.0,
+                max: 5.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_extra_player_stats_wait_for_their_level() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .add_event::<LevelReady>()
+            .insert_resource(LevelWalls::default().with_level_iid("level-a"))
+            .init_resource::<LevelPlayerStats>()
+            .add_systems(
+                Update,
+                (
+                    despawn_extra_players,
+                    apply_level_player_stats.after(despawn_extra_players),
+                ),
+            );
+        let player = app
+            .world
+            .spawn((
+                Player,
+                PlayerStats::default(),
+                Health {
+                    current: 4.0,
+                    max: PLAYER_HEALTH_MAX,
+                },
+            ))
+            .id();
+        app.update();
+
+        // The neighbor spawns with its own Player entity, under its Entities layer
+        let handle = app
+            .world
+            .resource_mut::<Assets<LdtkLevel>>()
+            .add(LdtkLevel {
+                level: bevy_ecs_ldtk::ldtk::Level {
+                    iid: "level-b".to_string(),
+                    ..default()
+                },
+                background_image: None,
+            });
+        let level_b = app.world.spawn(handle).id();
+        let layer = app.world.spawn_empty().set_parent(level_b).id();
+        let stats = PlayerStats {
+            speed: 150.0,
+            health: 2.0,
+        };
+        let extra = app
+            .world
+            .spawn((Player, stats, stats.starting_health()))
+            .set_parent(layer)
+            .id();
+        app.update();
+        assert!(app.world.get_entity(extra).is_none());
+        // Nothing changes until the player is in level-b, and health isn't refilled
+        assert_eq!(
+            app.world.get::<PlayerStats>(player),
+            Some(&PlayerStats::default())
+        );
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 4.0);
+
+        // The level being played getting ready again doesn't apply them either
+        app.world.send_event(LevelReady {
+            level_iid: "level-a".to_string(),
+        });
+        app.update();
+        assert_eq!(
+            app.world.get::<PlayerStats>(player),
+            Some(&PlayerStats::default())
+        );
+
+        *app.world.resource_mut::<LevelWalls>() = LevelWalls::default().with_level_iid("level-b");
+        app.world.send_event(LevelReady {
+            level_iid: "level-b".to_string(),
+        });
+        app.update();
+        assert_eq!(app.world.get::<PlayerStats>(player), Some(&stats));
+        // The new maximum applies, with the current health clamped to it
+        assert_eq!(
+            app.world.get::<Health>(player),
+            Some(&Health {
+                current: 2.0,
+                max: 2.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_player_stats_fall_back_to_defaults() {
+        assert_eq!(
+            PlayerStats::from(&EntityInstance::default()),
+            PlayerStats::default()
+        );
+        assert_eq!(PlayerStats::default().starting_health(), Health::default());
+
+        // Unset and non-positive fields are ignored
+        let stats = PlayerStats::from(&player_entity_instance(
+            r#"[
+                { "__identifier": "speed", "__type": "Float", "__value": null,
+                  "__tile": null, "defUid": 1, "realEditorValues": [] },
+                { "__identifier": "health", "__type": "Float", "__value": 0.0,
+                  "__tile": null, "defUid": 2, "realEditorValues": [] }
+            ]"#,
+        ));
+        assert_eq!(stats, PlayerStats::default());
     }
 }
@@ -0,0 +1,314 @@
+// replay.rs
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::player::read_move_input;
+use crate::rng::GameRng;
+
+/// ReplayPlugin records a run's input to a file and plays it back, for reproducing
+/// bugs.
+///
+/// With `--record=<path>`, the keys held and the length of every frame are kept,
+/// and written to `path` along with the `GameRng` seed when the game exits. With
+/// `--replay=<path>`, the `GameRng` is seeded from the recording and each frame is
+/// run with the recorded length and keys in place of the real ones, after which
+/// the keyboard and clock are handed back. Gameplay steps in `FixedUpdate` on the
+/// frame time and draws from the `GameRng`, so the same frames, keys and seed play
+/// out the same run, as long as it's started with the same map and settings. Only
+/// the keyboard is recorded; the mouse and gamepads aren't.
+///
+/// Nothing is recorded unless asked for on the command line, and recordings never
+/// leave the machine. The plugin must be added after `RngPlugin`, so a replay's
+/// seed replaces the one it picked.
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match replay_from_args(std::env::args()) {
+            Some(ReplayArg::Record(path)) => {
+                info!("🎬recording replay to {:?}", path);
+                app.insert_resource(ReplayRecording {
+                    path,
+                    replay: Replay::default(),
+                });
+            }
+            Some(ReplayArg::Play(path)) => match load_replay(&path) {
+                Ok(replay) => {
+                    info!(
+                        "🎬replaying {} frames from {:?} with seed {}",
+                        replay.frames.len(),
+                        path,
+                        replay.seed
+                    );
+                    app.insert_resource(GameRng::new(replay.seed))
+                        .insert_resource(ReplayPlayback::new(replay));
+                }
+                Err(err) => error!("🎬could not load replay {:?}: {}", path, err),
+            },
+            None => {}
+        }
+        app.add_systems(
+            First,
+            play_back_frame_time
+                .before(TimeSystem)
+                .run_if(resource_exists::<ReplayPlayback>()),
+        )
+        .add_systems(
+            PreUpdate,
+            (
+                record_frame.run_if(resource_exists::<ReplayRecording>()),
+                play_back_keys.run_if(resource_exists::<ReplayPlayback>()),
+            )
+                .after(InputSystem)
+                .before(read_move_input),
+        )
+        .add_systems(
+            Last,
+            write_replay_on_exit.run_if(resource_exists::<ReplayRecording>()),
+        );
+    }
+}
+
+/// What the command line asks to do with a replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayArg {
+    /// Record this run to the file.
+    Record(PathBuf),
+    /// Play back the run recorded in the file.
+    Play(PathBuf),
+}
+
+/// Finds a `--record=<path>` or `--replay=<path>` argument.
+///
+/// # Returns
+/// What the last such argument asks for, or `None` if there isn't one.
+pub fn replay_from_args(args: impl IntoIterator<Item = String>) -> Option<ReplayArg> {
+    args.into_iter()
+        .filter_map(|arg| {
+            if let Some(path) = arg.strip_prefix("--record=") {
+                Some(ReplayArg::Record(PathBuf::from(path)))
+            } else {
+                arg.strip_prefix("--replay=")
+                    .map(|path| ReplayArg::Play(PathBuf::from(path)))
+            }
+        })
+        .last()
+}
+
+/// A recorded run: the seed it was played with and its input, frame by frame.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// One frame of a recorded run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Real time the frame took, before any `TimeScale`.
+    pub delta: Duration,
+    /// Keys held during the frame, in order.
+    pub keys: Vec<KeyCode>,
+}
+
+/// The run being recorded and the file it's written to.
+#[derive(Resource, Debug)]
+pub struct ReplayRecording {
+    path: PathBuf,
+    replay: Replay,
+}
+
+/// The run being played back and how far through it playback is.
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    /// Index of the next frame to play.
+    frame: usize,
+    /// Keys held in the last frame played.
+    previous: Vec<KeyCode>,
+}
+
+impl ReplayPlayback {
+    /// Starts playing `replay` from its first frame.
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayback {
+            replay,
+            frame: 0,
+            previous: Vec::new(),
+        }
+    }
+}
+
+/// Loads a recorded run from `path`.
+pub fn load_replay(path: &Path) -> io::Result<Replay> {
+    let contents = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(io::Error::from)
+}
+
+/// Writes a recorded run to `path`, replacing what was there.
+pub fn save_replay(path: &Path, replay: &Replay) -> io::Result<()> {
+    let contents = serde_json::to_string(replay).map_err(io::Error::from)?;
+    fs::write(path, contents)
+}
+
+/// Records this frame's length and the keys held.
+fn record_frame(
+    time: Res<Time>,
+    input_res: Res<Input<KeyCode>>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    let mut keys: Vec<KeyCode> = input_res.get_pressed().copied().collect();
+    keys.sort();
+    recording.replay.frames.push(ReplayFrame {
+        delta: time.raw_delta(),
+        keys,
+    });
+}
+
+/// Writes the recording, with the seed it was played with, when the game exits.
+fn write_replay_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    rng: Res<GameRng>,
+    mut recording: ResMut<ReplayRecording>,
+) {
+    if exit_events.iter().last().is_none() {
+        return;
+    }
+    recording.replay.seed = rng.seed();
+    match save_replay(&recording.path, &recording.replay) {
+        Ok(()) => info!(
+            "🎬saved {} frames to {:?}",
+            recording.replay.frames.len(),
+            recording.path
+        ),
+        Err(err) => error!("🎬could not save replay {:?}: {}", recording.path, err),
+    }
+}
+
+/// Runs the clock at the next recorded frame's length, handing it back to the
+/// wall clock once the recording runs out.
+fn play_back_frame_time(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+) {
+    match playback.replay.frames.get(playback.frame) {
+        Some(frame) => *time_update_strategy = TimeUpdateStrategy::ManualDuration(frame.delta),
+        None => {
+            info!("🎬replay finished after {} frames", playback.frame);
+            *time_update_strategy = TimeUpdateStrategy::Automatic;
+            commands.remove_resource::<ReplayPlayback>();
+        }
+    }
+}
+
+/// Replaces the keyboard with the keys recorded for this frame.
+///
+/// Keys are pressed and let go against the last frame played, so `just_pressed`
+/// and `just_released` come out as they did in the recorded run.
+fn play_back_keys(mut playback: ResMut<ReplayPlayback>, mut input_res: ResMut<Input<KeyCode>>) {
+    let Some(frame) = playback.replay.frames.get(playback.frame) else {
+        return;
+    };
+    let keys = frame.keys.clone();
+    input_res.reset_all();
+    for &key in keys.iter() {
+        input_res.press(key);
+        if playback.previous.contains(&key) {
+            input_res.clear_just_pressed(key);
+        }
+    }
+    for &key in playback.previous.iter().filter(|key| !keys.contains(key)) {
+        input_res.press(key);
+        input_res.release(key);
+        input_res.clear_just_pressed(key);
+    }
+    playback.previous = keys;
+    playback.frame += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::Harness;
+    use bevy_ecs_ldtk::prelude::*;
+
+    #[test]
+    fn test_replay_from_args() {
+        let args = |args: &[&str]| replay_from_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(args(&["game"]), None);
+        assert_eq!(
+            args(&["game", "--record=run.json"]),
+            Some(ReplayArg::Record(PathBuf::from("run.json")))
+        );
+        assert_eq!(
+            args(&["game", "--record=a.json", "--replay=b.json"]),
+            Some(ReplayArg::Play(PathBuf::from("b.json")))
+        );
+    }
+
+    #[test]
+    fn test_replay_reproduces_run() {
+        let path = std::env::temp_dir().join(format!(
+            "exterminator_wizard-{}-replay.json",
+            std::process::id()
+        ));
+        let start = GridCoords::new(1, 1);
+        // A wall in the way, so the run depends on when each key was held
+        let walls = [(3, 2)];
+
+        let mut recorder = Harness::new(&walls, 8, 8, start);
+        recorder
+            .app
+            .insert_resource(GameRng::new(7))
+            .insert_resource(ReplayRecording {
+                path: path.clone(),
+                replay: Replay::default(),
+            })
+            .add_systems(PreUpdate, record_frame.before(read_move_input))
+            .add_systems(Last, write_replay_on_exit);
+        recorder.hold(&[KeyCode::D], Harness::frames_to_walk(1));
+        recorder.step(3);
+        recorder.hold(&[KeyCode::W], Harness::frames_to_walk(2));
+        recorder.hold(&[KeyCode::D, KeyCode::W], Harness::frames_to_walk(2));
+        recorder.step(5);
+        recorder.app.world.send_event(AppExit);
+        recorder.step(1);
+        let recorded = recorder.player_coords();
+        assert_ne!(recorded, start);
+
+        let replay = load_replay(&path).unwrap();
+        assert_eq!(replay.seed, 7);
+        let frames = replay.frames.len();
+        let mut player = Harness::new(&walls, 8, 8, start);
+        player
+            .app
+            .insert_resource(ReplayPlayback::new(replay))
+            .add_systems(
+                First,
+                play_back_frame_time
+                    .before(TimeSystem)
+                    .run_if(resource_exists::<ReplayPlayback>()),
+            )
+            .add_systems(
+                PreUpdate,
+                play_back_keys
+                    .run_if(resource_exists::<ReplayPlayback>())
+                    .before(read_move_input),
+            );
+        // No keys are pressed here; everything comes from the recording
+        player.step(frames);
+        assert_eq!(player.player_coords(), recorded);
+        player.step(1);
+        assert!(!player.app.world.contains_resource::<ReplayPlayback>());
+        fs::remove_file(&path).unwrap();
+    }
+}
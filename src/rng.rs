@@ -0,0 +1,131 @@
+// rng.rs
+
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::components::*;
+use crate::settings::GameConfig;
+
+/// RngPlugin inserts the `GameRng` every random gameplay decision draws from.
+///
+/// The seed is read from a `--seed=<number>` command-line argument, falling back to
+/// the one saved in the `GameConfig`, then to a fresh random seed. The seed is
+/// logged, so any run can be played again by passing it back in.
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let saved = app
+            .world
+            .get_resource::<GameConfig>()
+            .and_then(|config| config.seed);
+        let seed = seed_from_args(std::env::args())
+            .or(saved)
+            .unwrap_or_else(rand::random);
+        let rng = GameRng::new(seed);
+        info!("🎲seed: {}", rng.seed());
+        app.insert_resource(rng);
+    }
+}
+
+/// Seeded random number generator for gameplay, so runs with the same seed play
+/// out the same. Use it as any `rand::Rng`.
+///
+/// It's backed by ChaCha8, whose output for a seed is fixed, unlike `StdRng`'s, which
+/// may change between `rand` releases and break saved seeds and replays.
+#[derive(Resource, Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl GameRng {
+    /// Creates a generator starting from `seed`.
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed the generator started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Finds a `--seed=<number>` argument.
+///
+/// # Returns
+/// The seed given by the last such argument, or `None` if there isn't a valid one.
+pub fn seed_from_args(args: impl IntoIterator<Item = String>) -> Option<u64> {
+    args.into_iter()
+        .filter_map(|arg| arg.strip_prefix("--seed=")?.parse().ok())
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// Numbers drawn by `draw`, in order.
+    #[derive(Resource, Default)]
+    struct Drawn(Vec<u32>);
+
+    /// Draws a number from the app's `GameRng` each frame.
+    fn draw(mut rng: ResMut<GameRng>, mut drawn: ResMut<Drawn>) {
+        drawn.0.push(rng.gen_range(0..1000));
+    }
+
+    /// Runs an app seeded with `seed` for 20 frames, returning what it drew.
+    fn drawn_with_seed(seed: u64) -> Vec<u32> {
+        let mut app = App::new();
+        app.insert_resource(GameConfig {
+            seed: Some(seed),
+            ..default()
+        })
+        .add_plugins(RngPlugin)
+        .init_resource::<Drawn>()
+        .add_systems(Update, draw);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(app.world.resource::<GameRng>().seed(), seed);
+        app.world.resource::<Drawn>().0.clone()
+    }
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let first = drawn_with_seed(42);
+        assert_eq!(first.len(), 20);
+        assert_eq!(drawn_with_seed(42), first);
+        assert_ne!(drawn_with_seed(43), first);
+    }
+
+    #[test]
+    fn test_seed_from_args() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(seed_from_args(args(&["game", "--seed=7"])), Some(7));
+        assert_eq!(seed_from_args(args(&["--seed=7", "--seed=9"])), Some(9));
+        assert_eq!(seed_from_args(args(&["--seed=seven"])), None);
+        assert_eq!(seed_from_args(args(&["game"])), None);
+    }
+}
@@ -0,0 +1,693 @@
+// save.rs
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::enemy::enemy_stats;
+use crate::game_state::GameState;
+use crate::map::LevelWalls;
+use crate::player::{input_unlocked, Lives};
+use crate::score::Score;
+use crate::spell_fire::{ActiveSpell, Mana, SpellKind};
+
+/// SavePlugin saves the session to a save slot and restores it.
+///
+/// F6 writes the session to the `SaveSlot`, `SAVE_FILENAME` by default: the score,
+/// lives, active spell and mana, where each player stands and how hurt they are, and
+/// every living enemy with its position and health. F8 reads it back. The players
+/// are moved and healed in place, and the level's enemies are despawned and rebuilt
+/// from the save, set up the way `setup_enemies` would so the difficulty doesn't
+/// copy them again. Projectiles in flight aren't saved and are cleared on load, as
+/// are target dummies, which reset on their own anyway.
+///
+/// A save only loads into the level it was made in, and only if its `version`
+/// matches `SAVE_VERSION`; anything else is rejected with a warning and the session
+/// left as it is.
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlot>()
+            .init_resource::<EnemyAtlas>()
+            .add_event::<SessionLoaded>()
+            .add_systems(
+                Update,
+                (
+                    remember_enemy_atlas,
+                    (save_session, load_session)
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(input_unlocked),
+                    restore_session.after(load_session),
+                    rebuild_enemies.after(load_session),
+                ),
+            );
+    }
+}
+
+/// The file the session is saved to and loaded from.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct SaveSlot(pub PathBuf);
+
+impl Default for SaveSlot {
+    fn default() -> Self {
+        SaveSlot(PathBuf::from(SAVE_FILENAME))
+    }
+}
+
+/// Sprite sheet of the level's enemies, kept so enemies can be rebuilt from a save
+/// after the last of them has died.
+#[derive(Resource, Default)]
+struct EnemyAtlas(Option<Handle<TextureAtlas>>);
+
+/// Sent when a save has been read and is ready to be restored.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct SessionLoaded(pub SaveGame);
+
+/// Everything kept in a save slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    /// `SAVE_VERSION` of the game that wrote the save.
+    pub version: u32,
+    /// IID of the level the save was made in.
+    pub level_iid: String,
+    pub score: u32,
+    pub lives: u32,
+    pub active_spell: SpellKind,
+    pub mana: f32,
+    pub players: Vec<PlayerSave>,
+    pub enemies: Vec<EnemySave>,
+}
+
+/// A player as saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    /// Which player this is, going by `PlayerId`.
+    pub id: usize,
+    /// Translation relative to the level.
+    pub translation: Vec3,
+    pub cell: IVec2,
+    pub health: f32,
+}
+
+/// A living enemy as saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnemySave {
+    /// Translation relative to the level.
+    pub translation: Vec3,
+    pub cell: IVec2,
+    /// Cell the enemy patrols around.
+    pub origin: IVec2,
+    pub health: f32,
+    pub max_health: f32,
+    /// Frame of the enemies' sprite sheet the enemy is drawn with.
+    pub sprite_index: usize,
+    pub loot_chance: f32,
+    pub loot_value: u32,
+    /// Whether the enemy shoots instead of closing to melee.
+    pub ranged: bool,
+}
+
+/// Why a save couldn't be read.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The save isn't valid, or is missing fields this version needs.
+    Corrupt(serde_json::Error),
+    /// The save was written with a different `SAVE_VERSION`, given here.
+    Incompatible(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Corrupt(err) => write!(f, "corrupt save: {}", err),
+            SaveError::Incompatible(version) => write!(
+                f,
+                "save is from version {}, expected {}",
+                version, SAVE_VERSION
+            ),
+        }
+    }
+}
+
+/// Reads a save, checking its version before anything else.
+///
+/// # Returns
+/// The save, or why it was rejected.
+pub fn parse_save(contents: &str) -> Result<SaveGame, SaveError> {
+    #[derive(Deserialize)]
+    struct SaveVersion {
+        version: u32,
+    }
+
+    // A save from another version may not parse as this one, so look at the
+    // version alone first to tell that apart from a corrupt save
+    let SaveVersion { version } = serde_json::from_str(contents).map_err(SaveError::Corrupt)?;
+    if version != SAVE_VERSION {
+        return Err(SaveError::Incompatible(version));
+    }
+    serde_json::from_str(contents).map_err(SaveError::Corrupt)
+}
+
+/// Loads the save at `path`.
+///
+/// # Returns
+/// The save, or nothing if the file is missing, corrupt or from another version.
+pub fn load_game(path: &Path) -> Option<SaveGame> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            info!("💾no save at {:?}", path);
+            return None;
+        }
+        Err(err) => {
+            warn!("💾could not read {:?}: {}", path, err);
+            return None;
+        }
+    };
+    match parse_save(&contents) {
+        Ok(save) => Some(save),
+        Err(err) => {
+            warn!("💾ignoring {:?}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Writes `save` to `path`, replacing what was there.
+pub fn save_game(path: &Path, save: &SaveGame) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(save).map_err(io::Error::from)?;
+    fs::write(path, contents)
+}
+
+/// Finds the entity of the level being played.
+///
+/// # Arguments
+/// * `level_walls` - Resource giving the level being played.
+/// * `level_entities` - Query to access the spawned levels.
+/// * `level_assets` - Resource to look up each level's IID.
+fn current_level(
+    level_walls: &LevelWalls,
+    level_entities: &Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: &Assets<LdtkLevel>,
+) -> Option<Entity> {
+    level_entities.iter().find_map(|(entity, handle)| {
+        level_assets
+            .get(handle)
+            .filter(|ldtk_level| ldtk_level.level.iid == level_walls.level_iid())
+            .map(|_| entity)
+    })
+}
+
+/// Keeps hold of the enemies' sprite sheet as they're spawned.
+fn remember_enemy_atlas(
+    query: Query<&Handle<TextureAtlas>, (Added<Enemy>, Without<TargetDummy>)>,
+    mut enemy_atlas: ResMut<EnemyAtlas>,
+) {
+    if let Some(atlas) = query.iter().next() {
+        enemy_atlas.0 = Some(atlas.clone());
+    }
+}
+
+/// Saves the session to the save slot when F6 is pressed.
+///
+/// Only the enemies of the level being played are saved. Enemies in loaded
+/// neighbors are placed relative to their own level, and are left to it.
+///
+/// # Arguments
+/// * `input_res` - Resource giving the keys pressed this frame.
+/// * `slot` - Resource giving the file to save to.
+/// * `level_walls` - Resource giving the level being played.
+/// * `level_entities` - Query used to find the level being played.
+/// * `level_assets` - Resource to look up each level's IID.
+/// * `score` - Resource giving the score.
+/// * `lives` - Resource giving the lives left.
+/// * `active_spell` - Resource giving the spell being cast.
+/// * `mana` - Resource giving the mana left.
+/// * `player_query` - Query to access the players' positions and health.
+/// * `enemy_query` - Query to access the living enemies and their levels.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn save_session(
+    input_res: Res<Input<KeyCode>>,
+    slot: Res<SaveSlot>,
+    level_walls: Res<LevelWalls>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    score: Res<Score>,
+    lives: Res<Lives>,
+    active_spell: Res<ActiveSpell>,
+    mana: Res<Mana>,
+    player_query: Query<(Option<&PlayerId>, &Transform, &GridCoords, &Health), With<Player>>,
+    enemy_query: Query<
+        (
+            &Transform,
+            &GridCoords,
+            &Health,
+            &TextureAtlasSprite,
+            &LootDrop,
+            Option<&EnemyAi>,
+            Option<&RangedAttack>,
+            Option<&Parent>,
+        ),
+        (With<Enemy>, Without<TargetDummy>),
+    >,
+) {
+    if !input_res.just_pressed(KeyCode::F6) {
+        return;
+    }
+    let level = current_level(&level_walls, &level_entities, &level_assets);
+    let save = SaveGame {
+        version: SAVE_VERSION,
+        level_iid: level_walls.level_iid().to_string(),
+        score: score.0,
+        lives: lives.0,
+        active_spell: active_spell.0,
+        mana: mana.current,
+        players: player_query
+            .iter()
+            .map(|(id, transform, cell, health)| PlayerSave {
+                id: PlayerId::of(id).0,
+                translation: transform.translation,
+                cell: IVec2::from(*cell),
+                health: health.current,
+            })
+            .collect(),
+        enemies: enemy_query
+            .iter()
+            .filter(|(_, _, health, .., parent)| {
+                health.current > 0.0 && parent.map(|parent| parent.get()) == level
+            })
+            .map(
+                |(transform, cell, health, sprite, loot, ai, ranged, _)| EnemySave {
+                    translation: transform.translation,
+                    cell: IVec2::from(*cell),
+                    origin: IVec2::from(ai.map_or(*cell, |ai| ai.origin)),
+                    health: health.current,
+                    max_health: health.max,
+                    sprite_index: sprite.index,
+                    loot_chance: loot.chance,
+                    loot_value: loot.value,
+                    ranged: ranged.is_some(),
+                },
+            )
+            .collect(),
+    };
+    match save_game(&slot.0, &save) {
+        Ok(()) => info!(
+            "💾saved {} enemies in {} to {:?}",
+            save.enemies.len(),
+            save.level_iid,
+            slot.0
+        ),
+        Err(err) => error!("💾could not save to {:?}: {}", slot.0, err),
+    }
+}
+
+/// Reads the save slot when F8 is pressed, sending `SessionLoaded` if the save
+/// belongs to the level being played.
+fn load_session(
+    input_res: Res<Input<KeyCode>>,
+    slot: Res<SaveSlot>,
+    level_walls: Res<LevelWalls>,
+    mut loaded_events: EventWriter<SessionLoaded>,
+) {
+    if !input_res.just_pressed(KeyCode::F8) {
+        return;
+    }
+    let Some(save) = load_game(&slot.0) else {
+        return;
+    };
+    if save.level_iid != level_walls.level_iid() {
+        warn!(
+            "💾save is for level {}, not {}",
+            save.level_iid,
+            level_walls.level_iid()
+        );
+        return;
+    }
+    info!("💾loading {:?}", slot.0);
+    loaded_events.send(SessionLoaded(save));
+}
+
+/// Puts the score, lives, spell, mana and players back as they were saved.
+///
+/// Players missing from the save, like a co-op player who joined since, are left
+/// where they are.
+fn restore_session(
+    mut loaded_events: EventReader<SessionLoaded>,
+    mut score: ResMut<Score>,
+    mut lives: ResMut<Lives>,
+    mut active_spell: ResMut<ActiveSpell>,
+    mut mana: ResMut<Mana>,
+    mut player_query: Query<
+        (
+            Option<&PlayerId>,
+            &mut Transform,
+            &mut GridCoords,
+            &mut Health,
+            &mut Velocity2d,
+        ),
+        With<Player>,
+    >,
+) {
+    for SessionLoaded(save) in loaded_events.iter() {
+        score.0 = save.score;
+        lives.0 = save.lives;
+        active_spell.0 = save.active_spell;
+        mana.current = save.mana.min(mana.max);
+        for (id, mut transform, mut cell, mut health, mut velocity) in player_query.iter_mut() {
+            let Some(player) = save
+                .players
+                .iter()
+                .find(|player| player.id == PlayerId::of(id).0)
+            else {
+                continue;
+            };
+            transform.translation = player.translation;
+            *cell = GridCoords::from(player.cell);
+            health.current = player.health.min(health.max);
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+/// Replaces the level's enemies with the saved ones and clears projectiles in flight.
+///
+/// Enemies in loaded neighbors weren't saved, so they're left as they are.
+///
+/// # Arguments
+/// * `commands` - Commands to despawn the old enemies and spawn the saved ones.
+/// * `loaded_events` - Event reader for saves being loaded.
+/// * `difficulty` - Resource giving the contact damage the enemies deal.
+/// * `enemy_atlas` - Resource giving the enemies' sprite sheet.
+/// * `level_walls` - Resource giving the level being played.
+/// * `level_entities` - Query used to find the level the enemies belong to.
+/// * `level_assets` - Resource to look up each level's IID.
+/// * `enemy_query` - Query to access the enemies and their levels.
+/// * `projectile_query` - Query to access the projectiles in flight.
+///
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn rebuild_enemies(
+    mut commands: Commands,
+    mut loaded_events: EventReader<SessionLoaded>,
+    difficulty: Res<Difficulty>,
+    enemy_atlas: Res<EnemyAtlas>,
+    level_walls: Res<LevelWalls>,
+    level_entities: Query<(Entity, &Handle<LdtkLevel>)>,
+    level_assets: Res<Assets<LdtkLevel>>,
+    enemy_query: Query<(Entity, Option<&Parent>), (With<Enemy>, Without<TargetDummy>)>,
+    projectile_query: Query<Entity, Or<(With<SpellFire>, With<EnemyProjectile>)>>,
+) {
+    let Some(SessionLoaded(save)) = loaded_events.iter().last() else {
+        return;
+    };
+    let level_entity = current_level(&level_walls, &level_entities, &level_assets);
+    let level_enemies = enemy_query
+        .iter()
+        .filter(|(_, parent)| parent.map(|parent| parent.get()) == level_entity)
+        .map(|(entity, _)| entity);
+    for entity in level_enemies.chain(projectile_query.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    let texture_atlas = enemy_atlas.0.clone().unwrap_or_else(|| {
+        warn!("💾no enemy sprite sheet seen yet, restored enemies won't be drawn");
+        Handle::default()
+    });
+
+    for saved in save.enemies.iter() {
+        let (_, contact_damage) = enemy_stats(*difficulty);
+        let mut enemy = commands.spawn((
+            Enemy,
+            SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(saved.sprite_index),
+                texture_atlas: texture_atlas.clone(),
+                transform: Transform::from_translation(saved.translation),
+                ..default()
+            },
+            GridCoords::from(saved.cell),
+            LootDrop {
+                chance: saved.loot_chance,
+                value: saved.loot_value,
+            },
+            Health {
+                current: saved.health,
+                max: saved.max_health,
+            },
+            contact_damage,
+            EnemyState::default(),
+            EnemyAi::new(GridCoords::from(saved.origin)),
+            Interpolated::default(),
+            CollisionLayer::Enemy.groups(),
+        ));
+        if saved.ranged {
+            enemy.insert(RangedAttack::default());
+        }
+        if let Some(level_entity) = level_entity {
+            enemy.set_parent(level_entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in the temp directory unique to this test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "exterminator_wizard-{}-{}.json",
+            std::process::id(),
+            name
+        ))
+    }
+
+    /// Presses `key` for one frame.
+    fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().release(key);
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let path = temp_path("session");
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<LdtkLevel>()
+            .init_resource::<Input<KeyCode>>()
+            .insert_resource(SaveSlot(path.clone()))
+            .init_resource::<EnemyAtlas>()
+            .insert_resource(LevelWalls::from_cells(&[], 8, 8).with_level_iid("level-a"))
+            .init_resource::<Difficulty>()
+            .insert_resource(Score(120))
+            .insert_resource(Lives(2))
+            .insert_resource(ActiveSpell(SpellKind::Ice))
+            .init_resource::<Mana>()
+            .add_event::<SessionLoaded>()
+            .add_systems(
+                Update,
+                (
+                    save_session,
+                    load_session,
+                    restore_session.after(load_session),
+                    rebuild_enemies.after(load_session),
+                ),
+            );
+        app.world.resource_mut::<Mana>().current = 7.5;
+        let mut spawn_level = |level_iid: &str| {
+            let handle = app
+                .world
+                .resource_mut::<Assets<LdtkLevel>>()
+                .add(LdtkLevel {
+                    level: bevy_ecs_ldtk::ldtk::Level {
+                        iid: level_iid.to_string(),
+                        ..default()
+                    },
+                    background_image: None,
+                });
+            app.world.spawn((SpatialBundle::default(), handle)).id()
+        };
+        let level_a = spawn_level("level-a");
+        // A loaded neighbor with an enemy of its own, placed in its own cells
+        let level_b = spawn_level("level-b");
+        let neighbor_enemy = app
+            .world
+            .spawn((
+                Enemy,
+                SpriteSheetBundle {
+                    transform: Transform::from_xyz(8.0, 8.0, 0.0),
+                    ..default()
+                },
+                GridCoords::new(0, 0),
+                LootDrop::default(),
+                Health::default(),
+            ))
+            .set_parent(level_b)
+            .id();
+        let player = app
+            .world
+            .spawn((
+                Player,
+                Transform::from_xyz(24.0, 40.0, 0.0),
+                GridCoords::new(1, 2),
+                Health {
+                    current: 3.0,
+                    ..default()
+                },
+                Velocity2d::default(),
+            ))
+            .id();
+        app.world
+            .spawn((
+                Enemy,
+                SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(5),
+                    transform: Transform::from_xyz(88.0, 56.0, 0.0),
+                    ..default()
+                },
+                GridCoords::new(5, 3),
+                LootDrop::default(),
+                Health {
+                    current: 1.5,
+                    max: 4.0,
+                },
+                EnemyAi::new(GridCoords::new(4, 3)),
+                RangedAttack::default(),
+            ))
+            .set_parent(level_a);
+        let spell = app.world.spawn((SpellFire, Transform::default())).id();
+        app.update();
+        press(&mut app, KeyCode::F6);
+
+        // Only the level's own enemy is saved, not the neighbor's or the spell in flight
+        let save = load_game(&path).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.enemies.len(), 1);
+        assert_eq!(save.players.len(), 1);
+
+        // Play on: the enemy dies, the player moves and the score goes up
+        let enemies: Vec<Entity> = app
+            .world
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(&app.world)
+            .filter(|&enemy| enemy != neighbor_enemy)
+            .collect();
+        for enemy in enemies {
+            app.world.entity_mut(enemy).despawn_recursive();
+        }
+        app.world.get_mut::<Transform>(player).unwrap().translation = Vec3::ZERO;
+        *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(0, 0);
+        app.world.get_mut::<Health>(player).unwrap().current = 1.0;
+        app.world.resource_mut::<Score>().0 = 500;
+        app.world.resource_mut::<Lives>().0 = 0;
+        app.world.resource_mut::<ActiveSpell>().0 = SpellKind::Fire;
+        app.world.resource_mut::<Mana>().current = 0.0;
+        app.update();
+
+        press(&mut app, KeyCode::F8);
+        assert_eq!(app.world.resource::<Score>().0, 120);
+        assert_eq!(app.world.resource::<Lives>().0, 2);
+        assert_eq!(app.world.resource::<ActiveSpell>().0, SpellKind::Ice);
+        assert_eq!(app.world.resource::<Mana>().current, 7.5);
+        assert_eq!(
+            app.world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(24.0, 40.0, 0.0)
+        );
+        assert_eq!(
+            *app.world.get::<GridCoords>(player).unwrap(),
+            GridCoords::new(1, 2)
+        );
+        assert_eq!(app.world.get::<Health>(player).unwrap().current, 3.0);
+
+        let mut query = app.world.query_filtered::<(
+            &Transform,
+            &GridCoords,
+            &Health,
+            &TextureAtlasSprite,
+            &EnemyAi,
+            Option<&RangedAttack>,
+            &Parent,
+        ), With<Enemy>>();
+        let restored: Vec<_> = query
+            .iter(&app.world)
+            .filter(|(.., parent)| parent.get() == level_a)
+            .collect();
+        assert_eq!(restored.len(), 1);
+        let (transform, cell, health, sprite, ai, ranged, _) = restored[0];
+        assert_eq!(transform.translation, Vec3::new(88.0, 56.0, 0.0));
+        assert_eq!(*cell, GridCoords::new(5, 3));
+        assert_eq!(
+            *health,
+            Health {
+                current: 1.5,
+                max: 4.0
+            }
+        );
+        assert_eq!(sprite.index, 5);
+        assert_eq!(ai.origin, GridCoords::new(4, 3));
+        assert!(ranged.is_some());
+        // Projectiles in flight are cleared rather than restored
+        assert!(app.world.get_entity(spell).is_none());
+        // The neighbor's enemy is left where it was, in its own level
+        assert_eq!(
+            app.world.get::<Parent>(neighbor_enemy).unwrap().get(),
+            level_b
+        );
+        assert_eq!(
+            app.world
+                .get::<Transform>(neighbor_enemy)
+                .unwrap()
+                .translation,
+            Vec3::new(8.0, 8.0, 0.0)
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_incompatible_saves_rejected() {
+        let save = SaveGame {
+            version: SAVE_VERSION,
+            level_iid: "level-a".to_string(),
+            score: 10,
+            lives: 3,
+            active_spell: SpellKind::Homing,
+            mana: 20.0,
+            players: Vec::new(),
+            enemies: Vec::new(),
+        };
+        let contents = serde_json::to_string(&save).unwrap();
+        assert_eq!(parse_save(&contents).unwrap(), save);
+
+        let old = contents.replacen(
+            &format!("\"version\":{}", SAVE_VERSION),
+            &format!("\"version\":{}", SAVE_VERSION + 1),
+            1,
+        );
+        assert!(matches!(
+            parse_save(&old),
+            Err(SaveError::Incompatible(version)) if version == SAVE_VERSION + 1
+        ));
+        // An older layout missing fields is rejected on its version, not as corrupt
+        assert!(matches!(
+            parse_save(r#"{"version":0,"score":5}"#),
+            Err(SaveError::Incompatible(0))
+        ));
+        assert!(matches!(
+            parse_save("{ not json"),
+            Err(SaveError::Corrupt(_))
+        ));
+
+        let path = temp_path("incompatible");
+        fs::write(&path, old).unwrap();
+        assert!(load_game(&path).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+// score.rs
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::enemy::EnemyKilled;
+
+/// ScorePlugin keeps the score for the current run and the best score across runs.
+///
+/// The best score is loaded from `HIGH_SCORE_FILENAME` at startup and rewritten
+/// whenever the current score beats it.
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .add_event::<CollectiblePicked>()
+            .insert_resource(HighScore {
+                best: load_high_score(Path::new(HIGH_SCORE_FILENAME)),
+            })
+            .add_systems(
+                Update,
+                (
+                    score_kills,
+                    pick_up_collectibles,
+                    save_high_score_when_beaten
+                        .after(score_kills)
+                        .after(pick_up_collectibles),
+                ),
+            );
+    }
+}
+
+/// Points scored in the current run.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Score(pub u32);
+
+/// Sent when the player picks up a collectible.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectiblePicked {
+    pub collectible: Collectible,
+}
+
+/// The best score ever achieved.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub best: u32,
+}
+
+impl HighScore {
+    /// Records `score` if it beats the best.
+    ///
+    /// # Returns
+    /// `true` if the best score changed and should be saved.
+    pub fn record(&mut self, score: u32) -> bool {
+        if score <= self.best {
+            return false;
+        }
+        self.best = score;
+        true
+    }
+}
+
+/// Loads the best score from `path`.
+///
+/// # Returns
+/// The saved best score, or zero if the file is missing or corrupt.
+pub fn load_high_score(path: &Path) -> u32 {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return 0,
+        Err(err) => {
+            warn!("🏆could not read {:?}: {}", path, err);
+            return 0;
+        }
+    };
+    match serde_json::from_str::<HighScore>(&contents) {
+        Ok(high_score) => high_score.best,
+        Err(err) => {
+            warn!("🏆ignoring corrupt {:?}: {}", path, err);
+            0
+        }
+    }
+}
+
+/// Writes the best score to `path`, replacing what was there.
+pub fn save_high_score(path: &Path, high_score: &HighScore) -> io::Result<()> {
+    let contents = serde_json::to_string(high_score).map_err(io::Error::from)?;
+    fs::write(path, contents)
+}
+
+/// Adds `ENEMY_SCORE` for each enemy killed.
+fn score_kills(mut killed_events: EventReader<EnemyKilled>, mut score: ResMut<Score>) {
+    for _ in killed_events.iter() {
+        score.0 += ENEMY_SCORE;
+    }
+}
+
+/// Picks up collectibles in the player's cell, adding their value to the score.
+fn pick_up_collectibles(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    player_query: Query<&GridCoords, With<Player>>,
+    collectible_query: Query<(Entity, &GridCoords, &Collectible)>,
+    mut picked_events: EventWriter<CollectiblePicked>,
+) {
+    for player_coords in player_query.iter() {
+        for (entity, _, collectible) in collectible_query
+            .iter()
+            .filter(|(_, coords, _)| *coords == player_coords)
+        {
+            info!("🏆picked up {:?}", collectible);
+            score.0 += collectible.value;
+            commands.entity(entity).despawn_recursive();
+            picked_events.send(CollectiblePicked {
+                collectible: *collectible,
+            });
+        }
+    }
+}
+
+/// Saves the high score whenever the current score beats it.
+fn save_high_score_when_beaten(score: Res<Score>, mut high_score: ResMut<HighScore>) {
+    if !score.is_changed() || !high_score.record(score.0) {
+        return;
+    }
+    if let Err(err) = save_high_score(Path::new(HIGH_SCORE_FILENAME), &high_score) {
+        error!("🏆could not save high score: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A path in the temp directory unique to this test.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "exterminator_wizard-{}-{}.json",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_high_score_round_trip() {
+        let path = temp_path("round-trip");
+        save_high_score(&path, &HighScore { best: 1234 }).unwrap();
+        assert_eq!(load_high_score(&path), 1234);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_high_score_missing_or_corrupt() {
+        let path = temp_path("corrupt");
+        assert_eq!(load_high_score(&path), 0);
+
+        fs::write(&path, "{ not json").unwrap();
+        assert_eq!(load_high_score(&path), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_high_score_only_overwritten_when_higher() {
+        let mut high_score = HighScore { best: 500 };
+        assert!(!high_score.record(300));
+        assert!(!high_score.record(500));
+        assert_eq!(high_score.best, 500);
+        assert!(high_score.record(700));
+        assert_eq!(high_score.best, 700);
+    }
+
+    #[test]
+    fn test_score_kills() {
+        let mut app = App::new();
+        app.init_resource::<Score>()
+            .add_event::<EnemyKilled>()
+            .add_systems(Update, score_kills);
+        app.world.send_event(EnemyKilled {
+            enemy: Entity::PLACEHOLDER,
+        });
+        app.world.send_event(EnemyKilled {
+            enemy: Entity::PLACEHOLDER,
+        });
+        app.update();
+        assert_eq!(*app.world.resource::<Score>(), Score(2 * ENEMY_SCORE));
+    }
+
+    #[test]
+    fn test_pick_up_collectible() {
+        let mut app = App::new();
+        app.init_resource::<Score>()
+            .add_event::<CollectiblePicked>()
+            .add_systems(Update, pick_up_collectibles);
+        let coin = app
+            .world
+            .spawn((GridCoords::new(2, 1), Collectible { value: 50 }))
+            .id();
+        let player = app.world.spawn((Player, GridCoords::new(1, 1))).id();
+        app.update();
+        assert_eq!(*app.world.resource::<Score>(), Score(0));
+
+        *app.world.get_mut::<GridCoords>(player).unwrap() = GridCoords::new(2, 1);
+        app.update();
+        assert_eq!(*app.world.resource::<Score>(), Score(50));
+        assert!(app.world.get_entity(coin).is_none());
+    }
+}
@@ -0,0 +1,126 @@
+// screenshot.rs
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::window::PrimaryWindow;
+
+use crate::components::*;
+use crate::constants::*;
+
+/// ScreenshotPlugin saves the current frame to a timestamped PNG when F12 is pressed.
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, take_screenshot);
+    }
+}
+
+/// Builds the path of a screenshot taken at the given time.
+///
+/// # Arguments
+/// * `dir` - Directory the screenshot is saved to.
+/// * `unix_millis` - Milliseconds since the Unix epoch, in UTC.
+///
+/// # Returns
+/// `dir/screenshot-YYYYMMDD-HHMMSS-mmm.png`. The milliseconds keep screenshots
+/// taken within the same second apart.
+pub fn screenshot_path(dir: &Path, unix_millis: u128) -> PathBuf {
+    let secs = (unix_millis / 1000) as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let secs_of_day = secs.rem_euclid(86_400);
+    dir.join(format!(
+        "screenshot-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.png",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        unix_millis % 1000
+    ))
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+///
+/// Howard Hinnant's `civil_from_days` algorithm, for the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Saves the primary window's next frame to `SCREENSHOT_DIR` when F12 is pressed.
+///
+/// The directory is created if it doesn't exist.
+///
+/// # Arguments
+/// * `input_res` - Resource to get the current input state.
+/// * `window_query` - Query to find the primary window.
+/// * `screenshot_manager` - Bevy's screenshot API.
+///
+fn take_screenshot(
+    input_res: Res<Input<KeyCode>>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !input_res.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    let dir = Path::new(SCREENSHOT_DIR);
+    if let Err(err) = fs::create_dir_all(dir) {
+        error!("📷could not create {:?}: {}", dir, err);
+        return;
+    }
+    let unix_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = screenshot_path(dir, unix_millis);
+
+    match screenshot_manager.save_screenshot_to_disk(window, &path) {
+        Ok(()) => info!("📷saving screenshot to {:?}", path),
+        Err(err) => error!("📷could not take screenshot: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screenshot_path() {
+        let dir = Path::new(SCREENSHOT_DIR);
+        assert_eq!(
+            screenshot_path(dir, 0),
+            dir.join("screenshot-19700101-000000-000.png")
+        );
+        // 2024-02-29 13:45:07.089 UTC
+        assert_eq!(
+            screenshot_path(dir, 1_709_214_307_089),
+            dir.join("screenshot-20240229-134507-089.png")
+        );
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+}
@@ -0,0 +1,803 @@
+// settings.rs
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode, WindowResized};
+use serde::{Deserialize, Serialize};
+
+use crate::accessibility::AccessibilitySettings;
+use crate::components::*;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::hit_stop::HitStopSettings;
+use crate::physics::PhysicsSettings;
+use crate::player::{DebugSettings, MovementFeel, MovementMode, PlayerCollision};
+use crate::settings_menu::SettingsState;
+use crate::sound::AudioSettings;
+use crate::visuals::VisualSettings;
+
+/// SettingsPlugin keeps the player's `GameConfig` in sync with the game.
+///
+/// The config is loaded from `SETTINGS_FILENAME` in `main`, since the primary
+/// window is built from it, and saved again whenever it changes in game. Changes
+/// made in the settings menu are saved once it closes. F11 toggles fullscreen, F7
+/// cycles the `PresentModeSetting`, and the view is rescaled whenever the window
+/// is resized. With a `frame_cap` set, each frame is held back until its share of
+/// a second is up, so the game doesn't run the GPU flat out.
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .init_resource::<KeyBindings>()
+            .add_systems(
+                Update,
+                (
+                    toggle_fullscreen,
+                    cycle_present_mode,
+                    store_settings,
+                    save_config_when_changed
+                        .after(toggle_fullscreen)
+                        .after(cycle_present_mode)
+                        .after(store_settings)
+                        .run_if(in_state(SettingsState::Closed)),
+                    fit_view_to_window,
+                ),
+            )
+            .add_systems(Last, limit_frame_rate);
+    }
+}
+
+/// Settings chosen by the player, kept between runs.
+///
+/// Fields missing from the saved file take their default, so older files still load.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// Width of the window, in pixels.
+    pub window_width: f32,
+    /// Height of the window, in pixels.
+    pub window_height: f32,
+    /// Whether the game fills the screen instead of running in a window.
+    pub fullscreen: bool,
+    /// How finished frames are shown: waiting for vsync or not.
+    pub present_mode: PresentModeSetting,
+    /// Most frames drawn per second, or `None` to leave it to the `present_mode`.
+    pub frame_cap: Option<f32>,
+    /// Music and sound effect volumes.
+    pub audio: AudioSettings,
+    /// Bloom and tonemapping.
+    pub visuals: VisualSettings,
+    /// Colorblind palette.
+    pub accessibility: AccessibilitySettings,
+    /// Keys bound to each action.
+    pub keys: KeyBindings,
+    /// Movement keys of player two in local co-op.
+    pub coop_keys: CoopBindings,
+    /// Difficulty used when none is given on the command line.
+    pub difficulty: Difficulty,
+    /// Real-time or turn-based movement.
+    pub movement_mode: MovementMode,
+    /// How the player speeds up and slows down.
+    pub movement_feel: MovementFeel,
+    /// Whether walls stop the player by grid cell or by Rapier collider.
+    pub player_collision: PlayerCollision,
+    /// How long big hits freeze the game.
+    pub hit_stop: HitStopSettings,
+    /// Gravity and pixels per meter.
+    pub physics: PhysicsSettings,
+    /// Debug tools, like no-clip, that are off in normal play.
+    pub debug: DebugSettings,
+    /// Seed for gameplay randomness when none is given on the command line, or
+    /// `None` for a fresh one each run.
+    pub seed: Option<u64>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            window_width: WINDOW_WIDTH,
+            window_height: WINDOW_HEIGHT,
+            fullscreen: false,
+            present_mode: PresentModeSetting::default(),
+            frame_cap: None,
+            audio: AudioSettings::default(),
+            visuals: VisualSettings::default(),
+            accessibility: AccessibilitySettings::default(),
+            keys: KeyBindings::default(),
+            coop_keys: CoopBindings::default(),
+            difficulty: Difficulty::default(),
+            movement_mode: MovementMode::default(),
+            movement_feel: MovementFeel::default(),
+            player_collision: PlayerCollision::default(),
+            hit_stop: HitStopSettings::default(),
+            physics: PhysicsSettings::default(),
+            debug: DebugSettings::default(),
+            seed: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// The window mode matching the `fullscreen` flag.
+    pub fn window_mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+
+    /// Builds the primary window from the config.
+    ///
+    /// A resolution that isn't positive falls back to `WINDOW_WIDTH` by `WINDOW_HEIGHT`.
+    pub fn window(&self) -> Window {
+        let (width, height) = if self.window_width > 0.0 && self.window_height > 0.0 {
+            (self.window_width, self.window_height)
+        } else {
+            (WINDOW_WIDTH, WINDOW_HEIGHT)
+        };
+        Window {
+            title: format!(
+                "Exterminator Wizard v{} - ajw@ajw.io",
+                env!("CARGO_PKG_VERSION")
+            ),
+            resolution: (width, height).into(),
+            mode: self.window_mode(),
+            present_mode: self.present_mode.present_mode(),
+            resizable: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// How the window shows finished frames.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentModeSetting {
+    /// Waits for vsync, so the frame rate matches the display and nothing tears.
+    #[default]
+    Fifo,
+    /// Shows frames as soon as they're done, for the lowest latency, tearing and all.
+    Immediate,
+    /// Keeps drawing, but only shows the latest frame at vsync: low latency without
+    /// tearing, where the display supports it.
+    Mailbox,
+}
+
+impl PresentModeSetting {
+    /// The window's `PresentMode` for this setting.
+    ///
+    /// `Immediate` and `Mailbox` fall back to vsync where the display can't do them.
+    pub fn present_mode(self) -> PresentMode {
+        match self {
+            PresentModeSetting::Fifo => PresentMode::AutoVsync,
+            PresentModeSetting::Immediate => PresentMode::AutoNoVsync,
+            PresentModeSetting::Mailbox => PresentMode::Mailbox,
+        }
+    }
+
+    /// The next setting, wrapping from the last back to the first.
+    pub fn next(self) -> Self {
+        match self {
+            PresentModeSetting::Fifo => PresentModeSetting::Immediate,
+            PresentModeSetting::Immediate => PresentModeSetting::Mailbox,
+            PresentModeSetting::Mailbox => PresentModeSetting::Fifo,
+        }
+    }
+}
+
+/// How long a frame takes at `frame_cap` frames per second.
+///
+/// # Returns
+/// The frame time, or `None` when there's no cap or it isn't a positive number.
+pub fn frame_time(frame_cap: Option<f32>) -> Option<Duration> {
+    frame_cap
+        .filter(|cap| cap.is_finite() && *cap > 0.0)
+        .map(|cap| Duration::from_secs_f32(1.0 / cap))
+}
+
+/// Something the player does with a key that can be rebound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    CastUp,
+    CastDown,
+    CastLeft,
+    CastRight,
+    Interact,
+    ToggleInspector,
+    RestartLevel,
+}
+
+impl Action {
+    /// Every action, in the order the settings menu lists them.
+    pub const ALL: [Action; 11] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::CastUp,
+        Action::CastDown,
+        Action::CastLeft,
+        Action::CastRight,
+        Action::Interact,
+        Action::ToggleInspector,
+        Action::RestartLevel,
+    ];
+
+    /// Name shown for the action in the settings menu.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::CastUp => "Cast up",
+            Action::CastDown => "Cast down",
+            Action::CastLeft => "Cast left",
+            Action::CastRight => "Cast right",
+            Action::Interact => "Interact",
+            Action::ToggleInspector => "Inspector",
+            Action::RestartLevel => "Restart level",
+        }
+    }
+}
+
+/// The key bound to each `Action`.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_up: KeyCode,
+    pub move_down: KeyCode,
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+    pub cast_up: KeyCode,
+    pub cast_down: KeyCode,
+    pub cast_left: KeyCode,
+    pub cast_right: KeyCode,
+    pub interact: KeyCode,
+    pub toggle_inspector: KeyCode,
+    pub restart_level: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_up: KeyCode::W,
+            move_down: KeyCode::S,
+            move_left: KeyCode::A,
+            move_right: KeyCode::D,
+            cast_up: KeyCode::Up,
+            cast_down: KeyCode::Down,
+            cast_left: KeyCode::Left,
+            cast_right: KeyCode::Right,
+            interact: KeyCode::E,
+            // Out of the way, leaving Escape for menus
+            toggle_inspector: KeyCode::Grave,
+            restart_level: KeyCode::R,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The field holding `action`'s key.
+    fn slot(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::MoveUp => &mut self.move_up,
+            Action::MoveDown => &mut self.move_down,
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::CastUp => &mut self.cast_up,
+            Action::CastDown => &mut self.cast_down,
+            Action::CastLeft => &mut self.cast_left,
+            Action::CastRight => &mut self.cast_right,
+            Action::Interact => &mut self.interact,
+            Action::ToggleInspector => &mut self.toggle_inspector,
+            Action::RestartLevel => &mut self.restart_level,
+        }
+    }
+
+    /// The key bound to `action`.
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::MoveUp => self.move_up,
+            Action::MoveDown => self.move_down,
+            Action::MoveLeft => self.move_left,
+            Action::MoveRight => self.move_right,
+            Action::CastUp => self.cast_up,
+            Action::CastDown => self.cast_down,
+            Action::CastLeft => self.cast_left,
+            Action::CastRight => self.cast_right,
+            Action::Interact => self.interact,
+            Action::ToggleInspector => self.toggle_inspector,
+            Action::RestartLevel => self.restart_level,
+        }
+    }
+
+    /// The movement keys, in the order up, left, down, right.
+    pub fn move_keys(&self) -> [KeyCode; 4] {
+        [
+            self.move_up,
+            self.move_left,
+            self.move_down,
+            self.move_right,
+        ]
+    }
+
+    /// Binds `key` to `action`.
+    ///
+    /// An action already using `key` takes over `action`'s old key, so no key is
+    /// ever bound twice.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        let old_key = self.key(action);
+        if let Some(other) = Action::ALL
+            .into_iter()
+            .find(|&other| other != action && self.key(other) == key)
+        {
+            *self.slot(other) = old_key;
+        }
+        *self.slot(action) = key;
+    }
+}
+
+/// The keys player two moves with in local co-op. Player one's keys, and every
+/// other action, are in `KeyBindings`. A key can't be in both: a held key would
+/// move both players, or cast as it moves player two.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoopBindings {
+    pub move_up: KeyCode,
+    pub move_down: KeyCode,
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+}
+
+impl Default for CoopBindings {
+    fn default() -> Self {
+        CoopBindings {
+            move_up: KeyCode::I,
+            move_down: KeyCode::K,
+            move_left: KeyCode::J,
+            move_right: KeyCode::L,
+        }
+    }
+}
+
+impl CoopBindings {
+    /// The movement keys, in the order up, left, down, right.
+    pub fn move_keys(&self) -> [KeyCode; 4] {
+        [
+            self.move_up,
+            self.move_left,
+            self.move_down,
+            self.move_right,
+        ]
+    }
+
+    /// Finds player two's keys that are already taken.
+    ///
+    /// # Returns
+    /// The keys bound to an action in `keys`, or to more than one of player two's
+    /// directions, in order and each once. Empty when the bindings can be used.
+    pub fn conflicts(&self, keys: &KeyBindings) -> Vec<KeyCode> {
+        let move_keys = self.move_keys();
+        let mut conflicts: Vec<KeyCode> = move_keys
+            .iter()
+            .enumerate()
+            .filter(|&(index, key)| {
+                Action::ALL
+                    .into_iter()
+                    .any(|action| keys.key(action) == *key)
+                    || move_keys[..index].contains(key)
+            })
+            .map(|(_, &key)| key)
+            .collect();
+        conflicts.sort();
+        conflicts.dedup();
+        conflicts
+    }
+}
+
+/// Loads the config from `path`.
+///
+/// Co-op keys that clash with `KeyBindings` are rejected for the default ones.
+///
+/// # Returns
+/// The saved config, or the default if the file is missing or corrupt.
+pub fn load_config(path: &Path) -> GameConfig {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return GameConfig::default(),
+        Err(err) => {
+            warn!("⚙️could not read {:?}: {}", path, err);
+            return GameConfig::default();
+        }
+    };
+    match serde_json::from_str::<GameConfig>(&contents) {
+        Ok(mut config) => {
+            let conflicts = config.coop_keys.conflicts(&config.keys);
+            if !conflicts.is_empty() {
+                warn!(
+                    "⚙️ignoring co-op keys in {:?}, {:?} already bound",
+                    path, conflicts
+                );
+                config.coop_keys = CoopBindings::default();
+            }
+            config
+        }
+        Err(err) => {
+            warn!("⚙️ignoring corrupt {:?}: {}", path, err);
+            GameConfig::default()
+        }
+    }
+}
+
+/// Writes the config to `path`, replacing what was there.
+pub fn save_config(path: &Path, config: &GameConfig) -> io::Result<()> {
+    let contents = serde_json::to_string_pretty(config).map_err(io::Error::from)?;
+    fs::write(path, contents)
+}
+
+/// Switches the primary window between fullscreen and windowed when F11 is pressed.
+fn toggle_fullscreen(
+    input_res: Res<Input<KeyCode>>,
+    mut config: ResMut<GameConfig>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_res.just_pressed(KeyCode::F11) {
+        return;
+    }
+    config.fullscreen = !config.fullscreen;
+    info!("⚙️fullscreen: {}", config.fullscreen);
+    for mut window in window_query.iter_mut() {
+        window.mode = config.window_mode();
+    }
+}
+
+/// Cycles the primary window's present mode when F7 is pressed.
+fn cycle_present_mode(
+    input_res: Res<Input<KeyCode>>,
+    mut config: ResMut<GameConfig>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_res.just_pressed(KeyCode::F7) {
+        return;
+    }
+    config.present_mode = config.present_mode.next();
+    info!("⚙️present mode: {:?}", config.present_mode);
+    for mut window in window_query.iter_mut() {
+        window.present_mode = config.present_mode.present_mode();
+    }
+}
+
+/// Sleeps out the rest of the frame when it finished early for the `frame_cap`.
+///
+/// # Arguments
+/// * `config` - Resource holding the frame cap.
+/// * `frame_end` - When the last frame was let through.
+///
+fn limit_frame_rate(config: Res<GameConfig>, mut frame_end: Local<Option<Instant>>) {
+    let Some(frame_time) = frame_time(config.frame_cap) else {
+        *frame_end = None;
+        return;
+    };
+    if let Some(remaining) = frame_end.and_then(|end| frame_time.checked_sub(end.elapsed())) {
+        std::thread::sleep(remaining);
+    }
+    *frame_end = Some(Instant::now());
+}
+
+/// Camera scale showing the same height of the world as the default window does.
+///
+/// # Arguments
+/// * `window_height` - Height of the window, in logical pixels.
+///
+/// # Returns
+/// The orthographic projection scale, or `CAMERA_SCALE` for a window with no height.
+pub fn view_scale(window_height: f32) -> f32 {
+    if window_height <= 0.0 {
+        return CAMERA_SCALE;
+    }
+    CAMERA_SCALE * WINDOW_HEIGHT / window_height
+}
+
+/// UI scale keeping the HUD the same size relative to the window as in the default window.
+///
+/// # Returns
+/// The UI scale, or 1.0 for a window with no height.
+pub fn ui_scale(window_height: f32) -> f64 {
+    if window_height <= 0.0 {
+        return 1.0;
+    }
+    (window_height / WINDOW_HEIGHT) as f64
+}
+
+/// Rescales the camera and UI when the primary window is resized.
+///
+/// The camera keeps the same amount of the world in view, and the UI grows and
+/// shrinks with the window. The HUD and minimap are anchored to their corners, so
+/// they follow the window's edges.
+fn fit_view_to_window(
+    mut resize_events: EventReader<WindowResized>,
+    window_query: Query<(), With<PrimaryWindow>>,
+    mut camera_query: Query<&mut OrthographicProjection, With<Camera2d>>,
+    mut ui_scale_res: ResMut<UiScale>,
+) {
+    let Some(resized) = resize_events
+        .iter()
+        .filter(|event| window_query.contains(event.window))
+        .last()
+    else {
+        return;
+    };
+    info!("🖥️window resized to {}x{}", resized.width, resized.height);
+    for mut projection in camera_query.iter_mut() {
+        projection.scale = view_scale(resized.height);
+    }
+    ui_scale_res.scale = ui_scale(resized.height);
+}
+
+/// Copies changed key bindings, difficulty and movement mode into the `GameConfig`,
+/// which saves them.
+fn store_settings(
+    keys: Res<KeyBindings>,
+    difficulty: Res<Difficulty>,
+    movement_mode: Res<MovementMode>,
+    mut config: ResMut<GameConfig>,
+) {
+    if keys.is_changed() && !keys.is_added() && config.keys != *keys {
+        config.keys = keys.clone();
+    }
+    if difficulty.is_changed() && !difficulty.is_added() && config.difficulty != *difficulty {
+        config.difficulty = *difficulty;
+    }
+    if movement_mode.is_changed()
+        && !movement_mode.is_added()
+        && config.movement_mode != *movement_mode
+    {
+        config.movement_mode = *movement_mode;
+    }
+}
+
+/// Saves the config whenever it's changed in game.
+fn save_config_when_changed(config: Res<GameConfig>) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    if let Err(err) = save_config(Path::new(SETTINGS_FILENAME), &config) {
+        error!("⚙️could not save settings: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::window::WindowResolution;
+
+    use super::*;
+
+    #[test]
+    fn test_present_mode_setting() {
+        assert_eq!(PresentModeSetting::default(), PresentModeSetting::Fifo);
+        assert_eq!(
+            PresentModeSetting::Fifo.present_mode(),
+            PresentMode::AutoVsync
+        );
+        assert_eq!(
+            PresentModeSetting::Immediate.present_mode(),
+            PresentMode::AutoNoVsync
+        );
+        assert_eq!(
+            PresentModeSetting::Mailbox.present_mode(),
+            PresentMode::Mailbox
+        );
+        let window = GameConfig {
+            present_mode: PresentModeSetting::Mailbox,
+            ..default()
+        }
+        .window();
+        assert_eq!(window.present_mode, PresentMode::Mailbox);
+
+        // F7 comes back round to vsync
+        let mut mode = PresentModeSetting::Fifo;
+        for _ in 0..3 {
+            mode = mode.next();
+        }
+        assert_eq!(mode, PresentModeSetting::Fifo);
+    }
+
+    #[test]
+    fn test_frame_time() {
+        assert_eq!(frame_time(Some(4.0)), Some(Duration::from_millis(250)));
+        assert_eq!(frame_time(None), None);
+        assert_eq!(frame_time(Some(0.0)), None);
+        assert_eq!(frame_time(Some(-30.0)), None);
+        assert_eq!(frame_time(Some(f32::INFINITY)), None);
+    }
+
+    #[test]
+    fn test_config_to_window() {
+        let window = GameConfig::default().window();
+        assert_eq!(
+            window.resolution,
+            WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT)
+        );
+        assert_eq!(window.mode, WindowMode::Windowed);
+        // Vsync unless asked otherwise
+        assert_eq!(window.present_mode, PresentMode::AutoVsync);
+
+        let window = GameConfig {
+            window_width: 1920.0,
+            window_height: 1080.0,
+            fullscreen: true,
+            ..default()
+        }
+        .window();
+        assert_eq!(window.resolution.width(), 1920.0);
+        assert_eq!(window.resolution.height(), 1080.0);
+        assert_eq!(window.mode, WindowMode::BorderlessFullscreen);
+
+        let window = GameConfig {
+            window_width: 0.0,
+            ..default()
+        }
+        .window();
+        assert_eq!(window.resolution.width(), WINDOW_WIDTH);
+        assert_eq!(window.resolution.height(), WINDOW_HEIGHT);
+    }
+
+    #[test]
+    fn test_view_scale_keeps_world_in_view() {
+        assert_eq!(view_scale(WINDOW_HEIGHT), CAMERA_SCALE);
+        assert_eq!(ui_scale(WINDOW_HEIGHT), 1.0);
+
+        // Going from the old size to the new one shows the same height of the world
+        for (old_height, new_height) in [(720.0, 1080.0), (1080.0, 480.0), (600.0, 600.0)] {
+            assert_eq!(
+                view_scale(old_height) * old_height,
+                view_scale(new_height) * new_height
+            );
+        }
+        assert_eq!(view_scale(1440.0), CAMERA_SCALE / 2.0);
+        assert_eq!(ui_scale(1440.0), 2.0);
+
+        // A minimised window has no height
+        assert_eq!(view_scale(0.0), CAMERA_SCALE);
+        assert_eq!(ui_scale(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_resize_rescales_camera_and_ui() {
+        let mut app = App::new();
+        app.add_event::<WindowResized>()
+            .init_resource::<UiScale>()
+            .add_systems(Update, fit_view_to_window);
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        let other_window = app.world.spawn(Window::default()).id();
+        let camera = app
+            .world
+            .spawn((Camera2d::default(), OrthographicProjection::default()))
+            .id();
+
+        // Other windows are ignored
+        app.world.send_event(WindowResized {
+            window: other_window,
+            width: 100.0,
+            height: 100.0,
+        });
+        app.update();
+        assert_eq!(
+            app.world
+                .get::<OrthographicProjection>(camera)
+                .unwrap()
+                .scale,
+            1.0
+        );
+
+        app.world.send_event(WindowResized {
+            window,
+            width: 2560.0,
+            height: 1440.0,
+        });
+        app.update();
+        assert_eq!(
+            app.world
+                .get::<OrthographicProjection>(camera)
+                .unwrap()
+                .scale,
+            view_scale(1440.0)
+        );
+        assert_eq!(app.world.resource::<UiScale>().scale, 2.0);
+    }
+
+    #[test]
+    fn test_config_round_trip_and_defaults() {
+        let path = std::env::temp_dir().join(format!(
+            "exterminator_wizard-{}-settings.json",
+            std::process::id()
+        ));
+        assert_eq!(load_config(&path), GameConfig::default());
+
+        let config = GameConfig {
+            fullscreen: true,
+            ..default()
+        };
+        save_config(&path, &config).unwrap();
+        assert_eq!(load_config(&path), config);
+
+        // Missing fields take their default
+        fs::write(&path, r#"{ "window_width": 800.0 }"#).unwrap();
+        assert_eq!(
+            load_config(&path),
+            GameConfig {
+                window_width: 800.0,
+                ..default()
+            }
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bind_swaps_taken_key() {
+        let mut keys = KeyBindings::default();
+        keys.bind(Action::MoveUp, KeyCode::I);
+        assert_eq!(keys.key(Action::MoveUp), KeyCode::I);
+
+        // D already moves right, so moving right takes over the old I
+        keys.bind(Action::MoveUp, KeyCode::D);
+        assert_eq!(keys.key(Action::MoveUp), KeyCode::D);
+        assert_eq!(keys.key(Action::MoveRight), KeyCode::I);
+        assert_eq!(keys.key(Action::MoveLeft), KeyCode::A);
+    }
+
+    #[test]
+    fn test_coop_keys_overlapping_key_bindings_rejected() {
+        let keys = KeyBindings::default();
+        assert!(CoopBindings::default().conflicts(&keys).is_empty());
+        // Up casts for player one, and J is used for two directions
+        let coop_keys = CoopBindings {
+            move_up: KeyCode::Up,
+            move_left: KeyCode::J,
+            move_down: KeyCode::J,
+            move_right: KeyCode::E,
+        };
+        assert_eq!(
+            coop_keys.conflicts(&keys),
+            vec![KeyCode::E, KeyCode::J, KeyCode::Up]
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "exterminator_wizard-{}-coop-settings.json",
+            std::process::id()
+        ));
+        let config = GameConfig {
+            coop_keys,
+            ..default()
+        };
+        save_config(&path, &config).unwrap();
+        assert_eq!(load_config(&path).coop_keys, CoopBindings::default());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_f11_toggles_fullscreen() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<GameConfig>()
+            .add_systems(Update, toggle_fullscreen);
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::F11);
+        app.update();
+        assert!(app.world.resource::<GameConfig>().fullscreen);
+        assert_eq!(
+            app.world.get::<Window>(window).unwrap().mode,
+            WindowMode::BorderlessFullscreen
+        );
+    }
+}
@@ -0,0 +1,599 @@
+// settings_menu.rs
+
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use crate::accessibility::AccessibilitySettings;
+use crate::components::*;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::menu_navigation::navigate_menu;
+use crate::player::MovementMode;
+use crate::settings::{Action, KeyBindings};
+use crate::sound::AudioSettings;
+use crate::visuals::{step_bloom, VisualSettings};
+
+/// SettingsMenuPlugin shows the settings menu over the main menu or pause screen.
+///
+/// The menu writes straight into `AudioSettings`, `VisualSettings`,
+/// `AccessibilitySettings`, `Difficulty`, `MovementMode` and `KeyBindings`;
+/// `SettingsPlugin` saves them once it closes. Rebinding an action waits for the
+/// next key press, and Escape cancels it.
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<SettingsState>()
+            .init_resource::<RebindCapture>()
+            .add_systems(OnEnter(SettingsState::Open), setup_settings_menu)
+            .add_systems(OnExit(SettingsState::Open), despawn_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    handle_settings_buttons.after(navigate_menu),
+                    capture_rebind_key.after(handle_settings_buttons),
+                    update_settings_text.after(capture_rebind_key),
+                )
+                    .run_if(in_state(SettingsState::Open)),
+            );
+    }
+}
+
+/// Whether the settings menu is showing.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SettingsState {
+    #[default]
+    Closed,
+    Open,
+}
+
+/// The action waiting for its new key, if a rebind is in progress.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebindCapture(pub Option<Action>);
+
+/// One of the volume sliders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeSlider {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeSlider {
+    /// Name shown for the slider.
+    fn label(self) -> &'static str {
+        match self {
+            VolumeSlider::Master => "Master",
+            VolumeSlider::Music => "Music",
+            VolumeSlider::Sfx => "Effects",
+        }
+    }
+
+    /// The slider's level in `settings`.
+    fn level(self, settings: &AudioSettings) -> f32 {
+        match self {
+            VolumeSlider::Master => settings.master,
+            VolumeSlider::Music => settings.music,
+            VolumeSlider::Sfx => settings.sfx,
+        }
+    }
+
+    /// The level in `settings` the slider moves.
+    fn level_mut(self, settings: &mut AudioSettings) -> &mut f32 {
+        match self {
+            VolumeSlider::Master => &mut settings.master,
+            VolumeSlider::Music => &mut settings.music,
+            VolumeSlider::Sfx => &mut settings.sfx,
+        }
+    }
+}
+
+/// A line of the settings menu, whose text shows the current value.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsRow {
+    Volume(VolumeSlider),
+    Mute,
+    Bloom,
+    Tonemapping,
+    Vignette,
+    Colorblind,
+    Difficulty,
+    Movement,
+    Binding(Action),
+}
+
+/// Component for the buttons in the settings menu.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsButton {
+    /// Turn a slider down a step.
+    Lower(VolumeSlider),
+    /// Turn a slider up a step.
+    Raise(VolumeSlider),
+    ToggleMute,
+    /// Turn the bloom down a step.
+    LowerBloom,
+    /// Turn the bloom up a step.
+    RaiseBloom,
+    /// Move on to the next tonemapping curve.
+    CycleTonemapping,
+    /// Turn the low health vignette on or off.
+    ToggleVignette,
+    /// Switch between the standard and colorblind palettes.
+    ToggleColorblind,
+    /// Move on to the next difficulty.
+    CycleDifficulty,
+    /// Switch between real-time and turn-based movement.
+    ToggleMovement,
+    /// Wait for a new key for the action.
+    Rebind(Action),
+    /// Close the menu.
+    Close,
+}
+
+/// Marker for the root of the settings menu.
+#[derive(Component)]
+struct SettingsScreen;
+
+/// Moves a volume level by `steps` of `1 / SETTINGS_VOLUME_STEPS`, staying within 0.0..=1.0.
+pub fn step_volume(level: f32, steps: i32) -> f32 {
+    let step = (level * SETTINGS_VOLUME_STEPS as f32).round() as i32 + steps;
+    step.clamp(0, SETTINGS_VOLUME_STEPS) as f32 / SETTINGS_VOLUME_STEPS as f32
+}
+
+/// Text shown for a row of the settings menu.
+#[allow(clippy::too_many_arguments)]
+pub fn row_text(
+    row: SettingsRow,
+    audio: &AudioSettings,
+    visuals: &VisualSettings,
+    accessibility: &AccessibilitySettings,
+    difficulty: Difficulty,
+    movement_mode: MovementMode,
+    keys: &KeyBindings,
+    capture: RebindCapture,
+) -> String {
+    match row {
+        SettingsRow::Volume(slider) => {
+            format!("{} {:.0}%", slider.label(), slider.level(audio) * 100.0)
+        }
+        SettingsRow::Mute if audio.muted => "Sound off".to_string(),
+        SettingsRow::Mute => "Sound on".to_string(),
+        SettingsRow::Bloom => format!("Bloom {:.0}%", visuals.bloom_intensity * 100.0),
+        SettingsRow::Tonemapping => format!("Tonemapping {:?}", visuals.tonemapping),
+        SettingsRow::Vignette if visuals.low_health_vignette => {
+            "Low health vignette on".to_string()
+        }
+        SettingsRow::Vignette => "Low health vignette off".to_string(),
+        SettingsRow::Colorblind if accessibility.colorblind_mode => {
+            "Colorblind palette on".to_string()
+        }
+        SettingsRow::Colorblind => "Colorblind palette off".to_string(),
+        SettingsRow::Difficulty => format!("Difficulty {:?}", difficulty),
+        SettingsRow::Movement if movement_mode == MovementMode::TurnBased => {
+            "Movement turn-based".to_string()
+        }
+        SettingsRow::Movement => "Movement real-time".to_string(),
+        SettingsRow::Binding(action) if capture.0 == Some(action) => {
+            format!("{}: press a key", action.label())
+        }
+        SettingsRow::Binding(action) => format!("{}: {:?}", action.label(), keys.key(action)),
+    }
+}
+
+/// Spawns a small button labelled `label`.
+fn spawn_button(parent: &mut ChildBuilder, label: &str, button: SettingsButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(HUD_MARGIN), Val::Px(HUD_MARGIN / 2.0)),
+                    ..default()
+                },
+                background_color: Color::DARK_GRAY.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: HUD_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Spawns the settings menu above any other menu, one row per setting.
+#[allow(clippy::too_many_arguments)]
+fn setup_settings_menu(
+    mut commands: Commands,
+    audio: Res<AudioSettings>,
+    visuals: Res<VisualSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    difficulty: Res<Difficulty>,
+    movement_mode: Res<MovementMode>,
+    keys: Res<KeyBindings>,
+    capture: Res<RebindCapture>,
+) {
+    let rows = [
+        SettingsRow::Volume(VolumeSlider::Master),
+        SettingsRow::Volume(VolumeSlider::Music),
+        SettingsRow::Volume(VolumeSlider::Sfx),
+        SettingsRow::Mute,
+        SettingsRow::Bloom,
+        SettingsRow::Tonemapping,
+        SettingsRow::Vignette,
+        SettingsRow::Colorblind,
+        SettingsRow::Difficulty,
+        SettingsRow::Movement,
+    ]
+    .into_iter()
+    .chain(Action::ALL.into_iter().map(SettingsRow::Binding));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(HUD_MARGIN / 2.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                // Drawn over the menu it was opened from, which mustn't take the clicks
+                z_index: ZIndex::Global(1),
+                focus_policy: FocusPolicy::Block,
+                ..default()
+            },
+            SettingsScreen,
+            Name::new("Settings screen"),
+        ))
+        .with_children(|screen| {
+            screen.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font_size: HUD_FONT_SIZE * 2.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            for row in rows {
+                screen
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(HUD_MARGIN),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|line| {
+                        line.spawn((
+                            TextBundle::from_section(
+                                row_text(
+                                    row,
+                                    &audio,
+                                    &visuals,
+                                    &accessibility,
+                                    *difficulty,
+                                    *movement_mode,
+                                    &keys,
+                                    *capture,
+                                ),
+                                TextStyle {
+                                    font_size: HUD_FONT_SIZE,
+                                    color: Color::WHITE,
+                                    ..default()
+                                },
+                            ),
+                            row,
+                        ));
+                        match row {
+                            SettingsRow::Volume(slider) => {
+                                spawn_button(line, "-", SettingsButton::Lower(slider));
+                                spawn_button(line, "+", SettingsButton::Raise(slider));
+                            }
+                            SettingsRow::Mute => {
+                                spawn_button(line, "Toggle", SettingsButton::ToggleMute)
+                            }
+                            SettingsRow::Bloom => {
+                                spawn_button(line, "-", SettingsButton::LowerBloom);
+                                spawn_button(line, "+", SettingsButton::RaiseBloom);
+                            }
+                            SettingsRow::Tonemapping => {
+                                spawn_button(line, "Change", SettingsButton::CycleTonemapping)
+                            }
+                            SettingsRow::Vignette => {
+                                spawn_button(line, "Toggle", SettingsButton::ToggleVignette)
+                            }
+                            SettingsRow::Colorblind => {
+                                spawn_button(line, "Toggle", SettingsButton::ToggleColorblind)
+                            }
+                            SettingsRow::Difficulty => {
+                                spawn_button(line, "Change", SettingsButton::CycleDifficulty)
+                            }
+                            SettingsRow::Movement => {
+                                spawn_button(line, "Toggle", SettingsButton::ToggleMovement)
+                            }
+                            SettingsRow::Binding(action) => {
+                                spawn_button(line, "Rebind", SettingsButton::Rebind(action))
+                            }
+                        }
+                    });
+            }
+            spawn_button(screen, "Back", SettingsButton::Close);
+        });
+}
+
+/// Despawns the settings menu, dropping any rebind still waiting for a key.
+fn despawn_settings_menu(
+    mut commands: Commands,
+    mut capture: ResMut<RebindCapture>,
+    query: Query<Entity, With<SettingsScreen>>,
+) {
+    capture.0 = None;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Applies a pressed settings button to its resource.
+#[allow(clippy::too_many_arguments)]
+fn handle_settings_buttons(
+    query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut audio: ResMut<AudioSettings>,
+    mut visuals: ResMut<VisualSettings>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+    mut difficulty: ResMut<Difficulty>,
+    mut movement_mode: ResMut<MovementMode>,
+    mut capture: ResMut<RebindCapture>,
+    mut next_settings_state: ResMut<NextState<SettingsState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            SettingsButton::Lower(slider) => {
+                let level = slider.level_mut(&mut audio);
+                *level = step_volume(*level, -1);
+            }
+            SettingsButton::Raise(slider) => {
+                let level = slider.level_mut(&mut audio);
+                *level = step_volume(*level, 1);
+            }
+            SettingsButton::ToggleMute => audio.muted = !audio.muted,
+            SettingsButton::LowerBloom => {
+                visuals.bloom_intensity = step_bloom(visuals.bloom_intensity, -1)
+            }
+            SettingsButton::RaiseBloom => {
+                visuals.bloom_intensity = step_bloom(visuals.bloom_intensity, 1)
+            }
+            SettingsButton::CycleTonemapping => visuals.tonemapping = visuals.tonemapping.next(),
+            SettingsButton::ToggleVignette => {
+                visuals.low_health_vignette = !visuals.low_health_vignette
+            }
+            SettingsButton::ToggleColorblind => {
+                accessibility.colorblind_mode = !accessibility.colorblind_mode
+            }
+            SettingsButton::CycleDifficulty => *difficulty = difficulty.next(),
+            SettingsButton::ToggleMovement => {
+                *movement_mode = match *movement_mode {
+                    MovementMode::RealTime => MovementMode::TurnBased,
+                    MovementMode::TurnBased => MovementMode::RealTime,
+                }
+            }
+            SettingsButton::Rebind(action) => capture.0 = Some(action),
+            SettingsButton::Close => next_settings_state.set(SettingsState::Closed),
+        }
+    }
+}
+
+/// Binds the next key pressed to the action waiting in `RebindCapture`.
+///
+/// Escape cancels the rebind and keeps the old key.
+fn capture_rebind_key(
+    input_res: Res<Input<KeyCode>>,
+    mut capture: ResMut<RebindCapture>,
+    mut keys: ResMut<KeyBindings>,
+) {
+    let Some(action) = capture.0 else {
+        return;
+    };
+    // The rebind started this frame, maybe by Enter on a highlighted button, which
+    // isn't the new key
+    if capture.is_changed() {
+        return;
+    }
+    let Some(&key) = input_res.get_just_pressed().next() else {
+        return;
+    };
+    capture.0 = None;
+    if key == KeyCode::Escape {
+        return;
+    }
+    info!("⌨️{} bound to {:?}", action.label(), key);
+    keys.bind(action, key);
+}
+
+/// Refreshes the row texts when any setting changes.
+#[allow(clippy::too_many_arguments)]
+fn update_settings_text(
+    audio: Res<AudioSettings>,
+    visuals: Res<VisualSettings>,
+    accessibility: Res<AccessibilitySettings>,
+    difficulty: Res<Difficulty>,
+    movement_mode: Res<MovementMode>,
+    keys: Res<KeyBindings>,
+    capture: Res<RebindCapture>,
+    mut query: Query<(&mut Text, &SettingsRow)>,
+) {
+    if !audio.is_changed()
+        && !visuals.is_changed()
+        && !accessibility.is_changed()
+        && !difficulty.is_changed()
+        && !movement_mode.is_changed()
+        && !keys.is_changed()
+        && !capture.is_changed()
+    {
+        return;
+    }
+    for (mut text, row) in query.iter_mut() {
+        text.sections[0].value = row_text(
+            *row,
+            &audio,
+            &visuals,
+            &accessibility,
+            *difficulty,
+            *movement_mode,
+            &keys,
+            *capture,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visuals::TonemappingMode;
+
+    fn settings_menu_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<AudioSettings>()
+            .init_resource::<VisualSettings>()
+            .init_resource::<AccessibilitySettings>()
+            .init_resource::<Difficulty>()
+            .init_resource::<MovementMode>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<RebindCapture>()
+            .add_state::<SettingsState>()
+            .add_systems(
+                Update,
+                (
+                    handle_settings_buttons,
+                    capture_rebind_key.after(handle_settings_buttons),
+                ),
+            );
+        app
+    }
+
+    fn press_button(app: &mut App, button: SettingsButton) {
+        let entity = app.world.spawn((Interaction::Pressed, button)).id();
+        app.update();
+        app.world.despawn(entity);
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+    }
+
+    #[test]
+    fn test_rebind_captures_next_key() {
+        let mut app = settings_menu_app();
+        press_button(&mut app, SettingsButton::Rebind(Action::CastUp));
+        assert_eq!(
+            *app.world.resource::<RebindCapture>(),
+            RebindCapture(Some(Action::CastUp))
+        );
+        assert_eq!(
+            row_text(
+                SettingsRow::Binding(Action::CastUp),
+                &AudioSettings::default(),
+                &VisualSettings::default(),
+                &AccessibilitySettings::default(),
+                Difficulty::Normal,
+                MovementMode::RealTime,
+                &KeyBindings::default(),
+                *app.world.resource::<RebindCapture>(),
+            ),
+            "Cast up: press a key"
+        );
+
+        press_key(&mut app, KeyCode::I);
+        assert_eq!(*app.world.resource::<RebindCapture>(), RebindCapture(None));
+        assert_eq!(
+            app.world.resource::<KeyBindings>().key(Action::CastUp),
+            KeyCode::I
+        );
+
+        // Only the next key is taken
+        press_key(&mut app, KeyCode::K);
+        assert_eq!(
+            app.world.resource::<KeyBindings>().key(Action::CastUp),
+            KeyCode::I
+        );
+
+        // Escape gives up and keeps the old key
+        press_button(&mut app, SettingsButton::Rebind(Action::CastUp));
+        press_key(&mut app, KeyCode::Escape);
+        assert_eq!(*app.world.resource::<RebindCapture>(), RebindCapture(None));
+        assert_eq!(
+            app.world.resource::<KeyBindings>().key(Action::CastUp),
+            KeyCode::I
+        );
+    }
+
+    #[test]
+    fn test_buttons_change_resources() {
+        let mut app = settings_menu_app();
+        app.world
+            .resource_mut::<NextState<SettingsState>>()
+            .set(SettingsState::Open);
+        app.update();
+
+        press_button(&mut app, SettingsButton::Lower(VolumeSlider::Music));
+        press_button(&mut app, SettingsButton::Raise(VolumeSlider::Master));
+        press_button(&mut app, SettingsButton::ToggleMute);
+        press_button(&mut app, SettingsButton::CycleDifficulty);
+        press_button(&mut app, SettingsButton::ToggleMovement);
+        press_button(&mut app, SettingsButton::RaiseBloom);
+        press_button(&mut app, SettingsButton::CycleTonemapping);
+        press_button(&mut app, SettingsButton::ToggleVignette);
+        press_button(&mut app, SettingsButton::ToggleColorblind);
+        let visuals = app.world.resource::<VisualSettings>();
+        assert_eq!(
+            visuals.bloom_intensity,
+            step_bloom(VisualSettings::default().bloom_intensity, 1)
+        );
+        assert_eq!(visuals.tonemapping, TonemappingMode::BlenderFilmic);
+        assert!(!visuals.low_health_vignette);
+        assert!(
+            app.world
+                .resource::<AccessibilitySettings>()
+                .colorblind_mode
+        );
+        let audio = app.world.resource::<AudioSettings>();
+        assert_eq!(audio.music, 0.9);
+        assert_eq!(audio.master, 1.0);
+        assert!(audio.muted);
+        assert_eq!(*app.world.resource::<Difficulty>(), Difficulty::Hard);
+        assert_eq!(
+            *app.world.resource::<MovementMode>(),
+            MovementMode::TurnBased
+        );
+
+        press_button(&mut app, SettingsButton::Close);
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<SettingsState>>().get(),
+            SettingsState::Closed
+        );
+    }
+
+    #[test]
+    fn test_step_volume() {
+        assert_eq!(step_volume(0.5, 1), 0.6);
+        assert_eq!(step_volume(0.0, -1), 0.0);
+        assert_eq!(step_volume(1.0, 3), 1.0);
+        // Levels off the step grid snap onto it
+        assert_eq!(step_volume(0.43, 0), 0.4);
+    }
+}
@@ -0,0 +1,226 @@
+// sound.rs
+
+use bevy::audio::{AudioSink, AudioSinkPlayback, SpatialAudioSink};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::settings::GameConfig;
+
+/// SoundPlugin applies the player's volume settings to music and sound effects.
+///
+/// `AudioSettings` is loaded with the rest of the `GameConfig` in `main` and copied
+/// back into it whenever it changes, so it's saved with the other settings. Sounds
+/// get their volume as they start playing, and again whenever the settings change.
+/// F9 toggles mute.
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioSettings>().add_systems(
+            Update,
+            (
+                toggle_mute,
+                apply_volume::<AudioSink>.after(toggle_mute),
+                apply_volume::<SpatialAudioSink>.after(toggle_mute),
+                store_audio_settings.after(toggle_mute),
+            ),
+        );
+    }
+}
+
+/// Which volume slider a sound is played under.
+///
+/// Added to an entity playing a sound to pick its slider; sounds without one play
+/// under the sound effects slider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioChannel {
+    Music,
+    Sfx,
+}
+
+/// Volume levels chosen by the player, each from 0.0 (silent) to 1.0 (full).
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Scales every sound.
+    pub master: f32,
+    /// Scales the background music.
+    pub music: f32,
+    /// Scales one-shot sound effects.
+    pub sfx: f32,
+    /// Silences everything, keeping the levels for when it's unmuted.
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// The volume a sound on `channel` plays at.
+    ///
+    /// # Returns
+    /// The master level times the channel's level, each clamped to 0.0..=1.0, or
+    /// 0.0 while muted.
+    pub fn effective_volume(&self, channel: AudioChannel) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let level = match channel {
+            AudioChannel::Music => self.music,
+            AudioChannel::Sfx => self.sfx,
+        };
+        self.master.clamp(0.0, 1.0) * level.clamp(0.0, 1.0)
+    }
+}
+
+/// Mutes or unmutes all sound when F9 is pressed.
+fn toggle_mute(input_res: Res<Input<KeyCode>>, mut settings: ResMut<AudioSettings>) {
+    if input_res.just_pressed(KeyCode::F9) {
+        settings.muted = !settings.muted;
+        info!("🔈muted: {}", settings.muted);
+    }
+}
+
+/// Sets the volume of sounds that just started, or of every sound playing when the
+/// settings change.
+///
+/// Generic over the kind of sink so regular and spatial sounds are both covered.
+fn apply_volume<S: Component + AudioSinkPlayback>(
+    settings: Res<AudioSettings>,
+    sink_query: Query<(Ref<S>, Option<&AudioChannel>)>,
+) {
+    for (sink, channel) in sink_query.iter() {
+        if !settings.is_changed() && !sink.is_added() {
+            continue;
+        }
+        let channel = channel.copied().unwrap_or(AudioChannel::Sfx);
+        sink.set_volume(settings.effective_volume(channel));
+    }
+}
+
+/// Copies changed audio settings into the `GameConfig`, which saves them.
+fn store_audio_settings(settings: Res<AudioSettings>, mut config: ResMut<GameConfig>) {
+    if !settings.is_changed() || settings.is_added() || config.audio == *settings {
+        return;
+    }
+    config.audio = settings.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Stands in for an `AudioSink`, which needs an audio device.
+    #[derive(Component, Default)]
+    struct FakeSink {
+        volume: Mutex<f32>,
+    }
+
+    impl AudioSinkPlayback for FakeSink {
+        fn volume(&self) -> f32 {
+            *self.volume.lock().unwrap()
+        }
+        fn set_volume(&self, volume: f32) {
+            *self.volume.lock().unwrap() = volume;
+        }
+        fn speed(&self) -> f32 {
+            1.0
+        }
+        fn set_speed(&self, _speed: f32) {}
+        fn play(&self) {}
+        fn pause(&self) {}
+        fn is_paused(&self) -> bool {
+            false
+        }
+        fn stop(&self) {}
+        fn empty(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_effective_volume() {
+        let settings = AudioSettings {
+            master: 0.5,
+            music: 0.4,
+            sfx: 1.0,
+            muted: false,
+        };
+        assert_eq!(settings.effective_volume(AudioChannel::Music), 0.2);
+        assert_eq!(settings.effective_volume(AudioChannel::Sfx), 0.5);
+
+        // Levels out of range are clamped
+        let settings = AudioSettings {
+            master: 2.0,
+            music: -1.0,
+            ..default()
+        };
+        assert_eq!(settings.effective_volume(AudioChannel::Sfx), 1.0);
+        assert_eq!(settings.effective_volume(AudioChannel::Music), 0.0);
+    }
+
+    #[test]
+    fn test_apply_volume() {
+        let mut app = App::new();
+        app.insert_resource(AudioSettings {
+            master: 0.5,
+            music: 0.4,
+            ..default()
+        })
+        .add_systems(Update, apply_volume::<FakeSink>);
+        let sfx = app.world.spawn(FakeSink::default()).id();
+        let music = app
+            .world
+            .spawn((FakeSink::default(), AudioChannel::Music))
+            .id();
+        let volume = |app: &App, entity| app.world.get::<FakeSink>(entity).unwrap().volume();
+        app.update();
+        assert_eq!(volume(&app, sfx), 0.5);
+        assert_eq!(volume(&app, music), 0.2);
+
+        // A sound started later gets its volume as it starts
+        let late = app.world.spawn(FakeSink::default()).id();
+        app.update();
+        assert_eq!(volume(&app, late), 0.5);
+
+        // And every sound follows the settings when they change
+        app.world.resource_mut::<AudioSettings>().muted = true;
+        app.update();
+        assert_eq!(volume(&app, sfx), 0.0);
+        assert_eq!(volume(&app, music), 0.0);
+        assert_eq!(volume(&app, late), 0.0);
+    }
+
+    #[test]
+    fn test_mute_overrides_levels() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<AudioSettings>()
+            .init_resource::<GameConfig>()
+            .add_systems(
+                Update,
+                (toggle_mute, store_audio_settings.after(toggle_mute)),
+            );
+        app.update();
+        assert!(!app.world.resource::<GameConfig>().audio.muted);
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::F9);
+        app.update();
+        let settings = app.world.resource::<AudioSettings>().clone();
+        assert!(settings.muted);
+        assert_eq!(settings.effective_volume(AudioChannel::Music), 0.0);
+        assert_eq!(settings.effective_volume(AudioChannel::Sfx), 0.0);
+        // The change is kept in the config so it's saved
+        assert_eq!(app.world.resource::<GameConfig>().audio, settings);
+    }
+}
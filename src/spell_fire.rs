@@ -3,36 +3,440 @@
 use bevy::{
     prelude::*, render::mesh::shape::Cube, time::common_conditions::on_timer, utils::Duration,
 };
+use bevy_ecs_ldtk::prelude::*;
 use bevy_hanabi::prelude::*;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::accessibility::{palette_changed, AccessibilitySettings, Palette};
 use crate::components::*;
 use crate::constants::*;
+use crate::despawn::tick_despawn_timers;
+use crate::enemy::{index_enemy_positions, EnemyPositions};
+use crate::map::{grid_size_changed, GridSize, LevelWalls, WallDamaged};
+use crate::player::{input_unlocked, player_cell};
+use crate::settings::{Action, KeyBindings};
 
+/// SpellFirePlugin handles charging, casting and flying the player's spells.
+///
+/// Spell effects are built once at startup from the `AccessibilitySettings`
+/// palette, and rebuilt whenever it changes, so new casts pick up the colorblind
+/// colors straight away.
 impl Plugin for SpellFirePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                setup_spell_fire_effect,
-                // setup_spell_fire_collision,
-                spawn_spell_fire_from_input,
-                dbg_spell_fire.run_if(on_timer(Duration::from_secs(1))),
-            ),
-        );
+        app.init_resource::<SpellFirePool>()
+            .init_resource::<ActiveSpell>()
+            .init_resource::<Mana>()
+            .init_resource::<SpellCooldown>()
+            .add_event::<NoMana>()
+            .add_event::<SpellCast>()
+            .add_event::<SpellImpact>()
+            .add_event::<SpellExplosion>()
+            .add_systems(Startup, setup_spell_fire_effect)
+            .add_systems(
+                Update,
+                (
+                    // setup_spell_fire_collision,
+                    count_live_spell_fire,
+                    select_spell_from_input,
+                    regenerate_mana,
+                    tick_spell_cooldown,
+                    spawn_spell_fire_from_input
+                        .run_if(input_unlocked)
+                        .after(count_live_spell_fire)
+                        .after(tick_spell_cooldown),
+                    steer_homing_spells
+                        .after(index_enemy_positions)
+                        .before(move_spell_fire),
+                    move_spell_fire.after(tick_despawn_timers),
+                    spawn_impact_bursts.after(move_spell_fire),
+                    dbg_spell_fire.run_if(on_timer(Duration::from_secs(1))),
+                    setup_spell_fire_effect.run_if(palette_changed.or_else(grid_size_changed)),
+                ),
+            );
     }
 }
 
-fn setup_spell_fire_effect(
-    asset_server: Res<AssetServer>,
-    mut effects: ResMut<Assets<EffectAsset>>,
-) {
-    let texture_handle: Handle<Image> = asset_server.load("cloud.png");
+/// The kinds of spell the player can cast.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpellKind {
+    /// A bolt that flies straight.
+    #[default]
+    Fire,
+    /// A bolt that curves toward the nearest enemy.
+    Homing,
+    /// A bolt that slows the enemies it hits.
+    Ice,
+    /// A bolt that explodes where it lands, damaging every enemy nearby.
+    Explosion,
+    /// A line of fire on the ground ahead, burning enemies that stand in it.
+    Firewall,
+}
+
+impl SpellKind {
+    /// Mana needed to cast this kind of spell.
+    pub fn mana_cost(self) -> f32 {
+        match self {
+            SpellKind::Fire => SPELL_FIRE_MANA_COST,
+            SpellKind::Homing => SPELL_HOMING_MANA_COST,
+            SpellKind::Ice => SPELL_ICE_MANA_COST,
+            SpellKind::Explosion => SPELL_EXPLOSION_MANA_COST,
+            SpellKind::Firewall => SPELL_FIREWALL_MANA_COST,
+        }
+    }
+
+    /// The kind of a projectile, going by whether it homes, freezes or explodes.
+    pub fn of_projectile(
+        homing: Option<&Homing>,
+        freezing: Option<&Freezing>,
+        explosive: Option<&Explosive>,
+    ) -> Self {
+        match (homing, freezing, explosive) {
+            (Some(_), _, _) => SpellKind::Homing,
+            (None, Some(_), _) => SpellKind::Ice,
+            (None, None, Some(_)) => SpellKind::Explosion,
+            (None, None, None) => SpellKind::Fire,
+        }
+    }
+}
+
+/// The spell the player casts with the arrow keys.
+#[derive(Default, Resource, Debug)]
+pub struct ActiveSpell(pub SpellKind);
+
+/// The player's mana pool, spent on casting and regenerated over time.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Mana {
+    pub current: f32,
+    pub max: f32,
+    /// Mana regained per second.
+    pub regen: f32,
+}
+
+impl Default for Mana {
+    fn default() -> Self {
+        Mana {
+            current: PLAYER_MANA_MAX,
+            max: PLAYER_MANA_MAX,
+            regen: PLAYER_MANA_REGEN,
+        }
+    }
+}
+
+impl Mana {
+    /// Checks if there is enough mana for a spell costing `cost`.
+    pub fn can_afford(&self, cost: f32) -> bool {
+        self.current >= cost
+    }
+
+    /// Deducts `cost` if there is enough mana.
+    ///
+    /// # Returns
+    /// `true` if the mana was spent, `false` (leaving mana untouched) if it wasn't enough.
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if !self.can_afford(cost) {
+            return false;
+        }
+        self.current = (self.current - cost).clamp(0.0, self.max);
+        true
+    }
+
+    /// Regenerates mana for `seconds` of elapsed time, without exceeding `max`.
+    pub fn regenerate(&mut self, seconds: f32) {
+        self.current = (self.current + self.regen * seconds).clamp(0.0, self.max);
+    }
+}
+
+/// Time the player must wait after a cast before casting again.
+#[derive(Resource, Debug, Clone)]
+pub struct SpellCooldown {
+    pub timer: Timer,
+}
+
+impl Default for SpellCooldown {
+    fn default() -> Self {
+        SpellCooldown::new(SPELL_FIRE_COOLDOWN)
+    }
+}
+
+impl SpellCooldown {
+    /// Creates a cooldown of `seconds`, already over so the first cast isn't held up.
+    pub fn new(seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        timer.tick(timer.duration());
+        SpellCooldown { timer }
+    }
+
+    /// Checks if the cooldown is over, so the player can cast.
+    pub fn ready(&self) -> bool {
+        self.timer.elapsed() >= self.timer.duration()
+    }
+}
+
+/// Sent when the player tries to cast a spell without enough mana.
+#[derive(Event, Debug)]
+pub struct NoMana {
+    pub kind: SpellKind,
+    pub cost: f32,
+}
+
+/// Sent when a player successfully casts a spell.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpellCast {
+    pub caster: Entity,
+    pub kind: SpellKind,
+}
+
+/// Sent when a projectile is used up by hitting a wall or an enemy.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct SpellImpact {
+    /// Where the projectile was when it hit.
+    pub position: Vec3,
+    pub kind: SpellKind,
+}
+
+/// Sent when an explosive projectile goes off, to damage the enemies around it.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct SpellExplosion {
+    /// Where the projectile went off.
+    pub position: Vec3,
+    /// Enemies further away than this (in pixels) are unharmed.
+    pub radius: f32,
+    /// Damage dealt at the center, falling off toward the edge.
+    pub damage: f32,
+}
+
+/// Marker for a short-lived impact burst, despawned by its `DespawnTimer`.
+#[derive(Component)]
+pub struct ImpactBurst;
+
+/// Marker for the trailing particle effect attached to a projectile.
+#[derive(Component)]
+pub struct SpellTrail;
+
+/// Component holding a spell the player released while the `SpellCooldown` was
+/// still running, to be cast as soon as it's over.
+#[derive(Component, Debug)]
+pub struct BufferedCast {
+    /// Direction the spell will be fired in.
+    pub direction: Vec2,
+    /// How far the spell was charged, from 0.0 to 1.0.
+    pub charge: f32,
+    /// The spell that was active when it was released.
+    pub kind: SpellKind,
+    /// Time the cast is kept for before it's dropped.
+    pub timer: Timer,
+}
+
+impl BufferedCast {
+    /// Buffers a cast for `SPELL_CAST_BUFFER_SECONDS`.
+    pub fn new(direction: Vec2, charge: f32, kind: SpellKind) -> Self {
+        BufferedCast {
+            direction,
+            charge,
+            kind,
+            timer: Timer::from_seconds(SPELL_CAST_BUFFER_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Tracks how many spell_fire projectiles are alive, and refuses casts beyond the cap.
+#[derive(Resource, Debug)]
+pub struct SpellFirePool {
+    /// Maximum number of live projectiles.
+    pub max_live: usize,
+    live: usize,
+}
+
+impl Default for SpellFirePool {
+    fn default() -> Self {
+        SpellFirePool {
+            max_live: SPELL_FIRE_MAX_LIVE,
+            live: 0,
+        }
+    }
+}
+
+impl SpellFirePool {
+    /// Returns the number of live projectiles, including ones cast this frame.
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+    /// Claims a slot for a new projectile.
+    ///
+    /// # Returns
+    /// `true` if the projectile may be spawned, `false` if the cap is reached.
+    pub fn try_acquire(&mut self) -> bool {
+        if self.live >= self.max_live {
+            return false;
+        }
+        self.live += 1;
+        true
+    }
+}
+
+/// Shared assets for spell_fire projectiles, built once at startup instead of per cast.
+#[derive(Resource)]
+pub struct SpellFireAssets {
+    /// One particle effect per charge level, from uncharged to fully charged.
+    pub effects: Vec<Handle<EffectAsset>>,
+    /// The comet tail left behind by every projectile.
+    pub trail: Handle<EffectAsset>,
+    /// Burst shown where a fire spell hits.
+    pub impact_fire: Handle<EffectAsset>,
+    /// Burst shown where a homing spell hits.
+    pub impact_homing: Handle<EffectAsset>,
+    /// Burst shown where an ice spell hits.
+    pub impact_ice: Handle<EffectAsset>,
+    /// Blast shown where an explosive spell goes off.
+    pub explosion: Handle<EffectAsset>,
+    /// Flames rising from each burning cell of a firewall.
+    pub firewall: Handle<EffectAsset>,
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl SpellFireAssets {
+    /// The impact burst for a kind of spell.
+    pub fn impact(&self, kind: SpellKind) -> Handle<EffectAsset> {
+        match kind {
+            SpellKind::Fire => self.impact_fire.clone(),
+            SpellKind::Homing => self.impact_homing.clone(),
+            SpellKind::Ice => self.impact_ice.clone(),
+            SpellKind::Explosion => self.explosion.clone(),
+            SpellKind::Firewall => self.impact_fire.clone(),
+        }
+    }
+}
+
+/// Where a spell cast in `direction` appears, relative to the player's center.
+///
+/// # Returns
+/// The wizard's hand position: `SPELL_FIRE_HAND_REACH` out in the cast direction,
+/// at `SPELL_FIRE_HAND_HEIGHT`.
+pub fn spell_spawn_offset(direction: Vec2) -> Vec2 {
+    direction.normalize_or_zero() * SPELL_FIRE_HAND_REACH + Vec2::new(0.0, SPELL_FIRE_HAND_HEIGHT)
+}
+
+/// The cells a firewall cast in `direction` from `cell` burns.
+///
+/// # Returns
+/// `SPELL_FIREWALL_WIDTH` cells in a line across the cast direction, centered on
+/// the cell next to `cell` in that direction.
+pub fn firewall_cells(cell: GridCoords, direction: Vec2) -> Vec<GridCoords> {
+    let center = IVec2::new(cell.x, cell.y) + direction.round().as_ivec2();
+    let across = direction.perp().round().as_ivec2();
+    (0..SPELL_FIREWALL_WIDTH)
+        .map(|step| center + across * (step - SPELL_FIREWALL_WIDTH / 2))
+        .map(|burning| GridCoords::new(burning.x, burning.y))
+        .collect()
+}
+
+/// Rounds a charge fraction to one of `SPELL_FIRE_CHARGE_LEVELS` steps.
+pub fn charge_level(fraction: f32) -> usize {
+    (fraction.clamp(0.0, 1.0) * SPELL_FIRE_CHARGE_LEVELS as f32).round() as usize
+}
+
+/// Stats of a spell_fire projectile, scaled by how long the spell was charged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpellStats {
+    /// Projectile speed, in pixels per second.
+    pub speed: f32,
+    /// Damage dealt on hit.
+    pub damage: f32,
+    /// Distance flown before the projectile fizzles out, in pixels. The same at
+    /// every charge, so faster spells don't reach further.
+    pub range: f32,
+    /// Radius of the sphere particles are spawned in.
+    pub particle_radius: f32,
+    /// Initial speed of the particles.
+    pub particle_speed: f32,
+}
+
+impl SpellStats {
+    /// Maps a charge fraction (0.0 for a quick tap, 1.0 for full charge) to projectile stats,
+    /// with the range measured in `grid_size` cells.
+    ///
+    /// Every stat but range scales linearly from its base value up to
+    /// `SPELL_FIRE_MAX_CHARGE_MULTIPLIER` times the base at full charge. Range
+    /// stays `SPELL_FIRE_RANGE` cells, so faster spells don't reach further.
+    /// Fractions outside `0.0..=1.0` are clamped.
+    pub fn from_charge(fraction: f32, grid_size: GridSize) -> Self {
+        let multiplier = 1.0 + (SPELL_FIRE_MAX_CHARGE_MULTIPLIER - 1.0) * fraction.clamp(0.0, 1.0);
+        SpellStats {
+            speed: SPELL_FIRE_SPEED * multiplier,
+            damage: SPELL_FIRE_DAMAGE * multiplier,
+            range: SPELL_FIRE_RANGE * grid_size.pixels(),
+            particle_radius: multiplier,
+            particle_speed: 2.0 * multiplier,
+        }
+    }
+}
+
+/// The cast keys, each with the direction it fires in.
+fn cast_keys(keys: &KeyBindings) -> [(KeyCode, Vec2); 4] {
+    [
+        (keys.key(Action::CastUp), Vec2::Y),
+        (keys.key(Action::CastDown), Vec2::NEG_Y),
+        (keys.key(Action::CastLeft), Vec2::NEG_X),
+        (keys.key(Action::CastRight), Vec2::X),
+    ]
+}
+
+/// Returns the cast key that was just pressed, and the direction to fire in: along
+/// every cast key held with it, so two keys together cast diagonally.
+fn cast_key_just_pressed(
+    input_res: &Input<KeyCode>,
+    keys: &KeyBindings,
+) -> Option<(KeyCode, Vec2)> {
+    let (key, direction) = cast_keys(keys)
+        .into_iter()
+        .find(|(key, _)| input_res.just_pressed(*key))?;
+    Some((
+        key,
+        held_cast_direction(input_res, keys).unwrap_or(direction),
+    ))
+}
 
+/// The direction the held cast keys point in together.
+///
+/// # Returns
+/// The normalized sum of the held keys' directions, so Up and Right together point
+/// up-right, or `None` if no cast key is held or they cancel out.
+pub fn held_cast_direction(input_res: &Input<KeyCode>, keys: &KeyBindings) -> Option<Vec2> {
+    cast_keys(keys)
+        .into_iter()
+        .filter(|(key, _)| input_res.pressed(*key))
+        .map(|(_, direction)| direction)
+        .sum::<Vec2>()
+        .try_normalize()
+}
+
+/// Rotates `velocity` toward `to_target` by at most `max_turn` radians, keeping its speed.
+///
+/// # Arguments
+/// * `velocity` - Current velocity of the projectile.
+/// * `to_target` - Offset from the projectile to its target.
+/// * `max_turn` - Largest allowed change of direction this step, in radians.
+pub fn steer_towards(velocity: Vec2, to_target: Vec2, max_turn: f32) -> Vec2 {
+    if velocity == Vec2::ZERO || to_target == Vec2::ZERO {
+        return velocity;
+    }
+    let turn = velocity.angle_between(to_target).clamp(-max_turn, max_turn);
+    Vec2::from_angle(turn).rotate(velocity)
+}
+
+/// Builds the particle effect for a spell_fire projectile with the given stats.
+fn spell_fire_effect(
+    texture_handle: Handle<Image>,
+    stats: &SpellStats,
+    palette: &Palette,
+) -> EffectAsset {
     let mut gradient = Gradient::new();
     gradient.add_key(0.0, Vec4::splat(1.0));
-    gradient.add_key(0.1, Vec4::new(1.0, 1.0, 0.0, 1.0));
-    gradient.add_key(0.4, Vec4::new(1.0, 0.0, 0.0, 1.0));
+    gradient.add_key(0.1, palette.spell_fire[0]);
+    gradient.add_key(0.4, palette.spell_fire[1]);
     gradient.add_key(1.0, Vec4::splat(0.0));
 
     let writer = ExprWriter::new();
@@ -45,27 +449,251 @@ fn setup_spell_fire_effect(
 
     let init_pos = SetPositionSphereModifier {
         center: writer.lit(Vec3::ZERO).expr(),
-        radius: writer.lit(1.).expr(),
+        radius: writer.lit(stats.particle_radius).expr(),
         dimension: ShapeDimension::Volume,
     };
 
     let init_vel = SetVelocitySphereModifier {
         center: writer.lit(Vec3::ZERO).expr(),
-        speed: writer.lit(2.).expr(),
+        speed: writer.lit(stats.particle_speed).expr(),
     };
 
-    effects.add(
-        EffectAsset::new(32768, Spawner::rate(1000.0.into()), writer.finish())
-            .with_name("spell_fire")
-            .init(init_pos)
-            .init(init_vel)
-            .init(init_age)
-            .init(init_lifetime)
-            .render(ParticleTextureModifier {
-                texture: texture_handle.clone(),
-            })
-            .render(ColorOverLifetimeModifier { gradient }),
-    );
+    EffectAsset::new(32768, Spawner::rate(1000.0.into()), writer.finish())
+        .with_name("spell_fire")
+        .init(init_pos)
+        .init(init_vel)
+        .init(init_age)
+        .init(init_lifetime)
+        .render(ParticleTextureModifier {
+            texture: texture_handle,
+        })
+        .render(ColorOverLifetimeModifier { gradient })
+}
+
+/// Builds the trailing particle effect left behind by a moving projectile.
+///
+/// A low-rate emitter of short-lived, slow particles simulated in world space, so
+/// particles stay where they were emitted and fade out behind the projectile.
+fn spell_trail_effect(texture_handle: Handle<Image>, palette: &Palette) -> EffectAsset {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, palette.spell_trail[0]);
+    gradient.add_key(1.0, palette.spell_trail[1]);
+
+    let writer = ExprWriter::new();
+
+    let age = writer.lit(0.).expr();
+    let init_age = SetAttributeModifier::new(Attribute::AGE, age);
+
+    let lifetime = writer.lit(SPELL_TRAIL_LIFETIME).expr();
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+    let init_pos = SetPositionSphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        radius: writer.lit(0.5).expr(),
+        dimension: ShapeDimension::Volume,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        speed: writer.lit(0.5).expr(),
+    };
+
+    EffectAsset::new(
+        4096,
+        Spawner::rate(SPELL_TRAIL_RATE.into()),
+        writer.finish(),
+    )
+    .with_name("spell_trail")
+    .with_simulation_space(SimulationSpace::Global)
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_age)
+    .init(init_lifetime)
+    .render(ParticleTextureModifier {
+        texture: texture_handle,
+    })
+    .render(ColorOverLifetimeModifier { gradient })
+}
+
+/// Builds the flames rising from a burning cell of a firewall.
+///
+/// A steady emitter of short-lived particles spread over the cell, drifting slowly
+/// and fading from the fire colors to transparent.
+fn firewall_effect(
+    texture_handle: Handle<Image>,
+    palette: &Palette,
+    grid_size: GridSize,
+) -> EffectAsset {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, palette.spell_fire[0]);
+    gradient.add_key(0.5, palette.spell_fire[1]);
+    gradient.add_key(1.0, Vec4::splat(0.0));
+
+    let writer = ExprWriter::new();
+
+    let age = writer.lit(0.).expr();
+    let init_age = SetAttributeModifier::new(Attribute::AGE, age);
+
+    let lifetime = writer.lit(SPELL_IMPACT_SECONDS).expr();
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+    let init_pos = SetPositionSphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        radius: writer.lit(grid_size.pixels() / 2.0).expr(),
+        dimension: ShapeDimension::Volume,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        speed: writer.lit(2.0).expr(),
+    };
+
+    EffectAsset::new(
+        1024,
+        Spawner::rate(SPELL_FIREWALL_PARTICLE_RATE.into()),
+        writer.finish(),
+    )
+    .with_name("firewall")
+    .init(init_pos)
+    .init(init_vel)
+    .init(init_age)
+    .init(init_lifetime)
+    .render(ParticleTextureModifier {
+        texture: texture_handle,
+    })
+    .render(ColorOverLifetimeModifier { gradient })
+}
+
+/// Builds the radial burst shown where a projectile hits something.
+///
+/// All particles are emitted at once and fade from `color` to transparent.
+pub fn spell_impact_effect(texture_handle: Handle<Image>, color: Vec4) -> EffectAsset {
+    burst_effect(
+        texture_handle,
+        color,
+        SPELL_IMPACT_PARTICLES,
+        SPELL_IMPACT_SPEED,
+    )
+}
+
+/// Builds the blast shown where an explosive spell goes off, its particles flying
+/// out as far as the explosion reaches before they fade.
+fn spell_explosion_effect(
+    texture_handle: Handle<Image>,
+    palette: &Palette,
+    grid_size: GridSize,
+) -> EffectAsset {
+    burst_effect(
+        texture_handle,
+        palette.impact(SpellKind::Explosion),
+        SPELL_EXPLOSION_PARTICLES,
+        SPELL_EXPLOSION_RADIUS * grid_size.pixels() / SPELL_IMPACT_SECONDS,
+    )
+}
+
+/// Builds a radial burst of `particles` particles, emitted at once at `speed` and
+/// fading from `color` to transparent.
+fn burst_effect(
+    texture_handle: Handle<Image>,
+    color: Vec4,
+    particles: f32,
+    speed: f32,
+) -> EffectAsset {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, color);
+    gradient.add_key(1.0, color.truncate().extend(0.0));
+
+    let writer = ExprWriter::new();
+
+    let age = writer.lit(0.).expr();
+    let init_age = SetAttributeModifier::new(Attribute::AGE, age);
+
+    let lifetime = writer.lit(SPELL_IMPACT_SECONDS).expr();
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+    let init_pos = SetPositionSphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        radius: writer.lit(1.0).expr(),
+        dimension: ShapeDimension::Surface,
+    };
+
+    let init_vel = SetVelocitySphereModifier {
+        center: writer.lit(Vec3::ZERO).expr(),
+        speed: writer.lit(speed).expr(),
+    };
+
+    EffectAsset::new(1024, Spawner::once(particles.into(), true), writer.finish())
+        .with_name("spell_impact")
+        .init(init_pos)
+        .init(init_vel)
+        .init(init_age)
+        .init(init_lifetime)
+        .render(ParticleTextureModifier {
+            texture: texture_handle,
+        })
+        .render(ColorOverLifetimeModifier { gradient })
+}
+
+/// Builds the shared spell_fire assets: one effect per charge level, the trail
+/// and impact effects, plus the core mesh, colored from the player's palette and
+/// sized to the `GridSize`.
+///
+/// When rebuilt, the core's material is recolored in place, so projectiles
+/// already in flight take on the new palette too.
+#[allow(clippy::too_many_arguments)]
+fn setup_spell_fire_effect(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    accessibility: Res<AccessibilitySettings>,
+    grid_size: Res<GridSize>,
+    spell_fire_assets: Option<Res<SpellFireAssets>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let texture_handle: Handle<Image> = asset_server.load("cloud.png");
+    let palette = accessibility.palette();
+    let material = spell_fire_assets
+        .and_then(|spell_fire_assets| {
+            let handle = spell_fire_assets.material.clone();
+            let material = materials.get_mut(&handle)?;
+            material.base_color = palette.spell_core;
+            Some(handle)
+        })
+        .unwrap_or_else(|| materials.add(palette.spell_core.into()));
+
+    let spell_effects = (0..=SPELL_FIRE_CHARGE_LEVELS)
+        .map(|level| {
+            let stats =
+                SpellStats::from_charge(level as f32 / SPELL_FIRE_CHARGE_LEVELS as f32, *grid_size);
+            effects.add(spell_fire_effect(texture_handle.clone(), &stats, palette))
+        })
+        .collect();
+
+    commands.insert_resource(SpellFireAssets {
+        effects: spell_effects,
+        trail: effects.add(spell_trail_effect(texture_handle.clone(), palette)),
+        impact_fire: effects.add(spell_impact_effect(
+            texture_handle.clone(),
+            palette.impact(SpellKind::Fire),
+        )),
+        impact_homing: effects.add(spell_impact_effect(
+            texture_handle.clone(),
+            palette.impact(SpellKind::Homing),
+        )),
+        impact_ice: effects.add(spell_impact_effect(
+            texture_handle.clone(),
+            palette.impact(SpellKind::Ice),
+        )),
+        explosion: effects.add(spell_explosion_effect(
+            texture_handle.clone(),
+            palette,
+            *grid_size,
+        )),
+        firewall: effects.add(firewall_effect(texture_handle, palette, *grid_size)),
+        mesh: meshes.add(Mesh::from(Cube { size: 1.0 })),
+        material,
+    });
 }
 
 #[allow(clippy::type_complexity)]
@@ -88,102 +716,1194 @@ fn _setup_spell_fire_collision(
     }
 }
 
-/// When the player presses an arrow key, shoot a Spell_Fire in that direction.
+/// Charges and casts Spell_Fire from the cast keys, the arrow keys by default.
+///
+/// Pressing a cast key starts charging a `SpellCharge` on the player; releasing it
+/// shoots a Spell_Fire in that direction. Holding two cast keys together, like Up
+/// and Right, casts diagonally between them, at the same speed. The longer the key was held (up to
+/// `SPELL_FIRE_MAX_CHARGE_SECONDS`), the bigger, faster and more damaging the
+/// projectile; a fully charged one pierces through enemies. A quick tap still fires
+/// a basic bolt. A spell released while the `SpellCooldown` from the last cast is
+/// running is kept as a `BufferedCast` and fired the moment the cooldown is over,
+/// unless that's more than `SPELL_CAST_BUFFER_SECONDS` away or another cast key is
+/// pressed first. Casts are refused when the player lacks the spell's mana
+/// (sending `NoMana`) or when `SpellFirePool` is at its cap.
+/// Successful casts send `SpellCast` and push the player back with a `Recoil` scaled
+/// by the charge. Dying players can't cast.
+/// A firewall isn't a projectile and doesn't count toward the pool: whatever the
+/// charge, it sets the `firewall_cells` ahead of the player burning as a
+/// `DamageField` for `SPELL_FIREWALL_SECONDS`, with no recoil. The cells are in
+/// world grid coordinates, measured from the player's feet wherever its level is
+/// placed, and the field with its flames is spawned in world space.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn spawn_spell_fire_from_input(
     mut commands: Commands,
     input_res: Res<Input<KeyCode>>,
-    query: Query<&mut Transform, With<Player>>,
-    asset_server: Res<AssetServer>,
-    mut effects: ResMut<Assets<EffectAsset>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    keys: Res<KeyBindings>,
+    time: Res<Time>,
+    mut query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &AnimationState,
+            Option<&mut SpellCharge>,
+            Option<&mut BufferedCast>,
+            Option<&PlayerId>,
+        ),
+        With<Player>,
+    >,
+    spell_fire_assets: Res<SpellFireAssets>,
+    mut spell_fire_pool: ResMut<SpellFirePool>,
+    active_spell: Res<ActiveSpell>,
+    mut mana: ResMut<Mana>,
+    mut cooldown: ResMut<SpellCooldown>,
+    mut no_mana_events: EventWriter<NoMana>,
+    mut cast_events: EventWriter<SpellCast>,
+    grid_size: Res<GridSize>,
 ) {
-    for player_transform in query.iter() {
-        let impulse = if input_res.just_pressed(KeyCode::Up) {
-            Vec2::new(0.0, SPELL_FIRE_SPEED)
-        } else if input_res.just_pressed(KeyCode::Down) {
-            Vec2::new(0.0, -SPELL_FIRE_SPEED)
-        } else if input_res.just_pressed(KeyCode::Left) {
-            Vec2::new(-SPELL_FIRE_SPEED, 0.0)
-        } else if input_res.just_pressed(KeyCode::Right) {
-            Vec2::new(SPELL_FIRE_SPEED, 0.0)
-        } else {
-            Vec2::ZERO
+    for (player_entity, player_global, animation_state, spell_charge, buffered_cast, player_id) in
+        query.iter_mut()
+    {
+        // The cast keys are player one's
+        if *animation_state == AnimationState::Dying || PlayerId::of(player_id) != PlayerId::ONE {
+            continue;
+        }
+        // Starting a new charge replaces any cast still waiting on the cooldown
+        if spell_charge.is_none() {
+            if let Some((key, direction)) = cast_key_just_pressed(&input_res, &keys) {
+                commands
+                    .entity(player_entity)
+                    .insert(SpellCharge::new(key, direction))
+                    .remove::<BufferedCast>();
+                continue;
+            }
+        }
+        let (direction, charge, kind) = match (spell_charge, buffered_cast) {
+            (Some(mut spell_charge), _) => {
+                spell_charge.timer.tick(time.delta());
+                if input_res.pressed(spell_charge.key) {
+                    // A second key pressed while charging turns the cast diagonal
+                    if let Some(direction) = held_cast_direction(&input_res, &keys) {
+                        spell_charge.direction = direction;
+                    }
+                    continue;
+                }
+                commands.entity(player_entity).remove::<SpellCharge>();
+                if !cooldown.ready() {
+                    info!(
+                        "🔥cooling down: {:.2}s left, buffering cast",
+                        (cooldown.timer.duration() - cooldown.timer.elapsed()).as_secs_f32()
+                    );
+                    commands.entity(player_entity).insert(BufferedCast::new(
+                        spell_charge.direction,
+                        spell_charge.timer.percent(),
+                        active_spell.0,
+                    ));
+                    continue;
+                }
+                (
+                    spell_charge.direction,
+                    spell_charge.timer.percent(),
+                    active_spell.0,
+                )
+            }
+            (None, Some(mut buffered_cast)) => {
+                if !cooldown.ready() {
+                    if buffered_cast.timer.tick(time.delta()).finished() {
+                        info!("🔥buffered cast dropped: cooldown outlasted the buffer");
+                        commands.entity(player_entity).remove::<BufferedCast>();
+                    }
+                    continue;
+                }
+                commands.entity(player_entity).remove::<BufferedCast>();
+                (
+                    buffered_cast.direction,
+                    buffered_cast.charge,
+                    buffered_cast.kind,
+                )
+            }
+            (None, None) => continue,
         };
 
-        if impulse != Vec2::ZERO {
-            let texture_handle: Handle<Image> = asset_server.load("cloud.png");
-            let spell_transform = Transform::from_translation(Vec3::new(
-                player_transform.translation.x,
-                player_transform.translation.y,
-                player_transform.translation.z + 1.0,
-            ));
+        let cost = kind.mana_cost();
+        if !mana.can_afford(cost) {
+            info!(
+                "🔥no mana: {:?} costs {} but only {:.1} left",
+                kind, cost, mana.current
+            );
+            no_mana_events.send(NoMana { kind, cost });
+            continue;
+        }
+        if kind != SpellKind::Firewall && !spell_fire_pool.try_acquire() {
+            info!(
+                "🔥spell_fire refused: {} of {} already live",
+                spell_fire_pool.live(),
+                spell_fire_pool.max_live
+            );
+            continue;
+        }
+        mana.try_spend(cost);
+        cooldown.timer.reset();
+        cast_events.send(SpellCast {
+            caster: player_entity,
+            kind,
+        });
+
+        if kind == SpellKind::Firewall {
+            let cells = firewall_cells(
+                player_cell(player_global.translation().truncate(), *grid_size),
+                direction,
+            );
+            info!("🔥spawn firewall over {:?}", cells);
+            commands
+                .spawn((
+                    DamageField::new(
+                        cells.clone(),
+                        SPELL_FIREWALL_DAMAGE,
+                        SPELL_FIREWALL_TICK_SECONDS,
+                        SPELL_FIREWALL_HURTS_PLAYER,
+                    ),
+                    DespawnTimer::new(SPELL_FIREWALL_SECONDS),
+                    SpatialBundle::default(),
+                    Name::new("firewall"),
+                ))
+                .with_children(|field| {
+                    // Children, so the flames go out along with the field
+                    for cell in cells {
+                        field.spawn((
+                            ParticleEffectBundle {
+                                transform: Transform::from_translation(
+                                    grid_size.to_translation(cell).extend(1.0),
+                                ),
+                                ..ParticleEffectBundle::new(spell_fire_assets.firewall.clone())
+                            },
+                            Name::new("firewall_flames"),
+                        ));
+                    }
+                });
+            continue;
+        }
 
-            let mut gradient = Gradient::new();
-            gradient.add_key(0.0, Vec4::splat(1.0));
-            gradient.add_key(0.1, Vec4::new(1.0, 1.0, 0.0, 1.0));
-            gradient.add_key(0.4, Vec4::new(1.0, 0.0, 0.0, 1.0));
-            gradient.add_key(1.0, Vec4::splat(0.0));
+        let level = charge_level(charge);
+        let stats =
+            SpellStats::from_charge(level as f32 / SPELL_FIRE_CHARGE_LEVELS as f32, *grid_size);
+        let velocity = direction * stats.speed;
+        commands.entity(player_entity).insert(Recoil {
+            velocity: -direction * PLAYER_RECOIL_SPEED * stats.speed / SPELL_FIRE_SPEED,
+        });
 
-            let writer = ExprWriter::new();
+        // Projectiles fly at the world root, so they start from where the player is drawn
+        let spell_transform = Transform::from_translation(
+            player_global.translation() + spell_spawn_offset(direction).extend(1.0),
+        );
 
-            let age = writer.lit(0.).expr();
-            let init_age = SetAttributeModifier::new(Attribute::AGE, age);
+        info!(
+            "🔥spawn {:?} spell_fire@{:?} velocity@{:?} charge_level={}",
+            kind, spell_transform.translation, velocity, level
+        );
 
-            let lifetime = writer.lit(5.).expr();
-            let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+        let mut spell = commands.spawn(SpellFire);
+        match kind {
+            SpellKind::Fire | SpellKind::Firewall => {}
+            SpellKind::Homing => {
+                spell.insert(Homing::default());
+            }
+            SpellKind::Ice => {
+                spell.insert(Freezing::default());
+            }
+            SpellKind::Explosion => {
+                spell.insert(Explosive::default());
+            }
+        }
+        if level == SPELL_FIRE_CHARGE_LEVELS {
+            spell.insert(Piercing::new(SPELL_FIRE_PIERCE_HITS));
+        }
+        spell
+            .insert(Name::new("spell_fire"))
+            .insert(spell_transform)
+            .insert(ParticleEffectBundle::new(
+                spell_fire_assets.effects[level].clone(),
+            ))
+            .insert(SpellProjectile {
+                velocity,
+                damage: stats.damage,
+                traveled: 0.0,
+                range: stats.range,
+            })
+            .insert(DespawnTimer::new(SPELL_FIRE_LIFETIME))
+            .insert(CollisionLayer::PlayerSpell.groups())
+            .with_children(|p| {
+                p.spawn(PbrBundle {
+                    mesh: spell_fire_assets.mesh.clone(),
+                    material: spell_fire_assets.material.clone(),
+                    ..Default::default()
+                });
+                // A child, so it's despawned along with the projectile
+                p.spawn((
+                    ParticleEffectBundle::new(spell_fire_assets.trail.clone()),
+                    SpellTrail,
+                    Name::new("spell_trail"),
+                ));
+            });
+    }
+}
 
-            let init_pos = SetPositionSphereModifier {
-                center: writer.lit(Vec3::ZERO).expr(),
-                radius: writer.lit(1.).expr(),
-                dimension: ShapeDimension::Volume,
-            };
+/// Selects the active spell with the number keys (1: fire, 2: homing, 3: ice,
+/// 4: explosion, 5: firewall).
+fn select_spell_from_input(input_res: Res<Input<KeyCode>>, mut active_spell: ResMut<ActiveSpell>) {
+    let selected = if input_res.just_pressed(KeyCode::Key1) {
+        SpellKind::Fire
+    } else if input_res.just_pressed(KeyCode::Key2) {
+        SpellKind::Homing
+    } else if input_res.just_pressed(KeyCode::Key3) {
+        SpellKind::Ice
+    } else if input_res.just_pressed(KeyCode::Key4) {
+        SpellKind::Explosion
+    } else if input_res.just_pressed(KeyCode::Key5) {
+        SpellKind::Firewall
+    } else {
+        return;
+    };
+    if active_spell.0 != selected {
+        info!("🔥active spell: {:?}", selected);
+        active_spell.0 = selected;
+    }
+}
 
-            let init_vel = SetVelocitySphereModifier {
-                center: writer.lit(Vec3::ZERO).expr(),
-                speed: writer.lit(2.).expr(),
-            };
+/// Counts down the time until the player can cast again.
+fn tick_spell_cooldown(time: Res<Time>, mut cooldown: ResMut<SpellCooldown>) {
+    cooldown.timer.tick(time.delta());
+}
 
-            let effect = effects.add(
-                EffectAsset::new(32768, Spawner::rate(1000.0.into()), writer.finish())
-                    .with_name("spell_fire")
-                    .init(init_pos)
-                    .init(init_vel)
-                    .init(init_age)
-                    .init(init_lifetime)
-                    .render(ParticleTextureModifier {
-                        texture: texture_handle.clone(),
-                    })
-                    .render(ColorOverLifetimeModifier { gradient }),
-            );
+/// Regenerates the player's mana over time.
+fn regenerate_mana(time: Res<Time>, mut mana: ResMut<Mana>) {
+    if mana.current < mana.max {
+        mana.regenerate(time.delta_seconds());
+    }
+}
 
-            info!(
-                "🔥spawn spell_fire@{:?} impulse@{:?}",
-                spell_transform.translation, impulse
+/// Steers homing projectiles toward the nearest enemy within their radius.
+///
+/// Projectiles turn by at most `Homing::turn_rate` per second, so they arc toward
+/// the enemy rather than snapping; with no enemy in range they fly straight.
+fn steer_homing_spells(
+    time: Res<Time>,
+    mut projectile_query: Query<(&Transform, &mut SpellProjectile, &Homing)>,
+    enemy_positions: Res<EnemyPositions>,
+) {
+    for (transform, mut projectile, homing) in projectile_query.iter_mut() {
+        let position = transform.translation.truncate();
+        if let Some((_, enemy_position)) = enemy_positions.nearest_within(position, homing.radius) {
+            projectile.velocity = steer_towards(
+                projectile.velocity,
+                enemy_position - position,
+                homing.turn_rate * time.delta_seconds(),
             );
+        }
+    }
+}
 
-            commands
-                .spawn(SpellFire)
-                .insert(Name::new("spell_fire"))
-                .insert(spell_transform)
-                .insert(ParticleEffectBundle::new(effect))
-                // .insert(ExternalImpulse {
-                //     impulse: impulse,
-                //     torque_impulse: 0.0,
-                // })
-                .with_children(|p| {
-                    p.spawn(PbrBundle {
-                        mesh: meshes.add(Mesh::from(Cube { size: 1.0 })),
-                        material: materials.add(Color::RED.into()),
-                        ..Default::default()
-                    });
+/// Syncs the live projectile count in `SpellFirePool` with the world.
+fn count_live_spell_fire(
+    query: Query<(), With<SpellFire>>,
+    mut spell_fire_pool: ResMut<SpellFirePool>,
+) {
+    spell_fire_pool.live = query.iter().count();
+}
+
+/// Moves spell projectiles along their velocity and despawns them when they fly out
+/// of range or into a wall. Projectiles whose `DespawnTimer` ran out this frame
+/// are left for `tick_despawn_timers` to despawn, but still count as spent.
+///
+/// Projectiles move in world space. Wall hits send a `SpellImpact`. Only walls
+/// inside the current level stop a projectile, so spells can fly on into
+/// neighboring levels. Hitting a
+/// `Breakable` wall also sends a `WallDamaged` with the projectile's damage.
+/// `Explosive` projectiles also go off where they hit the wall or run out,
+/// sending a `SpellExplosion`.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn move_spell_fire(
+    mut commands: Commands,
+    time: Res<Time>,
+    level_walls: Res<LevelWalls>,
+    grid_size: Res<GridSize>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut SpellProjectile,
+        Option<&DespawnTimer>,
+        Option<&Homing>,
+        Option<&Freezing>,
+        Option<&Explosive>,
+    )>,
+    breakable_query: Query<(Entity, &GlobalTransform), With<Breakable>>,
+    mut impact_events: EventWriter<SpellImpact>,
+    mut explosion_events: EventWriter<SpellExplosion>,
+    mut wall_damaged_events: EventWriter<WallDamaged>,
+) {
+    for (entity, mut transform, mut projectile, despawn_timer, homing, freezing, explosive) in
+        query.iter_mut()
+    {
+        let step = projectile.velocity * time.delta_seconds();
+        transform.translation += step.extend(0.0);
+        projectile.traveled += step.length();
+
+        let world_cell = grid_size.to_grid_coords(transform.translation.truncate());
+        let cell = level_walls.to_local(&world_cell);
+        let hit_wall = level_walls.in_bounds(&cell) && level_walls.in_wall(&cell);
+        let expired = despawn_timer.is_some_and(|despawn_timer| despawn_timer.timer.finished());
+        let spent = hit_wall || expired || projectile.out_of_range();
+        if !spent {
+            continue;
+        }
+        if !expired {
+            commands.entity(entity).despawn_recursive();
+        }
+        if hit_wall {
+            let breakable = breakable_query.iter().find(|(_, wall_transform)| {
+                grid_size.to_grid_coords(wall_transform.translation().truncate()) == world_cell
+            });
+            if let Some((wall, _)) = breakable {
+                wall_damaged_events.send(WallDamaged {
+                    wall,
+                    damage: projectile.damage,
                 });
+            }
+        }
+        if hit_wall || explosive.is_some() {
+            impact_events.send(SpellImpact {
+                position: transform.translation,
+                kind: SpellKind::of_projectile(homing, freezing, explosive),
+            });
+        }
+        if let Some(explosive) = explosive {
+            explosion_events.send(SpellExplosion {
+                position: transform.translation,
+                radius: explosive.radius * grid_size.pixels(),
+                damage: projectile.damage,
+            });
         }
     }
 }
 
+/// Spawns a burst at each spell impact, colored by the kind of spell.
+fn spawn_impact_bursts(
+    mut commands: Commands,
+    mut impact_events: EventReader<SpellImpact>,
+    spell_fire_assets: Res<SpellFireAssets>,
+) {
+    for impact in impact_events.iter() {
+        commands.spawn((
+            ParticleEffectBundle {
+                transform: Transform::from_translation(impact.position),
+                ..ParticleEffectBundle::new(spell_fire_assets.impact(impact.kind))
+            },
+            ImpactBurst,
+            DespawnTimer::new(SPELL_IMPACT_SECONDS),
+            Name::new("spell_impact"),
+        ));
+    }
+}
+
 fn dbg_spell_fire(query: Query<&Transform, With<SpellFire>>) {
     for transform in query.iter() {
         info!("🔥dbg_spell_fire: {:?}", transform.translation);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use super::*;
+    use crate::player::{player_translation, InputLocked};
+
+    #[test]
+    fn test_spell_stats_minimum_charge() {
+        let stats = SpellStats::from_charge(0.0, GridSize::default());
+        assert_eq!(stats.speed, SPELL_FIRE_SPEED);
+        assert_eq!(stats.damage, SPELL_FIRE_DAMAGE);
+        assert_eq!(stats.particle_radius, 1.0);
+        assert_eq!(stats.particle_speed, 2.0);
+    }
+
+    #[test]
+    fn test_spell_stats_partial_charge() {
+        let half = SpellStats::from_charge(0.5, GridSize::default());
+        let expected = 1.0 + (SPELL_FIRE_MAX_CHARGE_MULTIPLIER - 1.0) * 0.5;
+        assert_eq!(half.speed, SPELL_FIRE_SPEED * expected);
+        assert_eq!(half.damage, SPELL_FIRE_DAMAGE * expected);
+        assert!(half.speed > SpellStats::from_charge(0.0, GridSize::default()).speed);
+        assert!(half.speed < SpellStats::from_charge(1.0, GridSize::default()).speed);
+    }
+
+    #[test]
+    fn test_spell_stats_capped_charge() {
+        let full = SpellStats::from_charge(1.0, GridSize::default());
+        assert_eq!(
+            full.speed,
+            SPELL_FIRE_SPEED * SPELL_FIRE_MAX_CHARGE_MULTIPLIER
+        );
+        assert_eq!(
+            full.damage,
+            SPELL_FIRE_DAMAGE * SPELL_FIRE_MAX_CHARGE_MULTIPLIER
+        );
+        assert_eq!(SpellStats::from_charge(4.0, GridSize::default()), full);
+        assert_eq!(
+            full.range,
+            SpellStats::from_charge(0.0, GridSize::default()).range
+        );
+        // Range is set in cells, so it follows the map's grid size
+        assert_eq!(
+            SpellStats::from_charge(0.0, GridSize(32)).range,
+            SPELL_FIRE_RANGE * 32.0
+        );
+        assert_eq!(
+            SpellStats::from_charge(-1.0, GridSize::default()),
+            SpellStats::from_charge(0.0, GridSize::default())
+        );
+    }
+
+    #[test]
+    fn test_charge_level() {
+        assert_eq!(charge_level(0.0), 0);
+        assert_eq!(charge_level(0.49), SPELL_FIRE_CHARGE_LEVELS / 2);
+        assert_eq!(charge_level(1.0), SPELL_FIRE_CHARGE_LEVELS);
+        assert_eq!(charge_level(7.0), SPELL_FIRE_CHARGE_LEVELS);
+    }
+
+    #[test]
+    fn test_spell_spawn_offset() {
+        let hand = |x: f32, y: f32| Vec2::new(x, y + SPELL_FIRE_HAND_HEIGHT);
+        let reach = SPELL_FIRE_HAND_REACH;
+        assert_eq!(spell_spawn_offset(Vec2::Y), hand(0.0, reach));
+        assert_eq!(spell_spawn_offset(Vec2::NEG_Y), hand(0.0, -reach));
+        assert_eq!(spell_spawn_offset(Vec2::NEG_X), hand(-reach, 0.0));
+        assert_eq!(spell_spawn_offset(Vec2::X), hand(reach, 0.0));
+    }
+
+    fn spell_fire_app(max_live: usize) -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<Time>()
+            .init_resource::<ActiveSpell>()
+            .init_resource::<Mana>()
+            // No cooldown, so tests can cast on consecutive frames
+            .insert_resource(SpellCooldown::new(0.0))
+            .init_resource::<InputLocked>()
+            .add_event::<NoMana>()
+            .add_event::<SpellCast>()
+            .add_event::<SpellImpact>()
+            .add_event::<SpellExplosion>()
+            .add_event::<WallDamaged>()
+            .init_resource::<LevelWalls>()
+            .init_resource::<GridSize>()
+            .insert_resource(SpellFirePool { max_live, live: 0 })
+            .insert_resource(SpellFireAssets {
+                effects: vec![Handle::default(); SPELL_FIRE_CHARGE_LEVELS + 1],
+                trail: Handle::default(),
+                impact_fire: Handle::default(),
+                impact_homing: Handle::default(),
+                impact_ice: Handle::default(),
+                explosion: Handle::default(),
+                firewall: Handle::default(),
+                mesh: Handle::default(),
+                material: Handle::default(),
+            })
+            .add_systems(
+                Update,
+                (
+                    count_live_spell_fire,
+                    tick_spell_cooldown,
+                    spawn_spell_fire_from_input
+                        .run_if(input_unlocked)
+                        .after(count_live_spell_fire)
+                        .after(tick_spell_cooldown),
+                ),
+            );
+        app.world.spawn((
+            Player,
+            AnimationState::default(),
+            Transform::default(),
+            GlobalTransform::default(),
+        ));
+        app
+    }
+
+    /// Taps the up arrow: one frame pressed, one frame released.
+    fn tap_cast(app: &mut App) {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+    }
+
+    fn live_spell_fire(app: &mut App) -> usize {
+        let count = app
+            .world
+            .query_filtered::<(), With<SpellFire>>()
+            .iter(&app.world)
+            .count();
+        count
+    }
+
+    #[test]
+    fn test_cast_adds_recoil_opposite_the_spell() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        tap_cast(&mut app);
+        let recoil = app
+            .world
+            .query_filtered::<&Recoil, With<Player>>()
+            .single(&app.world)
+            .velocity;
+        // The spell flies up, so the player is pushed down
+        assert_eq!(recoil, Vec2::new(0.0, -PLAYER_RECOIL_SPEED));
+    }
+
+    #[test]
+    fn test_player_spell_ignores_player() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        tap_cast(&mut app);
+        let groups = *app
+            .world
+            .query_filtered::<&CollisionGroups, With<SpellFire>>()
+            .single(&app.world);
+        assert_eq!(groups.memberships, COLLISION_GROUP_PLAYER_SPELL);
+        assert!(!groups.filters.contains(COLLISION_GROUP_PLAYER));
+        assert!(groups.filters.contains(COLLISION_GROUP_WALL));
+        assert!(groups.filters.contains(COLLISION_GROUP_ENEMY));
+        // The solver uses the same filter, so the spell can't push the player either
+        let solver_groups = *app
+            .world
+            .query_filtered::<&SolverGroups, With<SpellFire>>()
+            .single(&app.world);
+        assert!(!solver_groups.filters.contains(COLLISION_GROUP_PLAYER));
+    }
+
+    #[test]
+    fn test_cast_skipped_while_input_locked() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.world.resource_mut::<InputLocked>().inspector = true;
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 0);
+
+        app.world.resource_mut::<InputLocked>().inspector = false;
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 1);
+    }
+
+    #[test]
+    fn test_spell_fire_pool_rejects_over_cap() {
+        let mut pool = SpellFirePool {
+            max_live: 2,
+            live: 0,
+        };
+        assert!(pool.try_acquire());
+        assert!(pool.try_acquire());
+        assert!(!pool.try_acquire());
+        assert_eq!(pool.live(), 2);
+    }
+
+    #[test]
+    fn test_cast_cap_rejects_extra_projectile() {
+        let mut app = spell_fire_app(3);
+        for _ in 0..4 {
+            tap_cast(&mut app);
+        }
+        assert_eq!(live_spell_fire(&mut app), 3);
+    }
+
+    #[test]
+    fn test_cast_waits_for_cooldown() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(SpellCooldown::default());
+        tap_cast(&mut app);
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 1);
+        assert!(!app.world.resource::<SpellCooldown>().ready());
+
+        // The next frames each take a whole cooldown
+        let elapsed =
+            app.world.resource::<Time>().startup() + Duration::from_secs_f32(SPELL_FIRE_COOLDOWN);
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(elapsed);
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 2);
+    }
+
+    /// Lets `seconds` pass on every following frame, until changed again.
+    fn set_frame_time(app: &mut App, seconds: f32) {
+        let time = app.world.resource::<Time>();
+        let now =
+            time.last_update().unwrap_or_else(|| time.startup()) + Duration::from_secs_f32(seconds);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+    }
+
+    #[test]
+    fn test_cast_during_cooldown_fires_when_it_ends() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(SpellCooldown::default());
+        tap_cast(&mut app);
+        // Released just before the cooldown ends, so it waits instead of being dropped
+        let left = SPELL_CAST_BUFFER_SECONDS / 2.0;
+        set_frame_time(&mut app, SPELL_FIRE_COOLDOWN - left);
+        app.update();
+        set_frame_time(&mut app, 0.0);
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 1);
+        assert_eq!(
+            app.world
+                .query::<&BufferedCast>()
+                .single(&app.world)
+                .direction,
+            Vec2::Y
+        );
+
+        set_frame_time(&mut app, left);
+        app.update();
+        assert_eq!(live_spell_fire(&mut app), 2);
+        assert!(app
+            .world
+            .query::<&BufferedCast>()
+            .iter(&app.world)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_buffered_cast_dropped_after_window() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(SpellCooldown::default());
+        tap_cast(&mut app);
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 1);
+
+        // The cooldown outlasts the buffer, so the cast is gone once it ends
+        set_frame_time(&mut app, SPELL_CAST_BUFFER_SECONDS * 1.5);
+        for _ in 0..4 {
+            app.update();
+        }
+        assert!(app.world.resource::<SpellCooldown>().ready());
+        assert_eq!(live_spell_fire(&mut app), 1);
+    }
+
+    /// Taps cast over and over, topping up mana first so casts aren't refused for it.
+    fn cast_tight_loop(b: &mut test::Bencher, max_live: usize) {
+        let mut app = spell_fire_app(max_live);
+        b.iter(|| {
+            app.world.resource_mut::<Mana>().current = PLAYER_MANA_MAX;
+            tap_cast(&mut app);
+        });
+    }
+
+    #[bench]
+    fn bench_cast_tight_loop(b: &mut test::Bencher) {
+        cast_tight_loop(b, SPELL_FIRE_MAX_LIVE);
+    }
+
+    #[bench]
+    fn bench_cast_tight_loop_uncapped(b: &mut test::Bencher) {
+        // Every cast spawns a projectile, as before the cap, to compare against
+        cast_tight_loop(b, usize::MAX);
+    }
+
+    #[test]
+    fn test_steer_towards_turns_at_most_max_rate() {
+        let velocity = Vec2::new(100.0, 0.0);
+        let to_enemy = Vec2::new(0.0, 50.0); // 90 degrees to the left
+        let max_turn = 0.1;
+
+        let steered = steer_towards(velocity, to_enemy, max_turn);
+        let turned = velocity.angle_between(steered);
+        assert!((turned - max_turn).abs() < 1e-5);
+        assert!((steered.length() - velocity.length()).abs() < 1e-3);
+
+        // Enemy to the right turns the other way
+        let steered = steer_towards(velocity, Vec2::new(0.0, -50.0), max_turn);
+        assert!((velocity.angle_between(steered) + max_turn).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_steer_towards_small_correction_snaps() {
+        let velocity = Vec2::new(100.0, 0.0);
+        let to_enemy = Vec2::from_angle(0.05).rotate(Vec2::new(10.0, 0.0));
+        let steered = steer_towards(velocity, to_enemy, 0.1);
+        assert!(steered.normalize().abs_diff_eq(to_enemy.normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_trail_spawned_and_despawned_with_projectile() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.add_systems(
+            Update,
+            (
+                tick_despawn_timers,
+                move_spell_fire.after(tick_despawn_timers),
+            ),
+        );
+        tap_cast(&mut app);
+        tap_cast(&mut app);
+
+        // Each projectile has exactly one trail, as its child
+        let trail_parents: Vec<Entity> = app
+            .world
+            .query_filtered::<&Parent, With<SpellTrail>>()
+            .iter(&app.world)
+            .map(|parent| parent.get())
+            .collect();
+        assert_eq!(trail_parents.len(), 2);
+        for parent in &trail_parents {
+            assert!(app.world.get::<SpellFire>(*parent).is_some());
+        }
+        assert_ne!(trail_parents[0], trail_parents[1]);
+
+        let past_lifetime = app.world.resource::<Time>().startup()
+            + Duration::from_secs_f32(SPELL_FIRE_LIFETIME + 1.0);
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(past_lifetime);
+        app.update();
+        assert_eq!(live_spell_fire(&mut app), 0);
+        let trails = app
+            .world
+            .query_filtered::<(), With<SpellTrail>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(trails, 0);
+    }
+
+    #[test]
+    fn test_wall_hit_spawns_one_burst_that_despawns() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(LevelWalls::from_cells(&[(3, 0)], 5, 5))
+            .add_systems(
+                Update,
+                (
+                    move_spell_fire,
+                    spawn_impact_bursts.after(move_spell_fire),
+                    tick_despawn_timers,
+                ),
+            );
+        let bursts = |app: &mut App| {
+            let count = app
+                .world
+                .query_filtered::<(), With<ImpactBurst>>()
+                .iter(&app.world)
+                .count();
+            count
+        };
+        let spell = app
+            .world
+            .spawn((
+                SpellFire,
+                Homing::default(),
+                Transform::from_xyz(40.0, 8.0, 0.0),
+                SpellProjectile {
+                    velocity: Vec2::new(160.0, 0.0),
+                    damage: 1.0,
+                    traveled: 0.0,
+                    range: SPELL_FIRE_RANGE * GridSize::default().pixels(),
+                },
+            ))
+            .id();
+
+        // Not moving yet: still in the open cell
+        app.update();
+        assert!(app.world.get_entity(spell).is_some());
+        assert_eq!(bursts(&mut app), 0);
+
+        // 0.1s at 160px/s carries it from x = 40 into the wall at x = 48..64
+        let startup = app.world.resource::<Time>().startup();
+        let mut now = startup + Duration::from_secs_f32(0.1);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        assert!(app.world.get_entity(spell).is_none());
+        assert_eq!(bursts(&mut app), 1);
+
+        // The burst lingers, then goes away on its own
+        app.update();
+        assert_eq!(bursts(&mut app), 1);
+        now += Duration::from_secs_f32(SPELL_IMPACT_SECONDS);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        assert_eq!(bursts(&mut app), 0);
+    }
+
+    #[test]
+    fn test_projectile_despawns_at_range_regardless_of_speed() {
+        let range = SPELL_FIRE_RANGE * GridSize::default().pixels();
+        for speed in [
+            SPELL_FIRE_SPEED,
+            SPELL_FIRE_SPEED * SPELL_FIRE_MAX_CHARGE_MULTIPLIER,
+        ] {
+            let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+            app.add_systems(Update, move_spell_fire);
+            let spell = app
+                .world
+                .spawn((
+                    SpellFire,
+                    Transform::default(),
+                    SpellProjectile {
+                        velocity: Vec2::new(speed, 0.0),
+                        damage: 1.0,
+                        traveled: 0.0,
+                        range,
+                    },
+                ))
+                .id();
+            let startup = app.world.resource::<Time>().startup();
+            let fly = |app: &mut App, distance: f32| {
+                let now = startup + Duration::from_secs_f32(distance / speed);
+                app.world.resource_mut::<Time>().update_with_instant(now);
+                app.update();
+            };
+
+            fly(&mut app, range * 0.9);
+            assert!(app.world.get_entity(spell).is_some());
+            fly(&mut app, range * 1.1);
+            assert!(app.world.get_entity(spell).is_none());
+        }
+    }
+
+    #[test]
+    fn test_range_counts_distance_flown_not_displacement() {
+        let range = SPELL_FIRE_RANGE * GridSize::default().pixels();
+        let speed = SPELL_FIRE_SPEED;
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.add_systems(Update, move_spell_fire);
+        let spell = app
+            .world
+            .spawn((
+                SpellFire,
+                Transform::default(),
+                SpellProjectile {
+                    velocity: Vec2::new(speed, 0.0),
+                    damage: 1.0,
+                    traveled: 0.0,
+                    range,
+                },
+            ))
+            .id();
+        let startup = app.world.resource::<Time>().startup();
+        let fly_until = |app: &mut App, distance: f32| {
+            let now = startup + Duration::from_secs_f32(distance / speed);
+            app.world.resource_mut::<Time>().update_with_instant(now);
+            app.update();
+        };
+
+        fly_until(&mut app, range * 0.6);
+        assert!(app.world.get_entity(spell).is_some());
+        // Turning back, like a homing spell circling round, ends up near where it started
+        app.world
+            .get_mut::<SpellProjectile>(spell)
+            .unwrap()
+            .velocity = Vec2::new(-speed, 0.0);
+        fly_until(&mut app, range * 1.1);
+        assert!(app.world.get_entity(spell).is_none());
+    }
+
+    #[test]
+    fn test_explosive_goes_off_at_end_of_range() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.add_systems(
+            Update,
+            (
+                tick_despawn_timers,
+                move_spell_fire.after(tick_despawn_timers),
+            ),
+        );
+        let spell = app
+            .world
+            .spawn((
+                SpellFire,
+                Explosive::default(),
+                Transform::from_xyz(40.0, 8.0, 0.0),
+                SpellProjectile {
+                    velocity: Vec2::ZERO,
+                    damage: 2.0,
+                    traveled: 0.0,
+                    range: SPELL_FIRE_RANGE * GridSize::default().pixels(),
+                },
+                DespawnTimer::new(SPELL_FIRE_LIFETIME),
+            ))
+            .id();
+        app.update();
+        assert!(app.world.resource::<Events<SpellExplosion>>().is_empty());
+
+        let past_lifetime = app.world.resource::<Time>().startup()
+            + Duration::from_secs_f32(SPELL_FIRE_LIFETIME + 1.0);
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(past_lifetime);
+        app.update();
+        assert!(app.world.get_entity(spell).is_none());
+        let explosions: Vec<SpellExplosion> = app
+            .world
+            .resource_mut::<Events<SpellExplosion>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            explosions,
+            vec![SpellExplosion {
+                position: Vec3::new(40.0, 8.0, 0.0),
+                radius: SPELL_EXPLOSION_RADIUS * GridSize::default().pixels(),
+                damage: 2.0,
+            }]
+        );
+        // It shows a blast, though it hit nothing
+        let impacts: Vec<SpellImpact> = app
+            .world
+            .resource_mut::<Events<SpellImpact>>()
+            .drain()
+            .collect();
+        assert_eq!(impacts.len(), 1);
+        assert_eq!(impacts[0].kind, SpellKind::Explosion);
+    }
+
+    #[test]
+    fn test_homing_flies_straight_without_enemy() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<EnemyPositions>()
+            .add_systems(Update, steer_homing_spells);
+        let spell = app
+            .world
+            .spawn((
+                Transform::default(),
+                Homing::default(),
+                SpellProjectile {
+                    velocity: Vec2::X,
+                    damage: 1.0,
+                    traveled: 0.0,
+                    range: SPELL_FIRE_RANGE * GridSize::default().pixels(),
+                },
+            ))
+            .id();
+        app.update();
+        assert_eq!(
+            app.world.get::<SpellProjectile>(spell).unwrap().velocity,
+            Vec2::X
+        );
+    }
+
+    #[test]
+    fn test_mana_rejects_too_expensive_cast() {
+        let mut mana = Mana {
+            current: 15.0,
+            max: 100.0,
+            regen: 0.0,
+        };
+        assert!(!mana.try_spend(20.0));
+        assert_eq!(mana.current, 15.0);
+        assert!(mana.try_spend(10.0));
+        assert_eq!(mana.current, 5.0);
+    }
+
+    #[test]
+    fn test_mana_regen_clamps_to_max() {
+        let mut mana = Mana {
+            current: 95.0,
+            max: 100.0,
+            regen: 10.0,
+        };
+        mana.regenerate(0.25);
+        assert_eq!(mana.current, 97.5);
+        mana.regenerate(10.0);
+        assert_eq!(mana.current, 100.0);
+    }
+
+    #[test]
+    fn test_cast_without_mana_is_refused() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(Mana {
+            current: SPELL_FIRE_MANA_COST * 1.5,
+            max: PLAYER_MANA_MAX,
+            regen: 0.0,
+        });
+        tap_cast(&mut app);
+        tap_cast(&mut app);
+        assert_eq!(live_spell_fire(&mut app), 1);
+        assert_eq!(
+            app.world.resource::<Mana>().current,
+            SPELL_FIRE_MANA_COST * 0.5
+        );
+        assert_eq!(app.world.resource::<Events<NoMana>>().len(), 1);
+    }
+
+    /// Presses each frame's keys, a frame apart, then releases them all together,
+    /// returning the velocities of the spells cast.
+    fn cast_with_keys(frames: &[&[KeyCode]]) -> Vec<Vec2> {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        for keys in frames {
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            for &key in *keys {
+                input.press(key);
+            }
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().clear();
+        }
+        for &key in frames.concat().iter() {
+            app.world.resource_mut::<Input<KeyCode>>().release(key);
+        }
+        app.update();
+        app.world
+            .query::<&SpellProjectile>()
+            .iter(&app.world)
+            .map(|projectile| projectile.velocity)
+            .collect()
+    }
+
+    #[test]
+    fn test_two_cast_keys_cast_diagonally() {
+        let up_right = Vec2::new(1.0, 1.0).normalize() * SPELL_FIRE_SPEED;
+        // Pressed on the same frame, or the second while the first is held
+        for velocities in [
+            cast_with_keys(&[&[KeyCode::Up, KeyCode::Right]]),
+            cast_with_keys(&[&[KeyCode::Right], &[KeyCode::Up]]),
+        ] {
+            assert_eq!(velocities.len(), 1);
+            assert!(
+                velocities[0].abs_diff_eq(up_right, 1e-3),
+                "{:?}",
+                velocities
+            );
+            assert!((velocities[0].length() - SPELL_FIRE_SPEED).abs() < 1e-3);
+        }
+        // Opposite keys cancel out, leaving the first key's direction
+        assert_eq!(
+            cast_with_keys(&[&[KeyCode::Up], &[KeyCode::Down]]),
+            vec![Vec2::Y * SPELL_FIRE_SPEED]
+        );
+    }
+
+    #[test]
+    fn test_firewall_cells_across_cast_direction() {
+        let cell = GridCoords::new(2, 2);
+        assert_eq!(
+            firewall_cells(cell, Vec2::Y),
+            vec![
+                GridCoords::new(3, 3),
+                GridCoords::new(2, 3),
+                GridCoords::new(1, 3)
+            ]
+        );
+        assert_eq!(
+            firewall_cells(cell, Vec2::X),
+            vec![
+                GridCoords::new(3, 1),
+                GridCoords::new(3, 2),
+                GridCoords::new(3, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_firewall_cast_spawns_field_not_projectile() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(ActiveSpell(SpellKind::Firewall));
+        tap_cast(&mut app);
+
+        assert_eq!(live_spell_fire(&mut app), 0);
+        let fields: Vec<DamageField> = app
+            .world
+            .query::<&DamageField>()
+            .iter(&app.world)
+            .cloned()
+            .collect();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(
+            fields[0].cells,
+            firewall_cells(player_cell(Vec2::ZERO, GridSize::default()), Vec2::Y)
+        );
+        assert_eq!(
+            app.world.resource::<Mana>().current,
+            PLAYER_MANA_MAX - SPELL_FIREWALL_MANA_COST
+        );
+    }
+
+    #[test]
+    fn test_firewall_in_offset_level_burns_world_cells() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        app.insert_resource(ActiveSpell(SpellKind::Firewall));
+        let grid_size = GridSize::default();
+        // The player stands on cell (2, 2) of a level placed 10 cells east and 4 north
+        let level_origin = grid_size.to_translation(GridCoords::new(10, 4))
+            - grid_size.to_translation(GridCoords::new(0, 0));
+        let local = player_translation(GridCoords::new(2, 2), grid_size);
+        let mut players = app
+            .world
+            .query_filtered::<(&mut Transform, &mut GlobalTransform), With<Player>>();
+        let (mut transform, mut global) = players.single_mut(&mut app.world);
+        transform.translation = local.extend(0.0);
+        *global = GlobalTransform::from_translation((level_origin + local).extend(0.0));
+        tap_cast(&mut app);
+
+        let burning = firewall_cells(GridCoords::new(12, 6), Vec2::Y);
+        let (field, children) = app
+            .world
+            .query::<(&DamageField, &Children)>()
+            .single(&app.world);
+        assert_eq!(field.cells, burning);
+        // The field sits at the world origin, so its flames are placed in world space
+        let flames: Vec<Vec2> = children
+            .iter()
+            .map(|&flame| {
+                app.world
+                    .get::<Transform>(flame)
+                    .unwrap()
+                    .translation
+                    .truncate()
+            })
+            .collect();
+        let expected: Vec<Vec2> = burning
+            .iter()
+            .map(|&cell| grid_size.to_translation(cell))
+            .collect();
+        assert_eq!(flames, expected);
+    }
+
+    #[test]
+    fn test_spell_in_offset_level_starts_at_the_player_and_hits_world_walls() {
+        let mut app = spell_fire_app(SPELL_FIRE_MAX_LIVE);
+        let grid_size = GridSize::default();
+        // The level is placed 10 cells east and 4 north, with a wall on its cell (2, 4)
+        app.insert_resource(LevelWalls::from_cells(&[(2, 4)], 5, 5).with_origin(IVec2::new(10, 4)))
+            .add_systems(Update, move_spell_fire.after(spawn_spell_fire_from_input));
+        let level_origin = grid_size.to_translation(GridCoords::new(10, 4))
+            - grid_size.to_translation(GridCoords::new(0, 0));
+        let local = player_translation(GridCoords::new(2, 2), grid_size);
+        let mut players = app
+            .world
+            .query_filtered::<(&mut Transform, &mut GlobalTransform), With<Player>>();
+        let (mut transform, mut global) = players.single_mut(&mut app.world);
+        transform.translation = local.extend(0.0);
+        *global = GlobalTransform::from_translation((level_origin + local).extend(0.0));
+        tap_cast(&mut app);
+
+        // Spawned at the world root, from where the player is drawn
+        let (spell, spell_transform) = app
+            .world
+            .query_filtered::<(Entity, &Transform), With<SpellFire>>()
+            .single(&app.world);
+        assert!(app.world.get::<Parent>(spell).is_none());
+        assert_eq!(
+            spell_transform.translation,
+            (level_origin + local + spell_spawn_offset(Vec2::Y)).extend(1.0)
+        );
+
+        // A cell's flight up takes it from the player's head into the level's wall
+        let startup = app.world.resource::<Time>().startup();
+        let now = startup + Duration::from_secs_f32(grid_size.pixels() / SPELL_FIRE_SPEED);
+        app.world.resource_mut::<Time>().update_with_instant(now);
+        app.update();
+        assert!(app.world.get_entity(spell).is_none());
+    }
+}
@@ -0,0 +1,211 @@
+// target_dummy.rs
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::enemy::Damaged;
+use crate::map::GridSize;
+
+/// TargetDummyPlugin runs the target dummies of the practice range.
+///
+/// Dummies are placed in LDtk as "TargetDummy" entities, like the ones in
+/// `practice.ldtk`, which is played with `--map=practice.ldtk`. They're hurt through
+/// the same `Damaged` path as enemies, and the damage they've taken since they were
+/// last reset is shown above them. Once a dummy goes `TARGET_DUMMY_RESET_SECONDS`
+/// without a hit, it's put back to full health and its tally cleared. A dummy
+/// knocked down to no health stays where it is, out of the fight until it resets.
+impl Plugin for TargetDummyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                add_dummy_labels,
+                tally_dummy_damage,
+                reset_target_dummies.after(tally_dummy_damage),
+                update_dummy_labels.after(reset_target_dummies),
+            ),
+        )
+        .register_ldtk_entity::<TargetDummyBundle>("TargetDummy");
+    }
+}
+
+/// Marker for the text above a target dummy showing the damage it has taken.
+#[derive(Component)]
+struct DummyLabel;
+
+/// Text shown above a dummy for the damage it has taken.
+///
+/// # Returns
+/// The damage to one decimal place, or nothing if the dummy hasn't been hit.
+pub fn dummy_label_text(dummy: &TargetDummy) -> String {
+    if dummy.damage_taken > 0.0 {
+        format!("{:.1}", dummy.damage_taken)
+    } else {
+        String::new()
+    }
+}
+
+/// Gives each new target dummy a label above it, one cell up.
+fn add_dummy_labels(
+    mut commands: Commands,
+    grid_size: Res<GridSize>,
+    query: Query<Entity, Added<TargetDummy>>,
+) {
+    for dummy in query.iter() {
+        commands.entity(dummy).with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: TARGET_DUMMY_FONT_SIZE,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    transform: Transform::from_xyz(0.0, grid_size.pixels(), 1.0),
+                    ..default()
+                },
+                DummyLabel,
+            ));
+        });
+    }
+}
+
+/// Adds the damage from each hit on a dummy to its tally, and starts its reset wait
+/// over.
+fn tally_dummy_damage(
+    mut damaged_events: EventReader<Damaged>,
+    mut dummy_query: Query<&mut TargetDummy>,
+) {
+    for damaged in damaged_events.iter() {
+        let Ok(mut dummy) = dummy_query.get_mut(damaged.target) else {
+            continue;
+        };
+        dummy.damage_taken += damaged.amount;
+        dummy.reset.reset();
+    }
+}
+
+/// Puts dummies that have gone long enough without a hit back to full health.
+fn reset_target_dummies(time: Res<Time>, mut query: Query<(&mut TargetDummy, &mut Health)>) {
+    for (mut dummy, mut health) in query.iter_mut() {
+        if !dummy.reset.tick(time.delta()).just_finished() || dummy.damage_taken == 0.0 {
+            continue;
+        }
+        info!("🎯dummy reset after taking {}", dummy.damage_taken);
+        dummy.damage_taken = 0.0;
+        health.current = health.max;
+    }
+}
+
+/// Shows each dummy's tally in its label.
+fn update_dummy_labels(
+    dummy_query: Query<(&TargetDummy, &Children)>,
+    mut label_query: Query<&mut Text, With<DummyLabel>>,
+) {
+    for (dummy, children) in dummy_query.iter() {
+        let text = dummy_label_text(dummy);
+        for &child in children.iter() {
+            let Ok(mut label) = label_query.get_mut(child) else {
+                continue;
+            };
+            if label.sections[0].value != text {
+                label.sections[0].value = text.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::Duration;
+
+    /// Runs a frame `seconds` after the last one.
+    fn run_after(app: &mut App, seconds: f32) {
+        let last = {
+            let time = app.world.resource::<Time>();
+            time.last_update().unwrap_or_else(|| time.startup())
+        };
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(last + Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    /// Hurts `dummy` for `amount`, the way spells do.
+    fn hit(app: &mut App, dummy: Entity, amount: f32) {
+        app.world.get_mut::<Health>(dummy).unwrap().current -= amount;
+        app.world.send_event(Damaged {
+            target: dummy,
+            amount,
+        });
+    }
+
+    #[test]
+    fn test_dummy_tallies_damage_then_resets() {
+        let mut app = App::new();
+        // A map on an 8 pixel grid
+        app.init_resource::<Time>()
+            .insert_resource(GridSize(8))
+            .add_event::<Damaged>()
+            .add_systems(
+                Update,
+                (
+                    add_dummy_labels,
+                    tally_dummy_damage,
+                    reset_target_dummies.after(tally_dummy_damage),
+                    update_dummy_labels.after(reset_target_dummies),
+                ),
+            );
+        let dummy = app
+            .world
+            .spawn((
+                TargetDummy::default(),
+                Health {
+                    current: TARGET_DUMMY_HEALTH,
+                    max: TARGET_DUMMY_HEALTH,
+                },
+            ))
+            .id();
+        run_after(&mut app, 0.0);
+        let label = |app: &mut App| {
+            let mut query = app.world.query_filtered::<&Text, With<DummyLabel>>();
+            query.single(&app.world).sections[0].value.clone()
+        };
+        run_after(&mut app, 0.0);
+        assert_eq!(label(&mut app), "");
+        let mut label_transform = app.world.query_filtered::<&Transform, With<DummyLabel>>();
+        assert_eq!(label_transform.single(&app.world).translation.y, 8.0);
+
+        hit(&mut app, dummy, 4.0);
+        run_after(&mut app, 0.1);
+        hit(&mut app, dummy, 2.5);
+        run_after(&mut app, 0.1);
+        assert_eq!(
+            app.world.get::<TargetDummy>(dummy).unwrap().damage_taken,
+            6.5
+        );
+        assert_eq!(label(&mut app), "6.5");
+
+        // Each hit starts the wait over, so the dummy holds its damage until then
+        run_after(&mut app, TARGET_DUMMY_RESET_SECONDS - 0.5);
+        assert_eq!(
+            app.world.get::<Health>(dummy).unwrap().current,
+            TARGET_DUMMY_HEALTH - 6.5
+        );
+        run_after(&mut app, 0.6);
+        assert_eq!(
+            app.world.get::<Health>(dummy).unwrap().current,
+            TARGET_DUMMY_HEALTH
+        );
+        assert_eq!(
+            app.world.get::<TargetDummy>(dummy).unwrap().damage_taken,
+            0.0
+        );
+        assert_eq!(label(&mut app), "");
+    }
+}
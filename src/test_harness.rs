@@ -0,0 +1,117 @@
+// test_harness.rs
+
+//! Headless fixture for testing gameplay systems without a window or LDtk project.
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::Duration;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::interpolation::GameplayStep;
+use crate::map::{GridSize, LevelEdgeReached, LevelWalls};
+use crate::player::{
+    input_unlocked, move_player_from_input, physics_movement, player_translation, read_move_input,
+    InputLocked, MoveInput, MovementFeel, MovementMode, NoClip, PendingTurns, PlayerCollision,
+};
+use crate::settings::{CoopBindings, KeyBindings};
+
+/// Simulated time between frames, which is also the length of a fixed step.
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// A minimal `App` running the movement systems over a hand-built level.
+///
+/// Gameplay systems go in `FixedUpdate`, which runs once per frame.
+pub struct Harness {
+    pub app: App,
+    pub player: Entity,
+}
+
+impl Harness {
+    /// Builds the app and spawns a player.
+    ///
+    /// # Arguments
+    /// * `walls` - Wall cells of the level.
+    /// * `width` - Level width in cells.
+    /// * `height` - Level height in cells.
+    /// * `player_cell` - Cell the player starts in.
+    pub fn new(walls: &[(i32, i32)], width: i32, height: i32, player_cell: GridCoords) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .insert_resource(FixedTime::new(FRAME))
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<CoopBindings>()
+            .init_resource::<MovementMode>()
+            .init_resource::<PlayerCollision>()
+            .init_resource::<NoClip>()
+            // Walks at full speed from the first frame, so tests can count frames per cell
+            .insert_resource(MovementFeel::instant())
+            .init_resource::<InputLocked>()
+            .init_resource::<MoveInput>()
+            .init_resource::<Difficulty>()
+            .init_resource::<GridSize>()
+            .insert_resource(LevelWalls::from_cells(walls, width, height))
+            .add_event::<LevelEdgeReached>()
+            .init_resource::<PendingTurns>()
+            .add_systems(PreUpdate, read_move_input.run_if(input_unlocked))
+            .add_systems(
+                FixedUpdate,
+                move_player_from_input
+                    .run_if(input_unlocked)
+                    .run_if(not(physics_movement))
+                    .in_set(GameplayStep),
+            );
+
+        let translation = player_translation(player_cell, GridSize::default());
+        let player = app
+            .world
+            .spawn((
+                Player,
+                AnimationState::default(),
+                Transform::from_translation(translation.extend(0.0)),
+                TextureAtlasSprite::default(),
+                player_cell,
+                Velocity2d::default(),
+            ))
+            .id();
+
+        Harness { app, player }
+    }
+
+    /// Runs the schedule for `frames` frames with no keys held.
+    pub fn step(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+            self.app.world.resource_mut::<Input<KeyCode>>().clear();
+        }
+    }
+
+    /// Holds `keys` down for `frames` frames, then releases them.
+    pub fn hold(&mut self, keys: &[KeyCode], frames: usize) {
+        let mut input = self.app.world.resource_mut::<Input<KeyCode>>();
+        for &key in keys {
+            input.press(key);
+        }
+        self.step(frames);
+        self.app
+            .world
+            .resource_mut::<Input<KeyCode>>()
+            .release_all();
+    }
+
+    /// The player's current cell.
+    pub fn player_coords(&self) -> GridCoords {
+        *self.app.world.get::<GridCoords>(self.player).unwrap()
+    }
+
+    /// Number of frames needed to walk `cells` cells at `PLAYER_SPRITE_SPEED` on `Normal`,
+    /// with the harness's instant acceleration.
+    pub fn frames_to_walk(cells: i32) -> usize {
+        let pixels = (cells * GRID_SIZE) as f32;
+        (pixels / (PLAYER_SPRITE_SPEED * FRAME.as_secs_f32())).ceil() as usize
+    }
+}
@@ -0,0 +1,158 @@
+// tile_animation.rs
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_tilemap::tiles::TileTextureIndex;
+use serde::Deserialize;
+
+use crate::components::*;
+use crate::constants::*;
+
+/// TileAnimationPlugin cycles animated tiles authored in LDtk, like torches and water.
+///
+/// LDtk has no animation data of its own, so animations are written in a tileset's
+/// custom data for the first tile of the cycle, as JSON:
+/// `{"animation": [12, 13, 14, 15], "frame_seconds": 0.2}`. `frame_seconds` may be
+/// left out to use `TILE_ANIMATION_SPEED`. Each placed tile with such data gets an
+/// `Animation`, the same component sprites use, and steps its tilemap texture
+/// through the listed tile IDs. Custom data without an `animation` list, or that
+/// isn't JSON, is left for whatever else reads it.
+impl Plugin for TileAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                setup_tile_animations,
+                animate_tiles.after(setup_tile_animations),
+            ),
+        );
+    }
+}
+
+/// Animation data read from a tile's LDtk custom data.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct TileAnimationData {
+    /// Tile IDs in the tileset, in the order they're shown.
+    animation: Vec<usize>,
+    /// How long each frame is shown, in seconds.
+    #[serde(default = "default_frame_seconds")]
+    frame_seconds: f32,
+}
+
+fn default_frame_seconds() -> f32 {
+    TILE_ANIMATION_SPEED
+}
+
+/// Reads a looping `Animation` from a tile's LDtk custom data.
+///
+/// # Arguments
+/// * `data` - The tile's custom data.
+/// * `tile_id` - The tile placed in the level; the animation starts from it if it's one of the frames.
+///
+/// # Returns
+/// The animation, or `None` if the data doesn't describe one with at least one
+/// frame and a positive frame time.
+pub fn parse_tile_animation(data: &str, tile_id: usize) -> Option<Animation> {
+    let data: TileAnimationData = serde_json::from_str(data).ok()?;
+    if data.animation.is_empty() || !data.frame_seconds.is_finite() || data.frame_seconds <= 0.0 {
+        return None;
+    }
+    Some(Animation {
+        current: data
+            .animation
+            .iter()
+            .position(|&frame| frame == tile_id)
+            .unwrap_or(0),
+        frames: data.animation,
+        timer: Timer::from_seconds(data.frame_seconds, TimerMode::Repeating),
+        ..default()
+    })
+}
+
+/// Gives newly spawned tiles with animation custom data an `Animation`.
+///
+/// # Arguments
+/// * `commands` - Used to add the animation to tiles.
+/// * `tile_query` - Query to access new tiles' custom data and texture indices.
+///
+fn setup_tile_animations(
+    mut commands: Commands,
+    tile_query: Query<(Entity, &TileMetadata, &TileTextureIndex), Added<TileMetadata>>,
+) {
+    for (entity, metadata, texture_index) in tile_query.iter() {
+        if let Some(animation) = parse_tile_animation(&metadata.data, texture_index.0 as usize) {
+            commands.entity(entity).insert(animation);
+        }
+    }
+}
+
+/// Steps animated tiles to their next frame as their timers finish.
+///
+/// # Arguments
+/// * `time` - Resource to get time information for the animation timers.
+/// * `tile_query` - Query to access tiles' animations and texture indices.
+///
+pub fn animate_tiles(
+    time: Res<Time>,
+    mut tile_query: Query<(&mut Animation, &mut TileTextureIndex)>,
+) {
+    for (mut animation, mut texture_index) in tile_query.iter_mut() {
+        if !animation.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        if let Some(index) = animation.advance() {
+            texture_index.0 = index as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn test_parse_tile_animation() {
+        let animation = parse_tile_animation(r#"{"animation": [4, 5, 6]}"#, 5).unwrap();
+        assert_eq!(animation.frames, vec![4, 5, 6]);
+        assert_eq!(animation.current, 1);
+        assert!(animation.looping);
+        assert_eq!(
+            animation.timer.duration().as_secs_f32(),
+            TILE_ANIMATION_SPEED
+        );
+
+        // Custom data that isn't an animation is left alone
+        assert!(parse_tile_animation("torch", 0).is_none());
+        assert!(parse_tile_animation(r#"{"animation": []}"#, 0).is_none());
+        assert!(parse_tile_animation(r#"{"animation": [1], "frame_seconds": 0}"#, 1).is_none());
+    }
+
+    #[test]
+    fn test_animated_tile_cycles_frames() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TileAnimationPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                250,
+            )));
+        let tile = app
+            .world
+            .spawn((
+                TileMetadata {
+                    data: r#"{"animation": [12, 13, 14], "frame_seconds": 0.5}"#.to_string(),
+                },
+                TileTextureIndex(12),
+            ))
+            .id();
+        app.update(); // The first frame takes no time
+
+        let mut shown = Vec::new();
+        for _ in 0..8 {
+            app.update();
+            shown.push(app.world.get::<TileTextureIndex>(tile).unwrap().0);
+        }
+        // Two frames per step, wrapping back to the first tile
+        assert_eq!(shown, vec![12, 13, 13, 14, 14, 12, 12, 13]);
+    }
+}
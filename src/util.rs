@@ -1,6 +1,8 @@
 // util.rs
 
 use bevy::math::{Vec2, Vec3};
+use bevy_ecs_ldtk::ldtk::{FieldInstance, FieldValue};
+use bevy_ecs_ldtk::prelude::GridCoords;
 
 /// Converts a `Vec3` to `Vec2` by dropping the z element.
 ///
@@ -25,6 +27,49 @@ pub fn convert_vec3_to_vec2(vec3: Vec3) -> Vec2 {
     Vec2::new(vec3.x, vec3.y)
 }
 
+/// Looks up the value of an LDtk custom field by its identifier.
+///
+/// # Arguments
+///
+/// * `fields`: The field instances of an LDtk entity or level.
+/// * `identifier`: The field identifier, as named in the LDtk editor.
+///
+/// # Returns
+///
+/// The field's value, or `None` if the entity has no such field.
+pub fn ldtk_field<'a>(fields: &'a [FieldInstance], identifier: &str) -> Option<&'a FieldValue> {
+    fields
+        .iter()
+        .find(|field| field.identifier == identifier)
+        .map(|field| &field.value)
+}
+
+/// Number of orthogonal steps between two cells, ignoring walls.
+///
+/// # Arguments
+///
+/// * `a`, `b`: The cells to measure between.
+///
+/// # Returns
+///
+/// The sum of the horizontal and vertical distances, in cells.
+pub fn grid_manhattan(a: GridCoords, b: GridCoords) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+/// Number of steps between two cells when diagonal steps are allowed, ignoring walls.
+///
+/// # Arguments
+///
+/// * `a`, `b`: The cells to measure between.
+///
+/// # Returns
+///
+/// The larger of the horizontal and vertical distances, in cells.
+pub fn grid_chebyshev(a: GridCoords, b: GridCoords) -> i32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,4 +80,25 @@ mod tests {
         let vec2 = convert_vec3_to_vec2(vec3);
         assert_eq!(vec2, Vec2::new(1.0, 2.0));
     }
+
+    #[test]
+    fn test_grid_distances() {
+        let origin = GridCoords::new(0, 0);
+        assert_eq!(grid_manhattan(origin, origin), 0);
+        assert_eq!(grid_chebyshev(origin, origin), 0);
+
+        let a = GridCoords::new(1, 2);
+        let b = GridCoords::new(4, -2);
+        assert_eq!(grid_manhattan(a, b), 7);
+        assert_eq!(grid_chebyshev(a, b), 4);
+        // Symmetric
+        assert_eq!(grid_manhattan(b, a), 7);
+        assert_eq!(grid_chebyshev(b, a), 4);
+
+        // Both cells on the negative side
+        let c = GridCoords::new(-3, -5);
+        let d = GridCoords::new(-1, -6);
+        assert_eq!(grid_manhattan(c, d), 3);
+        assert_eq!(grid_chebyshev(c, d), 2);
+    }
 }
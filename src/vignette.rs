@@ -0,0 +1,201 @@
+// vignette.rs
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::ui::FocusPolicy;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::visuals::VisualSettings;
+
+/// VignettePlugin darkens the edges of the screen as the player's health drops.
+///
+/// A full-screen UI image of a dark red fade, clear in the middle and solid at the
+/// corners, is drawn beneath the rest of the UI. Each frame its opacity is set from
+/// the player's `Health`: clear at full health, up to `VIGNETTE_MAX_ALPHA` with none
+/// left. At or below `VIGNETTE_CRITICAL_FRACTION` of full health it also pulses.
+/// The texture is generated at startup rather than loaded. The effect can be turned
+/// off with `VisualSettings::low_health_vignette`, from the settings menu.
+impl Plugin for VignettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_vignette)
+            .add_systems(Update, update_vignette);
+    }
+}
+
+/// Marker for the full-screen low health vignette.
+#[derive(Component)]
+struct Vignette;
+
+/// How strongly the vignette shows for the given health.
+///
+/// # Returns
+/// The share of health lost: 0.0 at full health, 1.0 with none left.
+pub fn vignette_intensity(health: &Health) -> f32 {
+    if health.max <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - health.current / health.max).clamp(0.0, 1.0)
+}
+
+/// Opacity of the vignette for the given health, `seconds` into the game.
+///
+/// # Returns
+/// The intensity scaled to `VIGNETTE_MAX_ALPHA`, dipping by up to
+/// `VIGNETTE_PULSE_DEPTH` of itself in a pulse while health is critical.
+pub fn vignette_alpha(health: &Health, seconds: f32) -> f32 {
+    let alpha = vignette_intensity(health) * VIGNETTE_MAX_ALPHA;
+    let critical =
+        !health.is_dead() && 1.0 - vignette_intensity(health) <= VIGNETTE_CRITICAL_FRACTION;
+    if !critical {
+        return alpha;
+    }
+    let wave = 0.5 + 0.5 * (seconds * VIGNETTE_PULSE_RATE * std::f32::consts::TAU).sin();
+    alpha * (1.0 - VIGNETTE_PULSE_DEPTH * wave)
+}
+
+/// Builds the vignette texture: white, clear in the middle and fading in to solid
+/// at the corners, to be tinted by the node's `BackgroundColor`.
+fn vignette_image(size: u32) -> Image {
+    let center = (size as f32 - 1.0) / 2.0;
+    let data = (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            // 0.0 at the center, 1.0 at the corners
+            let distance = Vec2::new(x as f32 - center, y as f32 - center).length()
+                / (center * std::f32::consts::SQRT_2);
+            let fade = ((distance - 0.4) / 0.6).clamp(0.0, 1.0);
+            let alpha = fade * fade * (3.0 - 2.0 * fade);
+            [255, 255, 255, (alpha * 255.0).round() as u8]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+/// Spawns the vignette over the whole screen, hidden until the player is hurt.
+fn setup_vignette(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            image: UiImage::new(images.add(vignette_image(VIGNETTE_TEXTURE_SIZE))),
+            background_color: Color::rgba(0.4, 0.0, 0.0, 0.0).into(),
+            // Beneath the HUD and menus, and never in the way of a click
+            z_index: ZIndex::Global(-1),
+            focus_policy: FocusPolicy::Pass,
+            ..default()
+        },
+        Vignette,
+        Name::new("Low health vignette"),
+    ));
+}
+
+/// Sets the vignette's opacity from the player's health, or hides it when it's
+/// turned off or there's no player.
+///
+/// # Arguments
+/// * `time` - Resource giving the time since startup, for the pulse.
+/// * `settings` - Resource saying whether the vignette is turned on.
+/// * `player_query` - Query to access the player's health.
+/// * `vignette_query` - Query to access the vignette's tint.
+///
+fn update_vignette(
+    time: Res<Time>,
+    settings: Res<VisualSettings>,
+    player_query: Query<&Health, With<Player>>,
+    mut vignette_query: Query<&mut BackgroundColor, With<Vignette>>,
+) {
+    let alpha = match player_query.iter().next() {
+        Some(health) if settings.low_health_vignette => {
+            vignette_alpha(health, time.elapsed_seconds())
+        }
+        _ => 0.0,
+    };
+    for mut color in vignette_query.iter_mut() {
+        color.0.set_a(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(current: f32) -> Health {
+        Health { current, max: 3.0 }
+    }
+
+    #[test]
+    fn test_vignette_intensity() {
+        assert_eq!(vignette_intensity(&health(3.0)), 0.0);
+        assert_eq!(vignette_intensity(&health(0.0)), 1.0);
+        assert_eq!(vignette_intensity(&health(1.5)), 0.5);
+        // Over- and under-flowing health stays in range
+        assert_eq!(vignette_intensity(&health(5.0)), 0.0);
+        assert_eq!(vignette_intensity(&health(-1.0)), 1.0);
+        assert_eq!(
+            vignette_intensity(&Health {
+                current: 0.0,
+                max: 0.0
+            }),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_vignette_pulses_only_when_critical() {
+        // Full health shows nothing, and empty health the full vignette, without a pulse
+        assert_eq!(vignette_alpha(&health(3.0), 0.1), 0.0);
+        assert_eq!(vignette_alpha(&health(0.0), 0.1), VIGNETTE_MAX_ALPHA);
+
+        // Hurt but not critical holds steady
+        let hurt = health(2.0);
+        assert_eq!(vignette_alpha(&hurt, 0.0), vignette_alpha(&hurt, 0.1));
+
+        // Critical dips below its intensity and comes back
+        let critical = health(1.0);
+        let steady = vignette_intensity(&critical) * VIGNETTE_MAX_ALPHA;
+        let samples: Vec<f32> = (0..20)
+            .map(|step| vignette_alpha(&critical, step as f32 * 0.05))
+            .collect();
+        assert!(samples.iter().all(|&alpha| alpha <= steady + f32::EPSILON));
+        assert!(samples
+            .iter()
+            .any(|&alpha| alpha < steady * (1.0 - VIGNETTE_PULSE_DEPTH / 2.0)));
+    }
+
+    #[test]
+    fn test_vignette_hidden_when_turned_off() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<VisualSettings>()
+            .add_systems(Update, update_vignette);
+        app.world.spawn((Player, health(1.5)));
+        let vignette = app
+            .world
+            .spawn((Vignette, BackgroundColor(Color::rgba(0.4, 0.0, 0.0, 0.0))))
+            .id();
+        app.update();
+        let alpha = app.world.get::<BackgroundColor>(vignette).unwrap().0.a();
+        assert_eq!(alpha, 0.5 * VIGNETTE_MAX_ALPHA);
+
+        app.world
+            .resource_mut::<VisualSettings>()
+            .low_health_vignette = false;
+        app.update();
+        let alpha = app.world.get::<BackgroundColor>(vignette).unwrap().0.a();
+        assert_eq!(alpha, 0.0);
+    }
+}
@@ -0,0 +1,219 @@
+// visuals.rs
+
+use bevy::core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::settings::GameConfig;
+
+/// VisualsPlugin applies the player's bloom and tonemapping settings to the camera.
+///
+/// `VisualSettings` is loaded with the rest of the `GameConfig` in `main`, used to
+/// build the camera, and applied to it again whenever it changes, so the glow of
+/// the spells can be dialled in from the settings menu. Changes are copied back
+/// into the `GameConfig` so they're saved with the other settings.
+impl Plugin for VisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VisualSettings>()
+            .add_systems(Update, (apply_visual_settings, store_visual_settings));
+    }
+}
+
+/// The tonemapping curves the player can pick from.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TonemappingMode {
+    None,
+    Reinhard,
+    AcesFitted,
+    AgX,
+    #[default]
+    TonyMcMapface,
+    BlenderFilmic,
+}
+
+impl TonemappingMode {
+    /// Every mode, in the order the settings menu cycles through them.
+    pub const ALL: [TonemappingMode; 6] = [
+        TonemappingMode::None,
+        TonemappingMode::Reinhard,
+        TonemappingMode::AcesFitted,
+        TonemappingMode::AgX,
+        TonemappingMode::TonyMcMapface,
+        TonemappingMode::BlenderFilmic,
+    ];
+
+    /// The next mode, wrapping from the last back to the first.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The camera's `Tonemapping` for this mode.
+    pub fn tonemapping(self) -> Tonemapping {
+        match self {
+            TonemappingMode::None => Tonemapping::None,
+            TonemappingMode::Reinhard => Tonemapping::Reinhard,
+            TonemappingMode::AcesFitted => Tonemapping::AcesFitted,
+            TonemappingMode::AgX => Tonemapping::AgX,
+            TonemappingMode::TonyMcMapface => Tonemapping::TonyMcMapface,
+            TonemappingMode::BlenderFilmic => Tonemapping::BlenderFilmic,
+        }
+    }
+}
+
+/// Look of the picture, chosen by the player.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VisualSettings {
+    /// Strength of the glow around bright things like spells, from 0.0 (none) to
+    /// `VISUAL_BLOOM_MAX`.
+    pub bloom_intensity: f32,
+    /// How bright colors are brought into the display's range.
+    pub tonemapping: TonemappingMode,
+    /// Whether the edges of the screen darken as the player's health drops.
+    pub low_health_vignette: bool,
+}
+
+impl Default for VisualSettings {
+    fn default() -> Self {
+        VisualSettings {
+            bloom_intensity: BloomSettings::default().intensity,
+            tonemapping: TonemappingMode::default(),
+            low_health_vignette: true,
+        }
+    }
+}
+
+impl VisualSettings {
+    /// The camera's `BloomSettings` for these settings.
+    ///
+    /// # Returns
+    /// The default bloom with its intensity set to `bloom_intensity`, clamped to
+    /// 0.0..=`VISUAL_BLOOM_MAX`.
+    pub fn bloom_settings(&self) -> BloomSettings {
+        BloomSettings {
+            intensity: self.bloom_intensity.clamp(0.0, VISUAL_BLOOM_MAX),
+            ..default()
+        }
+    }
+}
+
+/// Moves a bloom intensity by `steps` of `VISUAL_BLOOM_STEP`, staying within
+/// 0.0..=`VISUAL_BLOOM_MAX`.
+pub fn step_bloom(intensity: f32, steps: i32) -> f32 {
+    let step = (intensity / VISUAL_BLOOM_STEP).round() as i32 + steps;
+    let max_step = (VISUAL_BLOOM_MAX / VISUAL_BLOOM_STEP).round() as i32;
+    step.clamp(0, max_step) as f32 * VISUAL_BLOOM_STEP
+}
+
+/// Sets the bloom and tonemapping of cameras that were just spawned, or of every
+/// camera when the settings change.
+fn apply_visual_settings(
+    settings: Res<VisualSettings>,
+    mut camera_query: Query<(Ref<Camera>, &mut BloomSettings, &mut Tonemapping)>,
+) {
+    for (camera, mut bloom, mut tonemapping) in camera_query.iter_mut() {
+        if !settings.is_changed() && !camera.is_added() {
+            continue;
+        }
+        *bloom = settings.bloom_settings();
+        *tonemapping = settings.tonemapping.tonemapping();
+    }
+}
+
+/// Copies changed visual settings into the `GameConfig`, which saves them.
+fn store_visual_settings(settings: Res<VisualSettings>, mut config: ResMut<GameConfig>) {
+    if !settings.is_changed() || settings.is_added() || config.visuals == *settings {
+        return;
+    }
+    config.visuals = settings.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_settings_clamped() {
+        let bloom = |intensity: f32| {
+            VisualSettings {
+                bloom_intensity: intensity,
+                ..default()
+            }
+            .bloom_settings()
+        };
+        assert_eq!(bloom(0.3).intensity, 0.3);
+        assert_eq!(bloom(-1.0).intensity, 0.0);
+        assert_eq!(bloom(5.0).intensity, VISUAL_BLOOM_MAX);
+        // Only the intensity is the player's; the rest is Bevy's default bloom
+        let default_bloom = BloomSettings::default();
+        assert_eq!(
+            bloom(0.3).high_pass_frequency,
+            default_bloom.high_pass_frequency
+        );
+        assert_eq!(
+            bloom(0.3).low_frequency_boost,
+            default_bloom.low_frequency_boost
+        );
+    }
+
+    #[test]
+    fn test_step_bloom() {
+        assert_eq!(step_bloom(0.0, -1), 0.0);
+        assert_eq!(step_bloom(0.0, 2), 2.0 * VISUAL_BLOOM_STEP);
+        assert_eq!(step_bloom(VISUAL_BLOOM_MAX, 1), VISUAL_BLOOM_MAX);
+    }
+
+    #[test]
+    fn test_settings_applied_to_camera() {
+        let mut app = App::new();
+        app.init_resource::<VisualSettings>()
+            .init_resource::<GameConfig>()
+            .add_systems(Update, (apply_visual_settings, store_visual_settings));
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                BloomSettings::default(),
+                Tonemapping::None,
+            ))
+            .id();
+        app.update();
+        assert_eq!(
+            *app.world.get::<Tonemapping>(camera).unwrap(),
+            Tonemapping::TonyMcMapface
+        );
+
+        *app.world.resource_mut::<VisualSettings>() = VisualSettings {
+            bloom_intensity: 0.5,
+            tonemapping: TonemappingMode::AgX,
+            ..default()
+        };
+        app.update();
+        assert_eq!(
+            app.world.get::<BloomSettings>(camera).unwrap().intensity,
+            0.5
+        );
+        assert_eq!(
+            *app.world.get::<Tonemapping>(camera).unwrap(),
+            Tonemapping::AgX
+        );
+        // The change is kept in the config so it's saved
+        assert_eq!(
+            app.world.resource::<GameConfig>().visuals.tonemapping,
+            TonemappingMode::AgX
+        );
+    }
+
+    #[test]
+    fn test_tonemapping_mode_cycles() {
+        let mut mode = TonemappingMode::default();
+        for _ in 0..TonemappingMode::ALL.len() {
+            mode = mode.next();
+        }
+        assert_eq!(mode, TonemappingMode::default());
+        assert_eq!(TonemappingMode::BlenderFilmic.next(), TonemappingMode::None);
+    }
+}